@@ -0,0 +1,70 @@
+//! Times the stages of rendering codes, see `qr2term::stage`.
+//!
+//! Run with `cargo bench --bench stages`. Every stage runs for payloads of a
+//! few sizes, printing the mean time per run.
+
+use std::time::{Duration, Instant};
+
+use qr2term::{matrix::Arena, render::Style, stage, QrOptions};
+
+/// How long to run every benchmark, after warming up.
+const MEASURE: Duration = Duration::from_millis(500);
+
+/// How long to warm up before measuring.
+const WARM_UP: Duration = Duration::from_millis(100);
+
+/// Run `f` repeatedly, printing the mean time per run.
+///
+/// Checks the sizes `f` returns, so its work isn't optimized away.
+fn bench<F: FnMut() -> usize>(name: &str, mut f: F) {
+    let start = Instant::now();
+    let mut size = 0;
+    while start.elapsed() < WARM_UP {
+        size += f();
+    }
+
+    let start = Instant::now();
+    let mut runs = 0u32;
+    while start.elapsed() < MEASURE {
+        size += f();
+        runs += 1;
+    }
+    let mean = start.elapsed() / runs;
+    assert!(size > 0, "{} produced nothing", name);
+    println!("{:<24} {:>12?} ({} runs)", name, mean, runs);
+}
+
+fn main() {
+    let payloads = [
+        ("short", "qr2term".to_string()),
+        ("url", format!("https://example.com/{}", "a".repeat(80))),
+        ("long", "a".repeat(1000)),
+    ];
+    for (name, payload) in &payloads {
+        let options = QrOptions::new();
+        bench(&format!("encode/{}", name), || {
+            stage::encode(payload, &options).unwrap().size()
+        });
+
+        let mut arena = Arena::new();
+        bench(&format!("encode_in/{}", name), || {
+            let matrix = stage::encode_in(payload, &options, &mut arena).unwrap();
+            let size = matrix.size();
+            arena.recycle(matrix);
+            size
+        });
+
+        let matrix = stage::encode(payload, &options).unwrap();
+        for style in [Style::HalfBlock, Style::FullBlock, Style::Braille] {
+            let options = QrOptions::new().style(style);
+            bench(&format!("layout/{}/{}", name, style), || {
+                stage::layout(&matrix, &options).columns()
+            });
+
+            let cells = stage::layout(&matrix, &options);
+            bench(&format!("emit/{}/{}", name, style), || {
+                stage::emit(&cells, &options).len()
+            });
+        }
+    }
+}
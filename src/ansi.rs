@@ -0,0 +1,84 @@
+//! ANSI escape sequence helpers, so styled output doesn't need a terminal library.
+
+use std::io::{Result as IoResult, Write};
+
+use crate::color::Rgb;
+
+/// ANSI 256-color palette index for white.
+pub const WHITE: u8 = 15;
+
+/// ANSI 256-color palette index for black.
+pub const BLACK: u8 = 0;
+
+/// Write `text` in the given foreground and background palette colors, resetting both after.
+#[inline]
+pub fn write_colored<W: Write>(target: &mut W, text: &str, fg: u8, bg: u8) -> IoResult<()> {
+    write!(
+        target,
+        "\x1B[48;5;{}m\x1B[38;5;{}m{}\x1B[49m\x1B[39m",
+        bg, fg, text
+    )
+}
+
+/// Switch to the given background palette color.
+///
+/// The basic colors use their own sequences, which more terminals and log viewers support.
+pub fn write_background<W: Write>(target: &mut W, color: u8) -> IoResult<()> {
+    match color {
+        0..=7 => write!(target, "\x1B[{}m", 40 + color),
+        8..=15 => write!(target, "\x1B[{}m", 100 + color - 8),
+        _ => write!(target, "\x1B[48;5;{}m", color),
+    }
+}
+
+/// Reset all colors and attributes.
+pub fn write_reset<W: Write>(target: &mut W) -> IoResult<()> {
+    write!(target, "\x1B[0m")
+}
+
+/// Hide the cursor.
+pub const HIDE_CURSOR: &str = "\x1B[?25l";
+
+/// Show the cursor.
+pub const SHOW_CURSOR: &str = "\x1B[?25h";
+
+/// Clear the whole current line, leaving the cursor where it is.
+pub const CLEAR_LINE: &str = "\x1B[2K";
+
+/// Write `url` as its own text, as OSC 8 hyperlink if `escape` is set.
+pub fn write_hyperlink<W: Write>(target: &mut W, url: &str, escape: bool) -> IoResult<()> {
+    match escape {
+        true => write!(target, "\x1B]8;;{}\x1B\\{}\x1B]8;;\x1B\\", url, url),
+        false => target.write_all(url.as_bytes()),
+    }
+}
+
+/// Make the current line the top half of double-width, double-height text (DECDHL).
+pub const DOUBLE_HEIGHT_TOP: &str = "\x1B#3";
+
+/// Make the current line the bottom half of double-width, double-height text (DECDHL).
+pub const DOUBLE_HEIGHT_BOTTOM: &str = "\x1B#4";
+
+/// Write `text` in the given 24-bit foreground and background colors, resetting both after.
+///
+/// Requires a terminal with truecolor support.
+#[inline]
+pub fn write_rgb<W: Write>(target: &mut W, text: &str, fg: Rgb, bg: Rgb) -> IoResult<()> {
+    write!(
+        target,
+        "\x1B[48;2;{};{};{}m\x1B[38;2;{};{};{}m{}\x1B[49m\x1B[39m",
+        bg[0], bg[1], bg[2], fg[0], fg[1], fg[2], text
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colored() {
+        let mut buf = vec![];
+        write_colored(&mut buf, "▄", WHITE, BLACK).unwrap();
+        assert_eq!(buf, "\x1B[48;5;0m\x1B[38;5;15m▄\x1B[49m\x1B[39m".as_bytes());
+    }
+}
@@ -0,0 +1,260 @@
+//! Large text banners above QR codes, in the style of `figlet`.
+//!
+//! Self-service kiosks show a code on a terminal across the room, with a call
+//! to action such as `SCAN HERE` that has to be read from afar.
+//! [`print_qr_with_banner`] prints it in big block letters above the code,
+//! as large as fits the width of the code:
+//!
+//! ```text
+//! ▄▀▀▀▀ ▄▀▀▀▀ ▄▀▀▀▄ █▄  █
+//!  ▀▀▀▄ █     █▀▀▀█ █ ▀▄█
+//! ▀▀▀▀   ▀▀▀▀ ▀   ▀ ▀   ▀
+//! ```
+//!
+//! Letters are drawn with half blocks from a built-in 5 by 5 pixel font,
+//! covering ASCII letters, digits and common punctuation. Other characters
+//! are drawn as `?`.
+
+use std::io::{self, Write};
+
+use crate::{default_options, Error, QrOptions, ToQrPayload};
+
+/// Largest number of columns and half rows per font pixel.
+pub const MAX_SCALE: usize = 3;
+
+/// Size of the glyphs of the font, in pixels.
+const GLYPH_SIZE: usize = 5;
+
+/// Glyphs of the font, one row of pixels per byte, leftmost in the highest bit.
+const FONT: [(char, [u8; GLYPH_SIZE]); 43] = [
+    (' ', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('A', [0b01110, 0b10001, 0b11111, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b11110, 0b10001, 0b11110]),
+    ('C', [0b01111, 0b10000, 0b10000, 0b10000, 0b01111]),
+    ('D', [0b11110, 0b10001, 0b10001, 0b10001, 0b11110]),
+    ('E', [0b11111, 0b10000, 0b11110, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b11110, 0b10000, 0b10000]),
+    ('G', [0b01111, 0b10000, 0b10011, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b11111, 0b10001, 0b10001]),
+    ('I', [0b11111, 0b00100, 0b00100, 0b00100, 0b11111]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10010, 0b10100, 0b11000, 0b10100, 0b10010]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b11001, 0b10101, 0b10011, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b11110, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b11110, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b01110, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10101, 0b11011, 0b10001]),
+    ('X', [0b10001, 0b01010, 0b00100, 0b01010, 0b10001]),
+    ('Y', [0b10001, 0b01010, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('0', [0b01110, 0b10011, 0b10101, 0b11001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00110, 0b01000, 0b11111]),
+    ('3', [0b11110, 0b00001, 0b00110, 0b00001, 0b11110]),
+    ('4', [0b00110, 0b01010, 0b10010, 0b11111, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b11110]),
+    ('6', [0b01110, 0b10000, 0b11110, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b00100]),
+    ('8', [0b01110, 0b10001, 0b01110, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b01111, 0b00001, 0b01110]),
+    ('!', [0b00100, 0b00100, 0b00100, 0b00000, 0b00100]),
+    ('?', [0b01110, 0b10001, 0b00110, 0b00000, 0b00100]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00100]),
+    ('-', [0b00000, 0b00000, 0b01110, 0b00000, 0b00000]),
+    (':', [0b00000, 0b00100, 0b00000, 0b00100, 0b00000]),
+    ('/', [0b00001, 0b00010, 0b00100, 0b01000, 0b10000]),
+];
+
+/// Get the glyph for `ch`, ignoring case, or the glyph for `?` if the font
+/// doesn't have it.
+fn glyph(ch: char) -> [u8; GLYPH_SIZE] {
+    let ch = ch.to_ascii_uppercase();
+    FONT.iter()
+        .find(|(glyph, _)| *glyph == ch)
+        .or_else(|| FONT.iter().find(|(glyph, _)| *glyph == '?'))
+        .map(|(_, rows)| *rows)
+        .unwrap_or_default()
+}
+
+/// Width of `text` drawn at the given `scale`, in columns.
+fn text_width(text: &str, scale: usize) -> usize {
+    // A pixel of space between letters
+    let len = text.chars().count();
+    (len * (GLYPH_SIZE + 1)).saturating_sub(1) * scale
+}
+
+/// Wrap the words of `text` into lines of at most `width` columns at the
+/// given `scale`, or `None` if a word is wider than that.
+fn wrap(text: &str, width: usize, scale: usize) -> Option<Vec<String>> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        if text_width(word, scale) > width {
+            return None;
+        }
+        match lines.last_mut() {
+            Some(line) if text_width(&format!("{} {}", line, word), scale) <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_owned()),
+        }
+    }
+    Some(lines)
+}
+
+/// Draw a single line of `text` at the given `scale`, indented by `indent`
+/// columns.
+fn draw_line(text: &str, scale: usize, indent: usize, banner: &mut String) {
+    let glyphs: Vec<_> = text.chars().map(glyph).collect();
+    let pixel = |x: usize, y: usize| {
+        let (x, y) = (x / scale, y / scale);
+        let (ch, col) = (x / (GLYPH_SIZE + 1), x % (GLYPH_SIZE + 1));
+        y < GLYPH_SIZE && col < GLYPH_SIZE && glyphs[ch][y] & 1 << (GLYPH_SIZE - 1 - col) != 0
+    };
+
+    let (width, height) = (text_width(text, scale), GLYPH_SIZE * scale);
+    for y in (0..height).step_by(2) {
+        let line: String = (0..width)
+            .map(|x| match (pixel(x, y), pixel(x, y + 1)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            })
+            .collect();
+        banner.push_str(&" ".repeat(indent));
+        banner.push_str(line.trim_end());
+        banner.push('\n');
+    }
+}
+
+/// Draw `text` in big letters, as large as fits `width` columns, centered in
+/// them.
+///
+/// Every font pixel is a square of columns and half rows, up to
+/// [`MAX_SCALE`] of each. Words are wrapped onto more lines if that draws
+/// them larger, and words that don't fit at all are drawn at the smallest
+/// scale regardless. Every line of the returned banner ends in a line
+/// break, lines are separated by an empty line.
+///
+/// # Examples
+///
+/// ```rust
+/// let banner = qr2term::banner::render_banner("Scan here", 60);
+/// assert_eq!(banner.lines().count(), 11);
+/// print!("{}", banner);
+/// ```
+pub fn render_banner(text: &str, width: usize) -> String {
+    let (scale, lines) = (1..=MAX_SCALE)
+        .rev()
+        .find_map(|scale| Some((scale, wrap(text, width, scale)?)))
+        .unwrap_or_else(|| (1, text.split_whitespace().map(str::to_owned).collect()));
+
+    let mut banner = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            banner.push('\n');
+        }
+        let indent = width.saturating_sub(text_width(line, scale)) / 2;
+        draw_line(line, scale, indent, &mut banner);
+    }
+    banner
+}
+
+/// Print the given `data` as QR code with a `banner` in big letters above it.
+///
+/// Uses the process wide [`default_options`].
+///
+/// Returns an error if generating the QR code or writing to the terminal failed.
+pub fn print_qr_with_banner<D: ToQrPayload>(banner: &str, data: D) -> Result<(), Error> {
+    print_qr_with_banner_with_options(banner, data, &default_options())
+}
+
+/// Print the given `data` as QR code with a `banner` in big letters above it,
+/// using the given `options`.
+///
+/// Returns an error if generating the QR code or writing to the terminal failed.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::{banner, QrOptions};
+///
+/// banner::print_qr_with_banner_with_options("Scan here", "https://example.com/checkin", &QrOptions::new())
+///     .unwrap();
+/// ```
+pub fn print_qr_with_banner_with_options<D: ToQrPayload>(
+    banner: &str,
+    data: D,
+    options: &QrOptions,
+) -> Result<(), Error> {
+    let mut stdout = io::stdout().lock();
+    render_qr_with_banner(banner, data, &options.for_terminal(), &mut stdout)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Render the given `data` as QR code with a `banner` in big letters above
+/// it, to the given writer.
+///
+/// The banner is as large as fits the width of the code, see
+/// [`render_banner`]. Data that doesn't fit a single QR code is an error,
+/// regardless of [`QrOptions::on_overflow`].
+///
+/// Returns an error if generating the QR code or writing failed.
+pub fn render_qr_with_banner<D: ToQrPayload, W: Write>(
+    banner: &str,
+    data: D,
+    options: &QrOptions,
+    target: &mut W,
+) -> Result<(), Error> {
+    let matrix = crate::code_matrix(&crate::generate_code(&data, options)?, options)?;
+    let renderer = crate::renderer(options);
+    target.write_all(render_banner(banner, renderer.width(&matrix)).as_bytes())?;
+    crate::render_qr_with(data, options, &renderer, target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Style;
+
+    #[test]
+    fn largest_scale() {
+        // 5 letters take 29 pixels
+        assert_eq!(render_banner("hello", 29).lines().count(), 3);
+        assert_eq!(render_banner("hello", 58).lines().count(), 5);
+        let banner = render_banner("hello", 100);
+        assert_eq!(banner.lines().count(), 8);
+        assert!(banner.lines().all(|line| line.chars().count() <= 100));
+        assert!(banner.starts_with(&format!("{:6}███", "")));
+    }
+
+    #[test]
+    fn wrapped() {
+        let banner = render_banner("SCAN HERE", 30);
+        let lines: Vec<_> = banner.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[3], "");
+        assert_eq!(render_banner("unknown ~", 8), render_banner("UNKNOWN ?", 8));
+    }
+
+    #[test]
+    fn above_code() {
+        // 29 modules of 2 columns fit 4 letters at scale 2
+        let options = QrOptions::new().style(Style::FullBlock);
+        let mut buf = Vec::new();
+        render_qr_with_banner("Scan", "qr2term", &options, &mut buf).unwrap();
+        let output = String::from_utf8(buf).unwrap();
+        let code = crate::try_generate_qr_string_with_options("qr2term", &options).unwrap();
+        let banner = output.strip_suffix(&code).unwrap();
+        assert_eq!(banner.lines().count(), 5);
+    }
+}
@@ -0,0 +1,120 @@
+//! Base32 encoding (RFC 4648), for pushing binary data through QR codes.
+//!
+//! Encoded text only uses characters of the QR code alphanumeric mode, so it
+//! results in smaller codes than Base64 text. [`base45`](crate::base45) is
+//! denser still, but Base32 is more widely supported by other tools.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+/// The Base32 alphabet, in order of value.
+const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Padding character, accepted but never produced.
+const PADDING: u8 = b'=';
+
+/// Encode `data` as Base32.
+///
+/// Padding is left out, as `=` is not part of the QR code alphanumeric mode.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::base32;
+///
+/// assert_eq!(base32::encode(b"foobar"), "MZXW6YTBOI");
+/// assert_eq!(base32::decode("MZXW6YTBOI").unwrap(), b"foobar");
+/// ```
+pub fn encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity((data.len() * 8 + 4) / 5);
+    let (mut buffer, mut bits) = (0u16, 0);
+    for &byte in data {
+        buffer = (buffer << 8) | u16::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[usize::from((buffer >> bits) & 0x1f)] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[usize::from((buffer << (5 - bits)) & 0x1f)] as char);
+    }
+    out
+}
+
+/// Decode Base32 `text`, with or without padding.
+///
+/// Lowercase letters are accepted too. Returns an error if the text contains
+/// characters outside of the alphabet, or has an invalid length.
+pub fn decode(text: &str) -> Result<Vec<u8>, DecodeError> {
+    let data = text.trim_end_matches(PADDING as char).as_bytes();
+    if matches!(data.len() % 8, 1 | 3 | 6) {
+        return Err(DecodeError::Length);
+    }
+
+    let mut out = Vec::with_capacity(data.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u16, 0);
+    for (index, &byte) in data.iter().enumerate() {
+        let value = ALPHABET
+            .iter()
+            .position(|&c| c == byte.to_ascii_uppercase())
+            .ok_or(DecodeError::Char(index))?;
+        buffer = (buffer << 5) | value as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+/// Error returned when decoding Base32 text failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The byte at the given index is not part of the Base32 alphabet.
+    Char(usize),
+
+    /// The text has a length that no encoded data can have.
+    Length,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Char(index) => write!(f, "invalid Base32 character at {}", index),
+            DecodeError::Length => f.write_str("invalid Base32 length"),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Examples from RFC 4648, without padding.
+    #[test]
+    fn rfc_vectors() {
+        for (data, text) in [
+            (&b""[..], ""),
+            (b"f", "MY"),
+            (b"fo", "MZXQ"),
+            (b"foo", "MZXW6"),
+            (b"foob", "MZXW6YQ"),
+            (b"fooba", "MZXW6YTB"),
+            (b"foobar", "MZXW6YTBOI"),
+        ] {
+            assert_eq!(encode(data), text);
+            assert_eq!(decode(text).unwrap(), data);
+        }
+        assert_eq!(decode("mzxw6yq=").unwrap(), b"foob");
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(decode("MZX"), Err(DecodeError::Length));
+        assert_eq!(decode("MZ1W"), Err(DecodeError::Char(2)));
+    }
+}
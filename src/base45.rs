@@ -0,0 +1,125 @@
+//! Base45 encoding (RFC 9285), for pushing binary data through QR codes.
+//!
+//! Base45 only uses characters of the QR code alphanumeric mode, which packs
+//! two characters in 11 bits. Binary data wrapped this way results in smaller
+//! codes than Base64 text, which has to be encoded in byte mode.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+/// The Base45 alphabet, in order of value.
+const ALPHABET: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Encode `data` as Base45.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::base45;
+///
+/// assert_eq!(base45::encode(b"Hello!!"), "%69 VD92EX0");
+/// assert_eq!(base45::decode("%69 VD92EX0").unwrap(), b"Hello!!");
+///
+/// // Encoded data is rendered in the compact alphanumeric mode
+/// let info = qr2term::qr_info(base45::encode(&[0xff; 100]), &Default::default()).unwrap();
+/// assert_eq!(info.mode, qr2term::qr::SegmentMode::Alphanumeric);
+/// ```
+pub fn encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity((data.len() + 1) / 2 * 3);
+    for chunk in data.chunks(2) {
+        let (mut value, digits) = match *chunk {
+            [a, b] => (usize::from(a) * 256 + usize::from(b), 3),
+            [a] => (usize::from(a), 2),
+            _ => unreachable!(),
+        };
+        for _ in 0..digits {
+            out.push(ALPHABET[value % 45] as char);
+            value /= 45;
+        }
+    }
+    out
+}
+
+/// Decode Base45 `text` produced by [`encode`].
+///
+/// Returns an error if the text contains characters outside of the alphabet,
+/// has an invalid length, or encodes values that don't fit in bytes.
+pub fn decode(text: &str) -> Result<Vec<u8>, DecodeError> {
+    let digits = text
+        .bytes()
+        .enumerate()
+        .map(|(index, byte)| {
+            ALPHABET
+                .iter()
+                .position(|&c| c == byte)
+                .ok_or(DecodeError::Char(index))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut out = Vec::with_capacity(digits.len() / 3 * 2 + 1);
+    for chunk in digits.chunks(3) {
+        let value = chunk
+            .iter()
+            .rev()
+            .fold(0, |value, digit| value * 45 + digit);
+        match chunk.len() {
+            3 if value <= 0xffff => out.extend_from_slice(&(value as u16).to_be_bytes()),
+            2 if value <= 0xff => out.push(value as u8),
+            1 => return Err(DecodeError::Length),
+            _ => return Err(DecodeError::Overflow),
+        }
+    }
+    Ok(out)
+}
+
+/// Error returned when decoding Base45 text failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The byte at the given index is not part of the Base45 alphabet.
+    Char(usize),
+
+    /// The text has a length that no encoded data can have.
+    Length,
+
+    /// A group of characters encodes a value too large for its bytes.
+    Overflow,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Char(index) => write!(f, "invalid Base45 character at {}", index),
+            DecodeError::Length => f.write_str("invalid Base45 length"),
+            DecodeError::Overflow => f.write_str("invalid Base45 value"),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Examples from RFC 9285.
+    #[test]
+    fn rfc_vectors() {
+        for (data, text) in [
+            (&b"AB"[..], "BB8"),
+            (b"Hello!!", "%69 VD92EX0"),
+            (b"base-45", "UJCLQE7W581"),
+            (b"ietf!", "QED8WEX0"),
+            (b"", ""),
+        ] {
+            assert_eq!(encode(data), text);
+            assert_eq!(decode(text).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(decode("GGW"), Err(DecodeError::Overflow));
+        assert_eq!(decode("BB8a"), Err(DecodeError::Char(3)));
+        assert_eq!(decode("BB8B"), Err(DecodeError::Length));
+    }
+}
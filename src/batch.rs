@@ -0,0 +1,783 @@
+//! Generating many codes from tabular data, such as event tickets or asset labels.
+//!
+//! Rows are read from CSV with [`read_csv`] or JSON with [`read_json`], and
+//! turned into payloads with a [`Template`] whose variables are column names.
+//! The codes are then printed one after another with [`print_batch`], or
+//! written as PNG images named by a column with [`write_images`].
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::json::{self, JsonError, Value};
+use crate::payload::{self, Template, TemplateError};
+use crate::qr::Qr;
+use crate::{default_options, png, reserve, split, ErrorCategory, QrError, QrOptions, ToQrPayload};
+
+/// Size of a module in written images, in pixels.
+pub const IMAGE_MODULE_SIZE: usize = 8;
+
+/// Format of batch input.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum InputFormat {
+    /// Comma-separated values with a header row, see [`read_csv`].
+    #[default]
+    Csv,
+
+    /// An array of JSON objects, see [`read_json`].
+    Json,
+}
+
+impl InputFormat {
+    /// Name of this format, as accepted by its `FromStr` implementation.
+    pub fn name(self) -> &'static str {
+        match self {
+            InputFormat::Csv => "csv",
+            InputFormat::Json => "json",
+        }
+    }
+
+    /// Read the rows of `text` in this format.
+    pub fn read(self, text: &str) -> Result<Vec<Row>, BatchError> {
+        match self {
+            InputFormat::Csv => read_csv(text),
+            InputFormat::Json => read_json(text),
+        }
+    }
+}
+
+impl Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = ParseInputFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        [InputFormat::Csv, InputFormat::Json]
+            .into_iter()
+            .find(|format| format.name() == name)
+            .ok_or(ParseInputFormatError { name })
+    }
+}
+
+/// Error returned when parsing an unknown [`InputFormat`] name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseInputFormatError {
+    name: String,
+}
+
+impl Display for ParseInputFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown input format '{}'", self.name)
+    }
+}
+
+impl Error for ParseInputFormatError {}
+
+/// A row of input, as column names and values.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Row {
+    columns: Vec<(String, String)>,
+}
+
+impl Row {
+    /// Get the value of the given column, if the row has it.
+    pub fn get(&self, column: &str) -> Option<&str> {
+        self.columns
+            .iter()
+            .find(|(name, _)| name == column)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The columns of this row, as `(name, value)` pairs.
+    pub fn columns(&self) -> Vec<(&str, &str)> {
+        self.columns
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect()
+    }
+
+    /// Render the payload of this row with `template`.
+    pub fn payload(&self, template: &Template) -> Result<String, TemplateError> {
+        template.render(&self.columns())
+    }
+}
+
+/// Error returned when generating a batch of codes failed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BatchError {
+    /// The CSV input is malformed at the given line.
+    InvalidCsv(usize),
+
+    /// The JSON input is invalid at the given byte position.
+    InvalidJson(usize),
+
+    /// A JSON object has the given column more than once.
+    DuplicateColumn(String),
+
+    /// The row with the given number, starting at 1, doesn't match the
+    /// header or is not a JSON object.
+    InvalidRow(usize),
+
+    /// Rendering the payload of the given row failed.
+    Template(usize, TemplateError),
+
+    /// Generating or writing the code of the given row failed.
+    Render(usize, crate::Error),
+
+    /// The given row doesn't have the column to name its image by.
+    MissingName(usize),
+
+    /// More than one row has the given image name.
+    DuplicateName(String),
+}
+
+impl BatchError {
+    /// Get the category of this error, for branching on the kind of failure.
+    ///
+    /// Malformed input rows, including missing or duplicate image names, are
+    /// decode failures.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            BatchError::Template(..) => ErrorCategory::InvalidPayload,
+            BatchError::Render(_, err) => err.category(),
+            _ => ErrorCategory::Decode,
+        }
+    }
+}
+
+impl Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchError::InvalidCsv(line) => write!(f, "invalid CSV at line {}", line),
+            BatchError::InvalidJson(position) => {
+                write!(f, "invalid JSON at position {}", position)
+            }
+            BatchError::DuplicateColumn(column) => write!(f, "duplicate column '{}'", column),
+            BatchError::InvalidRow(row) => write!(f, "invalid row {}", row),
+            BatchError::Template(row, err) => write!(f, "row {}: {}", row, err),
+            BatchError::Render(row, err) => write!(f, "row {}: {}", row, err),
+            BatchError::MissingName(row) => write!(f, "row {} has no name column", row),
+            BatchError::DuplicateName(name) => write!(f, "duplicate image name '{}'", name),
+        }
+    }
+}
+
+impl Error for BatchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BatchError::Template(_, err) => Some(err),
+            BatchError::Render(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Read rows from comma-separated values, as described in RFC 4180.
+///
+/// The first record is the header with the column names. Fields may be
+/// quoted with `"` to contain commas, line breaks and `""` for quotes.
+///
+/// Returns an error if a quoted field is malformed, or a record doesn't have
+/// as many fields as the header.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::batch;
+///
+/// let rows = batch::read_csv("id,name\n1,\"Doe, Jane\"\n").unwrap();
+/// assert_eq!(rows[0].get("name"), Some("Doe, Jane"));
+/// ```
+pub fn read_csv(text: &str) -> Result<Vec<Row>, BatchError> {
+    let mut records = parse_csv(text)?.into_iter();
+    let header = match records.next() {
+        Some(header) => header,
+        None => return Ok(Vec::new()),
+    };
+    records
+        .enumerate()
+        .map(|(i, fields)| match fields.len() == header.len() {
+            true => Ok(Row {
+                columns: header.iter().cloned().zip(fields).collect(),
+            }),
+            false => Err(BatchError::InvalidRow(i + 1)),
+        })
+        .collect()
+}
+
+/// Parse CSV `text` into records of fields.
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, BatchError> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let (mut line, mut start_line) = (1, 1);
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if field.is_empty() => loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                    Some('"') => match chars.peek() {
+                        None | Some(',' | '\r' | '\n') => break,
+                        Some(_) => return Err(BatchError::InvalidCsv(line)),
+                    },
+                    Some(c) => {
+                        if c == '\n' {
+                            line += 1;
+                        }
+                        field.push(c);
+                    }
+                    None => return Err(BatchError::InvalidCsv(start_line)),
+                }
+            },
+            ',' => fields.push(std::mem::take(&mut field)),
+            '\r' | '\n' => {
+                if c == '\r' {
+                    chars.next_if_eq(&'\n');
+                }
+                fields.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut fields));
+                line += 1;
+                start_line = line;
+            }
+            '"' => return Err(BatchError::InvalidCsv(line)),
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        records.push(fields);
+    }
+    // Blank lines are not records
+    records.retain(|fields| !(fields.len() == 1 && fields[0].is_empty()));
+    Ok(records)
+}
+
+/// Read rows from a JSON array of objects.
+///
+/// Strings are used as is, numbers as written, booleans as `true` or
+/// `false`, and `null` as an empty value. Arrays and objects are used as
+/// canonical JSON.
+///
+/// Returns an error if the JSON is invalid, is not an array, or an element is
+/// not an object.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::batch;
+///
+/// let rows = batch::read_json(r#"[{"id": 7, "name": "Jane"}]"#).unwrap();
+/// assert_eq!(rows[0].get("id"), Some("7"));
+/// ```
+pub fn read_json(text: &str) -> Result<Vec<Row>, BatchError> {
+    let value = json::parse(text).map_err(|err| match err {
+        JsonError::Invalid(position) => BatchError::InvalidJson(position),
+        JsonError::DuplicateKey(key) => BatchError::DuplicateColumn(key),
+    })?;
+    let elements = match value {
+        Value::Array(elements) => elements,
+        _ => return Err(BatchError::InvalidRow(0)),
+    };
+    elements
+        .into_iter()
+        .enumerate()
+        .map(|(i, element)| match element {
+            Value::Object(members) => Ok(Row {
+                columns: members
+                    .into_iter()
+                    .map(|(name, value)| {
+                        let value = match value {
+                            Value::Null => String::new(),
+                            Value::Bool(value) => value.to_string(),
+                            Value::Number(value) | Value::String(value) => value,
+                            value => value.to_canonical(),
+                        };
+                        (name, value)
+                    })
+                    .collect(),
+            }),
+            _ => Err(BatchError::InvalidRow(i + 1)),
+        })
+        .collect()
+}
+
+/// How to print a batch of codes.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::batch::{self, BatchOptions};
+/// use qr2term::payload;
+///
+/// let rows = batch::read_csv("id,name\n1,Ann\n2,Bob\n").unwrap();
+/// let template = payload::template("https://tickets.example/{id}").unwrap();
+/// let batch_options = BatchOptions::new().label("name").index(true);
+/// batch::print_batch(&rows, &template, &batch_options).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BatchOptions {
+    /// Column whose value captions every code, the row number if `None`.
+    pub label: Option<String>,
+
+    /// Whether to number the captions, and end with an index of all codes.
+    pub index: bool,
+}
+
+impl BatchOptions {
+    /// Construct options captioning codes by row number, without an index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the column whose value captions every code.
+    pub fn label(mut self, column: &str) -> Self {
+        self.label = Some(column.into());
+        self
+    }
+
+    /// Set whether to number the captions, and end with an index of all codes.
+    pub fn index(mut self, index: bool) -> Self {
+        self.index = index;
+        self
+    }
+}
+
+/// Longest payload preview in the index, in characters.
+pub const PREVIEW_LEN: usize = 40;
+
+/// Payload preview in the index for [secret](QrOptions::secret) payloads.
+const SECRET_PREVIEW: &str = "(secret)";
+
+/// Print a code for every row to the terminal, see [`render_batch`].
+///
+/// Uses the process wide [`default_options`].
+pub fn print_batch(
+    rows: &[Row],
+    template: &Template,
+    batch: &BatchOptions,
+) -> Result<(), BatchError> {
+    print_batch_with_options(rows, template, batch, &default_options())
+}
+
+/// Print a code for every row to the terminal using the given `options`, see
+/// [`render_batch`].
+pub fn print_batch_with_options(
+    rows: &[Row],
+    template: &Template,
+    batch: &BatchOptions,
+    options: &QrOptions,
+) -> Result<(), BatchError> {
+    let mut stdout = io::stdout();
+    render_batch(rows, template, batch, &options.for_terminal(), &mut stdout)
+}
+
+/// Render a code for every row into `target`, one after another.
+///
+/// Every code is preceded by a caption with the value of the
+/// [`label`](BatchOptions::label) column, or the row number if no column is
+/// given. With an [`index`](BatchOptions::index), captions are numbered and
+/// the output ends with a table of the number, label, symbol version and
+/// payload preview of every code, to find codes in long output. Secret
+/// payloads are left out of the index, see [`QrOptions::secret`].
+///
+/// Returns an error if rendering a payload or code failed.
+pub fn render_batch<W: Write>(
+    rows: &[Row],
+    template: &Template,
+    batch: &BatchOptions,
+    options: &QrOptions,
+    target: &mut W,
+) -> Result<(), BatchError> {
+    let mut entries = Vec::new();
+    for (i, row) in rows.iter().enumerate() {
+        let number = i + 1;
+        let payload = row
+            .payload(template)
+            .map_err(|err| BatchError::Template(number, err))?;
+        let label = batch
+            .label
+            .as_deref()
+            .map(|column| row.get(column).unwrap_or_default());
+        let result = render_row(&payload, number, label, batch, options, target);
+        if options.secret {
+            payload::wipe(&mut payload.into_bytes());
+        }
+        entries.extend(result?);
+    }
+
+    if batch.index {
+        write_index(target, &entries, batch.label.is_some())
+            .map_err(|err| BatchError::Render(rows.len(), err.into()))?;
+    }
+    Ok(())
+}
+
+/// Render the code for `payload` of row `number` into `target`, with the
+/// caption for its `label`.
+///
+/// Returns the index entry of the row, if the batch has an index.
+fn render_row<W: Write>(
+    payload: &str,
+    number: usize,
+    label: Option<&str>,
+    batch: &BatchOptions,
+    options: &QrOptions,
+    target: &mut W,
+) -> Result<Option<[String; 4]>, BatchError> {
+    let caption = match (label, batch.index) {
+        (Some(label), false) => label.to_owned(),
+        (Some(label), true) => format!("#{} {}", number, label),
+        (None, _) => format!("#{}", number),
+    };
+    writeln!(target, "{}", caption).map_err(|err| BatchError::Render(number, err.into()))?;
+    crate::render_qr_with(payload, options, &crate::renderer(options), target)
+        .map_err(|err| BatchError::Render(number, err))?;
+
+    if !batch.index {
+        return Ok(None);
+    }
+    let version =
+        symbol_version(payload, options).map_err(|err| BatchError::Render(number, err.into()))?;
+    let preview = match options.secret {
+        true => SECRET_PREVIEW.to_owned(),
+        false => preview(payload),
+    };
+    Ok(Some([
+        number.to_string(),
+        label.unwrap_or_default().to_owned(),
+        version,
+        preview,
+    ]))
+}
+
+/// Get the symbol version of the code for `payload`, with the number of
+/// codes if it is split over more than one.
+fn symbol_version(payload: &str, options: &QrOptions) -> Result<String, QrError> {
+    let payload = crate::prepare_payload(payload.to_qr_payload(), options);
+    let parts = split::split(&payload, options)?;
+    let mut versions = Vec::with_capacity(parts.len());
+    for part in &parts {
+        let version = Qr::from_payload(part, options.ec_level)?
+            .info()
+            .version_name();
+        if !versions.contains(&version) {
+            versions.push(version);
+        }
+    }
+    Ok(match parts.len() {
+        1 => versions.join(""),
+        count => format!("{} ({} codes)", versions.join("/"), count),
+    })
+}
+
+/// Get a single line preview of `payload`, of at most [`PREVIEW_LEN`] characters.
+fn preview(payload: &str) -> String {
+    let mut chars = payload
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c });
+    let mut preview: String = chars.by_ref().take(PREVIEW_LEN).collect();
+    if chars.next().is_some() {
+        preview.pop();
+        preview.push('…');
+    }
+    preview
+}
+
+/// Write the index table of `entries`, with the label column if `labeled`.
+fn write_index<W: Write>(target: &mut W, entries: &[[String; 4]], labeled: bool) -> io::Result<()> {
+    let header = ["#", "Label", "Version", "Payload"];
+    let columns: Vec<usize> = match labeled {
+        true => vec![0, 1, 2, 3],
+        false => vec![0, 2, 3],
+    };
+    let widths: Vec<usize> = columns
+        .iter()
+        .map(|&column| {
+            entries
+                .iter()
+                .map(|entry| entry[column].chars().count())
+                .chain([header[column].len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    writeln!(target)?;
+    let rows = std::iter::once(header.map(String::from)).chain(entries.iter().cloned());
+    for row in rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .zip(&widths)
+            .map(|(&column, &width)| {
+                let padding = width - row[column].chars().count();
+                match column {
+                    // Right-align the numbers
+                    0 => format!("{}{}", " ".repeat(padding), row[column]),
+                    _ => format!("{}{}", row[column], " ".repeat(padding)),
+                }
+            })
+            .collect();
+        writeln!(target, "{}", cells.join("  ").trim_end())?;
+    }
+    Ok(())
+}
+
+/// Write a PNG image of the code for every row into the directory `dir`.
+///
+/// Every image is named by the value of the `name` column with a `.png`
+/// extension, with path separators and other characters unsafe in file
+/// names replaced by `_`. Modules are [`IMAGE_MODULE_SIZE`] pixels wide, and
+/// the [reserved](QrOptions::reserve) area, if any, is left blank. Existing
+/// files are replaced.
+///
+/// Returns the paths of the written images, or an error if a row has no
+/// name, two rows have the same name, or rendering or writing a code failed.
+pub fn write_images<P: AsRef<Path>>(
+    rows: &[Row],
+    template: &Template,
+    name: &str,
+    options: &QrOptions,
+    dir: P,
+) -> Result<Vec<PathBuf>, BatchError> {
+    // Check all names first, so a bad row doesn't leave a partial batch
+    let mut names = HashSet::new();
+    let mut files = Vec::with_capacity(rows.len());
+    for (i, row) in rows.iter().enumerate() {
+        let file = row
+            .get(name)
+            .filter(|value| !value.trim().is_empty())
+            .map(file_name)
+            .ok_or(BatchError::MissingName(i + 1))?;
+        if !names.insert(file.clone()) {
+            return Err(BatchError::DuplicateName(file));
+        }
+        files.push(file);
+    }
+
+    let mut paths = Vec::with_capacity(rows.len());
+    for (i, (row, file)) in rows.iter().zip(files).enumerate() {
+        let number = i + 1;
+        let payload = row
+            .payload(template)
+            .map_err(|err| BatchError::Template(number, err))?;
+        let matrix = reserve::image_matrix(&payload, options)
+            .map_err(|err| BatchError::Render(number, err))?;
+        let image = png::encode(&matrix, IMAGE_MODULE_SIZE);
+        let path = dir.as_ref().join(format!("{}.png", file));
+        fs::write(&path, image).map_err(|err| BatchError::Render(number, err.into()))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Make `name` safe to use as file name.
+fn file_name(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    match name.starts_with('.') {
+        true => format!("_{}", &name[1..]),
+        false => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::Overflow;
+    use crate::payload;
+    use crate::render::Style;
+    use crate::theme::Theme;
+
+    #[test]
+    fn input_format_from_str() {
+        assert_eq!(" JSON ".parse(), Ok(InputFormat::Json));
+        assert_eq!("csv".parse(), Ok(InputFormat::Csv));
+        assert!("tsv".parse::<InputFormat>().is_err());
+    }
+
+    #[test]
+    fn csv() {
+        let rows = read_csv("id,note\r\n1,\"a, \"\"b\"\"\nc\"\n\n2,\n").unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].columns(), [("id", "1"), ("note", "a, \"b\"\nc")]);
+        assert_eq!(rows[1].columns(), [("id", "2"), ("note", "")]);
+        assert_eq!(read_csv("id\n1").unwrap()[0].get("id"), Some("1"));
+        assert!(read_csv("").unwrap().is_empty());
+
+        assert!(matches!(
+            read_csv("id,note\n1\n"),
+            Err(BatchError::InvalidRow(1))
+        ));
+        assert!(matches!(
+            read_csv("id\n\"1\"x\n"),
+            Err(BatchError::InvalidCsv(2))
+        ));
+        assert!(matches!(
+            read_csv("id\n\"1\n"),
+            Err(BatchError::InvalidCsv(2))
+        ));
+        assert!(matches!(
+            read_csv("id\n1\"\n"),
+            Err(BatchError::InvalidCsv(2))
+        ));
+    }
+
+    #[test]
+    fn json() {
+        let rows = read_json(r#"[{"id": 1, "ok": true, "tags": ["a"], "note": null}]"#).unwrap();
+        assert_eq!(
+            rows[0].columns(),
+            [
+                ("id", "1"),
+                ("ok", "true"),
+                ("tags", "[\"a\"]"),
+                ("note", "")
+            ]
+        );
+        assert!(matches!(read_json("{}"), Err(BatchError::InvalidRow(0))));
+        assert!(matches!(
+            read_json("[{}, 1]"),
+            Err(BatchError::InvalidRow(2))
+        ));
+        assert!(matches!(read_json("["), Err(BatchError::InvalidJson(_))));
+    }
+
+    #[test]
+    fn render() {
+        let rows = read_csv("id,name\n1,Ann\n2,Bob\n").unwrap();
+        let template = payload::template("https://x.y/t/{id}").unwrap();
+        let options = QrOptions::new()
+            .style(Style::Ascii)
+            .theme(Theme::Monochrome);
+
+        let mut out = Vec::new();
+        render_batch(
+            &rows,
+            &template,
+            &BatchOptions::new().label("name"),
+            &options,
+            &mut out,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let expected = format!(
+            "Ann\n{}Bob\n{}",
+            crate::generate_qr_string_with_options("https://x.y/t/1", &options).unwrap(),
+            crate::generate_qr_string_with_options("https://x.y/t/2", &options).unwrap()
+        );
+        assert_eq!(out, expected);
+
+        let mut out = Vec::new();
+        render_batch(&rows, &template, &BatchOptions::new(), &options, &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().starts_with("#1\n"));
+
+        let template = payload::template("{missing}").unwrap();
+        assert!(matches!(
+            render_batch(
+                &rows,
+                &template,
+                &BatchOptions::new(),
+                &options,
+                &mut Vec::new()
+            ),
+            Err(BatchError::Template(1, _))
+        ));
+    }
+
+    #[test]
+    fn index() {
+        let rows = read_csv("id,name\n1,Ann\n2,Bob\n").unwrap();
+        let template = payload::template("https://tickets.example/{id}").unwrap();
+        let options = QrOptions::new()
+            .style(Style::Ascii)
+            .theme(Theme::Monochrome);
+
+        let mut out = Vec::new();
+        let batch = BatchOptions::new().label("name").index(true);
+        render_batch(&rows, &template, &batch, &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("#1 Ann\n"));
+        assert!(out.contains("\n#2 Bob\n"));
+        assert!(out.ends_with(
+            "\n\n#  Label  Version  Payload\n\
+             1  Ann    2        https://tickets.example/1\n\
+             2  Bob    2        https://tickets.example/2\n"
+        ));
+
+        let mut out = Vec::new();
+        let rows: Vec<Row> = (0..10).map(|_| rows[0].clone()).collect();
+        let template = payload::template("{name}\n").unwrap();
+        let batch = BatchOptions::new().index(true);
+        render_batch(&rows, &template, &batch, &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\n #  Version  Payload\n 1  1        Ann\n"));
+        assert!(out.ends_with("\n10  1        Ann\n"));
+
+        let mut out = Vec::new();
+        render_batch(&rows, &template, &batch, &options.secret(true), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.ends_with("\n10  1        (secret)\n"));
+        assert!(!out.contains("Ann"));
+    }
+
+    #[test]
+    fn index_entries() {
+        assert_eq!(preview("a\tb"), "a b");
+        let long = "x".repeat(PREVIEW_LEN + 1);
+        assert_eq!(preview(&long).chars().count(), PREVIEW_LEN);
+        assert!(preview(&long).ends_with('…'));
+        assert_eq!(preview(&long[1..]), long[1..]);
+
+        let options = QrOptions::new().on_overflow(Overflow::Split);
+        assert_eq!(symbol_version("abc", &options).unwrap(), "1");
+        assert_eq!(
+            symbol_version(&"a".repeat(5000), &options).unwrap(),
+            "40/14 (3 codes)"
+        );
+    }
+
+    #[test]
+    fn images() {
+        let dir = std::env::temp_dir().join(format!("qr2term-batch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let template = payload::template("asset:{id}").unwrap();
+        let options = QrOptions::new();
+
+        let rows = read_csv("id,name\n1,../a/b\n2,.c\n").unwrap();
+        let paths = write_images(&rows, &template, "name", &options, &dir).unwrap();
+        assert_eq!(paths, [dir.join("_._a_b.png"), dir.join("_c.png")]);
+        let image = fs::read(&paths[0]).unwrap();
+        assert!(image.starts_with(b"\x89PNG"));
+
+        let rows = read_csv("id,name\n1,a\n2,a\n").unwrap();
+        assert!(matches!(
+            write_images(&rows, &template, "name", &options, &dir),
+            Err(BatchError::DuplicateName(name)) if name == "a"
+        ));
+        let rows = read_csv("id,name\n1,\n").unwrap();
+        assert!(matches!(
+            write_images(&rows, &template, "name", &options, &dir),
+            Err(BatchError::MissingName(1))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,136 @@
+//! Text printed beside QR codes.
+//!
+//! Command line wizards often show a code together with a few lines of
+//! explanation, such as what to scan it with, the URL it encodes or when it
+//! expires. [`print_beside`] prints such lines to the right of the code, one
+//! per row, starting at the top of the symbol:
+//!
+//! ```text
+//! █▀▀▀▀▀█ ▄▀▄ █▀▀▀▀▀█  Scan to pair your device
+//! █ ███ █ ▀▄▀ █ ███ █  or open https://example.com/pair
+//! █ ▀▀▀ █ █▄█ █ ▀▀▀ █  Expires in 10 minutes
+//! ```
+
+use std::io::{self, Write};
+
+use crate::{default_options, Error, QrOptions, ToQrPayload};
+
+/// Number of columns between the code and its text.
+pub const GAP: usize = 2;
+
+/// Print the given `data` as QR code with `lines` of text beside it.
+///
+/// Uses the process wide [`default_options`].
+///
+/// Returns an error if generating the QR code or writing to the terminal failed.
+pub fn print_beside<D: ToQrPayload, S: AsRef<str>>(data: D, lines: &[S]) -> Result<(), Error> {
+    print_beside_with_options(data, &default_options(), lines)
+}
+
+/// Print the given `data` as QR code with `lines` of text beside it, using
+/// the given `options`.
+///
+/// Returns an error if generating the QR code or writing to the terminal failed.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::{beside, QrOptions};
+///
+/// let lines = ["Scan to pair your device", "Expires in 10 minutes"];
+/// beside::print_beside_with_options("https://example.com/pair", &QrOptions::new(), &lines)
+///     .unwrap();
+/// ```
+pub fn print_beside_with_options<D: ToQrPayload, S: AsRef<str>>(
+    data: D,
+    options: &QrOptions,
+    lines: &[S],
+) -> Result<(), Error> {
+    let mut stdout = io::stdout().lock();
+    render_beside(data, options, lines, &mut stdout)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Render the given `data` as QR code with `lines` of text beside it, to the
+/// given writer.
+///
+/// Every line of text is printed to the right of a row of the code, separated
+/// by [`GAP`] columns, starting at the first row below the quiet zone. Lines
+/// that don't fit beside the code continue below it, in the same column.
+/// Data that doesn't fit a single QR code is an error, regardless of
+/// [`QrOptions::on_overflow`].
+///
+/// Returns an error if generating the QR code or writing failed.
+pub fn render_beside<D: ToQrPayload, S: AsRef<str>, W: Write>(
+    data: D,
+    options: &QrOptions,
+    lines: &[S],
+    target: &mut W,
+) -> Result<(), Error> {
+    let matrix = crate::code_matrix(&crate::generate_code(data, options)?, options)?;
+    let renderer = crate::renderer(options);
+    let mut buf = Vec::new();
+    renderer.render(&matrix, &mut buf)?;
+    let code = String::from_utf8(buf)
+        .map_err(|err| Error::Io(io::Error::new(io::ErrorKind::InvalidData, err)))?;
+
+    // Skip the rows of the quiet zone above the symbol
+    let top = match matrix.size() {
+        0 => 0,
+        size => options.quiet_zone * renderer.height(&matrix) / size,
+    };
+    let width = renderer.width(&matrix);
+    let rows = code.lines().count().max(top + lines.len());
+    for row in 0..rows {
+        let text = row
+            .checked_sub(top)
+            .and_then(|i| lines.get(i))
+            .map_or("", |line| line.as_ref());
+        match code.lines().nth(row) {
+            Some(code) if text.is_empty() => writeln!(target, "{}", code)?,
+            Some(code) => writeln!(target, "{}{:gap$}{}", code, "", text, gap = GAP)?,
+            None => writeln!(target, "{:indent$}{}", "", text, indent = width + GAP)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Style;
+    use crate::theme::Theme;
+
+    fn render(options: &QrOptions, lines: &[&str]) -> Vec<String> {
+        let mut buf = Vec::new();
+        render_beside("qr2term", options, lines, &mut buf).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        text.lines().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn text_beside_rows() {
+        let options = QrOptions::new().style(Style::Ascii);
+        let output = render(&options, &["Scan me", "Expires soon"]);
+        assert_eq!(output.len(), 25);
+        assert!(!output[1].contains("Scan"));
+        assert_eq!(&output[2][50..], "  Scan me");
+        assert_eq!(&output[3][50..], "  Expires soon");
+        assert_eq!(output[4].len(), 50);
+    }
+
+    #[test]
+    fn text_continues_below() {
+        let options = QrOptions::new()
+            .style(Style::HalfBlock)
+            .theme(Theme::Monochrome)
+            .quiet_zone(0);
+        let lines: Vec<String> = (0..12).map(|i| format!("line {}", i)).collect();
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let output = render(&options, &lines);
+        assert_eq!(output.len(), 12);
+        assert!(output[0].ends_with("  line 0"));
+        assert_eq!(output[11], format!("{:23}line 11", ""));
+    }
+}
@@ -0,0 +1,302 @@
+//! Caching rendered QR codes, for redrawing the same codes repeatedly.
+//!
+//! Dashboards and status screens often redraw the same code every second.
+//! A [`QrCache`] keeps the most recently rendered strings by payload and
+//! options, so redraws skip generating and rendering the code again.
+//!
+//! ```rust
+//! use qr2term::{cache::QrCache, QrOptions};
+//!
+//! let mut cache = QrCache::new(8);
+//! let options = QrOptions::new();
+//! for _ in 0..3 {
+//!     let code = cache.get_or_render("https://status.example/", &options).unwrap();
+//!     assert!(!code.is_empty());
+//! }
+//! assert_eq!(cache.stats().hits, 2);
+//! assert_eq!(cache.stats().misses, 1);
+//! ```
+
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+use std::mem;
+
+use crate::payload::{self, Payload};
+use crate::render::Style;
+use crate::{term, Error, QrOptions, ToQrPayload};
+
+/// Number of rendered codes kept by [`QrCache::default`].
+pub const DEFAULT_CAPACITY: usize = 16;
+
+/// Statistics of a [`QrCache`], for tuning its capacity.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct CacheStats {
+    /// Number of lookups that found a rendered code.
+    pub hits: u64,
+
+    /// Number of lookups that had to render the code.
+    pub misses: u64,
+
+    /// Number of rendered codes dropped to make room for others.
+    pub evictions: u64,
+
+    /// Number of rendered codes currently kept.
+    pub len: usize,
+
+    /// Maximum number of rendered codes kept.
+    pub capacity: usize,
+}
+
+impl CacheStats {
+    /// Fraction of lookups that found a rendered code, from 0 to 1.
+    ///
+    /// Is 0 if there were no lookups yet.
+    pub fn hit_ratio(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+impl Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} hits, {} misses, {} evictions, {}/{} entries",
+            self.hits, self.misses, self.evictions, self.len, self.capacity
+        )
+    }
+}
+
+/// A rendered code, with what it was rendered for.
+#[derive(Debug)]
+struct Entry {
+    payload: Payload<'static>,
+    options: QrOptions,
+
+    /// Size of the terminal the [`Style::Auto`] or [`Style::Smooth`] style
+    /// was fitted to.
+    terminal_size: Option<(usize, usize)>,
+
+    rendered: String,
+}
+
+impl Entry {
+    /// Overwrite the payload and rendered code if the options mark them as
+    /// [secret](QrOptions::secret).
+    fn wipe(&mut self) {
+        if self.options.secret {
+            self.payload.wipe();
+            payload::wipe(&mut mem::take(&mut self.rendered).into_bytes());
+        }
+    }
+}
+
+impl Drop for Entry {
+    fn drop(&mut self) {
+        self.wipe();
+    }
+}
+
+/// Least recently used cache of rendered QR codes, keyed by payload and options.
+///
+/// Rendered codes are kept until the cache is full, after which the least
+/// recently used code is dropped for every new one. Codes rendered with
+/// [`Style::Auto`] or [`Style::Smooth`] are also keyed by the terminal size,
+/// so they are rendered again when the terminal is resized. Codes of
+/// [secret](QrOptions::secret) payloads are wiped from memory when they are
+/// evicted or cleared, and when the cache is dropped.
+///
+/// Lookups compare against every kept code, so the cache is meant for the
+/// handful of codes a screen shows. Codes found in the cache don't invoke the
+/// [`hooks`](QrOptions::hooks) again.
+#[derive(Debug)]
+pub struct QrCache {
+    /// Kept codes, least recently used first.
+    entries: Vec<Entry>,
+    stats: CacheStats,
+}
+
+impl QrCache {
+    /// Construct an empty cache keeping at most `capacity` rendered codes,
+    /// and at least one.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            entries: Vec::with_capacity(capacity),
+            stats: CacheStats {
+                capacity,
+                ..CacheStats::default()
+            },
+        }
+    }
+
+    /// Get the rendered code for `data` and `options`, rendering it if it
+    /// isn't cached yet.
+    ///
+    /// The code is rendered like
+    /// [`try_generate_qr_string_with_options`](crate::try_generate_qr_string_with_options).
+    ///
+    /// Returns an error if generating or rendering the QR code failed.
+    pub fn get_or_render<D: ToQrPayload>(
+        &mut self,
+        data: D,
+        options: &QrOptions,
+    ) -> Result<&str, Error> {
+        let payload = data.to_qr_payload();
+        let terminal_size = match options.style {
+            Style::Auto | Style::Smooth if !options.deterministic => term::size(),
+            _ => None,
+        };
+        let found = self.entries.iter().position(|entry| {
+            entry.payload == payload
+                && entry.options == *options
+                && entry.terminal_size == terminal_size
+        });
+
+        match found {
+            Some(index) => {
+                self.stats.hits += 1;
+                let entry = self.entries.remove(index);
+                self.entries.push(entry);
+            }
+            None => {
+                self.stats.misses += 1;
+                let rendered = crate::try_generate_qr_string_with_options(&payload, options)?;
+                if self.entries.len() == self.stats.capacity {
+                    self.entries.remove(0);
+                    self.stats.evictions += 1;
+                }
+                self.entries.push(Entry {
+                    payload: payload.into_owned(),
+                    options: options.clone(),
+                    terminal_size,
+                    rendered,
+                });
+            }
+        }
+        Ok(&self.entries.last().expect("entry was just used").rendered)
+    }
+
+    /// Print the rendered code for `data` and `options` to the terminal, see
+    /// [`get_or_render`](Self::get_or_render).
+    ///
+    /// Returns an error if generating, rendering or printing the QR code failed.
+    pub fn print<D: ToQrPayload>(&mut self, data: D, options: &QrOptions) -> Result<(), Error> {
+        let rendered = self.get_or_render(data, &options.for_terminal())?;
+        let mut stdout = io::stdout();
+        stdout.write_all(rendered.as_bytes())?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Statistics of this cache, for tuning its capacity.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            len: self.entries.len(),
+            ..self.stats
+        }
+    }
+
+    /// Drop all rendered codes, keeping the statistics.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Default for QrCache {
+    /// Construct an empty cache keeping at most [`DEFAULT_CAPACITY`] rendered codes.
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::Mode;
+
+    #[test]
+    fn least_recently_used() {
+        let mut cache = QrCache::new(2);
+        let options = QrOptions::new();
+        let a = cache.get_or_render("a", &options).unwrap().to_owned();
+        assert_eq!(
+            a,
+            crate::try_generate_qr_string_with_options("a", &options).unwrap()
+        );
+        cache.get_or_render("b", &options).unwrap();
+        assert_eq!(cache.get_or_render("a", &options).unwrap(), a);
+        // Evicts "b", the least recently used
+        cache.get_or_render("c", &options).unwrap();
+        cache.get_or_render("a", &options).unwrap();
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 2,
+                misses: 3,
+                evictions: 1,
+                len: 2,
+                capacity: 2,
+            }
+        );
+        cache.get_or_render("b", &options).unwrap();
+        assert_eq!(cache.stats().misses, 4);
+        assert_eq!(
+            cache.stats().to_string(),
+            "2 hits, 4 misses, 2 evictions, 2/2 entries"
+        );
+    }
+
+    #[test]
+    fn keyed_by_payload_and_options() {
+        let mut cache = QrCache::default();
+        let options = QrOptions::new();
+        cache.get_or_render("123", &options).unwrap();
+        cache
+            .get_or_render(Payload::new(&b"123"[..]).with_mode(Mode::Byte), &options)
+            .unwrap();
+        cache
+            .get_or_render("123", &options.clone().quiet_zone(1))
+            .unwrap();
+        cache.get_or_render("123", &QrOptions::new()).unwrap();
+        assert_eq!(cache.stats().misses, 3);
+        assert_eq!(cache.stats().hits, 1);
+        assert_eq!(cache.stats().hit_ratio(), 0.25);
+
+        cache.clear();
+        assert_eq!(cache.stats().len, 0);
+        cache.get_or_render("123", &options).unwrap();
+        assert_eq!(cache.stats().misses, 4);
+    }
+
+    #[test]
+    fn wipes_secrets() {
+        let options = QrOptions::new().secret(true);
+        let mut entry = Entry {
+            payload: Payload::new(&b"token"[..]).into_owned(),
+            options,
+            terminal_size: None,
+            rendered: "code".into(),
+        };
+        entry.wipe();
+        assert_eq!(entry.payload.data(), [0; 5]);
+        assert!(entry.rendered.is_empty());
+    }
+
+    #[test]
+    fn minimal_capacity() {
+        let mut cache = QrCache::new(0);
+        let options = QrOptions::new();
+        cache.get_or_render("a", &options).unwrap();
+        cache.get_or_render("a", &options).unwrap();
+        cache.get_or_render("b", &options).unwrap();
+        let stats = cache.stats();
+        assert_eq!(
+            (stats.hits, stats.evictions, stats.len, stats.capacity),
+            (1, 1, 1, 1)
+        );
+        assert_eq!(CacheStats::default().hit_ratio(), 0.0);
+    }
+}
@@ -0,0 +1,530 @@
+//! Scanning codes back from a webcam, to verify transfers end to end.
+//!
+//! Frames are captured through `ffmpeg`, so no extra dependencies are
+//! needed, from Video4Linux devices on Linux, AVFoundation on macOS and
+//! DirectShow on Windows. [`scan`] locates and decodes a code in a captured
+//! [`Image`], and [`receive`] reassembles a [stream](crate::stream) from the
+//! captured frames, acknowledging every chunk to the sender's
+//! [`Scheduler`](crate::stream::Scheduler):
+//!
+//! ```rust,no_run
+//! use std::sync::mpsc;
+//! use qr2term::camera::{self, Camera};
+//!
+//! let (acknowledgements, feedback) = mpsc::channel();
+//! // Hand `feedback` to the sender, for example over a network connection
+//! # drop(feedback);
+//! let mut camera = Camera::open(camera::DEFAULT_DEVICE, 640, 480)?;
+//! let data = camera::receive(&mut camera, &acknowledgements)?;
+//! # Ok::<(), camera::CameraError>(())
+//! ```
+//!
+//! Codes are located by their finder patterns and sampled on a straight
+//! grid, so they need to face the camera, and may be rotated but not
+//! strongly tilted. Both dark codes on light backgrounds and light codes on
+//! dark backgrounds are found.
+
+use std::cmp::Reverse;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io::{self, Read};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::mpsc;
+
+use crate::debug::{self, DecodeError};
+use crate::matrix::Matrix;
+use crate::render::{Color, QrDark, QrLight};
+use crate::stream::{self, Feedback, Receiver};
+use crate::ErrorCategory;
+
+/// Webcam used when none is given: the first Video4Linux device on Linux,
+/// and the first AVFoundation device on macOS. DirectShow devices on Windows
+/// are named, such as `video=Integrated Camera`.
+#[cfg(target_os = "macos")]
+pub const DEFAULT_DEVICE: &str = "0";
+
+/// Webcam used when none is given: the first Video4Linux device on Linux,
+/// and the first AVFoundation device on macOS. DirectShow devices on Windows
+/// are named, such as `video=Integrated Camera`.
+#[cfg(not(target_os = "macos"))]
+pub const DEFAULT_DEVICE: &str = "/dev/video0";
+
+/// Grayscale image, such as a frame captured from a webcam.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    /// Width in pixels.
+    pub width: usize,
+
+    /// Height in pixels.
+    pub height: usize,
+
+    /// Luminance of every pixel, row by row, from black at 0 to white at 255.
+    pub pixels: Vec<u8>,
+}
+
+/// Webcam capturing grayscale frames through `ffmpeg`.
+///
+/// Iterate over the camera to capture frames one after another. Capturing
+/// stops when the camera is dropped.
+#[derive(Debug)]
+pub struct Camera {
+    child: Child,
+    stdout: ChildStdout,
+    width: usize,
+    height: usize,
+}
+
+impl Camera {
+    /// Start capturing from `device` in frames of `width` by `height` pixels,
+    /// see [`DEFAULT_DEVICE`].
+    ///
+    /// Returns an error if `ffmpeg` isn't installed or couldn't be started.
+    pub fn open(device: &str, width: usize, height: usize) -> Result<Self, CameraError> {
+        let input_format = match cfg!(target_os = "macos") {
+            true => "avfoundation",
+            false if cfg!(windows) => "dshow",
+            false => "v4l2",
+        };
+        let size = format!("{}x{}", width, height);
+        let mut child = Command::new("ffmpeg")
+            .args([
+                "-loglevel",
+                "error",
+                "-f",
+                input_format,
+                "-video_size",
+                &size,
+            ])
+            .args(["-i", device, "-f", "rawvideo", "-pix_fmt", "gray", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => CameraError::Unavailable,
+                _ => CameraError::Io(err),
+            })?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        log_event!(debug, "capturing {} frames from {}", size, device);
+        Ok(Self {
+            child,
+            stdout,
+            width,
+            height,
+        })
+    }
+
+    /// Capture the next frame.
+    ///
+    /// Returns an error if capturing stopped, such as for a missing device.
+    pub fn capture(&mut self) -> Result<Image, CameraError> {
+        let mut pixels = vec![0; self.width * self.height];
+        self.stdout.read_exact(&mut pixels)?;
+        Ok(Image {
+            width: self.width,
+            height: self.height,
+            pixels,
+        })
+    }
+}
+
+impl Iterator for Camera {
+    type Item = Result<Image, CameraError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.capture())
+    }
+}
+
+impl Drop for Camera {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Receive a streamed payload from captured `images`, such as of a
+/// [`Camera`], sending [`Feedback`] to the sender through `acknowledgements`.
+///
+/// Every chunk read is acknowledged as [received](Feedback::Received), codes
+/// found but not read, such as in blurry frames, as [missed](Feedback::Missed),
+/// and the whole payload as [complete](Feedback::Complete). Feedback is
+/// dropped once the sender stops listening.
+///
+/// Returns the payload, or an error if capturing stopped before it was
+/// complete.
+pub fn receive<I>(
+    images: I,
+    acknowledgements: &mpsc::Sender<Feedback>,
+) -> Result<Vec<u8>, CameraError>
+where
+    I: IntoIterator<Item = Result<Image, CameraError>>,
+{
+    let mut receiver = Receiver::new();
+    for image in images {
+        let frame = match scan(&image?) {
+            Ok(frame) => frame,
+            Err(DecodeError::Locate) => continue,
+            Err(_) => {
+                let _ = acknowledgements.send(Feedback::Missed);
+                continue;
+            }
+        };
+        match receiver.push(&frame) {
+            Ok(complete) => {
+                if let Some(feedback) = stream::acknowledgement(&frame) {
+                    let _ = acknowledgements.send(feedback);
+                }
+                if complete {
+                    let _ = acknowledgements.send(Feedback::Complete);
+                    return Ok(receiver.data().expect("payload is complete"));
+                }
+            }
+            Err(err) => log_event!(debug, "ignoring scanned frame: {}", err),
+        }
+    }
+    Err(CameraError::Io(io::ErrorKind::UnexpectedEof.into()))
+}
+
+/// Locate and decode the QR code in `image`.
+///
+/// Returns the decoded data, or an error if no code was found or decoding
+/// it failed.
+pub fn scan(image: &Image) -> Result<Vec<u8>, DecodeError> {
+    assert_eq!(image.pixels.len(), image.width * image.height, "image size");
+    let threshold = otsu_threshold(&image.pixels);
+    let mut result = Err(DecodeError::Locate);
+    for dark_is_low in [true, false] {
+        let dark: Vec<bool> = image
+            .pixels
+            .iter()
+            .map(|&pixel| (pixel <= threshold) == dark_is_low)
+            .collect();
+        let bitmap = Bitmap {
+            dark,
+            width: image.width,
+            height: image.height,
+        };
+        if let Some(matrix) = bitmap.sample() {
+            result = debug::decode(&matrix).map(|decoded| decoded.data);
+            if result.is_ok() {
+                break;
+            }
+        }
+    }
+    result
+}
+
+/// Pick the threshold separating dark from light pixels, by Otsu's method.
+fn otsu_threshold(pixels: &[u8]) -> u8 {
+    let mut histogram = [0usize; 256];
+    for &pixel in pixels {
+        histogram[usize::from(pixel)] += 1;
+    }
+    let total = pixels.len() as f64;
+    let sum: f64 = (0..256).map(|i| i as f64 * histogram[i] as f64).sum();
+    let (mut below, mut below_sum) = (0.0, 0.0);
+    let (mut best, mut best_variance) = (0, -1.0);
+    for (level, &count) in histogram.iter().enumerate() {
+        below += count as f64;
+        below_sum += level as f64 * count as f64;
+        let above = total - below;
+        if below == 0.0 || above == 0.0 {
+            continue;
+        }
+        let mean_difference = below_sum / below - (sum - below_sum) / above;
+        let variance = below * above * mean_difference * mean_difference;
+        if variance > best_variance {
+            best = level;
+            best_variance = variance;
+        }
+    }
+    best as u8
+}
+
+/// Thresholded image.
+struct Bitmap {
+    dark: Vec<bool>,
+    width: usize,
+    height: usize,
+}
+
+/// Finder pattern seen by scanning the image, or a cluster of them.
+#[derive(Debug, Clone, Copy)]
+struct Finder {
+    x: f64,
+    y: f64,
+    module: f64,
+    hits: usize,
+}
+
+impl Bitmap {
+    fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.dark[y * self.width + x]
+    }
+
+    /// Sample the modules of the code between the three most prominent
+    /// finder patterns.
+    fn sample(&self) -> Option<Matrix<Color>> {
+        let mut finders = self.finders();
+        if finders.len() < 3 {
+            return None;
+        }
+        finders.sort_by_key(|finder| Reverse(finder.hits));
+        finders.truncate(3);
+
+        // The corner finder is opposite the longest side
+        let distance = |a: &Finder, b: &Finder| (a.x - b.x).hypot(a.y - b.y);
+        let corner = (0..3)
+            .max_by(|&a, &b| {
+                let side = |i: usize| distance(&finders[(i + 1) % 3], &finders[(i + 2) % 3]);
+                side(a).total_cmp(&side(b))
+            })
+            .expect("three finders");
+        let (a, b, c) = (
+            finders[corner],
+            finders[(corner + 1) % 3],
+            finders[(corner + 2) % 3],
+        );
+
+        let module = (a.module + b.module + c.module) / 3.0;
+        let span = (distance(&a, &b) + distance(&a, &c)) / 2.0 / module;
+        let version = ((span - 10.0) / 4.0).round().clamp(1.0, 40.0) as usize;
+        let size = 17 + 4 * version;
+
+        // Finder centers are 3.5 modules in from the corners of the code
+        let between = (size - 7) as f64;
+        let mut pixels = Vec::with_capacity(size * size);
+        for row in 0..size {
+            for col in 0..size {
+                let u = (col as f64 + 0.5 - 3.5) / between;
+                let v = (row as f64 + 0.5 - 3.5) / between;
+                let x = a.x + u * (b.x - a.x) + v * (c.x - a.x);
+                let y = a.y + u * (b.y - a.y) + v * (c.y - a.y);
+                let dark = x >= 0.0
+                    && y >= 0.0
+                    && (x as usize) < self.width
+                    && (y as usize) < self.height
+                    && self.is_dark(x as usize, y as usize);
+                pixels.push(if dark { QrDark } else { QrLight });
+            }
+        }
+        Some(Matrix::new(pixels))
+    }
+
+    /// Find finder patterns, with their dark, light, dark, light, dark runs
+    /// in the ratio 1:1:3:1:1, horizontally and vertically.
+    fn finders(&self) -> Vec<Finder> {
+        let mut finders: Vec<Finder> = Vec::new();
+        for y in 0..self.height {
+            let row = (0..self.width).map(|x| self.is_dark(x, y));
+            for (start, runs) in pattern_runs(row) {
+                let module = match finder_module(&runs) {
+                    Some(module) => module,
+                    None => continue,
+                };
+                let x = start + runs[0] + runs[1] + runs[2] / 2;
+                let (y, vertical_module) = match self.cross_check(x, y) {
+                    Some(center) => center,
+                    None => continue,
+                };
+                let seen = Finder {
+                    x: x as f64 + 0.5,
+                    y,
+                    module: (module + vertical_module) / 2.0,
+                    hits: 1,
+                };
+                match finders.iter_mut().find(|finder| {
+                    (finder.x - seen.x).abs() < finder.module * 2.0
+                        && (finder.y - seen.y).abs() < finder.module * 2.0
+                }) {
+                    Some(finder) => {
+                        let hits = finder.hits as f64;
+                        finder.x = (finder.x * hits + seen.x) / (hits + 1.0);
+                        finder.y = (finder.y * hits + seen.y) / (hits + 1.0);
+                        finder.module = (finder.module * hits + seen.module) / (hits + 1.0);
+                        finder.hits += 1;
+                    }
+                    None => finders.push(seen),
+                }
+            }
+        }
+        finders
+    }
+
+    /// Check for a finder pattern in column `x` whose center run includes
+    /// row `y`, returning its vertical center and module size.
+    fn cross_check(&self, x: usize, y: usize) -> Option<(f64, f64)> {
+        let column = (0..self.height).map(|y| self.is_dark(x, y));
+        pattern_runs(column).into_iter().find_map(|(top, runs)| {
+            let center = top + runs[0] + runs[1];
+            match (center..center + runs[2]).contains(&y) {
+                true => Some((center as f64 + runs[2] as f64 / 2.0, finder_module(&runs)?)),
+                false => None,
+            }
+        })
+    }
+}
+
+/// Iterate over every five consecutive runs of a line of pixels starting with
+/// a dark run, as the position of the first and the run lengths.
+fn pattern_runs(line: impl Iterator<Item = bool>) -> Vec<(usize, [usize; 5])> {
+    let mut runs: Vec<(bool, usize, usize)> = Vec::new();
+    for (i, dark) in line.enumerate() {
+        match runs.last_mut() {
+            Some((color, _, len)) if *color == dark => *len += 1,
+            _ => runs.push((dark, i, 1)),
+        }
+    }
+    runs.windows(5)
+        .filter(|window| window[0].0)
+        .map(|window| (window[0].1, [0, 1, 2, 3, 4].map(|j| window[j].2)))
+        .collect()
+}
+
+/// Get the module size of runs in the ratio 1:1:3:1:1 of a finder pattern,
+/// or `None` if they are in another ratio.
+fn finder_module(runs: &[usize; 5]) -> Option<f64> {
+    let module = runs.iter().sum::<usize>() as f64 / 7.0;
+    let tolerance = module / 2.0;
+    let matches = runs
+        .iter()
+        .zip([1.0, 1.0, 3.0, 1.0, 1.0])
+        .all(|(&len, ratio)| (len as f64 - ratio * module).abs() < ratio * tolerance);
+    (module >= 1.0 && matches).then_some(module)
+}
+
+/// Error returned when capturing from a webcam failed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CameraError {
+    /// `ffmpeg` isn't installed.
+    Unavailable,
+
+    /// Capturing frames failed, or stopped.
+    Io(io::Error),
+}
+
+impl CameraError {
+    /// Get the category of this error, for branching on the kind of failure.
+    ///
+    /// A missing `ffmpeg` is an unsupported terminal, like a missing
+    /// clipboard tool.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            CameraError::Unavailable => ErrorCategory::UnsupportedTerminal,
+            CameraError::Io(err) => err.into(),
+        }
+    }
+}
+
+impl Display for CameraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CameraError::Unavailable => f.write_str("ffmpeg not found to capture from webcam"),
+            CameraError::Io(err) => write!(f, "failed to capture from webcam: {}", err),
+        }
+    }
+}
+
+impl Error for CameraError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CameraError::Unavailable => None,
+            CameraError::Io(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for CameraError {
+    fn from(err: io::Error) -> Self {
+        CameraError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qr::Qr;
+
+    /// Draw `data` as a code of `module` pixels, rotated by `angle` radians
+    /// around the center of an image of `width` pixels, with the quiet zone
+    /// and background in `light` and modules in `dark`.
+    fn photograph(
+        data: &[u8],
+        width: usize,
+        module: f64,
+        angle: f64,
+        light: u8,
+        dark: u8,
+    ) -> Image {
+        let matrix = Qr::from(data).unwrap().to_matrix();
+        let size = matrix.size() as f64;
+        let center = width as f64 / 2.0;
+        let (sin, cos) = angle.sin_cos();
+        let pixels = (0..width * width)
+            .map(|i| {
+                let (x, y) = ((i % width) as f64 - center, (i / width) as f64 - center);
+                let col = ((x * cos + y * sin) / module + size / 2.0).floor();
+                let row = ((-x * sin + y * cos) / module + size / 2.0).floor();
+                let inside = (0.0..size).contains(&col) && (0.0..size).contains(&row);
+                match inside
+                    && matrix.pixels()[row as usize * matrix.size() + col as usize] == QrDark
+                {
+                    true => dark,
+                    false => light,
+                }
+            })
+            .collect();
+        Image {
+            width,
+            height: width,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn scans_photographs() {
+        let image = photograph(b"qr2term", 200, 5.0, 0.0, 230, 20);
+        assert_eq!(scan(&image).unwrap(), b"qr2term");
+        let image = photograph(b"https://example.com/camera", 300, 6.5, 0.2, 200, 60);
+        assert_eq!(scan(&image).unwrap(), b"https://example.com/camera");
+        let image = photograph(b"inverted", 200, 5.0, -0.1, 10, 240);
+        assert_eq!(scan(&image).unwrap(), b"inverted");
+
+        let blank = Image {
+            width: 50,
+            height: 50,
+            pixels: vec![128; 2500],
+        };
+        assert_eq!(scan(&blank), Err(DecodeError::Locate));
+    }
+
+    #[test]
+    fn receives_stream() {
+        let data = b"payload sent over the air gap";
+        let frames = stream::sequence(data, 12);
+        let images = frames
+            .iter()
+            .cycle()
+            .skip(1)
+            .take(10)
+            .map(|frame| Ok(photograph(frame, 240, 4.0, 0.05, 220, 30)));
+        let (acknowledgements, feedback) = mpsc::channel();
+        assert_eq!(receive(images, &acknowledgements).unwrap(), data);
+        let feedback: Vec<_> = feedback.try_iter().collect();
+        assert_eq!(
+            feedback,
+            [
+                Feedback::Received(1),
+                Feedback::Received(2),
+                Feedback::Received(0),
+                Feedback::Complete
+            ]
+        );
+
+        let (acknowledgements, _) = mpsc::channel();
+        let err = receive(vec![], &acknowledgements).unwrap_err();
+        assert_eq!(err.category(), ErrorCategory::Io);
+    }
+}
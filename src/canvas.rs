@@ -0,0 +1,352 @@
+//! Compositing codes with other ANSI art, such as logos and banners.
+//!
+//! A [`Canvas`] is a grid of character cells, each with its own colors and
+//! attributes. Blocks of pre-rendered text with ANSI escape sequences, and QR
+//! codes, are drawn onto it at absolute positions, later blocks covering
+//! earlier ones. The composed screen is then printed at once, so branded
+//! landing screens show up whole rather than block by block:
+//!
+//! ```rust
+//! use qr2term::{canvas::Canvas, QrOptions};
+//!
+//! let mut canvas = Canvas::new();
+//! canvas.draw(2, 0, "\x1B[1;34mACME\x1B[0m Corp");
+//! canvas.draw_qr(0, 1, "https://acme.example/", &QrOptions::new()).unwrap();
+//! canvas.draw(52, 4, "Scan to get started");
+//! canvas.print().unwrap();
+//! ```
+//!
+//! Every character takes a single column, so blocks holding wide characters,
+//! such as CJK text or emoji, end up misaligned.
+
+use std::fmt::{self, Display, Write as _};
+use std::io::{self, Write};
+
+use crate::{queue, Error, QrOptions, ToQrPayload};
+
+/// A color set through SGR escape sequences.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum SgrColor {
+    /// One of the 16 basic colors, with their own sequences.
+    Basic(u8),
+
+    /// A 256-color palette index.
+    Indexed(u8),
+
+    /// A 24-bit color.
+    Rgb([u8; 3]),
+}
+
+impl SgrColor {
+    /// Parse an extended `38` or `48` color from the parameters after it.
+    fn extended<I: Iterator<Item = u16>>(params: &mut I) -> Option<Self> {
+        let mut byte = || params.next().map(|param| param.min(255) as u8);
+        match byte()? {
+            5 => Some(SgrColor::Indexed(byte()?)),
+            2 => Some(SgrColor::Rgb([byte()?, byte()?, byte()?])),
+            _ => None,
+        }
+    }
+
+    /// Write the parameters selecting this color, as foreground color if
+    /// `fg`, or as background color otherwise.
+    fn write_params(self, f: &mut String, fg: bool) {
+        let base = if fg { 30 } else { 40 };
+        let _ = match self {
+            SgrColor::Basic(color @ 0..=7) => write!(f, ";{}", base + color),
+            SgrColor::Basic(color) => write!(f, ";{}", base + 60 + color - 8),
+            SgrColor::Indexed(color) => write!(f, ";{};5;{}", base + 8, color),
+            SgrColor::Rgb([r, g, b]) => write!(f, ";{};2;{};{};{}", base + 8, r, g, b),
+        };
+    }
+}
+
+/// Colors and attributes of a cell, as set through SGR escape sequences.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+struct Sgr {
+    /// Foreground color, or `None` for the terminal's default.
+    fg: Option<SgrColor>,
+
+    /// Background color, or `None` for the terminal's default.
+    bg: Option<SgrColor>,
+
+    /// Attributes, such as bold and underline, bit `n` for SGR parameter `n`.
+    attrs: u16,
+}
+
+impl Sgr {
+    /// Apply the `;` separated parameters of an SGR escape sequence.
+    fn apply(&mut self, params: &str) {
+        // Empty parameters default to 0
+        let mut params = params.split(';').map(|param| param.parse().unwrap_or(0));
+        while let Some(param) = params.next() {
+            match param {
+                0 => *self = Sgr::default(),
+                1..=9 => self.attrs |= 1 << param,
+                22 => self.attrs &= !(1 << 1 | 1 << 2),
+                25 => self.attrs &= !(1 << 5 | 1 << 6),
+                23 | 24 | 27..=29 => self.attrs &= !(1 << (param - 20)),
+                30..=37 => self.fg = Some(SgrColor::Basic(param as u8 - 30)),
+                90..=97 => self.fg = Some(SgrColor::Basic(param as u8 - 90 + 8)),
+                38 => self.fg = SgrColor::extended(&mut params),
+                39 => self.fg = None,
+                40..=47 => self.bg = Some(SgrColor::Basic(param as u8 - 40)),
+                100..=107 => self.bg = Some(SgrColor::Basic(param as u8 - 100 + 8)),
+                48 => self.bg = SgrColor::extended(&mut params),
+                49 => self.bg = None,
+                _ => {}
+            }
+        }
+    }
+
+    /// The escape sequence switching from any state to this one.
+    fn sequence(&self) -> String {
+        let mut sequence = String::from("\x1B[0");
+        for attr in (1..=9).filter(|attr| self.attrs & 1 << attr != 0) {
+            let _ = write!(sequence, ";{}", attr);
+        }
+        if let Some(fg) = self.fg {
+            fg.write_params(&mut sequence, true);
+        }
+        if let Some(bg) = self.bg {
+            bg.write_params(&mut sequence, false);
+        }
+        sequence.push('m');
+        sequence
+    }
+}
+
+/// A character cell of a canvas.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct Cell {
+    /// The character shown.
+    ch: char,
+
+    /// Colors and attributes of the character.
+    style: Sgr,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            ch: ' ',
+            style: Sgr::default(),
+        }
+    }
+}
+
+/// A grid of character cells to compose codes and other ANSI art on.
+///
+/// The canvas starts empty and grows to fit the blocks drawn on it. Cells
+/// nothing was drawn on are blank, in the terminal's default colors.
+/// Displaying the canvas gives the composed text, with every row ending in
+/// a line break.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Canvas {
+    /// Rows of cells, each as long as the last cell drawn on it.
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Canvas {
+    /// Construct an empty canvas.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Width of the canvas, in columns.
+    pub fn width(&self) -> usize {
+        self.rows.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Height of the canvas, in rows.
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Draw a `block` of text with its top left corner at column `x` of row
+    /// `y`, covering whatever was drawn there before.
+    ///
+    /// Colors and attributes set through SGR escape sequences apply to the
+    /// characters after them, starting from the default at the start of the
+    /// block. Every line of the block starts at column `x`, and spaces cover
+    /// cells like other characters do. Other escape sequences and control
+    /// characters are left out.
+    pub fn draw(&mut self, x: usize, y: usize, block: &str) {
+        let mut style = Sgr::default();
+        let (mut col, mut row) = (x, y);
+        let mut chars = block.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\x1B' => match chars.next() {
+                    // CSI: parameters up to a final byte
+                    Some('[') => {
+                        let mut params = String::new();
+                        for ch in chars.by_ref() {
+                            match ch {
+                                '\x40'..='\x7E' => {
+                                    if ch == 'm' {
+                                        style.apply(&params);
+                                    }
+                                    break;
+                                }
+                                _ => params.push(ch),
+                            }
+                        }
+                    }
+                    // OSC: up to BEL or ST
+                    Some(']') => {
+                        while let Some(ch) = chars.next() {
+                            match ch {
+                                '\x07' => break,
+                                '\x1B' => {
+                                    chars.next();
+                                    break;
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                    // Intermediate bytes up to a final byte, such as DECDHL
+                    Some(' '..='/') => {
+                        chars.find(|ch| !(' '..='/').contains(ch));
+                    }
+                    _ => {}
+                },
+                '\n' => {
+                    col = x;
+                    row += 1;
+                }
+                '\r' => col = x,
+                ch if ch.is_control() => {}
+                ch => {
+                    self.put(col, row, Cell { ch, style });
+                    col += 1;
+                }
+            }
+        }
+    }
+
+    /// Draw `data` as QR code with its top left corner at column `x` of row
+    /// `y`, using the given `options`.
+    ///
+    /// The quiet zone covers what was drawn below it too. Data that doesn't
+    /// fit a single QR code is an error, regardless of
+    /// [`QrOptions::on_overflow`].
+    ///
+    /// Returns an error if generating or rendering the QR code failed.
+    pub fn draw_qr<D: ToQrPayload>(
+        &mut self,
+        x: usize,
+        y: usize,
+        data: D,
+        options: &QrOptions,
+    ) -> Result<(), Error> {
+        let matrix = crate::code_matrix(&crate::generate_code(data, options)?, options)?;
+        let mut buf = Vec::new();
+        crate::renderer(options).render(&matrix, &mut buf)?;
+        self.draw(x, y, &String::from_utf8_lossy(&buf));
+        Ok(())
+    }
+
+    /// Print the composed canvas to the terminal, in a single write.
+    ///
+    /// Waits for the turn to print through the [print queue](crate::queue),
+    /// so output of other threads printing through it doesn't end up in
+    /// between.
+    ///
+    /// Returns an error if writing to the terminal failed.
+    pub fn print(&self) -> Result<(), Error> {
+        let text = self.to_string();
+        let mut terminal = queue::lock_terminal();
+        terminal.write_all(text.as_bytes())?;
+        Ok(terminal.flush()?)
+    }
+
+    /// Render the composed canvas to the given writer, in a single write.
+    ///
+    /// Returns an error if writing failed.
+    pub fn render<W: Write>(&self, target: &mut W) -> io::Result<()> {
+        target.write_all(self.to_string().as_bytes())
+    }
+
+    /// Set the cell at column `x` of row `y`, growing the canvas to hold it.
+    fn put(&mut self, x: usize, y: usize, cell: Cell) {
+        if self.rows.len() <= y {
+            self.rows.resize_with(y + 1, Vec::new);
+        }
+        let row = &mut self.rows[y];
+        if row.len() <= x {
+            row.resize(x + 1, Cell::default());
+        }
+        row[x] = cell;
+    }
+}
+
+impl Display for Canvas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let default = Sgr::default();
+        for row in &self.rows {
+            let mut style = default;
+            for cell in row {
+                if cell.style != style {
+                    f.write_str(&cell.style.sequence())?;
+                    style = cell.style;
+                }
+                f.write_char(cell.ch)?;
+            }
+            if style != default {
+                f.write_str(&default.sequence())?;
+            }
+            f.write_char('\n')?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Style;
+
+    #[test]
+    fn overlapping_blocks() {
+        let mut canvas = Canvas::new();
+        canvas.draw(0, 0, "#####\n#####\n#####");
+        canvas.draw(2, 1, "\x1B[1;31mAB\x1B[22m\r\nC\x1B]8;;x\x07\x1B#3D");
+        canvas.draw(7, 3, "E");
+        assert_eq!((canvas.width(), canvas.height()), (8, 4));
+        assert_eq!(
+            canvas.to_string(),
+            "#####\n\
+             ##\x1B[0;1;31mAB\x1B[0m#\n\
+             ##\x1B[0;31mCD\x1B[0m#\n\
+             \x20      E\n"
+        );
+    }
+
+    #[test]
+    fn colors() {
+        let mut sgr = Sgr::default();
+        sgr.apply("4;92;48;5;208");
+        assert_eq!(sgr.sequence(), "\x1B[0;4;92;48;5;208m");
+        sgr.apply("24;38;2;1;2;3;49");
+        assert_eq!(sgr.sequence(), "\x1B[0;38;2;1;2;3m");
+        sgr.apply("");
+        assert_eq!(sgr, Sgr::default());
+    }
+
+    #[test]
+    fn qr_at_offset() {
+        let options = QrOptions::new().style(Style::Ascii);
+        let mut canvas = Canvas::new();
+        canvas.draw(0, 0, "header");
+        canvas.draw_qr(3, 1, "qr2term", &options).unwrap();
+        let code = crate::try_generate_qr_string_with_options("qr2term", &options).unwrap();
+        let text = canvas.to_string();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("header"));
+        for (line, code) in lines.zip(code.lines()) {
+            assert_eq!(line, format!("   {}", code));
+        }
+    }
+}
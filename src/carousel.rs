@@ -0,0 +1,264 @@
+//! Cycling through several codes in the same screen region.
+//!
+//! Signs and terminals often link to the same thing in different ways, such
+//! as an app store link for iOS, one for Android, and a web link, and have
+//! room for only one code. A [`Carousel`] shows them in turn, each for an
+//! [`interval`](Carousel::interval), with a label telling which one is
+//! showing:
+//!
+//! ```rust
+//! use std::time::{Duration, Instant};
+//!
+//! use qr2term::carousel::Carousel;
+//!
+//! let mut carousel = Carousel::new()
+//!     .entry("iOS", "https://apps.apple.com/app/id0000000000")
+//!     .entry("Android", "https://play.google.com/store/apps/details?id=com.example")
+//!     .entry("Web", "https://example.com/")
+//!     .interval(Duration::from_millis(100));
+//! let start = Instant::now();
+//! carousel.run(|| start.elapsed() > Duration::from_millis(300)).unwrap();
+//! ```
+
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::region::RenderHandle;
+use crate::{default_options, payload, Error, QrOptions};
+
+/// Time every code is shown for by default.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Longest time between checks whether to stop a running carousel.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Codes shown in turn in the same screen region.
+///
+/// Built with [`entry`](Self::entry) for every code, and shown with
+/// [`run`](Self::run), or stepped through by the application with
+/// [`show_to`](Self::show_to).
+#[derive(Debug, Clone)]
+pub struct Carousel {
+    /// Label and payload of every code.
+    entries: Vec<(String, String)>,
+
+    /// Time every code is shown for.
+    interval: Duration,
+
+    /// Options to render the codes with.
+    options: QrOptions,
+
+    /// Index of the code showing, if any.
+    current: Option<usize>,
+
+    /// Region of the code showing.
+    handle: Option<RenderHandle>,
+}
+
+impl Carousel {
+    /// Construct an empty carousel, showing every code for the
+    /// [`DEFAULT_INTERVAL`], using the process wide [`default_options`].
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            interval: DEFAULT_INTERVAL,
+            options: default_options(),
+            current: None,
+            handle: None,
+        }
+    }
+
+    /// Add a code for `payload`, labeled `label`.
+    pub fn entry<L: Into<String>, P: Into<String>>(mut self, label: L, payload: P) -> Self {
+        self.entries.push((label.into(), payload.into()));
+        self
+    }
+
+    /// Set the time every code is shown for.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set the options to render the codes with.
+    pub fn options(mut self, options: QrOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Number of codes in the carousel.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the carousel has no codes.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Index of the code showing, if any.
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// Show the codes in turn in the terminal until `stop` returns `true`,
+    /// see [`run_to`](Self::run_to).
+    ///
+    /// Returns an error if generating a QR code or writing to the terminal
+    /// failed.
+    pub fn run<F: FnMut() -> bool>(&mut self, stop: F) -> Result<(), Error> {
+        self.run_to(&mut io::stdout(), stop)
+    }
+
+    /// Show the codes in turn in the terminal the given writer writes to,
+    /// until `stop` returns `true`.
+    ///
+    /// Starts with the code after the one showing, or the first one, and
+    /// switches codes every [`interval`](Self::interval). `stop` is checked
+    /// several times a second. The last code is left showing, to
+    /// [erase](Self::erase_to) or show again later. Returns right away if the
+    /// carousel is empty.
+    ///
+    /// Returns an error if generating a QR code or writing failed.
+    pub fn run_to<W: Write, F: FnMut() -> bool>(
+        &mut self,
+        target: &mut W,
+        mut stop: F,
+    ) -> Result<(), Error> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        while !stop() {
+            let next = self.current.map_or(0, |current| (current + 1) % self.len());
+            self.show_to(next, target)?;
+
+            let shown = Instant::now();
+            while shown.elapsed() < self.interval {
+                if stop() {
+                    return Ok(());
+                }
+                thread::sleep(POLL_INTERVAL.min(self.interval.saturating_sub(shown.elapsed())));
+            }
+        }
+        Ok(())
+    }
+
+    /// Show the code at `index` in the terminal the given writer writes to,
+    /// in place of the code showing.
+    ///
+    /// The code is labeled with its label and position, such as
+    /// `Android (2/3)`, centered above it.
+    ///
+    /// Returns an error if generating the QR code or writing failed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn show_to<W: Write>(&mut self, index: usize, target: &mut W) -> Result<(), Error> {
+        let (label, payload) = &self.entries[index];
+        let mut code = Vec::new();
+        let result = crate::render_qr(payload, &self.options, &mut code).and_then(|()| {
+            let caption = format!("{} ({}/{})", label, index + 1, self.len());
+            let width = RenderHandle::measure(&code).columns();
+            let indent = width.saturating_sub(caption.chars().count()) / 2;
+            let mut frame = format!("{:indent$}{}\n", "", caption, indent = indent).into_bytes();
+            frame.extend_from_slice(&code);
+
+            let mut buf = self
+                .handle
+                .map_or_else(String::new, |handle| handle.erase_sequence())
+                .into_bytes();
+            buf.extend_from_slice(&frame);
+            let result = target.write_all(&buf).and_then(|()| target.flush());
+            if self.options.secret {
+                payload::wipe(&mut frame);
+                payload::wipe(&mut buf);
+            }
+            result?;
+            Ok(RenderHandle::measure(&frame))
+        });
+        if self.options.secret {
+            payload::wipe(&mut code);
+        }
+        self.handle = Some(result?);
+        self.current = Some(index);
+        Ok(())
+    }
+
+    /// Erase the code showing from the terminal the given writer writes to.
+    ///
+    /// Returns an error if writing failed.
+    pub fn erase_to<W: Write>(&mut self, target: &mut W) -> Result<(), Error> {
+        if let Some(mut handle) = self.handle.take() {
+            handle.erase_to(target)?;
+        }
+        self.current = None;
+        Ok(())
+    }
+}
+
+impl Default for Carousel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Style;
+
+    fn carousel() -> Carousel {
+        Carousel::new()
+            .entry("iOS", "ios")
+            .entry("Android", "android")
+            .options(QrOptions::new().style(Style::Ascii))
+            .interval(Duration::ZERO)
+    }
+
+    #[test]
+    fn labeled_in_place() {
+        let mut carousel = carousel();
+        let mut buf = Vec::new();
+        carousel.show_to(1, &mut buf).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert_eq!(
+            text.lines().next(),
+            Some(&*format!("{:18}Android (2/2)", ""))
+        );
+        let code = crate::try_generate_qr_string_with_options("android", &carousel.options);
+        assert!(text.ends_with(&code.unwrap()));
+
+        let mut buf = Vec::new();
+        carousel.show_to(0, &mut buf).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.starts_with("\r\x1B[26A\x1B[J"));
+        assert!(text.contains("iOS (1/2)\n"));
+
+        let mut buf = Vec::new();
+        carousel.erase_to(&mut buf).unwrap();
+        assert_eq!(buf, b"\r\x1B[26A\x1B[J");
+        assert_eq!(carousel.current(), None);
+    }
+
+    #[test]
+    fn runs_until_stopped() {
+        let mut carousel = carousel();
+        let mut buf = Vec::new();
+        let mut checks = 0;
+        carousel
+            .run_to(&mut buf, || {
+                checks += 1;
+                checks > 5
+            })
+            .unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert_eq!(text.matches("iOS (1/2)").count(), 3);
+        assert_eq!(text.matches("Android (2/2)").count(), 2);
+        assert_eq!(carousel.current(), Some(0));
+
+        let mut empty = Carousel::new();
+        empty.run_to(&mut Vec::new(), || false).unwrap();
+    }
+}
@@ -0,0 +1,280 @@
+//! Character sets to transcode text into, declared through ECI.
+//!
+//! Text is encoded as UTF-8 by default, which most scanners handle. Some
+//! legacy scanners misinterpret UTF-8 though, and expect text in a specific
+//! single byte character set. [`Charset::payload`] transcodes text into such
+//! a character set, and declares it with an Extended Channel Interpretation
+//! (ECI) designator so the scanner knows how to read the bytes.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::payload::{Mode, Payload};
+
+/// Characters of Windows-1252 bytes `0x80` to `0x9F`, where it differs from
+/// ISO-8859-1. Unassigned bytes are `None`.
+const WINDOWS_1252_HIGH: [Option<char>; 32] = [
+    Some('€'),
+    None,
+    Some('‚'),
+    Some('ƒ'),
+    Some('„'),
+    Some('…'),
+    Some('†'),
+    Some('‡'),
+    Some('ˆ'),
+    Some('‰'),
+    Some('Š'),
+    Some('‹'),
+    Some('Œ'),
+    None,
+    Some('Ž'),
+    None,
+    None,
+    Some('‘'),
+    Some('’'),
+    Some('“'),
+    Some('”'),
+    Some('•'),
+    Some('–'),
+    Some('—'),
+    Some('˜'),
+    Some('™'),
+    Some('š'),
+    Some('›'),
+    Some('œ'),
+    None,
+    Some('ž'),
+    Some('Ÿ'),
+];
+
+/// Bytes of ISO-8859-15 where it differs from ISO-8859-1, along with their characters.
+const ISO_8859_15_CHANGES: [(u8, char); 8] = [
+    (0xA4, '€'),
+    (0xA6, 'Š'),
+    (0xA8, 'š'),
+    (0xB4, 'Ž'),
+    (0xB8, 'ž'),
+    (0xBC, 'Œ'),
+    (0xBD, 'œ'),
+    (0xBE, 'Ÿ'),
+];
+
+/// A character set that text can be transcoded into.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Charset {
+    /// UTF-8, the default for text.
+    Utf8,
+
+    /// US-ASCII, 7-bit only.
+    Ascii,
+
+    /// ISO-8859-1, Latin-1 Western European.
+    Iso8859_1,
+
+    /// ISO-8859-15, Latin-9 Western European, with the euro sign.
+    Iso8859_15,
+
+    /// Windows-1252, Western European.
+    Windows1252,
+}
+
+impl Charset {
+    /// All supported character sets.
+    pub const ALL: [Charset; 5] = [
+        Charset::Utf8,
+        Charset::Ascii,
+        Charset::Iso8859_1,
+        Charset::Iso8859_15,
+        Charset::Windows1252,
+    ];
+
+    /// The name of this character set, as accepted by [`FromStr`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Charset::Utf8 => "utf-8",
+            Charset::Ascii => "ascii",
+            Charset::Iso8859_1 => "iso-8859-1",
+            Charset::Iso8859_15 => "iso-8859-15",
+            Charset::Windows1252 => "windows-1252",
+        }
+    }
+
+    /// The ECI designator that declares this character set.
+    pub fn eci(self) -> u32 {
+        match self {
+            Charset::Utf8 => 26,
+            Charset::Ascii => 27,
+            Charset::Iso8859_1 => 3,
+            Charset::Iso8859_15 => 17,
+            Charset::Windows1252 => 21,
+        }
+    }
+
+    /// Transcode `text` into this character set.
+    ///
+    /// Returns an error for the first character that this character set can't
+    /// represent.
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, EncodeError> {
+        if self == Charset::Utf8 {
+            return Ok(text.as_bytes().to_vec());
+        }
+        text.char_indices()
+            .map(|(index, c)| {
+                self.encode_char(c).ok_or(EncodeError {
+                    charset: self,
+                    character: c,
+                    index,
+                })
+            })
+            .collect()
+    }
+
+    /// Transcode `text` into this character set, as byte mode payload with
+    /// the character set declared through ECI.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use qr2term::charset::Charset;
+    ///
+    /// let payload = Charset::Iso8859_1.payload("Grüße")?;
+    /// assert_eq!(payload.data(), b"Gr\xFC\xDFe");
+    /// assert_eq!(payload.eci(), Some(3));
+    /// qr2term::print_qr(&payload).unwrap();
+    /// # Ok::<(), qr2term::charset::EncodeError>(())
+    /// ```
+    pub fn payload(self, text: &str) -> Result<Payload<'static>, EncodeError> {
+        Ok(Payload::new(self.encode(text)?)
+            .with_mode(Mode::Byte)
+            .with_eci(self.eci()))
+    }
+
+    /// Encode a single character in a single byte character set, if it can
+    /// represent it.
+    fn encode_char(self, c: char) -> Option<u8> {
+        if c.is_ascii() {
+            return Some(c as u8);
+        }
+
+        match self {
+            Charset::Utf8 | Charset::Ascii => None,
+            Charset::Iso8859_1 => u8::try_from(u32::from(c)).ok(),
+            Charset::Iso8859_15 => match ISO_8859_15_CHANGES.iter().find(|(_, r)| *r == c) {
+                Some(&(byte, _)) => Some(byte),
+                None => u8::try_from(u32::from(c))
+                    .ok()
+                    .filter(|byte| ISO_8859_15_CHANGES.iter().all(|(b, _)| b != byte)),
+            },
+            Charset::Windows1252 => match WINDOWS_1252_HIGH.iter().position(|&r| r == Some(c)) {
+                Some(index) => Some(0x80 + index as u8),
+                None => u8::try_from(u32::from(c))
+                    .ok()
+                    .filter(|byte| !(0x80..0xA0).contains(byte)),
+            },
+        }
+    }
+}
+
+impl Display for Charset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Charset {
+    type Err = ParseCharsetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_ascii_lowercase().replace('_', "-");
+        match name.as_str() {
+            "utf8" => Ok(Charset::Utf8),
+            "us-ascii" => Ok(Charset::Ascii),
+            "latin1" | "latin-1" => Ok(Charset::Iso8859_1),
+            "latin9" | "latin-9" => Ok(Charset::Iso8859_15),
+            "cp1252" => Ok(Charset::Windows1252),
+            name => Charset::ALL
+                .into_iter()
+                .find(|charset| charset.name() == name)
+                .ok_or_else(|| ParseCharsetError(s.to_owned())),
+        }
+    }
+}
+
+/// Error returned when parsing an unknown character set name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCharsetError(String);
+
+impl Display for ParseCharsetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown character set '{}'", self.0)
+    }
+}
+
+impl Error for ParseCharsetError {}
+
+/// Error returned when text can't be represented in a character set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodeError {
+    /// The character set that was encoded into.
+    pub charset: Charset,
+
+    /// The first character that can't be represented.
+    pub character: char,
+
+    /// The byte index of the character in the text.
+    pub index: usize,
+}
+
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "character '{}' at {} can't be represented in {}",
+            self.character, self.index, self.charset
+        )
+    }
+}
+
+impl Error for EncodeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qr::Qr;
+    use qrcode::EcLevel;
+
+    #[test]
+    fn encode() {
+        assert_eq!(Charset::Utf8.encode("é€").unwrap(), "é€".as_bytes());
+        assert_eq!(Charset::Iso8859_1.encode("é¤").unwrap(), [0xE9, 0xA4]);
+        assert_eq!(Charset::Iso8859_15.encode("é€").unwrap(), [0xE9, 0xA4]);
+        assert_eq!(Charset::Windows1252.encode("é€").unwrap(), [0xE9, 0x80]);
+
+        let err = Charset::Iso8859_1.encode("a€").unwrap_err();
+        assert_eq!((err.character, err.index), ('€', 1));
+        assert!(Charset::Iso8859_15.encode("¤").is_err());
+        assert!(Charset::Windows1252.encode("\u{81}").is_err());
+        assert!(Charset::Ascii.encode("é").is_err());
+    }
+
+    #[test]
+    fn from_str() {
+        for charset in Charset::ALL {
+            assert_eq!(charset.name().parse(), Ok(charset));
+        }
+        assert_eq!("Latin1".parse(), Ok(Charset::Iso8859_1));
+        assert_eq!("ISO_8859_15".parse(), Ok(Charset::Iso8859_15));
+        assert!("shift-jis".parse::<Charset>().is_err());
+    }
+
+    #[test]
+    fn payload() {
+        let payload = Charset::Windows1252.payload("€5").unwrap();
+        assert_eq!(payload.data(), b"\x805");
+        assert_eq!(payload.eci(), Some(21));
+        assert!(Qr::from_payload(&payload, EcLevel::M).is_ok());
+    }
+}
@@ -0,0 +1,232 @@
+//! Checksum suffixes, for cheap integrity checks on the scanning side.
+//!
+//! A payload gets a suffix of a [`SEPARATOR`] followed by its checksum in
+//! uppercase hexadecimal, such as `hello*3610A686` for a CRC-32, see
+//! [`append_checksum`]. The scanning side checks and strips it with
+//! [`verify_checksum`], catching payloads truncated or garbled on the way,
+//! such as by a scanner in keyboard mode dropping characters.
+//!
+//! Uppercase hexadecimal and the separator are both in the alphanumeric
+//! character set, so payloads encoded in alphanumeric mode stay in it.
+//! Unlike [`sign`](crate::sign), checksums don't need a key, and don't
+//! protect against deliberate tampering.
+//!
+//! Set [`QrOptions::checksum`](crate::QrOptions::checksum) to append one to
+//! every payload before encoding it.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::crypto::sha256::sha256;
+use crate::png::crc32;
+
+/// Separator between the payload and its checksum.
+pub const SEPARATOR: u8 = b'*';
+
+/// Number of leading bytes of the SHA-256 digest kept by [`Checksum::Sha256`].
+pub const SHA256_PREFIX_LEN: usize = 8;
+
+/// Checksum to append to payloads, none by default.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Checksum {
+    /// Don't append a checksum.
+    #[default]
+    None,
+
+    /// The CRC-32 of the payload, as used by PNG and zip, in 8 hex digits.
+    Crc32,
+
+    /// The first [`SHA256_PREFIX_LEN`] bytes of the SHA-256 digest of the
+    /// payload, in 16 hex digits.
+    Sha256,
+}
+
+impl Checksum {
+    /// All checksums.
+    pub const ALL: [Checksum; 3] = [Checksum::None, Checksum::Crc32, Checksum::Sha256];
+
+    /// Name of this checksum, as accepted by its `FromStr` implementation.
+    pub fn name(self) -> &'static str {
+        match self {
+            Checksum::None => "none",
+            Checksum::Crc32 => "crc32",
+            Checksum::Sha256 => "sha256",
+        }
+    }
+
+    /// The checksum of `data`, or `None` for [`Checksum::None`].
+    fn digest(self, data: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            Checksum::None => None,
+            Checksum::Crc32 => Some(crc32(data).to_be_bytes().to_vec()),
+            Checksum::Sha256 => Some(sha256(data)[..SHA256_PREFIX_LEN].to_vec()),
+        }
+    }
+
+    /// The checksum with a suffix of `hex_len` hex digits, if any.
+    fn from_hex_len(hex_len: usize) -> Option<Self> {
+        match hex_len {
+            8 => Some(Checksum::Crc32),
+            16 => Some(Checksum::Sha256),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Checksum {
+    type Err = ParseChecksumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        Checksum::ALL
+            .into_iter()
+            .find(|checksum| checksum.name() == name)
+            .ok_or(ParseChecksumError { name })
+    }
+}
+
+/// Error returned when parsing an unknown [`Checksum`] name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseChecksumError {
+    name: String,
+}
+
+impl Display for ParseChecksumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown checksum '{}'", self.name)
+    }
+}
+
+impl Error for ParseChecksumError {}
+
+/// Append the `checksum` of `data` to it, after a [`SEPARATOR`].
+///
+/// Returns `data` as is for [`Checksum::None`].
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::checksum::{self, Checksum};
+///
+/// let data = checksum::append_checksum(b"hello", Checksum::Crc32);
+/// assert_eq!(data, b"hello*3610A686");
+/// qr2term::print_qr(&data).unwrap();
+///
+/// assert_eq!(checksum::verify_checksum(&data).unwrap(), b"hello");
+/// ```
+pub fn append_checksum(data: &[u8], checksum: Checksum) -> Vec<u8> {
+    let digest = match checksum.digest(data) {
+        Some(digest) => digest,
+        None => return data.to_vec(),
+    };
+    let mut suffixed = Vec::with_capacity(data.len() + 1 + 2 * digest.len());
+    suffixed.extend_from_slice(data);
+    suffixed.push(SEPARATOR);
+    for byte in digest {
+        suffixed.extend_from_slice(format!("{:02X}", byte).as_bytes());
+    }
+    suffixed
+}
+
+/// Verify the checksum suffix of `decoded` data, returning the data without it.
+///
+/// The checksum is told by the length of the suffix: 8 hex digits for a
+/// [CRC-32](Checksum::Crc32), 16 for a [SHA-256 prefix](Checksum::Sha256).
+/// Hex digits are accepted in either case.
+///
+/// Returns an error if the data has no checksum suffix, or if the checksum
+/// doesn't match.
+pub fn verify_checksum(decoded: &[u8]) -> Result<&[u8], ChecksumError> {
+    let split = decoded
+        .iter()
+        .rposition(|&byte| byte == SEPARATOR)
+        .ok_or(ChecksumError::Missing)?;
+    let (data, hex) = (&decoded[..split], &decoded[split + 1..]);
+    let checksum = Checksum::from_hex_len(hex.len()).ok_or(ChecksumError::Missing)?;
+    if !hex.iter().all(u8::is_ascii_hexdigit) {
+        return Err(ChecksumError::Missing);
+    }
+    let expected: Vec<u8> = hex
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap())
+        .collect();
+    match checksum.digest(data) == Some(expected) {
+        true => Ok(data),
+        false => Err(ChecksumError::Mismatch),
+    }
+}
+
+/// Error returned when verifying a checksum suffix failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumError {
+    /// The data doesn't end with a checksum suffix.
+    Missing,
+
+    /// The checksum doesn't match, the data was altered on the way.
+    Mismatch,
+}
+
+impl Display for ChecksumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChecksumError::Missing => f.write_str("missing payload checksum"),
+            ChecksumError::Mismatch => f.write_str("payload checksum mismatch"),
+        }
+    }
+}
+
+impl Error for ChecksumError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        for checksum in [Checksum::Crc32, Checksum::Sha256] {
+            let data = append_checksum(b"ticket*42", checksum);
+            assert_eq!(verify_checksum(&data), Ok(&b"ticket*42"[..]));
+        }
+        let sha256 = append_checksum(b"abc", Checksum::Sha256);
+        assert_eq!(sha256, b"abc*BA7816BF8F01CFEA");
+        assert_eq!(append_checksum(b"abc", Checksum::None), b"abc");
+    }
+
+    #[test]
+    fn garbled() {
+        assert_eq!(
+            verify_checksum(b"hello*3610A687"),
+            Err(ChecksumError::Mismatch)
+        );
+        assert_eq!(
+            verify_checksum(b"hell*3610A686"),
+            Err(ChecksumError::Mismatch)
+        );
+        assert_eq!(verify_checksum(b"hello*3610a686"), Ok(&b"hello"[..]));
+        assert_eq!(verify_checksum(b"hello"), Err(ChecksumError::Missing));
+        assert_eq!(
+            verify_checksum(b"hello*3610A68"),
+            Err(ChecksumError::Missing)
+        );
+        assert_eq!(
+            verify_checksum(b"hello*3610A68G"),
+            Err(ChecksumError::Missing)
+        );
+    }
+
+    #[test]
+    fn parse() {
+        for checksum in Checksum::ALL {
+            assert_eq!(checksum.name().parse(), Ok(checksum));
+        }
+        assert_eq!(" CRC32 ".parse(), Ok(Checksum::Crc32));
+        assert!("md5".parse::<Checksum>().is_err());
+    }
+}
@@ -0,0 +1,299 @@
+//! Copying rendered QR codes to the system clipboard.
+//!
+//! The clipboard is accessed through the platform's command line tools, so no
+//! extra dependencies are needed: `wl-copy` on Wayland, `xclip` or `xsel` on
+//! X11, `pbcopy` on macOS and `clip` on Windows. Images can only be copied
+//! through `wl-copy` and `xclip`.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use crate::render::Renderer;
+use crate::{default_options, png, reserve, ErrorCategory, QrError, QrOptions, ToQrPayload};
+
+/// Size of a module in copied PNG images, in pixels.
+pub const PNG_MODULE_SIZE: usize = 8;
+
+/// Format to copy a rendered code in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Format {
+    /// Text without colors, for pasting into documents.
+    #[default]
+    Plain,
+
+    /// Text with ANSI colors, for pasting into terminals.
+    Ansi,
+
+    /// A PNG image, see [`PNG_MODULE_SIZE`].
+    Png,
+}
+
+impl Format {
+    /// Name of this format, as accepted by its `FromStr` implementation.
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Plain => "plain",
+            Format::Ansi => "ansi",
+            Format::Png => "png",
+        }
+    }
+
+    /// The MIME type of this format.
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Format::Plain | Format::Ansi => "text/plain;charset=utf-8",
+            Format::Png => "image/png",
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Format {
+    type Err = ParseFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        [Format::Plain, Format::Ansi, Format::Png]
+            .into_iter()
+            .find(|format| format.name() == name)
+            .ok_or(ParseFormatError { name })
+    }
+}
+
+/// Error returned when parsing an unknown [`Format`] name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFormatError {
+    name: String,
+}
+
+impl Display for ParseFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown clipboard format '{}'", self.name)
+    }
+}
+
+impl Error for ParseFormatError {}
+
+/// Error returned when copying a QR code to the clipboard failed.
+#[derive(Debug)]
+pub enum ClipboardError {
+    /// Generating the QR code failed.
+    Qr(QrError),
+
+    /// Running a clipboard tool failed.
+    Io(io::Error),
+
+    /// No clipboard tool supporting the format was found.
+    Unavailable(Format),
+
+    /// A [validator](crate::lint::Validator) rejected the payload, for the
+    /// given reason.
+    Rejected(String),
+}
+
+impl ClipboardError {
+    /// Get the category of this error, for branching on the kind of failure.
+    ///
+    /// A missing clipboard tool is an unsupported terminal.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            ClipboardError::Qr(err) => err.into(),
+            ClipboardError::Io(err) => err.into(),
+            ClipboardError::Unavailable(_) => ErrorCategory::UnsupportedTerminal,
+            ClipboardError::Rejected(_) => ErrorCategory::InvalidPayload,
+        }
+    }
+}
+
+impl Display for ClipboardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClipboardError::Qr(err) => write!(f, "failed to generate QR code: {}", err),
+            ClipboardError::Io(err) => write!(f, "failed to copy to clipboard: {}", err),
+            ClipboardError::Unavailable(format) => {
+                write!(f, "no clipboard tool found to copy {} format", format)
+            }
+            ClipboardError::Rejected(reason) => f.write_str(reason),
+        }
+    }
+}
+
+impl Error for ClipboardError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ClipboardError::Qr(err) => Some(err),
+            ClipboardError::Io(err) => Some(err),
+            ClipboardError::Unavailable(_) | ClipboardError::Rejected(_) => None,
+        }
+    }
+}
+
+impl From<QrError> for ClipboardError {
+    fn from(err: QrError) -> Self {
+        ClipboardError::Qr(err)
+    }
+}
+
+impl From<io::Error> for ClipboardError {
+    fn from(err: io::Error) -> Self {
+        ClipboardError::Io(err)
+    }
+}
+
+impl From<crate::Error> for ClipboardError {
+    fn from(err: crate::Error) -> Self {
+        match err {
+            crate::Error::Qr(err) => ClipboardError::Qr(err),
+            crate::Error::Io(err) => ClipboardError::Io(err),
+            crate::Error::Rejected(reason) => ClipboardError::Rejected(reason),
+            crate::Error::Reserve(err) => ClipboardError::Rejected(err.to_string()),
+            err => ClipboardError::Io(io::Error::new(io::ErrorKind::Other, err.to_string())),
+        }
+    }
+}
+
+/// Copy the given `data` as QR code to the clipboard, in the given `format`.
+///
+/// Uses the process wide [`default_options`].
+///
+/// Returns an error if generating the QR code or copying failed.
+pub fn copy_to_clipboard<D: ToQrPayload>(data: D, format: Format) -> Result<(), ClipboardError> {
+    copy_to_clipboard_with_options(data, &default_options(), format)
+}
+
+/// Copy the given `data` as QR code to the clipboard, in the given `format`,
+/// using the given `options`.
+///
+/// Returns an error if generating the QR code or copying failed.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use qr2term::clipboard::{self, Format};
+/// use qr2term::QrOptions;
+///
+/// clipboard::copy_to_clipboard_with_options("https://rust-lang.org/", &QrOptions::new(), Format::Png)
+///     .unwrap();
+/// ```
+pub fn copy_to_clipboard_with_options<D: ToQrPayload>(
+    data: D,
+    options: &QrOptions,
+    format: Format,
+) -> Result<(), ClipboardError> {
+    copy(&encode(data, options, format)?, format)
+}
+
+/// Render the given `data` as QR code in the given `format`.
+fn encode<D: ToQrPayload>(
+    data: D,
+    options: &QrOptions,
+    format: Format,
+) -> Result<Vec<u8>, ClipboardError> {
+    if format == Format::Png {
+        return Ok(png::encode(
+            &reserve::image_matrix(data, options)?,
+            PNG_MODULE_SIZE,
+        ));
+    }
+    let matrix = crate::code_matrix(&crate::generate_code(data, options)?, options)
+        .map_err(crate::Error::from)?;
+    let mut buf = Vec::new();
+    Renderer::new(options.style)
+        .theme(options.theme)
+        .non_breaking(options.non_breaking)
+        .ansi(format == Format::Ansi)
+        .render(&matrix, &mut buf)?;
+    Ok(buf)
+}
+
+/// Copy `contents` through the first available clipboard tool supporting `format`.
+fn copy(contents: &[u8], format: Format) -> Result<(), ClipboardError> {
+    for (program, args) in tools(format) {
+        let mut child = match Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err.into()),
+        };
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(contents)?;
+
+        // Tools without a display to connect to fail, try the next one
+        if child.wait()?.success() {
+            log_event!(
+                debug,
+                "copied {} format to clipboard through {}",
+                format,
+                program
+            );
+            return Ok(());
+        }
+    }
+    Err(ClipboardError::Unavailable(format))
+}
+
+/// Get the clipboard tools and their arguments that can copy `format`, in order of preference.
+fn tools(format: Format) -> Vec<(&'static str, Vec<&'static str>)> {
+    match format {
+        Format::Png => vec![
+            ("wl-copy", vec!["--type", format.mime_type()]),
+            (
+                "xclip",
+                vec!["-selection", "clipboard", "-t", format.mime_type()],
+            ),
+        ],
+        Format::Plain | Format::Ansi => vec![
+            ("wl-copy", vec!["--type", format.mime_type()]),
+            ("xclip", vec!["-selection", "clipboard"]),
+            ("xsel", vec!["--clipboard", "--input"]),
+            ("pbcopy", vec![]),
+            ("clip", vec![]),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_from_str() {
+        for format in [Format::Plain, Format::Ansi, Format::Png] {
+            assert_eq!(format.name().parse(), Ok(format));
+        }
+        assert!("gif".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn encode_formats() {
+        let options = QrOptions::new();
+        let plain = encode("qr2term", &options, Format::Plain).unwrap();
+        assert!(!plain.contains(&0x1B));
+        let ansi = encode("qr2term", &options, Format::Ansi).unwrap();
+        assert_eq!(
+            ansi,
+            crate::generate_qr_string_with_options("qr2term", &options)
+                .unwrap()
+                .as_bytes()
+        );
+        let png = encode("qr2term", &options, Format::Png).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+        assert_eq!(png[16..20], (25 * PNG_MODULE_SIZE as u32).to_be_bytes());
+    }
+}
@@ -0,0 +1,277 @@
+//! Experimental high capacity color QR codes.
+//!
+//! A [`ColorQr`] splits its payload over two or three regular QR codes of the
+//! same size, called planes, and layers them into a single colored symbol.
+//! Each plane controls one color channel of every module, so the symbol holds
+//! two or three times the data of a black and white code.
+//!
+//! Regular scanners can't read these codes. They're meant for moving data
+//! between machines through screen captures: sample the color of every module
+//! in the capture, and use [`separate`] to get back the black and white planes.
+//! Each plane is a regular QR code, and their decoded data joins up to the
+//! original payload.
+//!
+//! This mode is experimental, and its layout may change in any release.
+
+use std::io::{Result as IoResult, Write};
+
+use crate::ansi;
+use crate::matrix::Matrix;
+use crate::payload::ToQrPayload;
+use crate::qr::{Qr, QrInfo, Version};
+use crate::render::{Color, QrDark, QrLight};
+use crate::split::part_payload;
+use crate::{Error, QrOptions};
+
+/// An RGB color, as sampled from a screen capture.
+pub type Rgb = [u8; 3];
+
+/// Number of planes to layer in a color QR code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Planes {
+    /// Two planes, rendered in white, black, red and cyan.
+    #[default]
+    Two,
+
+    /// Three planes, one per RGB channel, rendered in eight colors.
+    Three,
+}
+
+impl Planes {
+    /// The number of planes.
+    pub fn count(self) -> usize {
+        match self {
+            Planes::Two => 2,
+            Planes::Three => 3,
+        }
+    }
+}
+
+/// A color QR code, layering multiple QR codes of the same size.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::color::{ColorQr, Planes};
+///
+/// let data = "qr2term".repeat(100);
+/// let code = ColorQr::new(&data, Planes::Three, &Default::default()).unwrap();
+/// code.print_stdout().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ColorQr {
+    planes: Vec<Matrix<Color>>,
+    info: Vec<QrInfo>,
+}
+
+impl ColorQr {
+    /// Generate a color QR code for `data`, split over the given number of `planes`.
+    ///
+    /// Error correction level and quiet zone are taken from `options`.
+    /// Returns an error if a plane can't hold its part of the data, or if a
+    /// [validator](crate::lint::Validator) rejects it.
+    pub fn new<D: ToQrPayload>(
+        data: D,
+        planes: Planes,
+        options: &QrOptions,
+    ) -> Result<Self, Error> {
+        let payload = crate::checked_payload(data.to_qr_payload(), options)?;
+        let part_len = (payload.data().len() + planes.count() - 1) / planes.count();
+        let parts: Vec<_> = (0..planes.count())
+            .map(|i| {
+                let start = (i * part_len).min(payload.data().len());
+                let end = (start + part_len).min(payload.data().len());
+                part_payload(&payload, &payload.data()[start..end])
+            })
+            .collect();
+
+        // Generate all planes in the version of the largest one
+        let mut version = 1;
+        let mut codes = Vec::with_capacity(parts.len());
+        for part in &parts {
+            let code = Qr::from_payload_min_version(part, options.ec_level, version)?;
+            if let Version::Normal(v) = code.info().version {
+                version = version.max(v);
+            }
+            codes.push(code);
+        }
+        for (code, part) in codes.iter_mut().zip(&parts) {
+            if code.info().version != Version::Normal(version) {
+                *code = Qr::from_payload_min_version(part, options.ec_level, version)?;
+            }
+        }
+
+        Ok(Self {
+            planes: codes
+                .iter()
+                .map(|code| crate::code_matrix(code, options))
+                .collect::<Result<_, _>>()?,
+            info: codes.iter().map(Qr::info).collect(),
+        })
+    }
+
+    /// Metadata about each plane, in order of the data they hold.
+    pub fn info(&self) -> &[QrInfo] {
+        &self.info
+    }
+
+    /// The black and white planes, including their quiet zone.
+    pub fn planes(&self) -> &[Matrix<Color>] {
+        &self.planes
+    }
+
+    /// Get the color of every module, including the quiet zone.
+    pub fn to_rgb(&self) -> Matrix<Rgb> {
+        let pixels = (0..self.planes[0].pixels().len())
+            .map(|i| {
+                let light: Vec<bool> = self
+                    .planes
+                    .iter()
+                    .map(|plane| plane.pixels()[i] == QrLight)
+                    .collect();
+                let channel = |light| if light { 255 } else { 0 };
+                match *light {
+                    [r, g] => [channel(r), channel(g), channel(g)],
+                    [r, g, b] => [channel(r), channel(g), channel(b)],
+                    _ => unreachable!(),
+                }
+            })
+            .collect();
+        Matrix::new(pixels)
+    }
+
+    /// Render the code to the given writer, two modules above each other per character.
+    pub fn render<W: Write>(&self, target: &mut W) -> IoResult<()> {
+        let rgb = self.to_rgb();
+        let width = rgb.size();
+        let pixels = rgb.pixels();
+
+        for row in (0..width).step_by(2) {
+            for col in 0..width {
+                let top = palette_index(pixels[row * width + col]);
+                let bottom = match pixels.get((row + 1) * width + col) {
+                    Some(&rgb) if row + 1 < width => palette_index(rgb),
+                    _ => ansi::WHITE,
+                };
+                // Inverted lower half block, like the half block style
+                ansi::write_colored(target, "▄", bottom, top)?;
+            }
+            writeln!(target)?;
+        }
+        Ok(())
+    }
+
+    /// Render the code to the terminal.
+    ///
+    /// Returns an error if writing to the terminal failed.
+    pub fn print_stdout(&self) -> IoResult<()> {
+        let mut stdout = std::io::stdout().lock();
+        self.render(&mut stdout)?;
+        stdout.flush()
+    }
+}
+
+/// Separate the module colors sampled from a color QR code into its black and white planes.
+///
+/// Every color channel is thresholded at half intensity. With two planes the
+/// first is read from the red channel, and the second from green and blue.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::color::{self, ColorQr, Planes};
+///
+/// let code = ColorQr::new("qr2term", Planes::Two, &Default::default()).unwrap();
+/// let planes = color::separate(&code.to_rgb(), Planes::Two);
+/// assert_eq!(planes[1].pixels(), code.planes()[1].pixels());
+/// ```
+pub fn separate(modules: &Matrix<Rgb>, planes: Planes) -> Vec<Matrix<Color>> {
+    let is_light = |[r, g, b]: Rgb, plane: usize| match (planes, plane) {
+        (Planes::Two, 1) => (u16::from(g) + u16::from(b)) / 2 >= 128,
+        _ => [r, g, b][plane] >= 128,
+    };
+
+    (0..planes.count())
+        .map(|plane| {
+            let pixels = modules
+                .pixels()
+                .iter()
+                .map(|&rgb| {
+                    if is_light(rgb, plane) {
+                        QrLight
+                    } else {
+                        QrDark
+                    }
+                })
+                .collect();
+            Matrix::new(pixels)
+        })
+        .collect()
+}
+
+/// Get the ANSI palette index for a module color, using the bright colors.
+fn palette_index([r, g, b]: Rgb) -> u8 {
+    match (r > 0, g > 0, b > 0) {
+        (false, false, false) => ansi::BLACK,
+        (r, g, b) => 8 + r as u8 + 2 * g as u8 + 4 * b as u8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn planes_roundtrip() {
+        let data = "qr2term ".repeat(40);
+        for planes in [Planes::Two, Planes::Three] {
+            let code = ColorQr::new(&data, planes, &QrOptions::new()).unwrap();
+            assert_eq!(code.planes().len(), planes.count());
+
+            // All planes share the same version
+            let version = code.info()[0].version;
+            assert!(code.info().iter().all(|info| info.version == version));
+
+            let separated = separate(&code.to_rgb(), planes);
+            for (plane, separated) in code.planes().iter().zip(&separated) {
+                assert_eq!(plane.pixels(), separated.pixels());
+            }
+        }
+    }
+
+    #[test]
+    fn denser_than_single_code() {
+        let data = "qr2term ".repeat(40);
+        let single = Qr::from(&data).unwrap().info().version;
+        let color = ColorQr::new(&data, Planes::Three, &QrOptions::new()).unwrap();
+        assert!(color.info()[0].module_count < single_width(single));
+    }
+
+    fn single_width(version: Version) -> usize {
+        match version {
+            Version::Normal(v) => 17 + 4 * v as usize,
+            Version::Micro(v) => 9 + 2 * v as usize,
+        }
+    }
+
+    /// Rendered with inverted lower half blocks, two modules per line.
+    #[test]
+    fn render_half_blocks() {
+        let code = ColorQr::new("qr2term", Planes::Two, &QrOptions::new()).unwrap();
+        let mut buf = Vec::new();
+        code.render(&mut buf).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(!text.contains('▀'));
+        let size = code.planes()[0].size();
+        assert_eq!(text.lines().count(), (size + 1) / 2);
+        assert_eq!(text.lines().next().unwrap().matches('▄').count(), size);
+    }
+
+    #[test]
+    fn palette() {
+        assert_eq!(palette_index([255, 255, 255]), ansi::WHITE);
+        assert_eq!(palette_index([0, 0, 0]), ansi::BLACK);
+        assert_eq!(palette_index([255, 0, 0]), 9);
+        assert_eq!(palette_index([0, 255, 255]), 14);
+    }
+}
@@ -0,0 +1,100 @@
+//! Payload compression, to fit more data in a single QR code.
+//!
+//! Compressed payloads are DEFLATE streams prefixed with a small header, see
+//! [`compress`]. They are encoded in byte mode, so a scanner shows binary data;
+//! use [`decompress`] on the scanned bytes to get the original payload back.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::deflate;
+pub use crate::deflate::InflateError;
+
+/// Magic bytes at the start of every compressed payload.
+pub const MAGIC: [u8; 2] = *b"QZ";
+
+/// Method byte for raw DEFLATE compression.
+const METHOD_DEFLATE: u8 = 1;
+
+/// Compress `data`, prefixed with a header identifying the compression method.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::compress;
+///
+/// let json = r#"{"ssid":"home","password":"hunter2","hidden":false}"#.repeat(4);
+/// let compressed = compress::compress(json.as_bytes());
+/// assert!(compressed.len() < json.len());
+/// assert_eq!(compress::decompress(&compressed).unwrap(), json.as_bytes());
+/// ```
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    out.push(METHOD_DEFLATE);
+    out.extend(deflate::deflate(data));
+    out
+}
+
+/// Decompress data produced by [`compress`].
+///
+/// Returns an error if the header is missing or the data is corrupt.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, DecompressError> {
+    match data {
+        [m0, m1, METHOD_DEFLATE, stream @ ..] if [*m0, *m1] == MAGIC => {
+            deflate::inflate(stream).map_err(DecompressError::Inflate)
+        }
+        [m0, m1, method, ..] if [*m0, *m1] == MAGIC => Err(DecompressError::Method(*method)),
+        _ => Err(DecompressError::Header),
+    }
+}
+
+/// Whether `data` starts with the header of a compressed payload.
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.len() > MAGIC.len() && data.starts_with(&MAGIC)
+}
+
+/// Error returned when decompressing a payload failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecompressError {
+    /// The data doesn't start with the compressed payload header.
+    Header,
+
+    /// The data is compressed with an unknown method.
+    Method(u8),
+
+    /// The compressed data is corrupt.
+    Inflate(InflateError),
+}
+
+impl Display for DecompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecompressError::Header => f.write_str("missing compressed payload header"),
+            DecompressError::Method(method) => write!(f, "unknown compression method {}", method),
+            DecompressError::Inflate(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for DecompressError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecompressError::Inflate(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header() {
+        let compressed = compress(b"qr2term");
+        assert!(is_compressed(&compressed));
+        assert!(!is_compressed(b"qr2term"));
+        assert_eq!(decompress(b"qr2term"), Err(DecompressError::Header));
+        assert_eq!(decompress(b"QZ\x09abc"), Err(DecompressError::Method(9)));
+    }
+}
@@ -0,0 +1,586 @@
+//! Config files with default options, such as for the command line binary.
+//!
+//! Config files are written in a subset of [TOML](https://toml.io/): `key =
+//! value` pairs with string, integer or boolean values, and `#` comments.
+//! Their keys are named like the `QR2TERM_*` environment variables:
+//!
+//! ```toml
+//! # ~/.config/qr2term/config.toml
+//! style = "full"
+//! theme = "high-contrast"
+//! ec_level = "Q"
+//! quiet_zone = 4
+//! format = "json"
+//! ```
+//!
+//! Read the config file at its default [`path`] with [`Config::load`], and
+//! apply it to options with [`Config::apply`]. Settings in `[profiles.NAME]`
+//! tables define [profiles](crate::profile) instead, applied with
+//! [`Config::apply_profile`].
+
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::batch::InputFormat;
+use crate::options::{self, Accessibility, EcLevel, Overflow};
+use crate::profile::{self, ProfileError};
+use crate::render::Style;
+use crate::theme::Theme;
+use crate::{ErrorCategory, QrOptions};
+
+/// Environment variable to override the path of the config file.
+pub const ENV_CONFIG: &str = "QR2TERM_CONFIG";
+
+/// Get the path of the config file.
+///
+/// This is `QR2TERM_CONFIG` if set, or `qr2term/config.toml` in
+/// `$XDG_CONFIG_HOME`, which defaults to `~/.config`. Returns `None` if
+/// neither the config directory nor the home directory is known.
+pub fn path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(ENV_CONFIG).filter(|path| !path.is_empty()) {
+        return Some(path.into());
+    }
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            let home = env::var_os("HOME").filter(|home| !home.is_empty())?;
+            Some(Path::new(&home).join(".config"))
+        })?;
+    Some(dir.join("qr2term").join("config.toml"))
+}
+
+/// Settings of a config file, `None` for settings it doesn't have.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::{config::Config, render::Style, QrOptions};
+///
+/// let config: Config = "style = \"full\"\nquiet_zone = 4\n".parse().unwrap();
+/// let options = config.apply(QrOptions::new());
+/// assert_eq!(options.style, Style::FullBlock);
+/// assert_eq!(options.quiet_zone, 4);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Render style, key `style`.
+    pub style: Option<Style>,
+
+    /// Colors, key `theme`.
+    pub theme: Option<Theme>,
+
+    /// Error correction level, key `ec_level`.
+    pub ec_level: Option<EcLevel>,
+
+    /// Quiet zone size in modules, key `quiet_zone`, at most
+    /// [`MAX_QUIET_ZONE`](options::MAX_QUIET_ZONE).
+    pub quiet_zone: Option<usize>,
+
+    /// What to do with data too long for one code, key `overflow`.
+    pub on_overflow: Option<Overflow>,
+
+    /// Accessibility mode, key `accessibility`.
+    pub accessibility: Option<Accessibility>,
+
+    /// How many times wider to draw every module, key `stretch`.
+    pub stretch: Option<usize>,
+
+    /// Format of batch input, key `format`.
+    pub format: Option<InputFormat>,
+
+    /// Settings of the profiles defined in `[profiles.NAME]` tables, by name.
+    pub profiles: Vec<(String, Config)>,
+}
+
+impl Config {
+    /// Read the config file at its default [`path`].
+    ///
+    /// Returns the empty config if there is no config file, unless its path
+    /// was given through `QR2TERM_CONFIG`.
+    pub fn load() -> Result<Self, ConfigError> {
+        let given = env::var_os(ENV_CONFIG).map_or(false, |path| !path.is_empty());
+        match path() {
+            Some(path) if given || path.exists() => Self::read(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Read the config file at `path`.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        fs::read_to_string(path)
+            .map_err(|err| ConfigError::Io(path.to_path_buf(), err))?
+            .parse()
+    }
+
+    /// Override the given `options` with the settings of this config.
+    pub fn apply(&self, mut options: QrOptions) -> QrOptions {
+        if let Some(style) = self.style {
+            options.style = style;
+        }
+        if let Some(theme) = self.theme {
+            options.theme = theme;
+        }
+        if let Some(ec_level) = self.ec_level {
+            options.ec_level = ec_level;
+        }
+        if let Some(quiet_zone) = self.quiet_zone {
+            options.quiet_zone = quiet_zone;
+        }
+        if let Some(on_overflow) = self.on_overflow {
+            options.on_overflow = on_overflow;
+        }
+        if let Some(accessibility) = self.accessibility {
+            options.accessibility = accessibility;
+        }
+        if let Some(stretch) = self.stretch {
+            options.stretch = stretch;
+        }
+        options
+    }
+
+    /// Get the settings of the profile `name` defined in this config.
+    pub fn profile(&self, name: &str) -> Option<&Config> {
+        self.profiles
+            .iter()
+            .find(|(profile, _)| profile == name)
+            .map(|(_, config)| config)
+    }
+
+    /// Apply the profile `name` to the given `options`.
+    ///
+    /// The profile defined in this config overrides the settings of the
+    /// [built-in profile](crate::profile::builtin) with the same name, if any.
+    /// Returns an error if neither exists.
+    pub fn apply_profile(&self, name: &str, options: QrOptions) -> Result<QrOptions, ProfileError> {
+        let builtin = profile::builtin(name, options.clone());
+        match (self.profile(name), builtin) {
+            (Some(profile), builtin) => Ok(profile.apply(builtin.unwrap_or(options))),
+            (None, Some(builtin)) => Ok(builtin),
+            (None, None) => Err(ProfileError::Unknown(name.into())),
+        }
+    }
+
+    /// Set the setting `key` to `value`, given at `line`.
+    fn set(&mut self, line: usize, key: &str, value: &Value) -> Result<(), ConfigError> {
+        let invalid = || ConfigError::InvalidValue(line, key.into());
+        match key {
+            "style" => self.style = Some(value.parse().ok_or_else(invalid)?),
+            "theme" => self.theme = Some(value.parse().ok_or_else(invalid)?),
+            "ec_level" => {
+                let ec_level = value.as_str().and_then(options::parse_ec_level);
+                self.ec_level = Some(ec_level.ok_or_else(invalid)?);
+            }
+            "quiet_zone" => {
+                let quiet_zone = value
+                    .as_usize()
+                    .filter(|&zone| zone <= options::MAX_QUIET_ZONE);
+                self.quiet_zone = Some(quiet_zone.ok_or_else(invalid)?);
+            }
+            "overflow" => self.on_overflow = Some(value.parse().ok_or_else(invalid)?),
+            "accessibility" => self.accessibility = Some(value.parse().ok_or_else(invalid)?),
+            "stretch" => {
+                let stretch = value.as_usize().filter(|&stretch| stretch > 0);
+                self.stretch = Some(stretch.ok_or_else(invalid)?);
+            }
+            "format" => self.format = Some(value.parse().ok_or_else(invalid)?),
+            _ => return Err(ConfigError::UnknownKey(line, key.into())),
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = Config::default();
+        let mut keys = Vec::new();
+        for entry in parse(s)? {
+            let settings = match &entry.table {
+                None => &mut config,
+                Some(table) => {
+                    let name = table
+                        .strip_prefix("profiles.")
+                        .filter(|name| !name.contains('.'))
+                        .ok_or_else(|| ConfigError::UnknownKey(entry.line, table.clone()))?;
+                    if config.profile(name).is_none() {
+                        config.profiles.push((name.into(), Config::default()));
+                    }
+                    let (_, profile) = config.profiles.iter_mut().find(|(n, _)| n == name).unwrap();
+                    profile
+                }
+            };
+            let key = (entry.table, entry.key);
+            if keys.contains(&key) {
+                return Err(ConfigError::DuplicateKey(entry.line, key.1));
+            }
+            settings.set(entry.line, &key.1, &entry.value)?;
+            keys.push(key);
+        }
+        Ok(config)
+    }
+}
+
+/// Error returned when reading a config file failed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// Reading the config file at the given path failed.
+    Io(PathBuf, io::Error),
+
+    /// The given line isn't a comment, table header or `key = value` pair.
+    Syntax(usize),
+
+    /// The key at the given line isn't a known setting.
+    UnknownKey(usize, String),
+
+    /// The key at the given line was already set.
+    DuplicateKey(usize, String),
+
+    /// The value of the key at the given line is invalid for its setting.
+    InvalidValue(usize, String),
+}
+
+impl ConfigError {
+    /// Get the category of this error, for branching on the kind of failure.
+    ///
+    /// Malformed config files are decode failures.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            ConfigError::Io(_, err) => err.into(),
+            _ => ErrorCategory::Decode,
+        }
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => {
+                write!(f, "failed to read config '{}': {}", path.display(), err)
+            }
+            ConfigError::Syntax(line) => write!(f, "invalid config syntax at line {}", line),
+            ConfigError::UnknownKey(line, key) => {
+                write!(f, "unknown config key '{}' at line {}", key, line)
+            }
+            ConfigError::DuplicateKey(line, key) => {
+                write!(f, "duplicate config key '{}' at line {}", key, line)
+            }
+            ConfigError::InvalidValue(line, key) => {
+                write!(f, "invalid value for config key '{}' at line {}", key, line)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// A value in a config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+}
+
+impl Value {
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_usize(&self) -> Option<usize> {
+        match *self {
+            Value::Integer(n) => usize::try_from(n).ok(),
+            _ => None,
+        }
+    }
+
+    /// Parse a string value, such as a style name.
+    fn parse<T: FromStr>(&self) -> Option<T> {
+        self.as_str()?.parse().ok()
+    }
+}
+
+/// A `key = value` pair in a config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    /// Line of the pair, starting at 1.
+    line: usize,
+
+    /// Name of the table the pair is in, `None` before any table header.
+    table: Option<String>,
+    key: String,
+    value: Value,
+}
+
+/// Parse the `key = value` pairs of a config file.
+fn parse(text: &str) -> Result<Vec<Entry>, ConfigError> {
+    let mut entries = Vec::new();
+    let mut table = None;
+    for (line, text) in (1..).zip(text.lines()) {
+        let syntax = || ConfigError::Syntax(line);
+        let text = text.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = text.strip_prefix('[') {
+            let (name, rest) = header.split_once(']').ok_or_else(syntax)?;
+            let name = name.trim();
+            if !is_comment(rest) || name.split('.').any(|part| !is_key(part.trim())) {
+                return Err(syntax());
+            }
+            let name: Vec<&str> = name.split('.').map(str::trim).collect();
+            table = Some(name.join("."));
+            continue;
+        }
+        let (key, value) = text.split_once('=').ok_or_else(syntax)?;
+        let key = key.trim();
+        if !is_key(key) {
+            return Err(syntax());
+        }
+        let (value, rest) = parse_value(value.trim()).ok_or_else(syntax)?;
+        if !is_comment(rest) {
+            return Err(syntax());
+        }
+        entries.push(Entry {
+            line,
+            table: table.clone(),
+            key: key.into(),
+            value,
+        });
+    }
+    Ok(entries)
+}
+
+/// Parse the value at the start of `text`, returning it and the remaining text.
+fn parse_value(text: &str) -> Option<(Value, &str)> {
+    if let Some(text) = text.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = text.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Some((Value::String(value), &text[i + 1..])),
+                '\\' => value.push(match chars.next()?.1 {
+                    '"' => '"',
+                    '\\' => '\\',
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    _ => return None,
+                }),
+                c => value.push(c),
+            }
+        }
+        return None;
+    }
+    if let Some(text) = text.strip_prefix('\'') {
+        let (value, rest) = text.split_once('\'')?;
+        return Some((Value::String(value.into()), rest));
+    }
+    let end = text
+        .find(|c: char| c.is_whitespace() || c == '#')
+        .unwrap_or(text.len());
+    let (word, rest) = text.split_at(end);
+    let value = match word {
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        _ => {
+            let digits = word.trim_start_matches(['+', '-']);
+            if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+                return None;
+            }
+            Value::Integer(word.replace('_', "").parse().ok()?)
+        }
+    };
+    Some((value, rest))
+}
+
+/// Whether `text` is a bare key.
+fn is_key(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Whether `text` is empty or a comment, ignoring surrounding whitespace.
+fn is_comment(text: &str) -> bool {
+    let text = text.trim();
+    text.is_empty() || text.starts_with('#')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings() {
+        let config: Config = "
+            # Defaults for all tools
+            style = \"full\"
+            theme = 'high-contrast'   # easier to scan
+            ec_level = \"q\"
+            quiet_zone = 4
+            overflow = \"split\"
+            accessibility = \"alongside\"
+            stretch = 2
+            format = \"json\"
+        "
+        .parse()
+        .unwrap();
+        assert_eq!(config.format, Some(InputFormat::Json));
+        assert_eq!(
+            config.apply(QrOptions::new()),
+            QrOptions::new()
+                .style(Style::FullBlock)
+                .theme(Theme::HighContrast)
+                .ec_level(EcLevel::Q)
+                .quiet_zone(4)
+                .on_overflow(Overflow::Split)
+                .accessibility(Accessibility::Alongside)
+                .stretch(2)
+        );
+
+        let config: Config = "".parse().unwrap();
+        assert_eq!(config, Config::default());
+        let options = QrOptions::new().quiet_zone(7);
+        assert_eq!(config.apply(options.clone()), options);
+    }
+
+    #[test]
+    fn profiles() {
+        let config: Config = "
+            style = \"ascii\"
+
+            [profiles.presentation]
+            theme = \"solarized-dark\"
+
+            [profiles.badge]
+            style = \"half\"
+            format = \"json\"
+        "
+        .parse()
+        .unwrap();
+        assert_eq!(config.style, Some(Style::Ascii));
+        assert_eq!(config.profiles.len(), 2);
+        assert_eq!(
+            config.profile("badge").unwrap().format,
+            Some(InputFormat::Json)
+        );
+
+        let options = config
+            .apply_profile("presentation", QrOptions::new())
+            .unwrap();
+        assert_eq!(options.style, Style::FullBlock);
+        assert_eq!(options.theme, Theme::SolarizedDark);
+        let options = config.apply_profile("badge", QrOptions::new()).unwrap();
+        assert_eq!(options.style, Style::HalfBlock);
+        let options = config.apply_profile("ci", QrOptions::new()).unwrap();
+        assert_eq!(options.style, Style::CiLog);
+        assert_eq!(
+            config
+                .apply_profile("talk", QrOptions::new())
+                .unwrap_err()
+                .to_string(),
+            "unknown profile 'talk'"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let err = |text: &str| text.parse::<Config>().unwrap_err().to_string();
+        assert_eq!(err("style = full"), "invalid config syntax at line 1");
+        assert_eq!(err("\nstyle"), "invalid config syntax at line 2");
+        assert_eq!(err("style = \"full"), "invalid config syntax at line 1");
+        assert_eq!(err("quiet_zone = 4 4"), "invalid config syntax at line 1");
+        assert_eq!(
+            err("colour = \"red\""),
+            "unknown config key 'colour' at line 1"
+        );
+        assert_eq!(
+            err("[profiles]\nstyle = \"full\""),
+            "unknown config key 'profiles' at line 2"
+        );
+        assert_eq!(
+            err("[profiles.a.b]\nstyle = \"full\""),
+            "unknown config key 'profiles.a.b' at line 2"
+        );
+        assert_eq!(
+            err("[profiles.a]\nstretch = 2\n[profiles.a]\nstretch = 3"),
+            "duplicate config key 'stretch' at line 4"
+        );
+        assert_eq!(
+            err("stretch = 2\nstretch = 3"),
+            "duplicate config key 'stretch' at line 2"
+        );
+        assert_eq!(
+            err("style = \"sparkles\""),
+            "invalid value for config key 'style' at line 1"
+        );
+        assert_eq!(
+            err("quiet_zone = -1"),
+            "invalid value for config key 'quiet_zone' at line 1"
+        );
+        assert_eq!(
+            err("style = \"full\"\nquiet_zone = 3000000000"),
+            "invalid value for config key 'quiet_zone' at line 2"
+        );
+        assert_eq!(
+            err("quiet_zone = \"4\""),
+            "invalid value for config key 'quiet_zone' at line 1"
+        );
+        assert_eq!(
+            err("stretch = 0"),
+            "invalid value for config key 'stretch' at line 1"
+        );
+        assert_eq!(
+            Config::read("does/not/exist.toml").unwrap_err().category(),
+            ErrorCategory::Io
+        );
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(
+            parse_value(r#""a \"b\"\n" # c"#),
+            Some((Value::String("a \"b\"\n".into()), " # c"))
+        );
+        assert_eq!(
+            parse_value(r"'C:\path'"),
+            Some((Value::String(r"C:\path".into()), ""))
+        );
+        assert_eq!(parse_value("1_000"), Some((Value::Integer(1000), "")));
+        assert_eq!(parse_value("-3#"), Some((Value::Integer(-3), "#")));
+        assert_eq!(parse_value("true"), Some((Value::Boolean(true), "")));
+        assert_eq!(parse_value("1__0"), None);
+        assert_eq!(parse_value("\"\\x\""), None);
+        assert_eq!(parse_value("yes"), None);
+    }
+
+    #[test]
+    fn tables() {
+        let entries = parse("a = 1\n[ profiles . ci ]\nb = true\n").unwrap();
+        assert_eq!(entries[0].table, None);
+        assert_eq!(entries[1].table.as_deref(), Some("profiles.ci"));
+        assert_eq!(entries[1].line, 3);
+        assert!(parse("[profiles.]").is_err());
+        assert!(parse("[a] b = 1").is_err());
+    }
+}
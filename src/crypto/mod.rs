@@ -0,0 +1,29 @@
+//! Minimal cryptographic primitives, so the crate doesn't need extra dependencies.
+//!
+//! These are not meant as general purpose implementations, and are only exposed
+//! through higher level helpers. SHA-256 is always built, for
+//! [`checksum`](crate::checksum) suffixes; HMAC, constant time comparison and
+//! randomness only serve the `payloads` feature, such as `sign`.
+
+pub mod sha256;
+
+#[cfg(all(feature = "payloads", unix))]
+use std::fs::File;
+#[cfg(all(feature = "payloads", unix))]
+use std::io::{self, Read};
+
+/// Compare two byte strings in time independent of their contents.
+#[cfg(feature = "payloads")]
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Fill `buf` with random bytes from the operating system, through
+/// `/dev/urandom`.
+///
+/// Only available on Unix, as std has no portable source of randomness.
+/// Returns an error if reading `/dev/urandom` failed.
+#[cfg(all(feature = "payloads", unix))]
+pub fn random_bytes(buf: &mut [u8]) -> io::Result<()> {
+    File::open("/dev/urandom")?.read_exact(buf)
+}
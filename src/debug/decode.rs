@@ -0,0 +1,582 @@
+//! Decoding QR code module matrices, independent from the encoder.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use qrcode::{EcLevel, Version};
+
+use super::layout::{alignment_positions, module_kind, ModuleKind};
+use super::reed_solomon;
+use crate::matrix::Matrix;
+use crate::qr::SegmentMode;
+use crate::render::{Color, QrDark};
+
+/// Error correction codewords per block, by error correction level in the
+/// order L, M, Q, H, and version.
+const EC_CODEWORDS_PER_BLOCK: [[u8; 41]; 4] = [
+    [
+        0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28,
+        30, 30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+    [
+        0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28,
+        28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+    ],
+    [
+        0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30, 24, 28, 28, 26, 30, 28, 30,
+        30, 30, 30, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+    [
+        0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24, 30, 28, 28, 26, 28, 30, 24,
+        30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+];
+
+/// Number of error correction blocks, by error correction level in the order
+/// L, M, Q, H, and version.
+const EC_BLOCKS: [[u8; 41]; 4] = [
+    [
+        0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13,
+        14, 15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25,
+    ],
+    [
+        0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21,
+        23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49,
+    ],
+    [
+        0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12, 17, 16, 18, 21, 20, 23, 23, 25, 27, 29,
+        34, 34, 35, 38, 40, 43, 45, 48, 51, 53, 56, 59, 62, 65, 68,
+    ],
+    [
+        0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18, 16, 19, 21, 25, 25, 25, 34, 30, 32,
+        35, 37, 40, 42, 45, 48, 51, 54, 57, 60, 63, 66, 70, 74, 77, 81,
+    ],
+];
+
+/// Mask XOR-ed with the format information.
+const FORMAT_INFO_MASK: u16 = 0x5412;
+
+/// Characters of the alphanumeric mode, by value.
+const ALPHANUMERIC: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// The contents of a decoded QR code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    /// Symbol version.
+    pub version: Version,
+
+    /// Error correction level.
+    pub ec_level: EcLevel,
+
+    /// Mask pattern reference, from 0 to 7.
+    pub mask: u8,
+
+    /// The decoded data, joined from all segments.
+    ///
+    /// Kanji segments are decoded to Shift JIS bytes, and GS1 data has its
+    /// FNC1 separators as ASCII group separators.
+    pub data: Vec<u8>,
+
+    /// Modes of the decoded data segments, in order.
+    pub segments: Vec<SegmentMode>,
+
+    /// The first ECI designator, if any.
+    pub eci: Option<u32>,
+
+    /// Whether FNC1 in first position marks the data as GS1.
+    pub gs1: bool,
+
+    /// Number of codewords fixed by error correction.
+    pub corrected: usize,
+}
+
+/// Decode the QR code in a module matrix.
+///
+/// The matrix may include a quiet zone, and the code may be rotated or
+/// mirrored. Only regular QR codes are supported, not Micro QR codes.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::{debug, qr::Qr};
+///
+/// let code = Qr::from("qr2term").unwrap();
+/// assert_eq!(debug::decode(&code.to_matrix()).unwrap().data, b"qr2term");
+/// ```
+pub fn decode(matrix: &Matrix<Color>) -> Result<Decoded, DecodeError> {
+    let grid = Grid::locate(matrix)?;
+
+    // A reflected symbol also has finder patterns in the expected corners, and
+    // may have plausible format information, so try all candidates
+    let mut result = Err(DecodeError::Format);
+    for grid in grid.orientations().iter().filter(|grid| grid.has_finders()) {
+        if let Some((ec_level, mask)) = grid.format() {
+            result = grid.decode(ec_level, mask);
+            if result.is_ok() {
+                break;
+            }
+        }
+    }
+    result
+}
+
+/// Error returned when decoding a module matrix failed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// No QR code of a valid size was found.
+    Locate,
+
+    /// The finder patterns or format information could not be read.
+    Format,
+
+    /// There are more errors than error correction can fix.
+    Correction,
+
+    /// The data bit stream is malformed.
+    Data,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DecodeError::Locate => "no QR code found",
+            DecodeError::Format => "unreadable QR code format information",
+            DecodeError::Correction => "too many errors in QR code",
+            DecodeError::Data => "malformed QR code data",
+        })
+    }
+}
+
+impl Error for DecodeError {}
+
+/// The modules of a symbol, without quiet zone, dark modules being `true`.
+#[derive(Clone)]
+struct Grid {
+    size: usize,
+    dark: Vec<bool>,
+}
+
+impl Grid {
+    /// Crop the symbol out of `matrix`, using the bounds of its dark modules.
+    fn locate(matrix: &Matrix<Color>) -> Result<Self, DecodeError> {
+        let width = matrix.size();
+        let dark: Vec<(usize, usize)> = (0..width * width)
+            .filter(|&i| matrix.pixels()[i] == QrDark)
+            .map(|i| (i / width, i % width))
+            .collect();
+        let top = dark
+            .iter()
+            .map(|&(row, _)| row)
+            .min()
+            .ok_or(DecodeError::Locate)?;
+        let bottom = dark.iter().map(|&(row, _)| row).max().unwrap_or(top);
+        let left = dark.iter().map(|&(_, col)| col).min().unwrap_or(0);
+        let right = dark.iter().map(|&(_, col)| col).max().unwrap_or(left);
+
+        let size = bottom - top + 1;
+        if size != right - left + 1 || !(21..=177).contains(&size) || (size - 17) % 4 != 0 {
+            return Err(DecodeError::Locate);
+        }
+        let dark = (0..size * size)
+            .map(|i| matrix.pixels()[(top + i / size) * width + left + i % size] == QrDark)
+            .collect();
+        Ok(Self { size, dark })
+    }
+
+    fn get(&self, row: usize, col: usize) -> bool {
+        self.dark[row * self.size + col]
+    }
+
+    /// All eight rotations and reflections of this grid.
+    fn orientations(&self) -> Vec<Grid> {
+        let n = self.size;
+        let transform = |f: &dyn Fn(usize, usize) -> (usize, usize)| Grid {
+            size: n,
+            dark: (0..n * n)
+                .map(|i| {
+                    let (row, col) = f(i / n, i % n);
+                    self.get(row, col)
+                })
+                .collect(),
+        };
+        vec![
+            self.clone(),
+            transform(&|r, c| (n - 1 - c, r)),
+            transform(&|r, c| (n - 1 - r, n - 1 - c)),
+            transform(&|r, c| (c, n - 1 - r)),
+            transform(&|r, c| (c, r)),
+            transform(&|r, c| (r, n - 1 - c)),
+            transform(&|r, c| (n - 1 - c, n - 1 - r)),
+            transform(&|r, c| (n - 1 - r, c)),
+        ]
+    }
+
+    /// Whether the top left, top right and bottom left corners hold finder patterns.
+    fn has_finders(&self) -> bool {
+        let far = self.size - 7;
+        [(0, 0), (0, far), (far, 0)].iter().all(|&(top, left)| {
+            (0..7).all(|r| {
+                (0..7).all(|c| {
+                    let ring = r.min(c).min(6 - r).min(6 - c);
+                    self.get(top + r, left + c) == (ring != 1)
+                })
+            })
+        })
+    }
+
+    /// Read the error correction level and mask from either copy of the format information.
+    fn format(&self) -> Option<(EcLevel, u8)> {
+        let n = self.size;
+        let read = |coords: &mut dyn Iterator<Item = (usize, usize)>| {
+            coords.fold(0u16, |bits, (r, c)| (bits << 1) | self.get(r, c) as u16)
+        };
+        let main = read(
+            &mut (0..9)
+                .filter(|&c| c != 6)
+                .map(|c| (8, c))
+                .chain((0..8).rev().filter(|&r| r != 6).map(|r| (r, 8))),
+        );
+        let side = read(
+            &mut (0..7)
+                .map(|i| (n - 1 - i, 8))
+                .chain((0..8).map(|i| (8, n - 8 + i))),
+        );
+
+        // Pick the closest valid format information, within its correction capacity
+        let (distance, data) = (0..32u16)
+            .map(|data| {
+                let bits = format_bits(data);
+                let distance = (bits ^ main).count_ones().min((bits ^ side).count_ones());
+                (distance, data)
+            })
+            .min()?;
+        if distance > 3 {
+            return None;
+        }
+        let ec_level = match data >> 3 {
+            0b01 => EcLevel::L,
+            0b00 => EcLevel::M,
+            0b11 => EcLevel::Q,
+            _ => EcLevel::H,
+        };
+        Some((ec_level, (data & 0b111) as u8))
+    }
+
+    /// Decode the data of this correctly oriented grid.
+    fn decode(&self, ec_level: EcLevel, mask: u8) -> Result<Decoded, DecodeError> {
+        let version = (self.size - 17) / 4;
+        let codewords = self.codewords(version, mask);
+        let (data, corrected) = correct_blocks(&codewords, version, ec_level)?;
+        let mut decoded = Decoded {
+            version: Version::Normal(version as i16),
+            ec_level,
+            mask,
+            data: Vec::new(),
+            segments: Vec::new(),
+            eci: None,
+            gs1: false,
+            corrected,
+        };
+        parse_segments(&data, version, &mut decoded)?;
+        Ok(decoded)
+    }
+
+    /// Read all codewords in placement order, removing the mask.
+    fn codewords(&self, version: usize, mask: u8) -> Vec<u8> {
+        let n = self.size;
+        let alignment = alignment_positions(version);
+        let mut bits = Vec::new();
+        let mut right = n - 1;
+        loop {
+            // Skip the vertical timing pattern
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vertical in 0..n {
+                let row = if upward { n - 1 - vertical } else { vertical };
+                for col in [right, right - 1] {
+                    if module_kind(version, &alignment, row, col) == ModuleKind::Data {
+                        bits.push(self.get(row, col) ^ is_masked(mask, row, col));
+                    }
+                }
+            }
+            if right == 1 {
+                break;
+            }
+            right -= 2;
+        }
+        bits.chunks_exact(8)
+            .map(|byte| byte.iter().fold(0, |acc, &bit| (acc << 1) | bit as u8))
+            .collect()
+    }
+}
+
+/// Get the masked 15 bit format information for the 5 bit `data`.
+fn format_bits(data: u16) -> u16 {
+    let mut remainder = data;
+    for _ in 0..10 {
+        remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+    }
+    ((data << 10) | remainder) ^ FORMAT_INFO_MASK
+}
+
+/// Whether the data mask pattern `mask` inverts the module.
+fn is_masked(mask: u8, row: usize, col: usize) -> bool {
+    let (i, j) = (row, col);
+    match mask {
+        0 => (i + j) % 2 == 0,
+        1 => i % 2 == 0,
+        2 => j % 3 == 0,
+        3 => (i + j) % 3 == 0,
+        4 => (i / 2 + j / 3) % 2 == 0,
+        5 => (i * j) % 2 + (i * j) % 3 == 0,
+        6 => ((i * j) % 2 + (i * j) % 3) % 2 == 0,
+        _ => ((i + j) % 2 + (i * j) % 3) % 2 == 0,
+    }
+}
+
+/// De-interleave the codewords into blocks, and error correct them.
+///
+/// Returns the data codewords, along with the number of corrected codewords.
+fn correct_blocks(
+    codewords: &[u8],
+    version: usize,
+    ec_level: EcLevel,
+) -> Result<(Vec<u8>, usize), DecodeError> {
+    let level = match ec_level {
+        EcLevel::L => 0,
+        EcLevel::M => 1,
+        EcLevel::Q => 2,
+        EcLevel::H => 3,
+    };
+    let block_count = EC_BLOCKS[level][version] as usize;
+    let ec_len = EC_CODEWORDS_PER_BLOCK[level][version] as usize;
+    let short_count = block_count - codewords.len() % block_count;
+    let short_len = codewords.len() / block_count;
+    let short_data_len = short_len - ec_len;
+
+    let mut blocks: Vec<Vec<u8>> = vec![Vec::new(); block_count];
+    let mut codewords = codewords.iter();
+    for i in 0..=short_len {
+        for (j, block) in blocks.iter_mut().enumerate() {
+            // Short blocks have one data codeword less than long blocks
+            if i == short_data_len && j < short_count {
+                continue;
+            }
+            block.extend(codewords.next());
+        }
+    }
+
+    let mut data = Vec::new();
+    let mut corrected = 0;
+    for mut block in blocks {
+        corrected += reed_solomon::correct(&mut block, ec_len).ok_or(DecodeError::Correction)?;
+        data.extend_from_slice(&block[..block.len() - ec_len]);
+    }
+    Ok((data, corrected))
+}
+
+/// Reads bits from a byte slice, most significant bit first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.position
+    }
+
+    fn read(&mut self, count: usize) -> Result<u32, DecodeError> {
+        if count > self.remaining() {
+            return Err(DecodeError::Data);
+        }
+        let value = (self.position..self.position + count).fold(0, |acc, i| {
+            (acc << 1) | u32::from(self.data[i / 8] >> (7 - i % 8) & 1)
+        });
+        self.position += count;
+        Ok(value)
+    }
+}
+
+/// Parse the data segments of the data codewords into `decoded`.
+fn parse_segments(data: &[u8], version: usize, decoded: &mut Decoded) -> Result<(), DecodeError> {
+    let version = Version::Normal(version as i16);
+    let mut bits = BitReader { data, position: 0 };
+
+    while bits.remaining() >= 4 {
+        let mode = match bits.read(4)? {
+            0b0000 => break,
+            0b0001 => SegmentMode::Numeric,
+            0b0010 => SegmentMode::Alphanumeric,
+            0b0100 => SegmentMode::Byte,
+            0b1000 => SegmentMode::Kanji,
+            0b0111 => {
+                let eci = match bits.read(8)? {
+                    first if first & 0x80 == 0 => first,
+                    first if first & 0xC0 == 0x80 => (first & 0x3F) << 8 | bits.read(8)?,
+                    first if first & 0xE0 == 0xC0 => (first & 0x1F) << 16 | bits.read(16)?,
+                    _ => return Err(DecodeError::Data),
+                };
+                decoded.eci.get_or_insert(eci);
+                continue;
+            }
+            0b0101 => {
+                decoded.gs1 = true;
+                continue;
+            }
+            0b1001 => {
+                bits.read(8)?;
+                continue;
+            }
+            0b0011 => {
+                bits.read(16)?;
+                continue;
+            }
+            _ => return Err(DecodeError::Data),
+        };
+
+        let count = bits.read(mode.length_bits_count(version))? as usize;
+        let out = &mut decoded.data;
+        match mode {
+            SegmentMode::Numeric => {
+                for chunk in (0..count).step_by(3).map(|i| (count - i).min(3)) {
+                    let value = bits.read([0, 4, 7, 10][chunk])?;
+                    let digits = format!("{:0width$}", value, width = chunk);
+                    if digits.len() != chunk {
+                        return Err(DecodeError::Data);
+                    }
+                    out.extend_from_slice(digits.as_bytes());
+                }
+            }
+            SegmentMode::Alphanumeric => {
+                let mut text = Vec::with_capacity(count);
+                for chunk in (0..count).step_by(2).map(|i| (count - i).min(2)) {
+                    let value = bits.read([0, 6, 11][chunk])? as usize;
+                    let chars = match chunk {
+                        2 => [value / 45, value % 45].to_vec(),
+                        _ => vec![value],
+                    };
+                    for c in chars {
+                        text.push(*ALPHANUMERIC.get(c).ok_or(DecodeError::Data)?);
+                    }
+                }
+                if decoded.gs1 {
+                    text = unescape_gs1(&text);
+                }
+                out.extend_from_slice(&text);
+            }
+            SegmentMode::Byte => {
+                for _ in 0..count {
+                    out.push(bits.read(8)? as u8);
+                }
+            }
+            SegmentMode::Kanji => {
+                for _ in 0..count {
+                    let value = bits.read(13)?;
+                    let code = (value / 0xC0) << 8 | (value % 0xC0);
+                    let code = if code + 0x8140 <= 0x9FFC {
+                        code + 0x8140
+                    } else {
+                        code + 0xC140
+                    };
+                    out.extend_from_slice(&(code as u16).to_be_bytes());
+                }
+            }
+        }
+        decoded.segments.push(mode);
+    }
+    Ok(())
+}
+
+/// Replace `%` by the group separator, and `%%` by `%`, in a GS1 alphanumeric segment.
+fn unescape_gs1(text: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut chars = text.iter().copied().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            (b'%', Some(b'%')) => {
+                chars.next();
+                out.push(b'%');
+            }
+            (b'%', _) => out.push(b'\x1D'),
+            (c, _) => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::Rotation;
+    use crate::payload::{Mode, Payload};
+    use crate::qr::Qr;
+
+    #[test]
+    fn all_versions_and_levels() {
+        for ec_level in [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+            for len in (1..2900).step_by(211) {
+                let data: Vec<u8> = (0..len).map(|i| (i * 7 % 256) as u8).collect();
+                let qr = match Qr::from_payload(&Payload::new(&data[..]), ec_level) {
+                    Ok(qr) => qr,
+                    Err(_) => break,
+                };
+                let decoded = decode(&qr.to_matrix()).unwrap();
+                assert_eq!(decoded.data, data);
+                assert_eq!(decoded.ec_level, ec_level);
+                assert_eq!(decoded.version, qr.info().version);
+                assert_eq!(decoded.mask, qr.info().mask);
+            }
+        }
+    }
+
+    #[test]
+    fn modes() {
+        let payload = Payload::new(&b"0123456789 HELLO WORLD %*+ hello"[..]);
+        let decoded = decode(&Qr::from_payload(&payload, EcLevel::M).unwrap().to_matrix()).unwrap();
+        assert_eq!(decoded.data, payload.data());
+
+        let payload = Payload::new(&b"12%\x1D34"[..])
+            .with_mode(Mode::Gs1)
+            .with_eci(3);
+        let decoded = decode(&Qr::from_payload(&payload, EcLevel::M).unwrap().to_matrix()).unwrap();
+        assert_eq!(decoded.data, payload.data());
+        assert_eq!((decoded.eci, decoded.gs1), (Some(3), true));
+    }
+
+    #[test]
+    fn orientation_and_damage() {
+        let qr = Qr::from("https://rust-lang.org/").unwrap();
+        for rotation in Rotation::ALL {
+            let mut matrix = qr.to_matrix();
+            matrix.surround(3, crate::render::QrLight);
+            matrix.mirror();
+            matrix.rotate(rotation);
+            assert_eq!(decode(&matrix).unwrap().data, b"https://rust-lang.org/");
+        }
+
+        // Flip a few data modules in the bottom right corner
+        let mut pixels = qr.to_matrix().pixels().to_vec();
+        let width = qr.info().module_count;
+        for i in [
+            width * width - 1,
+            width * width - 2,
+            width * (width - 1) - 1,
+        ] {
+            pixels[i] = match pixels[i] {
+                QrDark => crate::render::QrLight,
+                _ => QrDark,
+            };
+        }
+        let decoded = decode(&Matrix::new(pixels)).unwrap();
+        assert_eq!(decoded.data, b"https://rust-lang.org/");
+        assert!(decoded.corrected > 0);
+
+        assert_eq!(
+            decode(&Matrix::new(vec![crate::render::QrLight; 25])),
+            Err(DecodeError::Locate)
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! Comparing the module matrices of two QR codes.
+
+use std::io::{self, Write};
+
+use super::layout::render_colors;
+use crate::ansi;
+use crate::matrix::Matrix;
+use crate::render::{Color, QrDark, QrLight};
+
+/// Palette color of modules that differ between the compared matrices.
+pub const DIFF_COLOR: u8 = 196;
+
+/// Render the module matrix `a`, highlighting modules that differ in `b` in
+/// red, followed by the number of differing modules.
+///
+/// The matrices are aligned at their top left corner. If their sizes differ,
+/// modules outside of the smaller matrix are treated as light. Two modules are
+/// drawn above each other per character.
+///
+/// Returns the number of differing modules, or an error if writing failed.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::debug;
+/// use qr2term::qr::Qr;
+///
+/// let a = Qr::from("qr2term").unwrap().to_matrix();
+/// let b = Qr::from("qr2term!").unwrap().to_matrix();
+/// let mut stdout = std::io::stdout();
+/// let count = debug::diff(&a, &b, &mut stdout).unwrap();
+/// assert!(count > 0);
+/// ```
+pub fn diff<W: Write>(a: &Matrix<Color>, b: &Matrix<Color>, target: &mut W) -> io::Result<usize> {
+    let size = a.size().max(b.size());
+    let get = |matrix: &Matrix<Color>, row: usize, col: usize| {
+        let width = matrix.size();
+        match row < width && col < width {
+            true => matrix.pixels()[row * width + col],
+            false => QrLight,
+        }
+    };
+
+    let mut count = 0;
+    let pixels = (0..size * size)
+        .map(|i| {
+            let (row, col) = (i / size, i % size);
+            match (get(a, row, col), get(b, row, col)) {
+                (QrDark, QrDark) => ansi::BLACK,
+                (QrLight, QrLight) => ansi::WHITE,
+                _ => {
+                    count += 1;
+                    DIFF_COLOR
+                }
+            }
+        })
+        .collect();
+    render_colors(&Matrix::new(pixels), target)?;
+
+    match count {
+        1 => writeln!(target, "1 module differs")?,
+        _ => writeln!(target, "{} modules differ", count)?,
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qr::Qr;
+
+    #[test]
+    fn identical() {
+        let a = Qr::from("qr2term").unwrap().to_matrix();
+        let mut buf = Vec::new();
+        assert_eq!(diff(&a, &a, &mut buf).unwrap(), 0);
+        let output = String::from_utf8(buf).unwrap();
+        assert!(output.ends_with("0 modules differ\n"));
+        assert!(!output.contains("196"));
+    }
+
+    #[test]
+    fn differing_modules() {
+        let a = Matrix::new(vec![QrDark, QrLight, QrLight, QrDark]);
+        let b = Matrix::new(vec![QrDark, QrDark, QrLight, QrDark]);
+        let mut buf = Vec::new();
+        assert_eq!(diff(&a, &b, &mut buf).unwrap(), 1);
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "\x1B[48;5;15m\x1B[38;5;0m▀\x1B[49m\x1B[39m\
+             \x1B[48;5;0m\x1B[38;5;196m▀\x1B[49m\x1B[39m\n\
+             1 module differs\n"
+        );
+
+        // Modules outside of the smaller matrix are light
+        let c = Matrix::new(vec![QrDark; 9]);
+        assert_eq!(diff(&a, &c, &mut Vec::new()).unwrap(), 7);
+    }
+}
@@ -0,0 +1,389 @@
+//! The layout of function patterns and data in QR code symbols.
+
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+
+use crate::ansi;
+use crate::matrix::Matrix;
+use crate::qr::Version;
+use crate::render::{Color, QrDark, QrLight};
+use crate::{Error, QrOptions, ToQrPayload};
+
+/// The function of a module in a QR code symbol.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ModuleKind {
+    /// The light margin around the symbol.
+    QuietZone,
+
+    /// One of the three finder patterns in the corners.
+    Finder,
+
+    /// The light separator around a finder pattern.
+    Separator,
+
+    /// The alternating timing patterns between the finder patterns.
+    Timing,
+
+    /// An alignment pattern, in version 2 and up.
+    Alignment,
+
+    /// Format information, holding the error correction level and mask.
+    Format,
+
+    /// Version information, in version 7 and up.
+    Version,
+
+    /// The module next to the bottom left format information that is always dark.
+    DarkModule,
+
+    /// Data and error correction codewords, or remainder bits.
+    Data,
+}
+
+impl ModuleKind {
+    /// All module kinds.
+    pub const ALL: [ModuleKind; 9] = [
+        ModuleKind::QuietZone,
+        ModuleKind::Finder,
+        ModuleKind::Separator,
+        ModuleKind::Timing,
+        ModuleKind::Alignment,
+        ModuleKind::Format,
+        ModuleKind::Version,
+        ModuleKind::DarkModule,
+        ModuleKind::Data,
+    ];
+
+    /// Human readable name of this kind.
+    pub fn name(self) -> &'static str {
+        match self {
+            ModuleKind::QuietZone => "quiet zone",
+            ModuleKind::Finder => "finder",
+            ModuleKind::Separator => "separator",
+            ModuleKind::Timing => "timing",
+            ModuleKind::Alignment => "alignment",
+            ModuleKind::Format => "format",
+            ModuleKind::Version => "version",
+            ModuleKind::DarkModule => "dark module",
+            ModuleKind::Data => "data",
+        }
+    }
+
+    /// The ANSI 256-color palette index [`render_layout`] draws modules of
+    /// this kind in.
+    pub fn color(self, color: Color) -> u8 {
+        let (dark, light) = match self {
+            ModuleKind::QuietZone | ModuleKind::Data => (ansi::BLACK, ansi::WHITE),
+            ModuleKind::Finder | ModuleKind::Separator => (160, 217),
+            ModuleKind::Timing => (25, 153),
+            ModuleKind::Alignment => (28, 157),
+            ModuleKind::Format => (91, 183),
+            ModuleKind::Version => (130, 223),
+            ModuleKind::DarkModule => (136, 229),
+        };
+        match color {
+            QrDark => dark,
+            QrLight => light,
+        }
+    }
+}
+
+impl Display for ModuleKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Get the kind of every module of a symbol of the given `version`.
+///
+/// The matrix doesn't include a quiet zone. Micro QR codes are not supported.
+///
+/// # Panics
+///
+/// Panics if `version` is not a valid regular QR code version.
+pub fn layout(version: Version) -> Matrix<ModuleKind> {
+    let version = match version {
+        Version::Normal(version @ 1..=40) => version as usize,
+        _ => panic!("unsupported version {:?}", version),
+    };
+    let size = version * 4 + 17;
+    let alignment = alignment_positions(version);
+    Matrix::new(
+        (0..size * size)
+            .map(|i| module_kind(version, &alignment, i / size, i % size))
+            .collect(),
+    )
+}
+
+/// Get the kind of the module at `row` and `col` in a symbol of the given `version`.
+///
+/// `alignment` holds the alignment pattern center coordinates, see [`alignment_positions`].
+pub(super) fn module_kind(
+    version: usize,
+    alignment: &[usize],
+    row: usize,
+    col: usize,
+) -> ModuleKind {
+    let n = version * 4 + 17;
+    let near = |start: usize, len: usize, value: usize| (start..start + len).contains(&value);
+
+    for (top, left) in [(0, 0), (0, n - 7), (n - 7, 0)] {
+        if near(top, 7, row) && near(left, 7, col) {
+            return ModuleKind::Finder;
+        }
+        let (top, left) = (top.saturating_sub(1), left.saturating_sub(1));
+        if near(top, 8, row) && near(left, 8, col) {
+            return ModuleKind::Separator;
+        }
+    }
+    if (row, col) == (n - 8, 8) {
+        return ModuleKind::DarkModule;
+    }
+    if (row == 8 && col != 6 && (col < 9 || col >= n - 8))
+        || (col == 8 && row != 6 && (row < 9 || row >= n - 7))
+    {
+        return ModuleKind::Format;
+    }
+    if row == 6 || col == 6 {
+        return ModuleKind::Timing;
+    }
+    if version >= 7 && ((row < 6 && near(n - 11, 3, col)) || (col < 6 && near(n - 11, 3, row))) {
+        return ModuleKind::Version;
+    }
+    let is_alignment = alignment.iter().any(|&ar| {
+        alignment.iter().any(|&ac| {
+            let corner = (ar == 6 && (ac == 6 || ac == n - 7)) || (ar == n - 7 && ac == 6);
+            !corner && row.abs_diff(ar) <= 2 && col.abs_diff(ac) <= 2
+        })
+    });
+    if is_alignment {
+        ModuleKind::Alignment
+    } else {
+        ModuleKind::Data
+    }
+}
+
+/// Get the alignment pattern center coordinates for `version`.
+pub(super) fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let step = match version {
+        32 => 26,
+        _ => (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2,
+    };
+    let last = version * 4 + 10;
+    let mut positions: Vec<usize> = (0..count - 1).map(|i| last - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// Render the QR code for `data` with every module colored by its kind,
+/// followed by a legend.
+///
+/// Dark modules are drawn in a dark shade of the color of their kind, and
+/// light modules in a light shade, see [`ModuleKind::color`]. The quiet zone,
+/// mirroring and rotation configured in `options` are applied. Two modules are
+/// drawn above each other per character, or one module per two characters
+/// with row and column indices if [`QrOptions::ruler`] is set.
+///
+/// Returns an error if generating the QR code or writing failed.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::{debug, QrOptions};
+///
+/// let mut stdout = std::io::stdout();
+/// debug::render_layout("https://rust-lang.org/", &QrOptions::new(), &mut stdout).unwrap();
+/// ```
+pub fn render_layout<D: ToQrPayload, W: Write>(
+    data: D,
+    options: &QrOptions,
+    target: &mut W,
+) -> Result<(), Error> {
+    let code = crate::generate_code(data, options)?;
+    let layout = layout(code.info().version);
+    let pixels = code
+        .to_matrix()
+        .pixels()
+        .iter()
+        .zip(layout.pixels())
+        .map(|(&color, &kind)| kind.color(color))
+        .collect();
+
+    let mut matrix = Matrix::new(pixels);
+    matrix.surround(options.quiet_zone, ModuleKind::QuietZone.color(QrLight));
+    if options.mirror {
+        matrix.mirror();
+    }
+    matrix.rotate(options.rotate);
+    if options.ruler {
+        render_ruler(&matrix, options.quiet_zone, target)?;
+    } else {
+        render_colors(&matrix, target)?;
+    }
+
+    for kind in ModuleKind::ALL {
+        if kind == ModuleKind::QuietZone {
+            continue;
+        }
+        ansi::write_colored(target, "▀", kind.color(QrDark), kind.color(QrLight))?;
+        writeln!(target, " {}", kind)?;
+    }
+    Ok(())
+}
+
+/// Render a matrix of palette colors, two modules above each other per character.
+pub(super) fn render_colors<W: Write>(matrix: &Matrix<u8>, target: &mut W) -> io::Result<()> {
+    let width = matrix.size();
+    let pixels = matrix.pixels();
+    for row in (0..width).step_by(2) {
+        for col in 0..width {
+            let top = pixels[row * width + col];
+            let bottom = match row + 1 < width {
+                true => pixels[(row + 1) * width + col],
+                false => ansi::WHITE,
+            };
+            ansi::write_colored(target, "▀", top, bottom)?;
+        }
+        writeln!(target)?;
+    }
+    Ok(())
+}
+
+/// Render a matrix of palette colors with row and column indices, one module
+/// per two characters.
+///
+/// Indices start at zero after the `quiet_zone`, whose modules aren't labeled.
+/// Column indices are written vertically above the code, most significant
+/// digit first.
+pub(super) fn render_ruler<W: Write>(
+    matrix: &Matrix<u8>,
+    quiet_zone: usize,
+    target: &mut W,
+) -> io::Result<()> {
+    let width = matrix.size();
+    let modules = width.saturating_sub(quiet_zone * 2);
+    let label = |i: usize| {
+        (quiet_zone..quiet_zone + modules)
+            .contains(&i)
+            .then(|| i - quiet_zone)
+    };
+    let digits = modules.saturating_sub(1).to_string().len();
+
+    for digit in 0..digits {
+        write!(target, "{:1$}", "", digits + 1)?;
+        for col in 0..width {
+            let text = label(col)
+                .map(|index| format!("{:>1$}", index, digits))
+                .and_then(|index| index.chars().nth(digit))
+                .unwrap_or(' ');
+            write!(target, "{} ", text)?;
+        }
+        writeln!(target)?;
+    }
+
+    let pixels = matrix.pixels();
+    for row in 0..width {
+        match label(row) {
+            Some(index) => write!(target, "{:>1$} ", index, digits)?,
+            None => write!(target, "{:1$}", "", digits + 1)?,
+        }
+        for &color in &pixels[row * width..(row + 1) * width] {
+            ansi::write_colored(target, "  ", color, color)?;
+        }
+        writeln!(target)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::Payload;
+    use crate::qr::Qr;
+    use qrcode::EcLevel;
+
+    /// Check that the layout of `code` matches the one generated by the encoder.
+    fn matches_encoder(code: &Qr) -> bool {
+        let kinds = layout(code.info().version);
+        let width = code.info().module_count;
+        let colors = code.to_matrix();
+        kinds
+            .pixels()
+            .iter()
+            .zip(colors.pixels())
+            .enumerate()
+            .all(|(i, (&kind, &color))| {
+                let (row, col) = (i / width, i % width);
+                match kind {
+                    ModuleKind::Separator => color == QrLight,
+                    ModuleKind::DarkModule => color == QrDark,
+                    ModuleKind::Timing => (color == QrDark) == ((row + col) % 2 == 0),
+                    _ => true,
+                }
+            })
+    }
+
+    #[test]
+    fn module_counts() {
+        let count = |version, kind| {
+            layout(Version::Normal(version))
+                .pixels()
+                .iter()
+                .filter(|&&k| k == kind)
+                .count()
+        };
+        assert_eq!(count(1, ModuleKind::Finder), 3 * 49);
+        assert_eq!(count(1, ModuleKind::Format), 30);
+        assert_eq!(count(1, ModuleKind::Alignment), 0);
+        assert_eq!(count(1, ModuleKind::Data), 208);
+        assert_eq!(count(7, ModuleKind::Alignment), 6 * 25 - 2 * 5);
+        assert_eq!(count(7, ModuleKind::Version), 36);
+        assert_eq!(count(40, ModuleKind::Data), 29648);
+    }
+
+    #[test]
+    fn matches_generated() {
+        for len in [1, 100, 1000] {
+            let data = vec![b'x'; len];
+            let code = Qr::from_payload(&Payload::new(&data[..]), EcLevel::L).unwrap();
+            assert!(matches_encoder(&code));
+        }
+    }
+
+    #[test]
+    fn render_legend() {
+        let mut buf = Vec::new();
+        render_layout("qr2term", &QrOptions::new(), &mut buf).unwrap();
+        let output = String::from_utf8(buf).unwrap();
+        assert_eq!(output.lines().count(), 13 + ModuleKind::ALL.len() - 1);
+        assert!(output.contains("\x1B[48;5;217m\x1B[38;5;160m▀\x1B[49m\x1B[39m finder"));
+    }
+
+    #[test]
+    fn ruler() {
+        let options = QrOptions::new().ruler(true).quiet_zone(1);
+        let mut buf = Vec::new();
+        render_layout("qr2term", &options, &mut buf).unwrap();
+        let output = String::from_utf8(buf).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+
+        // Two header lines, then 21 modules and the quiet zone
+        assert_eq!(lines.len(), 2 + 23 + ModuleKind::ALL.len() - 1);
+        assert_eq!(
+            lines[0],
+            format!("{}{}2   ", " ".repeat(25), "1 ".repeat(10))
+        );
+        assert!(lines[1].starts_with("     0 1 2 3 4 5 6 7 8 9 0 1 "));
+        assert!(lines[2].starts_with("   \x1B[48;5;15m"));
+        assert!(
+            lines[3].starts_with(" 0 \x1B[48;5;15m\x1B[38;5;15m  \x1B[49m\x1B[39m\x1B[48;5;160m")
+        );
+        assert!(lines[23].starts_with("20 "));
+    }
+}
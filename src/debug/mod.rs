@@ -0,0 +1,202 @@
+//! Debugging helpers, for inspecting and verifying generated QR codes.
+
+mod decode;
+mod diff;
+mod layout;
+mod reed_solomon;
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+pub use self::decode::{decode, DecodeError, Decoded};
+pub use self::diff::{diff, DIFF_COLOR};
+pub use self::layout::{layout, render_layout, ModuleKind};
+
+use crate::matrix::MatrixError;
+use crate::qr::QrInfo;
+use crate::{split, QrError, QrOptions, ToQrPayload};
+
+/// Report of a [`roundtrip`] through generating, rendering and decoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoundtripReport {
+    /// Metadata about each generated code.
+    pub info: Vec<QrInfo>,
+
+    /// The data that was encoded, after compression if configured.
+    pub expected: Vec<u8>,
+
+    /// The data decoded from all codes, joined.
+    pub decoded: Vec<u8>,
+
+    /// The ECI designator that was encoded, if any.
+    pub expected_eci: Option<u32>,
+
+    /// The ECI designator decoded from the first code, if any.
+    pub decoded_eci: Option<u32>,
+
+    /// Number of codewords fixed by error correction, which is zero for a
+    /// correctly rendered code.
+    pub corrected: usize,
+}
+
+impl RoundtripReport {
+    /// Whether the decoded data and ECI designator match what was encoded.
+    pub fn is_match(&self) -> bool {
+        self.expected == self.decoded && self.expected_eci == self.decoded_eci
+    }
+
+    /// Get the index of the first byte where the decoded data differs from the
+    /// expected data, if any.
+    pub fn mismatch(&self) -> Option<usize> {
+        let common = self.expected.len().min(self.decoded.len());
+        (0..common)
+            .find(|&i| self.expected[i] != self.decoded[i])
+            .or_else(|| (self.expected.len() != self.decoded.len()).then_some(common))
+    }
+}
+
+/// Generate QR codes for `data`, render them to a module bitmap and decode
+/// them again, comparing the result.
+///
+/// The bitmap includes the quiet zone, mirroring and rotation configured in
+/// `options`, as printed to the terminal. Data that is split over multiple
+/// codes is joined after decoding. With [`Overflow::Truncate`](crate::options::Overflow::Truncate)
+/// the expected data is the full data, so truncated data doesn't match.
+///
+/// Returns an error if generating or decoding a code failed. Use
+/// [`RoundtripReport::is_match`] to check the decoded data.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::{debug, QrOptions};
+///
+/// let report = debug::roundtrip("https://rust-lang.org/", &QrOptions::new()).unwrap();
+/// assert!(report.is_match());
+/// ```
+pub fn roundtrip<D: ToQrPayload>(
+    data: D,
+    options: &QrOptions,
+) -> Result<RoundtripReport, RoundtripError> {
+    let payload = crate::prepare_payload(data.to_qr_payload(), options);
+    let mut report = RoundtripReport {
+        info: Vec::new(),
+        expected: payload.data().to_vec(),
+        decoded: Vec::new(),
+        expected_eci: payload.eci(),
+        decoded_eci: None,
+        corrected: 0,
+    };
+
+    for (i, part) in split::split(&payload, options)?.iter().enumerate() {
+        let code = crate::generate_part(part, options)?;
+        let decoded = decode(&crate::code_matrix(&code, options)?)?;
+        report.info.push(code.info());
+        report.decoded.extend_from_slice(&decoded.data);
+        report.corrected += decoded.corrected;
+        if i == 0 {
+            report.decoded_eci = decoded.eci;
+        }
+    }
+    Ok(report)
+}
+
+/// Error returned when a [`roundtrip`] failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoundtripError {
+    /// Generating the QR code failed.
+    Qr(QrError),
+
+    /// Decoding the rendered QR code failed.
+    Decode(DecodeError),
+
+    /// Rendering the QR code failed, as it would be too large.
+    Matrix(MatrixError),
+}
+
+impl Display for RoundtripError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoundtripError::Qr(err) => write!(f, "failed to generate QR code: {}", err),
+            RoundtripError::Decode(err) => write!(f, "failed to decode QR code: {}", err),
+            RoundtripError::Matrix(err) => write!(f, "failed to render QR code: {}", err),
+        }
+    }
+}
+
+impl Error for RoundtripError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RoundtripError::Qr(err) => Some(err),
+            RoundtripError::Decode(err) => Some(err),
+            RoundtripError::Matrix(err) => Some(err),
+        }
+    }
+}
+
+impl From<QrError> for RoundtripError {
+    fn from(err: QrError) -> Self {
+        RoundtripError::Qr(err)
+    }
+}
+
+impl From<MatrixError> for RoundtripError {
+    fn from(err: MatrixError) -> Self {
+        RoundtripError::Matrix(err)
+    }
+}
+
+impl From<DecodeError> for RoundtripError {
+    fn from(err: DecodeError) -> Self {
+        RoundtripError::Decode(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::charset::Charset;
+    use crate::gs1::Gs1;
+    use crate::options::{EcLevel, Overflow, Rotation};
+
+    #[test]
+    fn roundtrip_options() {
+        let options = QrOptions::new()
+            .ec_level(EcLevel::H)
+            .rotate(Rotation::Rotate270)
+            .mirror(true);
+        assert!(roundtrip("qr2term", &options).unwrap().is_match());
+        assert!(
+            roundtrip("qr2term ".repeat(200), &options.clone().compress(true))
+                .unwrap()
+                .is_match()
+        );
+
+        let report = roundtrip("a".repeat(3000), &options.on_overflow(Overflow::Split)).unwrap();
+        assert!(report.is_match());
+        assert!(report.info.len() > 1);
+    }
+
+    #[test]
+    fn roundtrip_payloads() {
+        let gs1 = Gs1::new()
+            .gtin("09501101530003")
+            .unwrap()
+            .batch("A%1")
+            .unwrap();
+        assert!(roundtrip(&gs1, &QrOptions::new()).unwrap().is_match());
+
+        let payload = Charset::Iso8859_1.payload("Grüße").unwrap();
+        let report = roundtrip(&payload, &QrOptions::new()).unwrap();
+        assert!(report.is_match());
+        assert_eq!(report.decoded_eci, Some(3));
+    }
+
+    #[test]
+    fn truncated_mismatch() {
+        let options = QrOptions::new().on_overflow(Overflow::Truncate);
+        let report = roundtrip("a".repeat(5000), &options).unwrap();
+        assert!(!report.is_match());
+        assert_eq!(report.mismatch(), Some(report.decoded.len()));
+    }
+}
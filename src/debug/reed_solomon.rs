@@ -0,0 +1,186 @@
+//! Reed-Solomon error correction over GF(256), as used by QR codes.
+
+/// Primitive polynomial of the QR code Galois field, `x^8 + x^4 + x^3 + x^2 + 1`.
+const PRIMITIVE: u16 = 0x11D;
+
+/// Exponent and logarithm tables of the Galois field.
+struct Field {
+    exp: [u8; 512],
+    log: [u8; 256],
+}
+
+impl Field {
+    fn new() -> Self {
+        let mut field = Field {
+            exp: [0; 512],
+            log: [0; 256],
+        };
+        let mut x: u16 = 1;
+        for i in 0..255 {
+            field.exp[i] = x as u8;
+            field.log[x as usize] = i as u8;
+            x <<= 1;
+            if x & 0x100 != 0 {
+                x ^= PRIMITIVE;
+            }
+        }
+        for i in 255..512 {
+            field.exp[i] = field.exp[i - 255];
+        }
+        field
+    }
+
+    fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        self.exp[self.log[a as usize] as usize + self.log[b as usize] as usize]
+    }
+
+    fn div(&self, a: u8, b: u8) -> u8 {
+        assert!(b != 0, "division by zero");
+        if a == 0 {
+            return 0;
+        }
+        self.exp[self.log[a as usize] as usize + 255 - self.log[b as usize] as usize]
+    }
+
+    /// Get `α^power`, for any power.
+    fn pow(&self, power: isize) -> u8 {
+        self.exp[power.rem_euclid(255) as usize]
+    }
+
+    /// Evaluate a polynomial with its lowest degree coefficient first at `x`.
+    fn eval(&self, poly: &[u8], x: u8) -> u8 {
+        poly.iter()
+            .rev()
+            .fold(0, |acc, &coefficient| self.mul(acc, x) ^ coefficient)
+    }
+}
+
+/// Correct errors in a block of data codewords followed by `ec_len` error
+/// correction codewords, in place.
+///
+/// Returns the number of corrected codewords, or `None` if the block has
+/// more errors than can be corrected.
+pub fn correct(block: &mut [u8], ec_len: usize) -> Option<usize> {
+    let field = Field::new();
+    let n = block.len();
+
+    // Syndromes, the received polynomial evaluated at the generator roots
+    // α^0 to α^(ec_len - 1). The first codeword has the highest degree.
+    let syndromes = |block: &[u8]| -> Vec<u8> {
+        (0..ec_len)
+            .map(|i| {
+                let x = field.pow(i as isize);
+                block.iter().fold(0, |acc, &c| field.mul(acc, x) ^ c)
+            })
+            .collect()
+    };
+    let s = syndromes(block);
+    if s.iter().all(|&s| s == 0) {
+        return Some(0);
+    }
+
+    // Berlekamp-Massey, finding the error locator polynomial
+    let mut locator = vec![1u8];
+    let mut previous = vec![1u8];
+    let (mut errors, mut shift, mut previous_discrepancy) = (0, 1, 1u8);
+    for k in 0..ec_len {
+        let discrepancy = (1..=errors).fold(s[k], |acc, i| {
+            acc ^ field.mul(*locator.get(i).unwrap_or(&0), s[k - i])
+        });
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+
+        let scale = field.div(discrepancy, previous_discrepancy);
+        let mut next = locator.clone();
+        next.resize(next.len().max(previous.len() + shift), 0);
+        for (i, &coefficient) in previous.iter().enumerate() {
+            next[i + shift] ^= field.mul(scale, coefficient);
+        }
+
+        if 2 * errors <= k {
+            previous = std::mem::replace(&mut locator, next);
+            errors = k + 1 - errors;
+            previous_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            locator = next;
+            shift += 1;
+        }
+    }
+    if 2 * errors > ec_len {
+        return None;
+    }
+
+    // Chien search, the locator has roots at the inverse error locations
+    let positions: Vec<usize> = (0..n)
+        .filter(|&degree| field.eval(&locator, field.pow(-(degree as isize))) == 0)
+        .collect();
+    if positions.len() != errors {
+        return None;
+    }
+
+    // Forney, with the error evaluator S(x)Λ(x) mod x^ec_len
+    let mut evaluator = vec![0u8; ec_len];
+    for (i, &si) in s.iter().enumerate() {
+        for (j, &lj) in locator.iter().enumerate().take(ec_len - i) {
+            evaluator[i + j] ^= field.mul(si, lj);
+        }
+    }
+    let derivative: Vec<u8> = locator
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, &c)| if i % 2 == 1 { c } else { 0 })
+        .collect();
+    for &degree in &positions {
+        let x = field.pow(degree as isize);
+        let x_inverse = field.pow(-(degree as isize));
+        let denominator = field.eval(&derivative, x_inverse);
+        if denominator == 0 {
+            return None;
+        }
+        let magnitude = field.mul(x, field.div(field.eval(&evaluator, x_inverse), denominator));
+        block[n - 1 - degree] ^= magnitude;
+    }
+
+    syndromes(block)
+        .iter()
+        .all(|&s| s == 0)
+        .then_some(positions.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corrects_errors() {
+        // "HELLO WORLD" 1-M data and error correction codewords, from the specification
+        let codewords = [
+            0x20, 0x5B, 0x0B, 0x78, 0xD1, 0x72, 0xDC, 0x4D, 0x43, 0x40, 0xEC, 0x11, 0xEC, 0x11,
+            0xEC, 0x11, 0xC4, 0x23, 0x27, 0x77, 0xEB, 0xD7, 0xE7, 0xE2, 0x5D, 0x17,
+        ];
+        let mut block = codewords;
+        assert_eq!(correct(&mut block, 10), Some(0));
+
+        for errors in [[0, 5, 25, 7, 13], [1, 2, 3, 4, 24]] {
+            let mut block = codewords;
+            for &i in &errors {
+                block[i] ^= 0x5A;
+            }
+            assert_eq!(correct(&mut block, 10), Some(5));
+            assert_eq!(block, codewords);
+        }
+
+        let mut block = codewords;
+        for i in 0..6 {
+            block[i * 3] ^= 0xFF;
+        }
+        assert_ne!(correct(&mut block, 10).map(|_| block), Some(codewords));
+    }
+}
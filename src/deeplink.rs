@@ -0,0 +1,640 @@
+//! Deep links into messaging and meeting apps, for support workflows.
+//!
+//! [`DeepLink`] builds links that open a call or chat when scanned, with the
+//! validation and encoding rules of each app in one place:
+//!
+//! - FaceTime video and audio calls, through `facetime://` and `facetime-audio://`
+//! - WhatsApp chats, through `https://wa.me/`
+//! - Telegram chats and bots, through `tg://resolve`
+//! - Zoom meetings, through `zoommtg://zoom.us/join`
+//! - App listings on Google Play and the App Store, for installing companion apps
+//! - Matrix rooms and users, through `https://matrix.to/`
+//! - Invites to any web app, with tokens as query parameters
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::payload::{Payload, ToQrPayload};
+
+/// A deep link into an app, see the [module documentation](self).
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::deeplink::DeepLink;
+///
+/// let link = DeepLink::whatsapp("+31 6 1234 5678", Some("Order #42 is late"))?;
+/// assert_eq!(link.as_str(), "https://wa.me/31612345678?text=Order%20%2342%20is%20late");
+/// qr2term::print_qr(&link).unwrap();
+/// # Ok::<(), qr2term::deeplink::DeepLinkError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeepLink {
+    url: String,
+}
+
+impl DeepLink {
+    /// Link to a FaceTime video call with the given phone number or email address.
+    ///
+    /// Returns an error if `handle` is neither a valid phone number nor an email address.
+    pub fn facetime(handle: &str) -> Result<Self, DeepLinkError> {
+        Ok(Self {
+            url: format!("facetime://{}", facetime_handle(handle)?),
+        })
+    }
+
+    /// Link to a FaceTime audio call with the given phone number or email address.
+    ///
+    /// Returns an error if `handle` is neither a valid phone number nor an email address.
+    pub fn facetime_audio(handle: &str) -> Result<Self, DeepLinkError> {
+        Ok(Self {
+            url: format!("facetime-audio://{}", facetime_handle(handle)?),
+        })
+    }
+
+    /// Link to a WhatsApp chat with the given phone number, with an optional
+    /// prefilled message.
+    ///
+    /// The phone number must include the country code, and may be formatted
+    /// with spaces, dashes, dots and parentheses.
+    ///
+    /// Returns an error if `phone` is not a valid international phone number.
+    pub fn whatsapp(phone: &str, text: Option<&str>) -> Result<Self, DeepLinkError> {
+        let digits = phone_digits(phone)
+            .filter(|digits| !digits.starts_with('0'))
+            .ok_or_else(|| DeepLinkError::InvalidPhone(phone.into()))?;
+        let mut url = format!("https://wa.me/{}", digits);
+        if let Some(text) = text {
+            url.push_str("?text=");
+            url.push_str(&encode(text));
+        }
+        Ok(Self { url })
+    }
+
+    /// Link to a Telegram user, group, channel or bot by its username, with an
+    /// optional start parameter for bots.
+    ///
+    /// A leading `@` in `username` is ignored.
+    ///
+    /// Returns an error if `username` is not 5 to 32 letters, digits and
+    /// underscores, or if `start` is not up to 64 letters, digits, underscores
+    /// and dashes.
+    pub fn telegram(username: &str, start: Option<&str>) -> Result<Self, DeepLinkError> {
+        let name = username.trim().trim_start_matches('@');
+        let valid = (5..=32).contains(&name.len())
+            && name.starts_with(|c: char| c.is_ascii_alphabetic())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(DeepLinkError::InvalidUsername(username.into()));
+        }
+
+        let mut url = format!("tg://resolve?domain={}", name);
+        if let Some(start) = start {
+            let valid = (1..=64).contains(&start.len())
+                && start
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            if !valid {
+                return Err(DeepLinkError::InvalidStartParameter(start.into()));
+            }
+            url.push_str("&start=");
+            url.push_str(start);
+        }
+        Ok(Self { url })
+    }
+
+    /// Link to join a Zoom meeting, with an optional passcode.
+    ///
+    /// The meeting ID may be formatted with spaces and dashes.
+    ///
+    /// Returns an error if `meeting_id` is not 9 to 11 digits.
+    pub fn zoom(meeting_id: &str, passcode: Option<&str>) -> Result<Self, DeepLinkError> {
+        let digits: String = meeting_id
+            .chars()
+            .filter(|&c| c != ' ' && c != '-')
+            .collect();
+        if !(9..=11).contains(&digits.len()) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(DeepLinkError::InvalidMeetingId(meeting_id.into()));
+        }
+
+        let mut url = format!("zoommtg://zoom.us/join?action=join&confno={}", digits);
+        if let Some(passcode) = passcode {
+            url.push_str("&pwd=");
+            url.push_str(&encode(passcode));
+        }
+        Ok(Self { url })
+    }
+
+    /// Link to the Google Play listing of the Android app with the given package name.
+    ///
+    /// Returns an error if `package` is not a valid package name, such as `com.example.app`.
+    pub fn play_store(package: &str) -> Result<Self, DeepLinkError> {
+        Ok(Self {
+            url: play_store_url(package)?,
+        })
+    }
+
+    /// Link to the App Store listing of the iOS app with the given numeric ID,
+    /// with or without its `id` prefix.
+    ///
+    /// Returns an error if `app_id` is not a valid App Store ID, such as `id284882215`.
+    pub fn app_store(app_id: &str) -> Result<Self, DeepLinkError> {
+        Ok(Self {
+            url: app_store_url(app_id)?,
+        })
+    }
+
+    /// Link to the listings of an app on Android, iOS or both.
+    ///
+    /// With a single platform, this is the link to its store listing. With both,
+    /// this is `chooser` with `android` and `ios` query parameters appended,
+    /// for a smart-link page that redirects to the store of the scanning
+    /// device. Without a `chooser`, this is a text listing both links, one
+    /// per line, for the user to pick from.
+    ///
+    /// Returns an error if neither platform is given, if an ID is invalid, or
+    /// if `chooser` is not an HTTP or HTTPS URL.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use qr2term::deeplink::DeepLink;
+    ///
+    /// let link = DeepLink::app_listing(
+    ///     Some("com.example.companion"),
+    ///     Some("id1234567890"),
+    ///     Some("https://example.com/get-app"),
+    /// )?;
+    /// assert_eq!(
+    ///     link.as_str(),
+    ///     "https://example.com/get-app?android=com.example.companion&ios=1234567890"
+    /// );
+    /// # Ok::<(), qr2term::deeplink::DeepLinkError>(())
+    /// ```
+    pub fn app_listing(
+        android: Option<&str>,
+        ios: Option<&str>,
+        chooser: Option<&str>,
+    ) -> Result<Self, DeepLinkError> {
+        let url = match (android, ios) {
+            (None, None) => return Err(DeepLinkError::MissingApp),
+            (Some(package), None) => play_store_url(package)?,
+            (None, Some(app_id)) => app_store_url(app_id)?,
+            (Some(package), Some(app_id)) => {
+                let android = play_store_url(package)?;
+                let ios = app_store_url(app_id)?;
+                match chooser {
+                    Some(chooser) => with_query(
+                        chooser,
+                        &[("android", package.trim()), ("ios", app_store_id(app_id)?)],
+                    )?,
+                    None => format!("Android: {}\niOS: {}", android, ios),
+                }
+            }
+        };
+        Ok(Self { url })
+    }
+
+    /// Link to a Matrix room by its alias, such as `#ops:example.org`, or its
+    /// ID, such as `!abc123:example.org`.
+    ///
+    /// The `via` servers help clients join rooms by ID, and are usually
+    /// servers of users already in the room.
+    ///
+    /// Returns an error if `room` is not a room alias or ID.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use qr2term::deeplink::DeepLink;
+    ///
+    /// let link = DeepLink::matrix_room("#ops:example.org", &[])?;
+    /// assert_eq!(link.as_str(), "https://matrix.to/#/%23ops%3Aexample.org");
+    /// # Ok::<(), qr2term::deeplink::DeepLinkError>(())
+    /// ```
+    pub fn matrix_room(room: &str, via: &[&str]) -> Result<Self, DeepLinkError> {
+        let mut url = matrix_to(room, &['#', '!'])?;
+        for (i, server) in via.iter().enumerate() {
+            url.push(if i == 0 { '?' } else { '&' });
+            url.push_str("via=");
+            url.push_str(&encode(server.trim()));
+        }
+        Ok(Self { url })
+    }
+
+    /// Link to a Matrix user by their ID, such as `@alice:example.org`.
+    ///
+    /// Returns an error if `user` is not a user ID.
+    pub fn matrix_user(user: &str) -> Result<Self, DeepLinkError> {
+        Ok(Self {
+            url: matrix_to(user, &['@'])?,
+        })
+    }
+
+    /// Link to an invite page of a web app, with the given query parameters
+    /// such as an invite token.
+    ///
+    /// Parameters are percent-encoded and appended to any query `url` has.
+    ///
+    /// Returns an error if `url` is not an HTTP or HTTPS URL, or has a fragment.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use qr2term::deeplink::DeepLink;
+    ///
+    /// let link = DeepLink::invite("https://chat.example.com/signup", &[("token", "a+b/c")])?;
+    /// assert_eq!(link.as_str(), "https://chat.example.com/signup?token=a%2Bb%2Fc");
+    /// # Ok::<(), qr2term::deeplink::DeepLinkError>(())
+    /// ```
+    pub fn invite(url: &str, params: &[(&str, &str)]) -> Result<Self, DeepLinkError> {
+        Ok(Self {
+            url: with_query(url, params)?,
+        })
+    }
+
+    /// The link.
+    pub fn as_str(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Display for DeepLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.url)
+    }
+}
+
+impl ToQrPayload for DeepLink {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        self.url.to_qr_payload()
+    }
+}
+
+/// Error returned when building a [`DeepLink`] from invalid parts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DeepLinkError {
+    /// The phone number is not a valid international phone number.
+    InvalidPhone(String),
+
+    /// The FaceTime handle is neither a phone number nor an email address.
+    InvalidHandle(String),
+
+    /// The Telegram username is invalid.
+    InvalidUsername(String),
+
+    /// The Telegram bot start parameter is invalid.
+    InvalidStartParameter(String),
+
+    /// The Zoom meeting ID is invalid.
+    InvalidMeetingId(String),
+
+    /// The Android package name is invalid.
+    InvalidPackageName(String),
+
+    /// The App Store ID is invalid.
+    InvalidAppStoreId(String),
+
+    /// The URL is not an HTTP or HTTPS URL without a fragment.
+    InvalidUrl(String),
+
+    /// The Matrix room alias, room ID or user ID is invalid.
+    InvalidMatrixId(String),
+
+    /// Neither an Android nor an iOS app was given.
+    MissingApp,
+}
+
+impl Display for DeepLinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeepLinkError::InvalidPhone(phone) => write!(f, "invalid phone number '{}'", phone),
+            DeepLinkError::InvalidHandle(handle) => {
+                write!(f, "invalid FaceTime phone number or email '{}'", handle)
+            }
+            DeepLinkError::InvalidUsername(name) => {
+                write!(f, "invalid Telegram username '{}'", name)
+            }
+            DeepLinkError::InvalidStartParameter(start) => {
+                write!(f, "invalid Telegram start parameter '{}'", start)
+            }
+            DeepLinkError::InvalidMeetingId(id) => write!(f, "invalid Zoom meeting ID '{}'", id),
+            DeepLinkError::InvalidPackageName(package) => {
+                write!(f, "invalid Android package name '{}'", package)
+            }
+            DeepLinkError::InvalidAppStoreId(id) => write!(f, "invalid App Store ID '{}'", id),
+            DeepLinkError::InvalidUrl(url) => write!(f, "invalid URL '{}'", url),
+            DeepLinkError::InvalidMatrixId(id) => write!(f, "invalid Matrix ID '{}'", id),
+            DeepLinkError::MissingApp => f.write_str("no Android or iOS app given"),
+        }
+    }
+}
+
+impl Error for DeepLinkError {}
+
+/// Get the digits of an international `phone` number, without formatting or leading `+`.
+///
+/// Returns `None` if the number has other characters, or not 7 to 15 digits.
+fn phone_digits(phone: &str) -> Option<String> {
+    let phone = phone.trim();
+    let phone = phone.strip_prefix('+').unwrap_or(phone);
+    let digits: String = phone.chars().filter(|c| !" -.()".contains(*c)).collect();
+    let valid = (7..=15).contains(&digits.len()) && digits.bytes().all(|b| b.is_ascii_digit());
+    valid.then_some(digits)
+}
+
+/// Get the FaceTime handle for a phone number or email address.
+fn facetime_handle(handle: &str) -> Result<String, DeepLinkError> {
+    let trimmed = handle.trim();
+    match trimmed.split_once('@') {
+        Some((user, domain))
+            if !user.is_empty()
+                && domain.contains('.')
+                && !trimmed.contains(char::is_whitespace) =>
+        {
+            Ok(encode(trimmed).replace("%40", "@"))
+        }
+        Some(_) => Err(DeepLinkError::InvalidHandle(handle.into())),
+        None => phone_digits(trimmed)
+            .map(|digits| match trimmed.starts_with('+') {
+                true => format!("+{}", digits),
+                false => digits,
+            })
+            .ok_or_else(|| DeepLinkError::InvalidHandle(handle.into())),
+    }
+}
+
+/// Get the Google Play listing URL for an Android `package` name.
+///
+/// A package name has at least two dot-separated segments of letters, digits
+/// and underscores, each starting with a letter.
+fn play_store_url(package: &str) -> Result<String, DeepLinkError> {
+    let name = package.trim();
+    let valid = name.contains('.')
+        && name.split('.').all(|segment| {
+            segment.starts_with(|c: char| c.is_ascii_alphabetic())
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+    if !valid {
+        return Err(DeepLinkError::InvalidPackageName(package.into()));
+    }
+    Ok(format!(
+        "https://play.google.com/store/apps/details?id={}",
+        name
+    ))
+}
+
+/// Get the numeric App Store ID from `app_id`, with or without its `id` prefix.
+fn app_store_id(app_id: &str) -> Result<&str, DeepLinkError> {
+    let trimmed = app_id.trim();
+    let id = trimmed.strip_prefix("id").unwrap_or(trimmed);
+    if id.is_empty() || id.len() > 12 || !id.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(DeepLinkError::InvalidAppStoreId(app_id.into()));
+    }
+    Ok(id)
+}
+
+/// Get the App Store listing URL for an iOS `app_id`.
+fn app_store_url(app_id: &str) -> Result<String, DeepLinkError> {
+    Ok(format!(
+        "https://apps.apple.com/app/id{}",
+        app_store_id(app_id)?
+    ))
+}
+
+/// Get the `matrix.to` link for a Matrix `id` starting with one of the given sigils.
+///
+/// The ID must have a non-empty local part and server name, separated by `:`.
+fn matrix_to(id: &str, sigils: &[char]) -> Result<String, DeepLinkError> {
+    let trimmed = id.trim();
+    let valid = trimmed.starts_with(sigils)
+        && !trimmed.contains(char::is_whitespace)
+        && matches!(
+            trimmed[1..].split_once(':'),
+            Some((local, server)) if !local.is_empty() && !server.is_empty()
+        );
+    if !valid {
+        return Err(DeepLinkError::InvalidMatrixId(id.into()));
+    }
+    Ok(format!("https://matrix.to/#/{}", encode(trimmed)))
+}
+
+/// Append the percent-encoded query `params` to the HTTP or HTTPS `url`.
+fn with_query(url: &str, params: &[(&str, &str)]) -> Result<String, DeepLinkError> {
+    let trimmed = url.trim();
+    let scheme = trimmed.split_once("://").map(|(scheme, _)| scheme);
+    let valid = matches!(scheme, Some("http") | Some("https"))
+        && !trimmed.contains(char::is_whitespace)
+        && !trimmed.contains('#');
+    if !valid {
+        return Err(DeepLinkError::InvalidUrl(url.into()));
+    }
+
+    let mut url = trimmed.to_owned();
+    let mut separator = match url.contains('?') {
+        true => '&',
+        false => '?',
+    };
+    for (name, value) in params {
+        url.push(separator);
+        url.push_str(&encode(name));
+        url.push('=');
+        url.push_str(&encode(value));
+        separator = '&';
+    }
+    Ok(url)
+}
+
+/// Percent-encode all but the unreserved characters of `text`, for use in a URL component.
+fn encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(char::from(byte))
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn facetime() {
+        assert_eq!(
+            DeepLink::facetime("+1 (408) 555-0100").unwrap().as_str(),
+            "facetime://+14085550100"
+        );
+        assert_eq!(
+            DeepLink::facetime_audio("support+mac@example.com")
+                .unwrap()
+                .as_str(),
+            "facetime-audio://support%2Bmac@example.com"
+        );
+        assert!(DeepLink::facetime("support@localhost").is_err());
+        assert!(DeepLink::facetime("call me").is_err());
+    }
+
+    #[test]
+    fn whatsapp() {
+        assert_eq!(
+            DeepLink::whatsapp("+44 20 7946 0958", None)
+                .unwrap()
+                .as_str(),
+            "https://wa.me/442079460958"
+        );
+        assert_eq!(
+            DeepLink::whatsapp("15551234567", Some("Hi & bye ✓"))
+                .unwrap()
+                .as_str(),
+            "https://wa.me/15551234567?text=Hi%20%26%20bye%20%E2%9C%93"
+        );
+        assert_eq!(
+            DeepLink::whatsapp("06 1234 5678", None),
+            Err(DeepLinkError::InvalidPhone("06 1234 5678".into()))
+        );
+        assert!(DeepLink::whatsapp("+1 555 CALL NOW", None).is_err());
+    }
+
+    #[test]
+    fn telegram() {
+        assert_eq!(
+            DeepLink::telegram("@qr2term_bot", Some("pair-42"))
+                .unwrap()
+                .as_str(),
+            "tg://resolve?domain=qr2term_bot&start=pair-42"
+        );
+        assert!(DeepLink::telegram("abc", None).is_err());
+        assert!(DeepLink::telegram("1support", None).is_err());
+        assert_eq!(
+            DeepLink::telegram("support", Some("a b")),
+            Err(DeepLinkError::InvalidStartParameter("a b".into()))
+        );
+    }
+
+    #[test]
+    fn zoom() {
+        assert_eq!(
+            DeepLink::zoom("123 4567 8901", Some("s3cr3t/="))
+                .unwrap()
+                .as_str(),
+            "zoommtg://zoom.us/join?action=join&confno=12345678901&pwd=s3cr3t%2F%3D"
+        );
+        assert!(DeepLink::zoom("1234", None).is_err());
+        assert!(DeepLink::zoom("123-456-78x", None).is_err());
+    }
+
+    #[test]
+    fn matrix() {
+        assert_eq!(
+            DeepLink::matrix_room("!abc123:example.org", &["example.org", "matrix.org"])
+                .unwrap()
+                .as_str(),
+            "https://matrix.to/#/%21abc123%3Aexample.org?via=example.org&via=matrix.org"
+        );
+        assert_eq!(
+            DeepLink::matrix_user(" @alice:example.org ")
+                .unwrap()
+                .as_str(),
+            "https://matrix.to/#/%40alice%3Aexample.org"
+        );
+        assert!(DeepLink::matrix_room("@alice:example.org", &[]).is_err());
+        assert!(DeepLink::matrix_room("#ops", &[]).is_err());
+        assert!(DeepLink::matrix_room("#:example.org", &[]).is_err());
+        assert_eq!(
+            DeepLink::matrix_user("@alice:"),
+            Err(DeepLinkError::InvalidMatrixId("@alice:".into()))
+        );
+    }
+
+    #[test]
+    fn invite() {
+        assert_eq!(
+            DeepLink::invite("https://example.com/join?team=ops", &[("token", "x y")])
+                .unwrap()
+                .as_str(),
+            "https://example.com/join?team=ops&token=x%20y"
+        );
+        assert_eq!(
+            DeepLink::invite("http://example.com/", &[])
+                .unwrap()
+                .as_str(),
+            "http://example.com/"
+        );
+        assert!(DeepLink::invite("https://example.com/#/join", &[]).is_err());
+        assert_eq!(
+            DeepLink::invite("example.com", &[]),
+            Err(DeepLinkError::InvalidUrl("example.com".into()))
+        );
+    }
+
+    #[test]
+    fn store_listings() {
+        assert_eq!(
+            DeepLink::play_store(" com.example.app_2 ")
+                .unwrap()
+                .as_str(),
+            "https://play.google.com/store/apps/details?id=com.example.app_2"
+        );
+        assert!(DeepLink::play_store("example").is_err());
+        assert!(DeepLink::play_store("com.1example").is_err());
+        assert!(DeepLink::play_store("com..example").is_err());
+
+        assert_eq!(
+            DeepLink::app_store("284882215").unwrap().as_str(),
+            "https://apps.apple.com/app/id284882215"
+        );
+        assert_eq!(
+            DeepLink::app_store("id284882215"),
+            DeepLink::app_store("284882215")
+        );
+        assert_eq!(
+            DeepLink::app_store("id"),
+            Err(DeepLinkError::InvalidAppStoreId("id".into()))
+        );
+    }
+
+    #[test]
+    fn app_listing() {
+        assert_eq!(
+            DeepLink::app_listing(Some("com.example.app"), None, Some("https://x.y")),
+            DeepLink::play_store("com.example.app")
+        );
+        assert_eq!(
+            DeepLink::app_listing(None, Some("42"), None),
+            DeepLink::app_store("42")
+        );
+        assert_eq!(
+            DeepLink::app_listing(
+                Some("com.example.app"),
+                Some("id42"),
+                Some("https://example.com/app?ref=qr")
+            )
+            .unwrap()
+            .as_str(),
+            "https://example.com/app?ref=qr&android=com.example.app&ios=42"
+        );
+        assert_eq!(
+            DeepLink::app_listing(Some("com.example.app"), Some("42"), None)
+                .unwrap()
+                .as_str(),
+            "Android: https://play.google.com/store/apps/details?id=com.example.app\n\
+             iOS: https://apps.apple.com/app/id42"
+        );
+        assert_eq!(
+            DeepLink::app_listing(Some("com.example.app"), Some("42"), Some("ftp://x.y")),
+            Err(DeepLinkError::InvalidUrl("ftp://x.y".into()))
+        );
+        assert_eq!(
+            DeepLink::app_listing(None, None, None),
+            Err(DeepLinkError::MissingApp)
+        );
+    }
+}
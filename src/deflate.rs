@@ -0,0 +1,468 @@
+//! Minimal raw DEFLATE (RFC 1951) encoder and decoder.
+//!
+//! The encoder uses LZ77 with hash chains and the fixed Huffman codes, which
+//! compresses small payloads well without the overhead of dynamic tables. The
+//! decoder supports all block types.
+
+use std::fmt::{self, Display};
+
+/// Size of the LZ77 sliding window.
+const WINDOW_SIZE: usize = 32768;
+
+/// Minimum and maximum length of an LZ77 match.
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+/// Maximum number of hash chain entries to consider per position.
+const MAX_CHAIN: usize = 64;
+
+/// Number of bits of the hash table index.
+const HASH_BITS: u32 = 12;
+
+/// End of block symbol.
+const END_OF_BLOCK: u16 = 256;
+
+/// Base lengths and extra bits for length symbols 257 to 285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distances and extra bits for distance symbols 0 to 29.
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Order in which code length code lengths are stored in a dynamic block header.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Error returned when decoding an invalid DEFLATE stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InflateError(&'static str);
+
+impl Display for InflateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid deflate stream: {}", self.0)
+    }
+}
+
+impl std::error::Error for InflateError {}
+
+/// Compress `data` into a raw DEFLATE stream.
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut out = BitWriter::default();
+
+    // Single final block with fixed Huffman codes
+    out.write(1, 1);
+    out.write(1, 2);
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; data.len()];
+    let mut pos = 0;
+    while pos < data.len() {
+        let (len, dist) = longest_match(data, pos, &head, &prev);
+        let step = if len >= MIN_MATCH {
+            write_length(&mut out, len);
+            write_distance(&mut out, dist);
+            len
+        } else {
+            write_literal(&mut out, data[pos] as u16);
+            1
+        };
+
+        // Insert all covered positions into the hash chains
+        for i in pos..pos + step {
+            if i + MIN_MATCH <= data.len() {
+                let hash = hash(&data[i..]);
+                prev[i] = head[hash];
+                head[hash] = i;
+            }
+        }
+        pos += step;
+    }
+
+    write_literal(&mut out, END_OF_BLOCK);
+    out.finish()
+}
+
+/// Decompress a raw DEFLATE stream.
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>, InflateError> {
+    let mut input = BitReader::new(data);
+    let mut out = Vec::new();
+
+    loop {
+        let last = input.read(1)? == 1;
+        match input.read(2)? {
+            0 => inflate_stored(&mut input, &mut out)?,
+            1 => {
+                let (lit, dist) = fixed_tables();
+                inflate_block(&mut input, &mut out, &lit, &dist)?;
+            }
+            2 => {
+                let (lit, dist) = dynamic_tables(&mut input)?;
+                inflate_block(&mut input, &mut out, &lit, &dist)?;
+            }
+            _ => return Err(InflateError("reserved block type")),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+/// Hash the first three bytes of `data`.
+fn hash(data: &[u8]) -> usize {
+    let value = (data[0] as u32) << 16 | (data[1] as u32) << 8 | data[2] as u32;
+    (value.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+/// Find the longest earlier match for the data at `pos`, as `(length, distance)`.
+fn longest_match(data: &[u8], pos: usize, head: &[usize], prev: &[usize]) -> (usize, usize) {
+    if pos + MIN_MATCH > data.len() {
+        return (0, 0);
+    }
+
+    let max_len = (data.len() - pos).min(MAX_MATCH);
+    let (mut best_len, mut best_dist) = (0, 0);
+    let mut candidate = head[hash(&data[pos..])];
+    for _ in 0..MAX_CHAIN {
+        if candidate == usize::MAX || pos - candidate > WINDOW_SIZE {
+            break;
+        }
+        let len = data[candidate..]
+            .iter()
+            .zip(&data[pos..pos + max_len])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if len > best_len {
+            best_len = len;
+            best_dist = pos - candidate;
+            if len == max_len {
+                break;
+            }
+        }
+        candidate = prev[candidate];
+    }
+
+    (best_len, best_dist)
+}
+
+/// Write a literal or end of block symbol using the fixed literal/length code.
+fn write_literal(out: &mut BitWriter, symbol: u16) {
+    let (code, len) = match symbol {
+        0..=143 => (0x30 + symbol, 8),
+        144..=255 => (0x190 + symbol - 144, 9),
+        256..=279 => (symbol - 256, 7),
+        _ => (0xC0 + symbol - 280, 8),
+    };
+    out.write_code(code as u32, len);
+}
+
+/// Write a match length using the fixed literal/length code.
+fn write_length(out: &mut BitWriter, len: usize) {
+    let index = LENGTH_BASE
+        .iter()
+        .rposition(|&base| base as usize <= len)
+        .unwrap();
+    write_literal(out, 257 + index as u16);
+    out.write(
+        (len - LENGTH_BASE[index] as usize) as u32,
+        LENGTH_EXTRA[index],
+    );
+}
+
+/// Write a match distance using the fixed distance code.
+fn write_distance(out: &mut BitWriter, dist: usize) {
+    let index = DIST_BASE
+        .iter()
+        .rposition(|&base| base as usize <= dist)
+        .unwrap();
+    out.write_code(index as u32, 5);
+    out.write((dist - DIST_BASE[index] as usize) as u32, DIST_EXTRA[index]);
+}
+
+/// Copy a stored block.
+fn inflate_stored(input: &mut BitReader, out: &mut Vec<u8>) -> Result<(), InflateError> {
+    input.align();
+    let len = input.read(16)?;
+    let nlen = input.read(16)?;
+    if len != !nlen & 0xFFFF {
+        return Err(InflateError("stored block length mismatch"));
+    }
+    for _ in 0..len {
+        out.push(input.read(8)? as u8);
+    }
+    Ok(())
+}
+
+/// Decode a Huffman compressed block using the given tables.
+fn inflate_block(
+    input: &mut BitReader,
+    out: &mut Vec<u8>,
+    lit: &Huffman,
+    dist: &Huffman,
+) -> Result<(), InflateError> {
+    loop {
+        let symbol = lit.decode(input)?;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            END_OF_BLOCK => return Ok(()),
+            _ => {
+                let index = (symbol - 257) as usize;
+                if index >= LENGTH_BASE.len() {
+                    return Err(InflateError("invalid length symbol"));
+                }
+                let len = LENGTH_BASE[index] as usize + input.read(LENGTH_EXTRA[index])? as usize;
+
+                let index = dist.decode(input)? as usize;
+                if index >= DIST_BASE.len() {
+                    return Err(InflateError("invalid distance symbol"));
+                }
+                let distance = DIST_BASE[index] as usize + input.read(DIST_EXTRA[index])? as usize;
+                if distance > out.len() {
+                    return Err(InflateError("distance too far back"));
+                }
+
+                let start = out.len() - distance;
+                for i in 0..len {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+/// Construct the fixed literal/length and distance tables.
+fn fixed_tables() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+/// Read the literal/length and distance tables of a dynamic block.
+fn dynamic_tables(input: &mut BitReader) -> Result<(Huffman, Huffman), InflateError> {
+    let nlen = input.read(5)? as usize + 257;
+    let ndist = input.read(5)? as usize + 1;
+    let ncode = input.read(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..ncode] {
+        code_lengths[index] = input.read(3)? as u8;
+    }
+    let code = Huffman::new(&code_lengths);
+
+    let mut lengths = vec![0u8; nlen + ndist];
+    let mut index = 0;
+    while index < lengths.len() {
+        let symbol = code.decode(input)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 if index > 0 => (lengths[index - 1], 3 + input.read(2)?),
+            16 => return Err(InflateError("repeat without previous length")),
+            17 => (0, 3 + input.read(3)?),
+            _ => (0, 11 + input.read(7)?),
+        };
+        for _ in 0..repeat {
+            *lengths
+                .get_mut(index)
+                .ok_or(InflateError("too many code lengths"))? = value;
+            index += 1;
+        }
+    }
+
+    Ok((
+        Huffman::new(&lengths[..nlen]),
+        Huffman::new(&lengths[nlen..]),
+    ))
+}
+
+/// Canonical Huffman decoding table.
+struct Huffman {
+    /// Number of codes of each bit length.
+    counts: [u16; 16],
+
+    /// Symbols ordered by code.
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// Construct a table from the code length of each symbol.
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+
+        Self { counts, symbols }
+    }
+
+    /// Decode a single symbol.
+    fn decode(&self, input: &mut BitReader) -> Result<u16, InflateError> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= input.read(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(InflateError("invalid Huffman code"))
+    }
+}
+
+/// Writes bits least significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u32,
+    count: u8,
+}
+
+impl BitWriter {
+    /// Write the `count` lowest bits of `value`.
+    fn write(&mut self, value: u32, count: u8) {
+        for i in 0..count {
+            self.bits |= ((value >> i) & 1) << self.count;
+            self.count += 1;
+            if self.count == 8 {
+                self.out.push(self.bits as u8);
+                self.bits = 0;
+                self.count = 0;
+            }
+        }
+    }
+
+    /// Write a Huffman code of `len` bits, which is stored most significant bit first.
+    fn write_code(&mut self, code: u32, len: u8) {
+        let reversed = code.reverse_bits() >> (32 - len as u32);
+        self.write(reversed, len);
+    }
+
+    /// Flush the last partial byte and return the written bytes.
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.bits as u8);
+        }
+        self.out
+    }
+}
+
+/// Reads bits least significant bit first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit: u8,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            bit: 0,
+        }
+    }
+
+    /// Read `count` bits, up to 16.
+    fn read(&mut self, count: u8) -> Result<u32, InflateError> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = self
+                .data
+                .get(self.pos)
+                .ok_or(InflateError("unexpected end of data"))?;
+            value |= (((byte >> self.bit) & 1) as u32) << i;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.pos += 1;
+            }
+        }
+        Ok(value)
+    }
+
+    /// Skip to the next byte boundary.
+    fn align(&mut self) {
+        if self.bit > 0 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let json = br#"{"name":"qr2term","keywords":["qrcode","terminal","qr","code"]}"#.repeat(8);
+        let binary: Vec<u8> = (0..2000u32).map(|i| (i * 7 % 251) as u8).collect();
+        for data in [
+            &b""[..],
+            b"a",
+            b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            &json,
+            &binary,
+        ] {
+            let compressed = deflate(data);
+            assert_eq!(inflate(&compressed).unwrap(), data);
+        }
+        assert!(deflate(&json).len() < json.len() / 4);
+    }
+
+    /// Decode a dynamic Huffman block produced by zlib.
+    #[test]
+    fn inflate_dynamic() {
+        let compressed = [
+            0xe5, 0x8c, 0xc1, 0x11, 0x83, 0x30, 0x0c, 0x04, 0x5b, 0xb9, 0x0a, 0x68, 0x80, 0x6a,
+            0x0c, 0x08, 0xac, 0x60, 0x2c, 0x90, 0x6d, 0x08, 0x54, 0x8f, 0x60, 0x98, 0xc9, 0x3b,
+            0x6f, 0xf6, 0x7b, 0xbb, 0x97, 0x3d, 0x61, 0x29, 0xdc, 0x8e, 0x68, 0x54, 0xb6, 0x88,
+            0x5e, 0xbe, 0xf8, 0x94, 0x69, 0x4e, 0x90, 0x95, 0x14, 0xd9, 0xe6, 0xe0, 0x8e, 0x1d,
+            0x9d, 0x0c, 0x35, 0x66, 0x67, 0xde, 0xb4, 0xa3, 0x31, 0x69, 0xe3, 0xec, 0xd1, 0xf3,
+            0x4a, 0x36, 0x1d, 0x14, 0x11, 0x78, 0x29, 0xa2, 0xd6, 0x0e, 0xa9, 0xba, 0xb3, 0x77,
+            0xbf, 0xd2, 0x8f, 0xfc, 0xe0, 0x2e, 0xe4, 0x82, 0x99, 0x63, 0x4c, 0xc9, 0x7b, 0xd5,
+            0x2e, 0x94, 0x3f, 0xd4, 0x13,
+        ];
+        let mut expected =
+            b"the quick brown fox jumps over the lazy dog; pack my box with five dozen liquor jugs. "
+                .repeat(4);
+        expected.extend(b"eeeeeeeeeeettttttttaaaaaoooooiiinnsshhrrdlu".repeat(2));
+        assert_eq!(inflate(&compressed).unwrap(), expected);
+    }
+
+    #[test]
+    fn inflate_invalid() {
+        assert!(inflate(&[]).is_err());
+        assert!(inflate(&[0xff, 0xff]).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! Error types.
+
+use std::error;
+use std::fmt::{self, Display};
+use std::io;
+
+use crate::matrix::MatrixError;
+use crate::reserve::ReserveError;
+use crate::QrError;
+
+/// Result type with the crate [`Error`].
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Error returned by the non-panicking `try_` functions.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Generating the QR code failed, for example because the data is too long.
+    Qr(QrError),
+
+    /// Writing the rendered QR code failed.
+    Io(io::Error),
+
+    /// The rendered QR code is wider than allowed.
+    ///
+    /// See [`QrOptions::max_width`](crate::QrOptions::max_width).
+    TooWide {
+        /// Width of the rendered code, in columns.
+        width: usize,
+
+        /// The maximum width, in columns.
+        max_width: usize,
+    },
+
+    /// Standard output is not a terminal, which is required to keep a code on
+    /// screen, such as with [`present_qr`](crate::present_qr).
+    NotATerminal,
+
+    /// A [validator](crate::lint::Validator) rejected the payload, for the
+    /// given reason.
+    Rejected(String),
+
+    /// The [reserved](crate::QrOptions::reserve) area would keep the code
+    /// from scanning.
+    Reserve(ReserveError),
+
+    /// The rendered code, with its quiet zone, would have more pixels than
+    /// fit in memory.
+    Matrix(MatrixError),
+}
+
+impl Error {
+    /// Get the category of this error, for branching on the kind of failure.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Error::Qr(err) => err.into(),
+            Error::Io(err) => err.into(),
+            Error::TooWide { .. } | Error::NotATerminal => ErrorCategory::UnsupportedTerminal,
+            Error::Rejected(_) | Error::Reserve(_) | Error::Matrix(_) => {
+                ErrorCategory::InvalidPayload
+            }
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Qr(err) => write!(f, "failed to generate QR code: {}", err),
+            Error::Io(err) => write!(f, "failed to write QR code: {}", err),
+            Error::TooWide { width, max_width } => write!(
+                f,
+                "QR code is {} columns wide, exceeding the maximum of {} columns",
+                width, max_width
+            ),
+            Error::NotATerminal => f.write_str("standard output is not a terminal"),
+            Error::Rejected(reason) => f.write_str(reason),
+            Error::Reserve(err) => err.fmt(f),
+            Error::Matrix(err) => write!(f, "failed to render QR code: {}", err),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Qr(err) => Some(err),
+            Error::Io(err) => Some(err),
+            Error::Reserve(err) => Some(err),
+            Error::Matrix(err) => Some(err),
+            Error::TooWide { .. } | Error::NotATerminal | Error::Rejected(_) => None,
+        }
+    }
+}
+
+impl From<QrError> for Error {
+    fn from(err: QrError) -> Self {
+        Error::Qr(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<MatrixError> for Error {
+    fn from(err: MatrixError) -> Self {
+        Error::Matrix(err)
+    }
+}
+
+impl From<ReserveError> for Error {
+    fn from(err: ReserveError) -> Self {
+        Error::Reserve(err)
+    }
+}
+
+/// Category of an error, for branching on the kind of failure, such as to
+/// pick an exit code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCategory {
+    /// The payload doesn't fit a QR code.
+    PayloadTooLong,
+
+    /// The payload can't be encoded, such as characters not supported by its
+    /// encoding mode, or the options keep it from being rendered, such as a
+    /// quiet zone too large to fit in memory.
+    InvalidPayload,
+
+    /// The terminal can't show the code, because it is too narrow or not a
+    /// terminal at all, or lacks a tool such as a clipboard.
+    UnsupportedTerminal,
+
+    /// Reading or writing failed.
+    Io,
+
+    /// Input couldn't be decoded, such as text that isn't valid UTF-8, or
+    /// malformed rows or codes.
+    Decode,
+}
+
+impl From<&QrError> for ErrorCategory {
+    fn from(err: &QrError) -> Self {
+        match err {
+            QrError::DataTooLong => ErrorCategory::PayloadTooLong,
+            _ => ErrorCategory::InvalidPayload,
+        }
+    }
+}
+
+impl From<&io::Error> for ErrorCategory {
+    /// Errors of kind [`InvalidData`](io::ErrorKind::InvalidData) are decode
+    /// failures, all others I/O failures.
+    fn from(err: &io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::InvalidData => ErrorCategory::Decode,
+            _ => ErrorCategory::Io,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn categories() {
+        assert_eq!(
+            Error::Qr(QrError::DataTooLong).category(),
+            ErrorCategory::PayloadTooLong
+        );
+        assert_eq!(
+            Error::Qr(QrError::InvalidCharacter).category(),
+            ErrorCategory::InvalidPayload
+        );
+        let err = io::Error::new(io::ErrorKind::InvalidData, "not UTF-8");
+        assert_eq!(Error::Io(err).category(), ErrorCategory::Decode);
+        let err = io::Error::from(io::ErrorKind::BrokenPipe);
+        assert_eq!(Error::Io(err).category(), ErrorCategory::Io);
+        let err = Error::TooWide {
+            width: 80,
+            max_width: 40,
+        };
+        assert_eq!(err.category(), ErrorCategory::UnsupportedTerminal);
+        assert_eq!(
+            Error::NotATerminal.category(),
+            ErrorCategory::UnsupportedTerminal
+        );
+        assert_eq!(
+            Error::Matrix(MatrixError::TooLarge).category(),
+            ErrorCategory::InvalidPayload
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! Exporting rendered QR codes to files.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::render::{Renderer, Style};
+use crate::{default_options, QrError, QrOptions, ToQrPayload};
+
+/// Column budget of login screens, the width of a classic console.
+pub const DEFAULT_COLUMNS: usize = 80;
+
+/// Error returned when exporting a QR code failed.
+#[derive(Debug)]
+pub enum ExportError {
+    /// Generating the QR code failed.
+    Qr(QrError),
+
+    /// Writing the file failed.
+    Io(io::Error),
+
+    /// The rendered output is wider than the column budget.
+    TooWide {
+        /// Width of the widest line, in columns.
+        width: usize,
+
+        /// The column budget.
+        columns: usize,
+    },
+
+    /// A [validator](crate::lint::Validator) rejected the payload, for the
+    /// given reason.
+    Rejected(String),
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Qr(err) => write!(f, "failed to generate QR code: {}", err),
+            ExportError::Io(err) => write!(f, "failed to write QR code: {}", err),
+            ExportError::TooWide { width, columns } => write!(
+                f,
+                "QR code is {} columns wide, exceeding the budget of {} columns",
+                width, columns
+            ),
+            ExportError::Rejected(reason) => f.write_str(reason),
+        }
+    }
+}
+
+impl Error for ExportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExportError::Qr(err) => Some(err),
+            ExportError::Io(err) => Some(err),
+            ExportError::TooWide { .. } | ExportError::Rejected(_) => None,
+        }
+    }
+}
+
+impl From<QrError> for ExportError {
+    fn from(err: QrError) -> Self {
+        ExportError::Qr(err)
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(err: io::Error) -> Self {
+        ExportError::Io(err)
+    }
+}
+
+impl From<crate::Error> for ExportError {
+    fn from(err: crate::Error) -> Self {
+        match err {
+            crate::Error::Qr(err) => ExportError::Qr(err),
+            crate::Error::Io(err) => ExportError::Io(err),
+            crate::Error::TooWide { width, max_width } => ExportError::TooWide {
+                width,
+                columns: max_width,
+            },
+            crate::Error::Rejected(reason) => ExportError::Rejected(reason),
+            err => ExportError::Io(io::Error::new(io::ErrorKind::Unsupported, err.to_string())),
+        }
+    }
+}
+
+/// Write the given `data` as QR code to a message of the day file at `path`,
+/// such as `/etc/motd` or `/etc/issue`.
+///
+/// Uses the process wide [`default_options`] and a budget of
+/// [`DEFAULT_COLUMNS`] columns, see [`motd_with_options`].
+pub fn motd<D: ToQrPayload, P: AsRef<Path>>(data: D, path: P) -> Result<(), ExportError> {
+    motd_with_options(data, &default_options(), DEFAULT_COLUMNS, path)
+}
+
+/// Write the given `data` as QR code to a message of the day file at `path`,
+/// using the given `options`.
+///
+/// The code is rendered without colors, as login screens show files as is.
+/// Dark modules are drawn in the foreground color, so the code appears
+/// inverted on a dark background. The [`Style::Auto`] style picks the largest
+/// style that fits `columns`. An existing file is replaced.
+///
+/// Returns an error if generating the QR code or writing the file failed, or
+/// if any line of the output is wider than `columns`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use qr2term::{export, render::Style, QrOptions};
+///
+/// let options = QrOptions::new().style(Style::Auto);
+/// export::motd_with_options("https://support.example.com/", &options, 80, "/etc/motd").unwrap();
+/// ```
+pub fn motd_with_options<D: ToQrPayload, P: AsRef<Path>>(
+    data: D,
+    options: &QrOptions,
+    columns: usize,
+    path: P,
+) -> Result<(), ExportError> {
+    fs::write(path, render_motd(data, options, columns)?)?;
+    Ok(())
+}
+
+/// Render the given `data` as colorless QR code within `columns`, see [`motd_with_options`].
+fn render_motd<D: ToQrPayload>(
+    data: D,
+    options: &QrOptions,
+    columns: usize,
+) -> Result<String, ExportError> {
+    let style = match options.style {
+        Style::Auto => {
+            let matrix = crate::code_matrix(&crate::generate_code(&data, options)?, options)
+                .map_err(crate::Error::from)?;
+            Style::fit(&matrix, Some((columns, usize::MAX)))
+        }
+        style => style,
+    };
+    let mut buf = Vec::new();
+    crate::render_qr_with(&data, options, &Renderer::new(style).ansi(false), &mut buf)?;
+    let text = String::from_utf8(buf)
+        .map_err(|err| ExportError::Io(io::Error::new(io::ErrorKind::InvalidData, err)))?;
+
+    let width = text
+        .lines()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    if width > columns {
+        return Err(ExportError::TooWide { width, columns });
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colorless_within_budget() {
+        let options = QrOptions::new().style(Style::Auto);
+        let text = render_motd("qr2term", &options, 80).unwrap();
+        assert!(!text.contains('\x1B'));
+        assert!(text.lines().all(|line| line.chars().count() == 50));
+
+        let text = render_motd("qr2term", &options, 40).unwrap();
+        assert!(text.lines().all(|line| line.chars().count() == 25));
+
+        let options = QrOptions::new().style(Style::Ascii);
+        assert!(matches!(
+            render_motd("qr2term", &options, 40),
+            Err(ExportError::TooWide {
+                width: 50,
+                columns: 40
+            })
+        ));
+    }
+
+    #[test]
+    fn write_file() {
+        let path = std::env::temp_dir().join(format!("qr2term-motd-{}", std::process::id()));
+        let options = QrOptions::new().style(Style::HalfBlock);
+        motd_with_options("qr2term", &options, DEFAULT_COLUMNS, &path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            text,
+            render_motd("qr2term", &options, DEFAULT_COLUMNS).unwrap()
+        );
+    }
+}
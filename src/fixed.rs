@@ -0,0 +1,305 @@
+//! Fixed-size matrices, for codes of a version known at compile time.
+//!
+//! Embedded targets often show a code of a single known version, such as a
+//! provisioning URL of fixed length. A [`FixedQrMatrix`] stores its modules
+//! in an array sized for its version, one bit per module, and
+//! [`Renderer::render_fixed`] renders it without allocating. The matrix can
+//! be filled from a generated [`Qr`], or module by module from a code
+//! generated elsewhere, such as a bitmap embedded at build time.
+//!
+//! ```rust
+//! use qr2term::fixed::FixedQrMatrix;
+//! use qr2term::qr::Qr;
+//! use qr2term::render::{Renderer, Style};
+//!
+//! let code = Qr::from("https://rust-lang.org/").unwrap();
+//! let matrix = FixedQrMatrix::<2>::from_qr(&code).unwrap();
+//! let mut out = Vec::new();
+//! Renderer::new(Style::HalfBlock)
+//!     .render_fixed(&matrix, 4, &mut out)
+//!     .unwrap();
+//! ```
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io::{Result as IoResult, Write};
+
+use crate::qr::{Qr, Version};
+use crate::render::{QrDark, Renderer};
+
+/// A QR code version, to select the storage of a [`FixedQrMatrix`] through [`FixedVersion`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct FixedSize<const V: usize>;
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// QR code versions a [`FixedQrMatrix`] can be constructed for, 1 to 40.
+pub trait FixedVersion: private::Sealed {
+    /// Width and height of codes of this version, in modules.
+    const WIDTH: usize;
+
+    /// Storage of one bit per module, row by row.
+    type Bits: AsRef<[u8]> + AsMut<[u8]> + Copy + Eq + fmt::Debug;
+
+    /// Storage with all bits cleared.
+    const EMPTY: Self::Bits;
+}
+
+macro_rules! fixed_versions {
+    ($($version:literal)*) => {$(
+        impl private::Sealed for FixedSize<$version> {}
+
+        impl FixedVersion for FixedSize<$version> {
+            const WIDTH: usize = 17 + 4 * $version;
+            type Bits = [u8; ((17 + 4 * $version) * (17 + 4 * $version) + 7) / 8];
+            const EMPTY: Self::Bits = [0; ((17 + 4 * $version) * (17 + 4 * $version) + 7) / 8];
+        }
+    )*};
+}
+
+fixed_versions!(
+    1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20
+    21 22 23 24 25 26 27 28 29 30 31 32 33 34 35 36 37 38 39 40
+);
+
+/// A QR code matrix of version `V`, stored without heap allocation.
+///
+/// Holds one bit per module, so a version 1 code takes 56 bytes and a
+/// version 40 code about 4 KiB.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FixedQrMatrix<const V: usize>
+where
+    FixedSize<V>: FixedVersion,
+{
+    bits: <FixedSize<V> as FixedVersion>::Bits,
+}
+
+impl<const V: usize> FixedQrMatrix<V>
+where
+    FixedSize<V>: FixedVersion,
+{
+    /// Width and height of the matrix, in modules.
+    pub const WIDTH: usize = <FixedSize<V> as FixedVersion>::WIDTH;
+
+    /// Construct a matrix with all modules light.
+    pub fn new() -> Self {
+        Self {
+            bits: <FixedSize<V> as FixedVersion>::EMPTY,
+        }
+    }
+
+    /// Construct a matrix with the modules `is_dark` tells are dark, by row and column.
+    pub fn from_fn<F: FnMut(usize, usize) -> bool>(mut is_dark: F) -> Self {
+        let mut matrix = Self::new();
+        for row in 0..Self::WIDTH {
+            for col in 0..Self::WIDTH {
+                matrix.set(row, col, is_dark(row, col));
+            }
+        }
+        matrix
+    }
+
+    /// Construct a matrix from the modules of a generated `code`, without its quiet zone.
+    ///
+    /// Returns an error if the code is not of version `V`.
+    pub fn from_qr(code: &Qr) -> Result<Self, FixedMatrixError> {
+        let version = code.info().version;
+        if version != Version::Normal(V as i16) {
+            return Err(FixedMatrixError::VersionMismatch {
+                expected: V,
+                actual: version,
+            });
+        }
+        let matrix = code.to_matrix();
+        let pixels = matrix.pixels();
+        Ok(Self::from_fn(|row, col| {
+            pixels[row * Self::WIDTH + col] == QrDark
+        }))
+    }
+
+    /// Whether the module at the given row and column is dark.
+    ///
+    /// Modules beyond the edge of the matrix are light.
+    pub fn is_dark(&self, row: usize, col: usize) -> bool {
+        if row >= Self::WIDTH || col >= Self::WIDTH {
+            return false;
+        }
+        let bit = row * Self::WIDTH + col;
+        self.bits.as_ref()[bit / 8] & (0x80 >> (bit % 8)) != 0
+    }
+
+    /// Set whether the module at the given row and column is dark.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the module is beyond the edge of the matrix.
+    pub fn set(&mut self, row: usize, col: usize, dark: bool) {
+        assert!(
+            row < Self::WIDTH && col < Self::WIDTH,
+            "module ({}, {}) is beyond the edge of the matrix",
+            row,
+            col
+        );
+        let bit = row * Self::WIDTH + col;
+        let byte = &mut self.bits.as_mut()[bit / 8];
+        match dark {
+            true => *byte |= 0x80 >> (bit % 8),
+            false => *byte &= !(0x80 >> (bit % 8)),
+        }
+    }
+}
+
+impl<const V: usize> Default for FixedQrMatrix<V>
+where
+    FixedSize<V>: FixedVersion,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Error returned when a code can't be stored in a [`FixedQrMatrix`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FixedMatrixError {
+    /// The code is of another version than the matrix.
+    VersionMismatch {
+        /// Version of the matrix.
+        expected: usize,
+
+        /// Version of the code.
+        actual: Version,
+    },
+}
+
+impl Display for FixedMatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FixedMatrixError::VersionMismatch {
+                expected,
+                actual: Version::Normal(actual),
+            } => write!(
+                f,
+                "expected a version {} code, got version {}",
+                expected, actual
+            ),
+            FixedMatrixError::VersionMismatch {
+                expected,
+                actual: Version::Micro(actual),
+            } => write!(
+                f,
+                "expected a version {} code, got micro version {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl Error for FixedMatrixError {}
+
+impl Renderer {
+    /// Render a fixed-size `matrix` with a quiet zone of `quiet_zone` modules
+    /// to the given writer, without allocating.
+    ///
+    /// [`Style::Auto`](crate::render::Style::Auto) renders as the default
+    /// style, [`Style::CiLog`](crate::render::Style::CiLog) as
+    /// [`Style::FullBlock`](crate::render::Style::FullBlock), and double size
+    /// is ignored.
+    ///
+    /// Returns an error if writing failed, or if the rendering would be wider
+    /// than [`MAX_COLUMNS`](crate::render::MAX_COLUMNS).
+    pub fn render_fixed<const V: usize, W: Write>(
+        &self,
+        matrix: &FixedQrMatrix<V>,
+        quiet_zone: usize,
+        target: &mut W,
+    ) -> IoResult<()>
+    where
+        FixedSize<V>: FixedVersion,
+    {
+        let width = quiet_zone
+            .saturating_mul(2)
+            .saturating_add(FixedQrMatrix::<V>::WIDTH);
+        self.render_pixels(
+            width,
+            |row, col| match (row.checked_sub(quiet_zone), col.checked_sub(quiet_zone)) {
+                (Some(row), Some(col)) => matrix.is_dark(row, col),
+                _ => false,
+            },
+            target,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Style;
+    use crate::QrOptions;
+
+    #[test]
+    fn modules() {
+        assert_eq!(FixedQrMatrix::<1>::WIDTH, 21);
+        assert_eq!(FixedQrMatrix::<40>::WIDTH, 177);
+        assert_eq!(std::mem::size_of::<FixedQrMatrix<1>>(), 56);
+
+        let mut matrix = FixedQrMatrix::<1>::new();
+        matrix.set(20, 20, true);
+        matrix.set(0, 3, true);
+        assert!(matrix.is_dark(20, 20) && matrix.is_dark(0, 3));
+        assert!(!matrix.is_dark(0, 2) && !matrix.is_dark(21, 0));
+        matrix.set(0, 3, false);
+        assert_eq!(
+            matrix,
+            FixedQrMatrix::from_fn(|row, col| row == 20 && col == 20)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn set_beyond_edge() {
+        FixedQrMatrix::<1>::new().set(0, 21, true);
+    }
+
+    #[test]
+    fn render_like_matrix() {
+        let code = Qr::from("qr2term").unwrap();
+        assert_eq!(
+            FixedQrMatrix::<2>::from_qr(&code),
+            Err(FixedMatrixError::VersionMismatch {
+                expected: 2,
+                actual: Version::Normal(1),
+            })
+        );
+
+        let matrix = FixedQrMatrix::<1>::from_qr(&code).unwrap();
+        for style in [
+            Style::HalfBlock,
+            Style::FullBlock,
+            Style::Ascii,
+            Style::Quadrant,
+            Style::Braille,
+        ] {
+            for stretch in [1, 2] {
+                let options = QrOptions::new().style(style).stretch(stretch);
+                let renderer = crate::renderer(&options);
+                let mut fixed = Vec::new();
+                renderer
+                    .render_fixed(&matrix, options.quiet_zone, &mut fixed)
+                    .unwrap();
+                let mut expected = Vec::new();
+                renderer
+                    .render(&crate::code_matrix(&code, &options).unwrap(), &mut expected)
+                    .unwrap();
+                assert_eq!(fixed, expected, "{} stretched {}", style, stretch);
+            }
+        }
+
+        let err = Renderer::new(Style::FullBlock)
+            .render_fixed(&matrix, usize::MAX, &mut Vec::new())
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
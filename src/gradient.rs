@@ -0,0 +1,237 @@
+//! Gradient coloring for truecolor terminals and PNG images.
+//!
+//! A [`Gradient`] draws dark modules in colors that blend from one color into
+//! another across the code, for example to match a brand. Colors are darkened
+//! where needed to keep enough contrast with the background for scanners, see
+//! [`MIN_CONTRAST_RATIO`].
+//!
+//! Gradients are written to terminals as 24-bit color escape sequences, which
+//! not all terminals support, or exported as truecolor PNG images with
+//! [`Gradient::to_png`].
+
+use std::io::{self, Result as IoResult, Write};
+
+use crate::ansi;
+use crate::color::Rgb;
+use crate::matrix::Matrix;
+use crate::render::{Color, QrDark, QrLight};
+use crate::theme::{self, MIN_CONTRAST_RATIO};
+use crate::{png, reserve, Error, QrOptions, ToQrPayload};
+
+/// How colors blend across the code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum GradientKind {
+    /// From the top row to the bottom row.
+    #[default]
+    Vertical,
+
+    /// From the center to the corners.
+    Radial,
+}
+
+/// Colors blending across a code, see the [module documentation](self).
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::gradient::{Gradient, GradientKind};
+///
+/// let gradient = Gradient::new(GradientKind::Radial, [128, 0, 128], [0, 64, 160]);
+/// gradient.print_qr("https://rust-lang.org/", &Default::default()).unwrap();
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Gradient {
+    kind: GradientKind,
+    from: Rgb,
+    to: Rgb,
+    background: Rgb,
+}
+
+impl Gradient {
+    /// Construct a gradient of the given kind, blending dark modules `from`
+    /// one color `to` another, on a white background.
+    pub fn new(kind: GradientKind, from: Rgb, to: Rgb) -> Self {
+        Self {
+            kind,
+            from,
+            to,
+            background: [255, 255, 255],
+        }
+    }
+
+    /// Set the color of light modules.
+    ///
+    /// Should be a light color, as dark modules can at most be darkened to
+    /// black to keep them apart from the background.
+    pub fn background(mut self, background: Rgb) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Get the color of a dark module at `row` and `col` in a matrix of
+    /// `size` by `size` modules.
+    ///
+    /// The blended color is darkened until it is darker than the background,
+    /// with a contrast of at least [`MIN_CONTRAST_RATIO`].
+    pub fn color_at(&self, row: usize, col: usize, size: usize) -> Rgb {
+        let max = size.saturating_sub(1).max(1) as f64;
+        let t = match self.kind {
+            GradientKind::Vertical => row as f64 / max,
+            GradientKind::Radial => {
+                let (dy, dx) = (row as f64 - max / 2.0, col as f64 - max / 2.0);
+                (dx * dx + dy * dy).sqrt() / (max / 2.0 * 2f64.sqrt())
+            }
+        };
+        let t = t.clamp(0.0, 1.0);
+        let blend = |i: usize| {
+            let (from, to) = (f64::from(self.from[i]), f64::from(self.to[i]));
+            from + (to - from) * t
+        };
+        let color = [blend(0), blend(1), blend(2)];
+
+        // Darken in steps, black is the last resort
+        (0..=STEPS)
+            .map(|step| {
+                let scale = 1.0 - step as f64 / STEPS as f64;
+                color.map(|channel| (channel * scale).round() as u8)
+            })
+            .find(|&color| {
+                theme::luminance(color) < theme::luminance(self.background)
+                    && theme::contrast_ratio(color, self.background) >= MIN_CONTRAST_RATIO
+            })
+            .unwrap_or([0, 0, 0])
+    }
+
+    /// Render `matrix` to the given writer, two modules above each other per character.
+    pub fn render<W: Write>(&self, matrix: &Matrix<Color>, target: &mut W) -> IoResult<()> {
+        let width = matrix.size();
+        let pixels = matrix.pixels();
+        let color = |row: usize, col: usize| match pixels.get(row * width + col) {
+            Some(QrDark) => self.color_at(row, col, width),
+            Some(QrLight) | None => self.background,
+        };
+
+        for row in (0..width).step_by(2) {
+            for col in 0..width {
+                // Inverted lower half block, like the half block style
+                ansi::write_rgb(target, "▄", color(row + 1, col), color(row, col))?;
+            }
+            writeln!(target)?;
+        }
+        Ok(())
+    }
+
+    /// Encode `matrix` as truecolor PNG image, drawing every module as a
+    /// square of `module_size` by `module_size` pixels.
+    pub fn render_png(&self, matrix: &Matrix<Color>, module_size: usize) -> Vec<u8> {
+        let size = matrix.size();
+        png::encode_rgb(size, module_size, |row, col| {
+            match matrix.pixels()[row * size + col] {
+                QrDark => self.color_at(row, col, size),
+                QrLight => self.background,
+            }
+        })
+    }
+
+    /// Encode the given `data` as QR code in this gradient as truecolor PNG
+    /// image, using the given `options`, see [`render_png`](Self::render_png).
+    ///
+    /// The configured style and theme are ignored, and
+    /// [reserved](QrOptions::reserve) areas are blanked like in other image
+    /// exports.
+    ///
+    /// Returns an error if generating the QR code failed, or the reserved
+    /// area would keep it from scanning.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use qr2term::gradient::{Gradient, GradientKind};
+    ///
+    /// let gradient = Gradient::new(GradientKind::Vertical, [128, 0, 128], [0, 64, 160]);
+    /// let png = gradient.to_png("https://rust-lang.org/", &Default::default(), 8).unwrap();
+    /// assert!(png.starts_with(b"\x89PNG"));
+    /// ```
+    pub fn to_png<D: ToQrPayload>(
+        &self,
+        data: D,
+        options: &QrOptions,
+        module_size: usize,
+    ) -> Result<Vec<u8>, Error> {
+        let matrix = reserve::image_matrix(data, options)?;
+        Ok(self.render_png(&matrix, module_size))
+    }
+
+    /// Print the given `data` as QR code in this gradient to the terminal,
+    /// using the given `options`.
+    ///
+    /// The configured style and theme are ignored.
+    ///
+    /// Returns an error if generating the QR code or writing to the terminal failed.
+    pub fn print_qr<D: ToQrPayload>(&self, data: D, options: &QrOptions) -> Result<(), Error> {
+        let matrix = crate::code_matrix(&crate::generate_code(data, options)?, options)?;
+        let mut stdout = io::stdout().lock();
+        self.render(&matrix, &mut stdout)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+/// Number of steps to darken a color in until it contrasts enough.
+const STEPS: usize = 20;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blends() {
+        let gradient = Gradient::new(GradientKind::Vertical, [0, 0, 0], [0, 0, 100]);
+        assert_eq!(gradient.color_at(0, 5, 11), [0, 0, 0]);
+        assert_eq!(gradient.color_at(5, 0, 11), [0, 0, 50]);
+        assert_eq!(gradient.color_at(10, 10, 11), [0, 0, 100]);
+
+        let gradient = Gradient::new(GradientKind::Radial, [0, 0, 0], [0, 0, 100]);
+        assert_eq!(gradient.color_at(5, 5, 11), [0, 0, 0]);
+        assert_eq!(gradient.color_at(0, 10, 11), [0, 0, 100]);
+    }
+
+    #[test]
+    fn contrast_guardrail() {
+        for kind in [GradientKind::Vertical, GradientKind::Radial] {
+            let gradient = Gradient::new(kind, [255, 200, 0], [100, 200, 255]);
+            for row in 0..25 {
+                for col in 0..25 {
+                    let color = gradient.color_at(row, col, 25);
+                    assert!(theme::contrast_ratio(color, [255; 3]) >= MIN_CONTRAST_RATIO);
+                }
+            }
+        }
+
+        // Nothing contrasts with a black background
+        let gradient = Gradient::new(GradientKind::Vertical, [255; 3], [255; 3]);
+        assert_eq!(gradient.background([0; 3]).color_at(0, 0, 25), [0; 3]);
+    }
+
+    #[test]
+    fn render_rgb() {
+        let matrix = Matrix::new(vec![QrDark, QrLight, QrLight, QrLight]);
+        let gradient = Gradient::new(GradientKind::Vertical, [0, 0, 80], [0, 0, 0]);
+        let mut buf = Vec::new();
+        gradient.render(&matrix, &mut buf).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "\x1B[48;2;0;0;80m\x1B[38;2;255;255;255m▄\x1B[49m\x1B[39m\
+             \x1B[48;2;255;255;255m\x1B[38;2;255;255;255m▄\x1B[49m\x1B[39m\n"
+        );
+    }
+
+    #[test]
+    fn render_png() {
+        let matrix = Matrix::new(vec![QrDark, QrLight, QrLight, QrLight]);
+        let gradient = Gradient::new(GradientKind::Vertical, [0, 0, 80], [0, 0, 0]);
+        let png = gradient.render_png(&matrix, 2);
+        // 4 by 4 pixels, 8-bit truecolor
+        assert_eq!(png[16..26], [0, 0, 0, 4, 0, 0, 0, 4, 8, 2]);
+    }
+}
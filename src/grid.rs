@@ -0,0 +1,245 @@
+//! Arranging several codes side by side in a grid.
+//!
+//! Data [split](crate::options::Overflow::Split) over several codes, or split
+//! by the application itself, makes for a long scroll when printed one code
+//! below the other. A grid puts as many codes next to each other as fit the
+//! width of the terminal, balancing the rows, such as 2×2 for four codes or
+//! 3×1 for three, each captioned with its part number:
+//!
+//! ```rust
+//! use qr2term::options::{Layout, Overflow};
+//! use qr2term::QrOptions;
+//!
+//! let options = QrOptions::new().on_overflow(Overflow::Split).layout(Layout::Grid);
+//! qr2term::print_qr_with_options("a".repeat(5000), &options).unwrap();
+//! ```
+
+use std::io::{self, Write};
+
+use crate::normalize::strip_ansi;
+use crate::{default_options, payload, term, Error, QrOptions, ToQrPayload};
+
+/// Number of columns between codes.
+pub const GAP: usize = 2;
+
+/// Number of columns to fill if the width of the terminal isn't known, or
+/// for [deterministic](QrOptions::deterministic) output.
+pub const DEFAULT_COLUMNS: usize = 80;
+
+/// Get the number of codes per row to arrange `count` codes of `width`
+/// columns in, to fit `columns` columns.
+///
+/// As many codes as fit go on a row, after which the rows are balanced, so
+/// four codes that fit three on a row are arranged 2×2 rather than 3 and 1.
+/// At least one code goes on a row, even if it doesn't fit.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::grid::per_row;
+///
+/// assert_eq!(per_row(4, 25, 80), 2);
+/// assert_eq!(per_row(3, 25, 80), 3);
+/// assert_eq!(per_row(2, 50, 80), 1);
+/// ```
+pub fn per_row(count: usize, width: usize, columns: usize) -> usize {
+    if count == 0 {
+        return 0;
+    }
+    let fit = ((columns + GAP) / (width + GAP)).clamp(1, count);
+    let rows = (count + fit - 1) / fit;
+    (count + rows - 1) / rows
+}
+
+/// Get the number of columns the grid may fill, for the given `options`.
+pub(crate) fn columns(options: &QrOptions) -> usize {
+    match options.deterministic {
+        true => DEFAULT_COLUMNS,
+        false => term::size().map_or(DEFAULT_COLUMNS, |(columns, _)| columns),
+    }
+}
+
+/// Write the rendered `blocks` side by side in a grid fitting `columns`
+/// columns to the given writer.
+///
+/// Blocks are padded to the width of the widest one, and aligned at their
+/// top.
+pub(crate) fn write_grid<W: Write>(
+    blocks: &[Vec<u8>],
+    columns: usize,
+    target: &mut W,
+) -> io::Result<()> {
+    let blocks: Vec<Vec<(String, usize)>> = blocks
+        .iter()
+        .map(|block| {
+            String::from_utf8_lossy(block)
+                .lines()
+                .map(|line| {
+                    let width = String::from_utf8_lossy(&strip_ansi(line.as_bytes()))
+                        .chars()
+                        .count();
+                    (line.to_owned(), width)
+                })
+                .collect()
+        })
+        .collect();
+    let width = blocks
+        .iter()
+        .flatten()
+        .map(|(_, width)| *width)
+        .max()
+        .unwrap_or(0);
+
+    for row in blocks.chunks(per_row(blocks.len(), width, columns).max(1)) {
+        let height = row.iter().map(Vec::len).max().unwrap_or(0);
+        for i in 0..height {
+            let mut line = String::new();
+            for (j, block) in row.iter().enumerate() {
+                let (text, text_width) = block.get(i).map_or(("", 0), |(text, w)| (text, *w));
+                if j + 1 == row.len() {
+                    line.push_str(text);
+                } else {
+                    line.push_str(text);
+                    line.push_str(&" ".repeat(width - text_width + GAP));
+                }
+            }
+            writeln!(target, "{}", line.trim_end_matches(' '))?;
+        }
+    }
+    Ok(())
+}
+
+/// Print the given `payloads` as QR codes side by side in a grid, see
+/// [`render_grid`].
+///
+/// Uses the process wide [`default_options`].
+///
+/// Returns an error if generating a QR code or writing to the terminal failed.
+pub fn print_grid<D: ToQrPayload>(payloads: &[D]) -> Result<(), Error> {
+    print_grid_with_options(payloads, &default_options())
+}
+
+/// Print the given `payloads` as QR codes side by side in a grid, using the
+/// given `options`, see [`render_grid`].
+///
+/// Returns an error if generating a QR code or writing to the terminal failed.
+pub fn print_grid_with_options<D: ToQrPayload>(
+    payloads: &[D],
+    options: &QrOptions,
+) -> Result<(), Error> {
+    let mut stdout = io::stdout().lock();
+    render_grid(payloads, &options.for_terminal(), &mut stdout)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Render the given `payloads`, split by the application, as QR codes side
+/// by side in a grid fitting the width of the terminal, to the given writer.
+///
+/// Every code is captioned with its number and the number of codes, such
+/// as `part 2/4`, centered below it. Codes are rendered as a whole before
+/// any is written.
+///
+/// Returns an error if generating a QR code or writing failed.
+///
+/// # Examples
+///
+/// ```rust
+/// let parts = ["WIFI:S:guest;T:WPA;P:hunter2;;", "https://example.com/welcome"];
+/// qr2term::grid::render_grid(&parts, &qr2term::QrOptions::new(), &mut std::io::stdout())
+///     .unwrap();
+/// ```
+pub fn render_grid<D: ToQrPayload, W: Write>(
+    payloads: &[D],
+    options: &QrOptions,
+    target: &mut W,
+) -> Result<(), Error> {
+    let mut blocks = Vec::with_capacity(payloads.len());
+    let result = render_blocks(payloads, options, &mut blocks)
+        .and_then(|()| Ok(write_grid(&blocks, columns(options), target)?));
+    if options.secret {
+        blocks.iter_mut().for_each(|block| payload::wipe(block));
+    }
+    result
+}
+
+/// Render every one of `payloads` with its caption into its own block.
+fn render_blocks<D: ToQrPayload>(
+    payloads: &[D],
+    options: &QrOptions,
+    blocks: &mut Vec<Vec<u8>>,
+) -> Result<(), Error> {
+    let options = QrOptions {
+        trailing_newline: true,
+        ..options.clone()
+    };
+    for (i, data) in payloads.iter().enumerate() {
+        blocks.push(Vec::new());
+        let block = blocks.last_mut().unwrap();
+        crate::render_qr(data, &options, block)?;
+
+        let width = String::from_utf8_lossy(&strip_ansi(block))
+            .lines()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let caption = format!("part {}/{}", i + 1, payloads.len());
+        let indent = width.saturating_sub(caption.len()) / 2;
+        writeln!(block, "{:indent$}{}", "", caption, indent = indent)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::{Layout, Overflow};
+    use crate::render::Style;
+
+    #[test]
+    fn balanced_rows() {
+        assert_eq!(per_row(4, 25, 80), 2);
+        assert_eq!(per_row(5, 25, 80), 3);
+        assert_eq!(per_row(6, 10, 200), 6);
+        assert_eq!(per_row(2, 100, 80), 1);
+        assert_eq!(per_row(0, 10, 80), 0);
+    }
+
+    #[test]
+    fn side_by_side() {
+        let blocks = [
+            b"ab\ncd\n".to_vec(),
+            b"\x1B[1mx\x1B[0m\n".to_vec(),
+            b"yz\n".to_vec(),
+        ];
+        let mut buf = Vec::new();
+        write_grid(&blocks, 6, &mut buf).unwrap();
+        assert_eq!(buf, b"ab  \x1B[1mx\x1B[0m\ncd\nyz\n");
+    }
+
+    #[test]
+    fn split_in_grid() {
+        // Split codes are too wide to fit side by side
+        let options = QrOptions::new()
+            .style(Style::HalfBlock)
+            .on_overflow(Overflow::Split)
+            .deterministic(true);
+        let data = "a".repeat(4000);
+        let stacked = crate::try_generate_qr_string_with_options(&data, &options).unwrap();
+        let grid = options.layout(Layout::Grid);
+        let output = crate::try_generate_qr_string_with_options(&data, &grid).unwrap();
+        assert_eq!(output.lines().count(), stacked.lines().count());
+        assert!(output.contains("part 2/2"));
+
+        let mut buf = Vec::new();
+        render_grid(&["one", "two", "three"], &grid, &mut buf).unwrap();
+        let output = String::from_utf8(strip_ansi(&buf)).unwrap();
+        assert_eq!(output.lines().count(), 14);
+        assert!(output
+            .lines()
+            .all(|line| line.chars().count() <= DEFAULT_COLUMNS));
+        assert!(output
+            .lines()
+            .any(|line| line.contains("part 1/3") && line.contains("part 3/3")));
+    }
+}
@@ -0,0 +1,279 @@
+//! GS1 element strings, for supply chain QR codes.
+//!
+//! [`Gs1`] builds an element string from application identifiers (AIs), such as
+//! a GTIN, batch number and expiry date. It is encoded in GS1 mode, with FNC1
+//! in first position, so scanners report it as GS1 data.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::payload::{Mode, Payload, ToQrPayload};
+
+/// ASCII group separator, terminating variable length element strings.
+pub const GROUP_SEPARATOR: char = '\x1D';
+
+/// AI prefixes with a predefined length, along with the total length of their
+/// element string including the AI.
+const PREDEFINED_LENGTHS: [(&str, usize); 22] = [
+    ("00", 20),
+    ("01", 16),
+    ("02", 16),
+    ("03", 16),
+    ("04", 18),
+    ("11", 8),
+    ("12", 8),
+    ("13", 8),
+    ("14", 8),
+    ("15", 8),
+    ("16", 8),
+    ("17", 8),
+    ("18", 8),
+    ("19", 8),
+    ("20", 4),
+    ("31", 10),
+    ("32", 10),
+    ("33", 10),
+    ("34", 10),
+    ("35", 10),
+    ("36", 10),
+    ("41", 16),
+];
+
+/// Maximum length of an element string value.
+const MAX_VALUE_LEN: usize = 90;
+
+/// A GS1 element string, built from application identifiers and their values.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::gs1::Gs1;
+///
+/// let gs1 = Gs1::new()
+///     .gtin("09501101530003")?
+///     .expiry(2027, 12, 31)?
+///     .batch("AB-123")?;
+/// assert_eq!(gs1.to_string(), "(01)09501101530003(17)271231(10)AB-123");
+/// qr2term::print_qr(&gs1).unwrap();
+/// # Ok::<(), qr2term::gs1::Gs1Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Gs1 {
+    elements: Vec<(String, String)>,
+}
+
+impl Gs1 {
+    /// Construct an empty element string.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a GTIN (AI 01), validating its check digit.
+    ///
+    /// GTIN-8, GTIN-12 and GTIN-13 are padded with leading zeros to GTIN-14.
+    pub fn gtin(self, gtin: &str) -> Result<Self, Gs1Error> {
+        if !matches!(gtin.len(), 8 | 12 | 13 | 14) || !is_numeric(gtin) {
+            return Err(Gs1Error::Value("01".into()));
+        }
+        if !valid_check_digit(gtin) {
+            return Err(Gs1Error::CheckDigit);
+        }
+        self.ai("01", &format!("{:0>14}", gtin))
+    }
+
+    /// Add a batch or lot number (AI 10), of up to 20 characters.
+    pub fn batch(self, batch: &str) -> Result<Self, Gs1Error> {
+        if batch.len() > 20 {
+            return Err(Gs1Error::Value("10".into()));
+        }
+        self.ai("10", batch)
+    }
+
+    /// Add an expiration date (AI 17).
+    ///
+    /// A `day` of zero means the end of the month. Only the last two digits of
+    /// the `year` are encoded.
+    pub fn expiry(self, year: u16, month: u8, day: u8) -> Result<Self, Gs1Error> {
+        if !(1..=12).contains(&month) || day > 31 {
+            return Err(Gs1Error::Value("17".into()));
+        }
+        self.ai("17", &format!("{:02}{:02}{:02}", year % 100, month, day))
+    }
+
+    /// Add a serial number (AI 21), of up to 20 characters.
+    pub fn serial(self, serial: &str) -> Result<Self, Gs1Error> {
+        if serial.len() > 20 {
+            return Err(Gs1Error::Value("21".into()));
+        }
+        self.ai("21", serial)
+    }
+
+    /// Add an element string for any application identifier.
+    ///
+    /// The AI must be 2 to 4 digits, and the value must be non-empty GS1
+    /// character set 82 text. Values of AIs with a predefined length are
+    /// checked to be numeric and of that length.
+    pub fn ai(mut self, ai: &str, value: &str) -> Result<Self, Gs1Error> {
+        if !(2..=4).contains(&ai.len()) || !is_numeric(ai) {
+            return Err(Gs1Error::Ai(ai.into()));
+        }
+        let valid = match predefined_length(ai) {
+            Some(len) => ai.len() + value.len() == len && is_numeric(value),
+            None => (1..=MAX_VALUE_LEN).contains(&value.len()) && value.chars().all(is_cset82),
+        };
+        if !valid {
+            return Err(Gs1Error::Value(ai.into()));
+        }
+
+        self.elements.push((ai.into(), value.into()));
+        Ok(self)
+    }
+
+    /// Get the element string to encode, with variable length values
+    /// terminated by a [`GROUP_SEPARATOR`] where needed.
+    pub fn element_string(&self) -> String {
+        let mut data = String::new();
+        for (i, (ai, value)) in self.elements.iter().enumerate() {
+            data.push_str(ai);
+            data.push_str(value);
+            if predefined_length(ai).is_none() && i + 1 < self.elements.len() {
+                data.push(GROUP_SEPARATOR);
+            }
+        }
+        data
+    }
+}
+
+impl ToQrPayload for Gs1 {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        Payload::new(Cow::Owned(self.element_string().into_bytes())).with_mode(Mode::Gs1)
+    }
+}
+
+/// Formats the human readable interpretation, such as `(01)09501101530003(10)AB-123`.
+impl Display for Gs1 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (ai, value) in &self.elements {
+            write!(f, "({}){}", ai, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the human readable interpretation, such as `(01)09501101530003(10)AB-123`.
+///
+/// Values can't contain `(`, as it starts the next AI.
+impl FromStr for Gs1 {
+    type Err = Gs1Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s.strip_prefix('(').ok_or(Gs1Error::Syntax)?;
+        rest.split('(').try_fold(Gs1::new(), |gs1, element| {
+            let (ai, value) = element.split_once(')').ok_or(Gs1Error::Syntax)?;
+            gs1.ai(ai, value)
+        })
+    }
+}
+
+/// Get the total element string length for `ai`, if it has a predefined length.
+fn predefined_length(ai: &str) -> Option<usize> {
+    PREDEFINED_LENGTHS
+        .iter()
+        .find(|(prefix, _)| ai.starts_with(prefix))
+        .map(|&(_, len)| len)
+}
+
+fn is_numeric(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Whether `c` is part of GS1 character set 82.
+fn is_cset82(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!\"%&'()*+,-./:;<=>?_".contains(c)
+}
+
+/// Validate the GS1 mod 10 check digit, the last digit of `digits`.
+fn valid_check_digit(digits: &str) -> bool {
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .map(|b| u32::from(b - b'0'))
+        .enumerate()
+        .map(|(i, digit)| if i % 2 == 1 { digit * 3 } else { digit })
+        .sum();
+    sum % 10 == 0
+}
+
+/// Error returned when building a GS1 element string failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Gs1Error {
+    /// The application identifier is not 2 to 4 digits.
+    Ai(String),
+
+    /// The value is invalid for the application identifier.
+    Value(String),
+
+    /// The GTIN check digit is wrong.
+    CheckDigit,
+
+    /// The human readable interpretation is malformed.
+    Syntax,
+}
+
+impl Display for Gs1Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Gs1Error::Ai(ai) => write!(f, "invalid GS1 application identifier '{}'", ai),
+            Gs1Error::Value(ai) => {
+                write!(f, "invalid value for GS1 application identifier ({})", ai)
+            }
+            Gs1Error::CheckDigit => f.write_str("invalid GTIN check digit"),
+            Gs1Error::Syntax => f.write_str("invalid GS1 human readable interpretation"),
+        }
+    }
+}
+
+impl Error for Gs1Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qr::{Qr, SegmentMode};
+    use qrcode::EcLevel;
+
+    #[test]
+    fn element_string() {
+        let gs1 = Gs1::new()
+            .batch("AB%1")
+            .and_then(|gs1| gs1.gtin("9501101530003"))
+            .and_then(|gs1| gs1.serial("42"))
+            .unwrap();
+        assert_eq!(gs1.element_string(), "10AB%1\x1D01095011015300032142");
+        assert_eq!(gs1.to_string().parse(), Ok(gs1.clone()));
+
+        let qr = Qr::from_payload(&gs1.to_qr_payload(), EcLevel::M).unwrap();
+        assert_eq!(qr.info().mode, SegmentMode::Byte);
+    }
+
+    #[test]
+    fn validation() {
+        assert_eq!(Gs1::new().gtin("09501101530004"), Err(Gs1Error::CheckDigit));
+        assert_eq!(Gs1::new().gtin("12345"), Err(Gs1Error::Value("01".into())));
+        assert_eq!(
+            Gs1::new().expiry(2027, 13, 1),
+            Err(Gs1Error::Value("17".into()))
+        );
+        assert_eq!(Gs1::new().ai("1", "x"), Err(Gs1Error::Ai("1".into())));
+        assert_eq!(
+            Gs1::new().ai("17", "2712"),
+            Err(Gs1Error::Value("17".into()))
+        );
+        assert_eq!(
+            Gs1::new().ai("21", "a b"),
+            Err(Gs1Error::Value("21".into()))
+        );
+        assert_eq!("01)123".parse::<Gs1>(), Err(Gs1Error::Syntax));
+    }
+}
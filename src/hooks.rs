@@ -0,0 +1,153 @@
+//! Callbacks into the host application while QR codes are emitted.
+//!
+//! Implement [`Hooks`] and set it through [`QrOptions::hooks`](crate::QrOptions::hooks)
+//! to log, trace or display metadata about every code that is generated and
+//! rendered.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::lint::Lint;
+use crate::qr::QrInfo;
+
+/// Callbacks invoked while generating and rendering QR codes.
+///
+/// All methods do nothing by default, so only the events of interest need to
+/// be implemented.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::{hooks::Hooks, QrInfo, QrOptions};
+///
+/// struct Log;
+///
+/// impl Hooks for Log {
+///     fn on_generated(&self, info: &QrInfo) {
+///         eprintln!("generated {}", info);
+///     }
+/// }
+///
+/// let options = QrOptions::new().hooks(Log);
+/// qr2term::print_qr_with_options("https://rust-lang.org/", &options).unwrap();
+/// ```
+pub trait Hooks: Send + Sync {
+    /// Called after a QR code is generated, before it is rendered.
+    fn on_generated(&self, _info: &QrInfo) {}
+
+    /// Called after a QR code is rendered, with the size of the rendering in
+    /// terminal `rows` and `columns`.
+    fn on_rendered(&self, _rows: usize, _columns: usize) {}
+
+    /// Called before rendering each of the codes that data is split over, see
+    /// [`Overflow::Split`](crate::options::Overflow::Split).
+    ///
+    /// `index` starts at zero and is smaller than `count`. Not called for data
+    /// that fits a single code.
+    fn on_frame(&self, _index: usize, _count: usize) {}
+
+    /// Called for every [`Lint`] found in the payload, before generating its
+    /// code.
+    fn on_lint(&self, _lint: &Lint) {}
+}
+
+/// Shared handle to [`Hooks`], as stored in [`QrOptions`](crate::QrOptions).
+///
+/// Handles are equal if they refer to the same hooks.
+#[derive(Clone)]
+pub struct SharedHooks(Arc<dyn Hooks>);
+
+impl SharedHooks {
+    /// Share the given `hooks`.
+    pub fn new<H: Hooks + 'static>(hooks: H) -> Self {
+        Self(Arc::new(hooks))
+    }
+}
+
+impl std::ops::Deref for SharedHooks {
+    type Target = dyn Hooks;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl fmt::Debug for SharedHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedHooks(..)")
+    }
+}
+
+impl PartialEq for SharedHooks {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::as_ptr(&self.0) as *const () == Arc::as_ptr(&other.0) as *const ()
+    }
+}
+
+impl Eq for SharedHooks {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::options::{EcLevel, Overflow};
+    use crate::QrOptions;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl Hooks for Arc<Recorder> {
+        fn on_generated(&self, info: &QrInfo) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("generated {}", info.module_count));
+        }
+
+        fn on_rendered(&self, rows: usize, columns: usize) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("rendered {}x{}", rows, columns));
+        }
+
+        fn on_frame(&self, index: usize, count: usize) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("frame {}/{}", index, count));
+        }
+
+        fn on_lint(&self, lint: &Lint) {
+            self.0.lock().unwrap().push(format!("lint {:?}", lint));
+        }
+    }
+
+    #[test]
+    fn events() {
+        let recorder = Arc::new(Recorder::default());
+        let options = QrOptions::new().hooks(recorder.clone());
+        crate::try_generate_qr_string_with_options("qr2term", &options).unwrap();
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            ["generated 21", "rendered 13x25"]
+        );
+
+        recorder.0.lock().unwrap().clear();
+        let options = options.on_overflow(Overflow::Split).ec_level(EcLevel::H);
+        crate::try_generate_qr_string_with_options("a".repeat(2000), &options).unwrap();
+        let events = recorder.0.lock().unwrap();
+        assert_eq!(events[0], "lint LongPayload { len: 2000 }");
+        assert_eq!(events[1], "frame 0/2");
+        assert_eq!(events[4], "frame 1/2");
+        assert_eq!(events.len(), 7);
+    }
+
+    #[test]
+    fn shared_eq() {
+        let hooks = SharedHooks::new(Arc::new(Recorder::default()));
+        assert_eq!(hooks, hooks.clone());
+        assert_ne!(hooks, SharedHooks::new(Arc::new(Recorder::default())));
+    }
+}
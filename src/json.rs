@@ -0,0 +1,373 @@
+//! Minimal JSON parser, so the crate doesn't need extra dependencies.
+
+use std::fmt::{self, Display};
+
+/// Deepest nesting of arrays and objects accepted.
+const MAX_DEPTH: usize = 64;
+
+/// A parsed JSON value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// A number, as written.
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    /// Members of an object, in order of appearance.
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Write this value as canonical JSON: without whitespace, with object
+    /// keys sorted, and strings escaped with as few escapes as possible.
+    pub fn write_canonical(&self, out: &mut String) {
+        match self {
+            Value::Null => out.push_str("null"),
+            Value::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+            Value::Number(number) => out.push_str(number),
+            Value::String(s) => write_string(out, s),
+            Value::Array(elements) => {
+                out.push('[');
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    element.write_canonical(out);
+                }
+                out.push(']');
+            }
+            Value::Object(members) => {
+                let mut members: Vec<_> = members.iter().collect();
+                members.sort_by(|(a, _), (b, _)| a.cmp(b));
+                out.push('{');
+                for (i, (key, value)) in members.into_iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write_string(out, key);
+                    out.push(':');
+                    value.write_canonical(out);
+                }
+                out.push('}');
+            }
+        }
+    }
+
+    /// This value as canonical JSON, see [`Value::write_canonical`].
+    pub fn to_canonical(&self) -> String {
+        let mut out = String::new();
+        self.write_canonical(&mut out);
+        out
+    }
+}
+
+/// Error returned when parsing invalid JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonError {
+    /// The JSON is invalid at the given byte position.
+    Invalid(usize),
+
+    /// An object has the given key more than once.
+    DuplicateKey(String),
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::Invalid(position) => write!(f, "invalid JSON at position {}", position),
+            JsonError::DuplicateKey(key) => write!(f, "duplicate key '{}'", key),
+        }
+    }
+}
+
+/// Parse the JSON `text`.
+///
+/// Returns an error if the text is not a single valid JSON value, nests
+/// deeper than 64 levels, or has an object with duplicate keys.
+pub fn parse(text: &str) -> Result<Value, JsonError> {
+    let mut parser = Parser {
+        json: text,
+        position: 0,
+    };
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.position != text.len() {
+        return Err(parser.error());
+    }
+    Ok(value)
+}
+
+/// Quote and escape `s` as a JSON string, escaping only what is required.
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Recursive descent JSON parser.
+struct Parser<'a> {
+    json: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    /// Error at the current position.
+    fn error(&self) -> JsonError {
+        JsonError::Invalid(self.position)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.json.as_bytes().get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.position += 1;
+        }
+    }
+
+    /// Consume `byte`, or fail.
+    fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+        match self.peek() {
+            Some(next) if next == byte => {
+                self.position += 1;
+                Ok(())
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    /// Parse a value at the given nesting `depth`.
+    fn value(&mut self, depth: usize) -> Result<Value, JsonError> {
+        self.skip_whitespace();
+        if depth > MAX_DEPTH {
+            return Err(self.error());
+        }
+        match self.peek() {
+            Some(b'{') => self.object(depth),
+            Some(b'[') => self.array(depth),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'-' | b'0'..=b'9') => self.number().map(Value::Number),
+            _ => {
+                let rest = &self.json[self.position..];
+                let (literal, value) = [
+                    ("true", Value::Bool(true)),
+                    ("false", Value::Bool(false)),
+                    ("null", Value::Null),
+                ]
+                .into_iter()
+                .find(|(literal, _)| rest.starts_with(literal))
+                .ok_or_else(|| self.error())?;
+                self.position += literal.len();
+                Ok(value)
+            }
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Value, JsonError> {
+        self.expect(b'{')?;
+        let mut members: Vec<(String, Value)> = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            let value = self.value(depth + 1)?;
+            if members.iter().any(|(existing, _)| *existing == key) {
+                return Err(JsonError::DuplicateKey(key));
+            }
+            members.push((key, value));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    return Ok(Value::Object(members));
+                }
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Value, JsonError> {
+        self.expect(b'[')?;
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(Value::Array(elements));
+        }
+        loop {
+            elements.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(Value::Array(elements));
+                }
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    /// Parse a string, returning its unescaped contents.
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect(b'"')?;
+        let mut out = String::new();
+        loop {
+            let c = self.json[self.position..]
+                .chars()
+                .next()
+                .ok_or_else(|| self.error())?;
+            match c {
+                '"' => {
+                    self.position += 1;
+                    return Ok(out);
+                }
+                '\\' => {
+                    self.position += 1;
+                    out.push(self.escape()?);
+                }
+                c if c < ' ' => return Err(self.error()),
+                c => {
+                    self.position += c.len_utf8();
+                    out.push(c);
+                }
+            }
+        }
+    }
+
+    /// Parse the escape sequence after a backslash.
+    fn escape(&mut self) -> Result<char, JsonError> {
+        let c = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.position += 1;
+                let high = self.hex4()?;
+                let code = match high {
+                    0xD800..=0xDBFF => {
+                        self.expect(b'\\')?;
+                        self.expect(b'u')?;
+                        let low = self.hex4()?;
+                        if !(0xDC00..=0xDFFF).contains(&low) {
+                            return Err(self.error());
+                        }
+                        0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                    }
+                    _ => high,
+                };
+                return char::from_u32(code).ok_or_else(|| self.error());
+            }
+            _ => return Err(self.error()),
+        };
+        self.position += 1;
+        Ok(c)
+    }
+
+    /// Parse four hexadecimal digits.
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits = self
+            .json
+            .get(self.position..self.position + 4)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error())?;
+        self.position += 4;
+        u32::from_str_radix(digits, 16).map_err(|_| self.error())
+    }
+
+    /// Parse a number, returning it as written.
+    fn number(&mut self) -> Result<String, JsonError> {
+        let start = self.position;
+        let digits = |parser: &mut Self| {
+            let start = parser.position;
+            while matches!(parser.peek(), Some(b'0'..=b'9')) {
+                parser.position += 1;
+            }
+            parser.position > start
+        };
+
+        if self.peek() == Some(b'-') {
+            self.position += 1;
+        }
+        if self.peek() == Some(b'0') {
+            self.position += 1;
+        } else if !digits(self) {
+            return Err(self.error());
+        }
+        if self.peek() == Some(b'.') {
+            self.position += 1;
+            if !digits(self) {
+                return Err(self.error());
+            }
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.position += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.position += 1;
+            }
+            if !digits(self) {
+                return Err(self.error());
+            }
+        }
+        Ok(self.json[start..self.position].into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical() {
+        let json =
+            " {\"b\" : [1, -0.5e+3, true, null, {}], \"a\":{\"z\":\"\\u00e9\\/\\n\", \"y\":[]}}\n";
+        assert_eq!(
+            parse(json).unwrap().to_canonical(),
+            r#"{"a":{"y":[],"z":"é/\n"},"b":[1,-0.5e+3,true,null,{}]}"#
+        );
+        assert_eq!(
+            parse(r#"["😀\u0001"]"#).unwrap().to_canonical(),
+            "[\"\u{1F600}\\u0001\"]"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(parse(r#"{"a":1,}"#), Err(JsonError::Invalid(7)));
+        assert_eq!(parse(r#"{"a":01}"#), Err(JsonError::Invalid(6)));
+        assert_eq!(parse(r#"{"a":1} x"#), Err(JsonError::Invalid(8)));
+        assert!(parse(r#""\ud800""#).is_err());
+        assert!(parse("tru").is_err());
+        assert!(parse(&"[".repeat(100)).is_err());
+        assert_eq!(
+            parse(r#"{"a":1,"b":{"c":1,"c":2}}"#),
+            Err(JsonError::DuplicateKey("c".into()))
+        );
+    }
+}
@@ -15,6 +15,52 @@
 //!
 //! ![qr2term example screenshot](./res/qr2term-example.png)
 //!
+//! # Features
+//! By default this crate only depends on [`qrcode`](https://docs.rs/qrcode).
+//! The following cargo features are available:
+//!
+//! - `terminal`: terminal control, such as moving the cursor, through `crossterm`,
+//!   [`responsive`](crate::responsive) rendering that follows the terminal size, and
+//!   full screen presentation through [`present_qr`](crate::present_qr), and
+//!   paging long output through [`pager`](crate::pager)
+//! - `cli`: the `qr2term` command line binary
+//! - `clipboard`: copy codes as text or PNG image to the system clipboard, see
+//!   [`clipboard`](crate::clipboard)
+//! - `config`: read default options from a config file, see
+//!   [`config`](crate::config)
+//! - `payloads`: helpers for structured payloads, such as
+//!   [`provisioning`](crate::payload::provisioning) and
+//!   [`template`](crate::payload::template) payloads, and [`sign`](crate::sign)
+//! - `url`: validate and normalize URL payloads, see [`payload::url`](crate::payload::url)
+//! - `export`: export codes to files, such as login messages, see
+//!   [`export`](crate::export)
+//! - `debug`: decode, diff and annotate rendered codes, see [`debug`](crate::debug)
+//! - `stream`: stream payloads as a sequence of codes, see [`stream`](crate::stream)
+//! - `batch`: generate codes for every row of CSV or JSON input, see
+//!   [`batch`](crate::batch)
+//! - `uefi`: render fixed-size codes as UCS-2 lines for UEFI consoles, see
+//!   [`uefi`](crate::uefi)
+//! - `banner`: large text banners above codes, such as `SCAN HERE` on kiosks,
+//!   see [`banner`](crate::banner)
+//! - `service`: request and response types with a pure handler for rendering
+//!   services, see [`service`](crate::service)
+//! - `simd`: find runs of identical cells with SSE2 on x86_64 when rendering,
+//!   see [`rle`](crate::rle)
+//! - `camera`: capture frames from a webcam through `ffmpeg` and scan codes
+//!   back from them, acknowledging streamed chunks, see [`camera`](crate::camera)
+//! - `log`: log the chosen symbol, render style, fallback decisions and timing
+//!   through the [`log`](https://docs.rs/log) facade, and print codes into the log
+//!   with [`log_qr`](crate::log_qr)
+//! - `test-util`: an in-memory terminal to assert on rendered output, see
+//!   [`testing`](crate::testing)
+//!
+//! # WebAssembly
+//! Rendering into strings and printing to stdout works under WASI
+//! (`wasm32-wasip1`), without probing a terminal: [`Style::Auto`](render::Style::Auto)
+//! fits the code to the `COLUMNS` and `LINES` environment variables if the
+//! host passes them. The `terminal` and `cli` features need a real terminal,
+//! and aren't supported there.
+//!
 //! # Based on
 //! This library is based on [`qair`](https://code.willemp.be/willem/qair),
 //! which didn't provide the renderer as a library on it's own.
@@ -23,24 +69,126 @@
 //! - [https://crates.io/crates/qair](https://crates.io/crates/qair)
 //! - [https://code.willemp.be/willem/qair/src/branch/master/src/console_barcode_renderer.rs](https://code.willemp.be/willem/qair/src/branch/master/src/console_barcode_renderer.rs)
 
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+/// Log an event through the `log` crate, if the `log` feature is enabled.
+///
+/// Without the feature the arguments are still type checked, but nothing is
+/// formatted or logged.
+macro_rules! log_event {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "log")]
+        log::$level!($($arg)+);
+        #[cfg(not(feature = "log"))]
+        let _ = format_args!($($arg)+);
+    }};
+}
+
+pub(crate) mod ansi;
+#[cfg(feature = "banner")]
+pub mod banner;
+pub mod base32;
+pub mod base45;
+#[cfg(feature = "batch")]
+pub mod batch;
+pub mod beside;
+pub mod cache;
+#[cfg(feature = "camera")]
+pub mod camera;
+pub mod canvas;
+pub mod carousel;
+pub mod charset;
+pub mod checksum;
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+pub mod color;
+pub mod compress;
+#[cfg(feature = "config")]
+pub mod config;
+pub(crate) mod crypto;
+#[cfg(feature = "debug")]
+pub mod debug;
+pub mod deeplink;
+pub(crate) mod deflate;
+pub mod error;
+#[cfg(feature = "export")]
+pub mod export;
+pub mod fixed;
+pub mod gradient;
+pub mod grid;
+pub mod gs1;
+pub mod hooks;
+#[cfg(any(feature = "batch", feature = "payloads", feature = "service"))]
+pub(crate) mod json;
+pub mod lint;
+#[cfg(feature = "log")]
+pub(crate) mod logging;
 pub mod matrix;
+pub mod normalize;
+pub mod notify;
+pub mod options;
+#[cfg(feature = "terminal")]
+pub mod pager;
+pub mod payload;
+pub mod plan;
+pub(crate) mod png;
+#[cfg(feature = "terminal")]
+pub(crate) mod present;
+pub mod profile;
+pub mod prompt;
 pub mod qr;
+pub mod queue;
+pub mod region;
 pub mod render;
+pub mod reserve;
+#[cfg(feature = "terminal")]
+pub mod responsive;
+pub mod rle;
+#[cfg(feature = "service")]
+pub mod service;
+pub mod session;
+#[cfg(feature = "payloads")]
+pub mod sign;
+pub mod split;
+pub mod stage;
+#[cfg(feature = "stream")]
+pub mod stream;
+pub(crate) mod term;
+#[cfg(feature = "test-util")]
+pub mod testing;
+pub mod text;
+pub mod theme;
+#[cfg(feature = "uefi")]
+pub mod uefi;
 pub(crate) mod util;
+pub mod wireguard;
 
 pub use qrcode::types::QrError;
 
-use crate::matrix::Matrix;
-use crate::render::Renderer;
+pub use crate::error::{Error, ErrorCategory};
+#[cfg(feature = "log")]
+pub use crate::logging::{log_qr, log_qr_with_options, LOG_PREFIX, LOG_TARGET};
+pub use crate::options::{default_options, set_default_options, QrOptions};
+pub use crate::payload::ToQrPayload;
+pub use crate::plan::{plan, QrPlan};
+#[cfg(feature = "terminal")]
+pub use crate::present::{present_qr, present_qr_with_options};
+pub use crate::qr::QrInfo;
+pub use crate::text::QrText;
 
-/// Quiet zone size in pixels around QR code.
-///
-/// Should be 4, but using 2 for small terminals:
-/// https://qrworld.wordpress.com/2011/08/09/the-quiet-zone/
-const QUIET_ZONE_WIDTH: usize = 2;
+use qrcode::canvas::MaskPattern;
+
+use crate::matrix::{Arena, Matrix, MatrixError};
+use crate::payload::Payload;
+use crate::qr::Qr;
+use crate::render::{Color, Renderer};
+use crate::util::Timer;
 
 /// Print the given `data` as QR code in the terminal.
 ///
+/// Uses the process wide [`default_options`].
+///
 /// Returns an error if generating the QR code failed.
 ///
 /// # Examples
@@ -49,21 +197,138 @@ const QUIET_ZONE_WIDTH: usize = 2;
 /// qr2term::print_qr("https://rust-lang.org/").unwrap();
 /// ```
 ///
+/// Errors without a [`QrError`] counterpart are reported as the closest one,
+/// see [`print_qr_with_options`].
+///
 /// # Panics
 ///
-/// Panics if printing the QR code to the terminal failed.
-pub fn print_qr<D: AsRef<[u8]>>(data: D) -> Result<(), QrError> {
-    // Generate QR code pixel matrix
-    let mut matrix = qr::Qr::from(data)?.to_matrix();
-    matrix.surround(QUIET_ZONE_WIDTH, render::QrLight);
+/// Panics if writing the QR code to the terminal failed. Use [`try_print_qr`]
+/// to get an error instead. The next major version will switch this function
+/// over to the non-panicking behavior.
+pub fn print_qr<D: ToQrPayload>(data: D) -> Result<(), QrError> {
+    print_qr_with_options(data, &default_options())
+}
 
-    // Render QR code to stdout
-    Renderer::default().print_stdout(&matrix);
+/// Print the given `data` as QR code in the terminal, using the given `options`.
+///
+/// Returns an error if generating the QR code failed.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::{render::Style, QrOptions};
+///
+/// let options = QrOptions::new().style(Style::Ascii);
+/// qr2term::print_qr_with_options("https://rust-lang.org/", &options).unwrap();
+/// ```
+///
+/// Errors without a [`QrError`] counterpart are reported as the closest one:
+/// codes wider than [`QrOptions::max_width`] or too large to render as
+/// [`QrError::DataTooLong`], and payloads rejected by a
+/// [validator](crate::lint::Validator) or the [reserved](QrOptions::reserve)
+/// area as [`QrError::InvalidCharacter`]. Use [`try_print_qr_with_options`] to
+/// get the exact error.
+///
+/// # Panics
+///
+/// Panics if writing the QR code to the terminal failed. Use
+/// [`try_print_qr_with_options`] to get an error instead.
+pub fn print_qr_with_options<D: ToQrPayload>(data: D, options: &QrOptions) -> Result<(), QrError> {
+    try_print_qr_with_options(data, options).map_err(|err| {
+        legacy_error(err).unwrap_or_else(|err| panic!("failed to print QR code to stdout: {}", err))
+    })
+}
+
+/// Convert an error of the `try_` functions to the closest [`QrError`], for the
+/// functions returning one. Errors not caused by the code, such as failing to
+/// write it, are returned unchanged.
+fn legacy_error(err: Error) -> Result<QrError, Error> {
+    match err {
+        Error::Qr(err) => Ok(err),
+        Error::TooWide { .. } | Error::Matrix(_) => Ok(QrError::DataTooLong),
+        Error::Rejected(_) | Error::Reserve(_) => Ok(QrError::InvalidCharacter),
+        err => Err(err),
+    }
+}
+
+/// Print the given `data` as QR code in the terminal.
+///
+/// Uses the process wide [`default_options`].
+///
+/// Returns an error if generating the QR code or writing it to the terminal failed.
+///
+/// # Examples
+///
+/// ```rust
+/// qr2term::try_print_qr("https://rust-lang.org/").unwrap();
+/// ```
+pub fn try_print_qr<D: ToQrPayload>(data: D) -> Result<(), Error> {
+    try_print_qr_with_options(data, &default_options())
+}
+
+/// Print the given `data` as QR code in the terminal, using the given `options`.
+///
+/// Returns an error if generating the QR code or writing it to the terminal failed.
+pub fn try_print_qr_with_options<D: ToQrPayload>(
+    data: D,
+    options: &QrOptions,
+) -> Result<(), Error> {
+    let mut stdout = io::stdout().lock();
+    render_qr(data, &options.for_terminal(), &mut stdout)?;
+    stdout.flush()?;
     Ok(())
 }
 
+/// Print the given `data` as QR code in the terminal, in turn with other
+/// threads printing through the [print queue](queue).
+///
+/// Uses the process wide [`default_options`].
+///
+/// Returns an error if generating the QR code or writing it to the terminal failed.
+///
+/// # Examples
+///
+/// ```rust
+/// let threads: Vec<_> = (1..=3)
+///     .map(|i| std::thread::spawn(move || qr2term::print_qr_locked(format!("device {}", i))))
+///     .collect();
+/// for thread in threads {
+///     thread.join().unwrap().unwrap();
+/// }
+/// ```
+pub fn print_qr_locked<D: ToQrPayload>(data: D) -> Result<(), Error> {
+    print_qr_locked_with_options(data, &default_options())
+}
+
+/// Print the given `data` as QR code in the terminal, using the given
+/// `options`, in turn with other threads printing through the
+/// [print queue](queue).
+///
+/// The code is rendered before waiting for the turn, so the terminal is only
+/// held while writing it.
+///
+/// Returns an error if generating the QR code or writing it to the terminal failed.
+pub fn print_qr_locked_with_options<D: ToQrPayload>(
+    data: D,
+    options: &QrOptions,
+) -> Result<(), Error> {
+    let mut buf = Vec::new();
+    let result = render_qr(data, &options.for_terminal(), &mut buf).and_then(|()| {
+        let mut terminal = queue::lock_terminal();
+        terminal.write_all(&buf)?;
+        terminal.flush()?;
+        Ok(())
+    });
+    if options.secret {
+        payload::wipe(&mut buf);
+    }
+    result
+}
+
 /// Generate `String` from the given `data` as QR code.
 ///
+/// Uses the process wide [`default_options`].
+///
 /// Returns an error if generating the QR code failed.
 ///
 /// # Examples
@@ -73,18 +338,865 @@ pub fn print_qr<D: AsRef<[u8]>>(data: D) -> Result<(), QrError> {
 /// print!("{}", qr_string);
 /// ```
 ///
+/// Errors without a [`QrError`] counterpart are reported as the closest one,
+/// see [`generate_qr_string_with_options`].
+///
 /// # Panics
 ///
-/// Panics if generating the QR code string failed.
-pub fn generate_qr_string<D: AsRef<[u8]>>(data: D) -> Result<String, QrError> {
-    // Generate QR code pixel matrix
-    let mut matrix = qr::Qr::from(data)?.to_matrix();
-    matrix.surround(QUIET_ZONE_WIDTH, render::QrLight);
+/// Panics if writing the QR code to the string failed, which the built-in
+/// styles don't do. Use [`try_generate_qr_string`] to get an error instead.
+pub fn generate_qr_string<D: ToQrPayload>(data: D) -> Result<String, QrError> {
+    generate_qr_string_with_options(data, &default_options())
+}
 
-    // Render QR code to a String
+/// Generate `String` from the given `data` as QR code, using the given `options`.
+///
+/// Returns an error if generating the QR code failed.
+///
+/// Errors without a [`QrError`] counterpart are reported as the closest one:
+/// codes wider than [`QrOptions::max_width`] or too large to render as
+/// [`QrError::DataTooLong`], and payloads rejected by a
+/// [validator](crate::lint::Validator) or the [reserved](QrOptions::reserve)
+/// area as [`QrError::InvalidCharacter`]. Use
+/// [`try_generate_qr_string_with_options`] to get the exact error.
+///
+/// # Panics
+///
+/// Panics if writing the QR code to the string failed, which the built-in
+/// styles don't do. Use [`try_generate_qr_string_with_options`] to get an error
+/// instead.
+pub fn generate_qr_string_with_options<D: ToQrPayload>(
+    data: D,
+    options: &QrOptions,
+) -> Result<String, QrError> {
+    try_generate_qr_string_with_options(data, options).map_err(|err| {
+        legacy_error(err).unwrap_or_else(|err| panic!("failed to generate QR code string: {}", err))
+    })
+}
+
+/// Generate `String` from the given `data` as QR code.
+///
+/// Uses the process wide [`default_options`].
+///
+/// Returns an error if generating or rendering the QR code failed.
+pub fn try_generate_qr_string<D: ToQrPayload>(data: D) -> Result<String, Error> {
+    try_generate_qr_string_with_options(data, &default_options())
+}
+
+/// Generate `String` from the given `data` as QR code, using the given `options`.
+///
+/// Returns an error if generating or rendering the QR code failed.
+pub fn try_generate_qr_string_with_options<D: ToQrPayload>(
+    data: D,
+    options: &QrOptions,
+) -> Result<String, Error> {
     let mut buf = Vec::new();
-    Renderer::default()
-        .render(&matrix, &mut buf)
-        .expect("failed to generate QR code string");
-    Ok(String::from_utf8(buf).unwrap())
+    render_qr(data, options, &mut buf)?;
+    String::from_utf8(buf).map_err(|err| Error::Io(io::Error::new(io::ErrorKind::InvalidData, err)))
+}
+
+/// Render `data` as QR code using the given `options`, invoking `row` with
+/// every completed terminal row, without its line ending.
+///
+/// For consumers placing rows themselves, such as curses interfaces, without
+/// building the whole output first. Rows hold the same text, including color
+/// escape sequences, as the lines of
+/// [`try_generate_qr_string_with_options`].
+///
+/// Returns an error if generating or rendering the QR code failed.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut rows = Vec::new();
+/// let options = qr2term::QrOptions::new();
+/// qr2term::render_rows("https://rust-lang.org/", &options, |row| rows.push(row.len())).unwrap();
+/// assert!(!rows.is_empty());
+/// ```
+pub fn render_rows<D: ToQrPayload, F: FnMut(&str)>(
+    data: D,
+    options: &QrOptions,
+    row: F,
+) -> Result<(), Error> {
+    let mut rows = RowWriter {
+        line: Vec::new(),
+        row,
+    };
+    render_qr(data, options, &mut rows)?;
+    if !rows.line.is_empty() {
+        rows.emit()?;
+    }
+    Ok(())
+}
+
+/// Writer collecting output into lines, and invoking a callback with every completed line.
+struct RowWriter<F: FnMut(&str)> {
+    /// The incomplete line.
+    line: Vec<u8>,
+    row: F,
+}
+
+impl<F: FnMut(&str)> RowWriter<F> {
+    /// Invoke the callback with the collected line, without a `\r` ending
+    /// it in [raw mode](QrOptions::raw_mode).
+    fn emit(&mut self) -> io::Result<()> {
+        let line = self.line.strip_suffix(b"\r").unwrap_or(&self.line);
+        let line = std::str::from_utf8(line)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        (self.row)(line);
+        self.line.clear();
+        Ok(())
+    }
+}
+
+impl<F: FnMut(&str)> Write for RowWriter<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut lines = buf.split(|&byte| byte == b'\n');
+        self.line
+            .extend_from_slice(lines.next().unwrap_or_default());
+        for line in lines {
+            self.emit()?;
+            self.line.extend_from_slice(line);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Get metadata about the QR code symbol that would be generated for `data`.
+///
+/// Returns an error if generating the QR code failed.
+///
+/// # Examples
+///
+/// ```rust
+/// let info = qr2term::qr_info("https://rust-lang.org/", &qr2term::default_options()).unwrap();
+/// println!("{}", info);
+/// ```
+pub fn qr_info<D: ToQrPayload>(data: D, options: &QrOptions) -> Result<QrInfo, QrError> {
+    Ok(unchecked_code(data, options)?.info())
+}
+
+/// Generate and render the QR code for `data` to the given writer.
+///
+/// If the data is split over multiple codes as configured through
+/// [`QrOptions::on_overflow`], each code is followed by a caption with its part number.
+pub(crate) fn render_qr<D: ToQrPayload, W: Write>(
+    data: D,
+    options: &QrOptions,
+    target: &mut W,
+) -> Result<(), Error> {
+    render_qr_with(data, options, &renderer(options), target)
+}
+
+/// Get the renderer configured by `options`.
+pub(crate) fn renderer(options: &QrOptions) -> Renderer {
+    Renderer::new(options.style)
+        .theme(options.theme)
+        .double_size(options.double_size)
+        .stretch(options.stretch)
+        .non_breaking(options.non_breaking)
+        .max_width(options.max_width)
+        .deterministic(options.deterministic)
+}
+
+/// Generate and render the QR code for `data` to the given writer, using the given `renderer`.
+///
+/// Honors the cursor, reset, trailing newline, raw mode and notification
+/// options around the output. See [`render_qr`].
+pub(crate) fn render_qr_with<D: ToQrPayload, W: Write>(
+    data: D,
+    options: &QrOptions,
+    renderer: &Renderer,
+    target: &mut W,
+) -> Result<(), Error> {
+    if options.raw_mode.is_enabled() {
+        let mut target = util::CrLf::new(target);
+        write_qr_with(data, options, renderer, &mut target)?;
+        return Ok(target.flush()?);
+    }
+    write_qr_with(data, options, renderer, target)
+}
+
+/// Generate and render the QR code for `data` to the given writer like
+/// [`render_qr_with`], regardless of raw mode.
+fn write_qr_with<D: ToQrPayload, W: Write>(
+    data: D,
+    options: &QrOptions,
+    renderer: &Renderer,
+    target: &mut W,
+) -> Result<(), Error> {
+    if options.hide_cursor {
+        target.write_all(ansi::HIDE_CURSOR.as_bytes())?;
+    }
+    if options.trailing_newline {
+        render_codes(data, options, renderer, target)?;
+    } else {
+        // Hold back the output to drop its final newline
+        let mut buf = Vec::new();
+        let result = render_codes(data, options, renderer, &mut buf).and_then(|_| {
+            let len = buf.len() - usize::from(buf.last() == Some(&b'\n'));
+            Ok(target.write_all(&buf[..len])?)
+        });
+        if options.secret {
+            util::wipe(&mut buf, 0);
+        }
+        result?;
+    }
+    options.notify.send(target)?;
+    if options.reset {
+        ansi::write_reset(target)?;
+    }
+    if options.hide_cursor {
+        target.write_all(ansi::SHOW_CURSOR.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Generate and render the QR codes for `data` to the given writer, using the given `renderer`.
+fn render_codes<D: ToQrPayload, W: Write>(
+    data: D,
+    options: &QrOptions,
+    renderer: &Renderer,
+    target: &mut W,
+) -> Result<(), Error> {
+    let mut payload = checked_payload(data.to_qr_payload(), options)?;
+    let result = render_parts(&payload, options, renderer, target);
+    if options.secret {
+        payload.wipe();
+    }
+    result
+}
+
+/// Render the QR codes for the parts `payload` is split into to the given
+/// writer, using the given `renderer`.
+fn render_parts<W: Write>(
+    payload: &Payload,
+    options: &QrOptions,
+    renderer: &Renderer,
+    target: &mut W,
+) -> Result<(), Error> {
+    let parts = split::split(payload, options)?;
+    let hooks = options.hooks.as_deref();
+    if parts.len() > 1 {
+        log_event!(debug, "data split over {} codes", parts.len());
+    }
+    let mut arena = Arena::new();
+    let grid = options.layout == options::Layout::Grid && parts.len() > 1;
+    let mut blocks = Vec::new();
+    let result = parts.iter().enumerate().try_for_each(|(i, part)| {
+        if let (Some(hooks), true) = (hooks, parts.len() > 1) {
+            hooks.on_frame(i, parts.len());
+        }
+
+        // Generate QR code pixel matrix
+        let start = Timer::start();
+        let code = generate_part(part, options)?;
+        log_event!(debug, "generated {} in {:?}", code.info(), start.elapsed());
+        if let Some(hooks) = hooks {
+            hooks.on_generated(&code.info());
+        }
+        let mut matrix = code_matrix_in(&code, options, &mut arena)?;
+        let number = (parts.len() > 1).then(|| (i + 1, parts.len()));
+        let result = match grid {
+            // Render into a block of its own, to arrange side by side
+            true => {
+                blocks.push(Vec::new());
+                let block = blocks.last_mut().unwrap();
+                render_code(&code, &matrix, number, part, options, renderer, block)
+            }
+            false => render_code(&code, &matrix, number, part, options, renderer, target),
+        };
+        if options.secret {
+            matrix.wipe(render::QrLight);
+        }
+        arena.recycle(matrix);
+        result
+    });
+    let result = match (result, grid) {
+        (Ok(()), true) => Ok(grid::write_grid(&blocks, grid::columns(options), target)?),
+        (result, _) => result,
+    };
+    if options.secret {
+        blocks.iter_mut().for_each(|block| payload::wipe(block));
+    }
+    result
+}
+
+/// Render the generated QR `code` with the given `matrix` to the given writer,
+/// using the given `renderer`.
+///
+/// `number` holds the part number and count if the data was split into `part`s.
+fn render_code<W: Write>(
+    code: &Qr,
+    matrix: &Matrix<Color>,
+    number: Option<(usize, usize)>,
+    part: &Payload,
+    options: &QrOptions,
+    renderer: &Renderer,
+    target: &mut W,
+) -> Result<(), Error> {
+    let hooks = options.hooks.as_deref();
+    if let Some(max_width) = options.max_width {
+        let width = renderer.width(matrix);
+        if width > max_width {
+            return Err(Error::TooWide { width, max_width });
+        }
+    }
+
+    // Render QR code, with part caption if split
+    if options.accessibility != options::Accessibility::Instead {
+        let start = Timer::start();
+        renderer.render(matrix, target)?;
+        log_event!(
+            debug,
+            "rendered in {} style in {:?}",
+            renderer.resolve_style(matrix),
+            start.elapsed()
+        );
+        if let Some(hooks) = hooks {
+            hooks.on_rendered(renderer.height(matrix), renderer.width(matrix));
+        }
+        if let (Some((part, count)), options::Overflow::Split) = (number, options.on_overflow) {
+            let caption = format!("part {}/{}", part, count);
+            let indent = renderer.width(matrix).saturating_sub(caption.len()) / 2;
+            writeln!(target, "{:indent$}{}", "", caption, indent = indent)?;
+        }
+        let hyperlink = options.hyperlink != options::Hyperlink::Off;
+        if let (true, None, false) = (hyperlink, number, options.secret) {
+            if let Some(url) = link_url(part.data()) {
+                let style = renderer.resolve_style(matrix);
+                let escape = options.hyperlink == options::Hyperlink::Escaped
+                    && style != render::Style::CiLog;
+                ansi::write_hyperlink(target, url, escape)?;
+                writeln!(target)?;
+            }
+        }
+        if let (Some(len), false) = (options.preview.filter(|&len| len > 0), options.secret) {
+            let caption = preview_caption(part.data(), len);
+            let width = caption.chars().count();
+            let indent = renderer.width(matrix).saturating_sub(width) / 2;
+            writeln!(target, "{:indent$}{}", "", caption, indent = indent)?;
+        }
+    }
+    if options.accessibility != options::Accessibility::Off {
+        let content = match options.secret {
+            true => None,
+            false => Some(part.data()),
+        };
+        writeln!(
+            target,
+            "{}",
+            describe(&code.info(), number, content, options)
+        )?;
+    }
+    Ok(())
+}
+
+/// Get the URL `data` holds to print as hyperlink, see [`QrOptions::hyperlink`].
+fn link_url(data: &[u8]) -> Option<&str> {
+    let url = std::str::from_utf8(data).ok()?;
+    let scheme = url.get(..8).unwrap_or(url).to_ascii_lowercase();
+    let is_http = scheme.starts_with("http://") || scheme.starts_with("https://");
+    let printable = !url.chars().any(|c| c.is_whitespace() || c.is_control());
+    (is_http && printable).then_some(url)
+}
+
+/// Get the single line preview of `data` of at most `len` characters, with
+/// its CRC-32 if shortened, see [`QrOptions::preview`].
+fn preview_caption(data: &[u8], len: usize) -> String {
+    let text = String::from_utf8_lossy(data);
+    let mut chars = text.chars().map(|c| if c.is_control() { ' ' } else { c });
+    let mut preview: String = chars.by_ref().take(len).collect();
+    if chars.next().is_none() {
+        return preview;
+    }
+    preview.pop();
+    preview.push('…');
+    format!("{} [{:08x}]", preview, png::crc32(data))
+}
+
+/// Describe a generated code for screen readers, see [`options::Accessibility`].
+///
+/// `number` holds the part number and count if the data was split, `data` is
+/// `None` for secret data.
+fn describe(
+    info: &QrInfo,
+    number: Option<(usize, usize)>,
+    data: Option<&[u8]>,
+    options: &QrOptions,
+) -> String {
+    let code = match number {
+        Some((part, count)) => format!("QR code {} of {}", part, count),
+        None => "QR code".to_owned(),
+    };
+    let content = match data.map(|data| (data, std::str::from_utf8(data))) {
+        None => "secret data".to_owned(),
+        Some((data, _)) if options.compress => {
+            format!("{} bytes of compressed data", data.len())
+        }
+        Some((data, Ok(text))) => match options.preview.filter(|&len| len > 0) {
+            Some(len) => format!("{:?}", preview_caption(data, len)),
+            None => format!("{:?}", text),
+        },
+        Some((data, Err(_))) => format!("{} bytes of binary data", data.len()),
+    };
+    format!(
+        "{}, version {}, error correction {:?}, encodes {}",
+        code,
+        info.version_name(),
+        info.ec_level,
+        content
+    )
+}
+
+/// Generate the QR code for `data`.
+pub(crate) fn generate_code<D: ToQrPayload>(data: D, options: &QrOptions) -> Result<Qr, Error> {
+    let payload = checked_payload(data.to_qr_payload(), options)?;
+    Ok(generate_part(&payload, options)?)
+}
+
+/// Generate the QR code for `data` without checking it, to inspect the code
+/// rather than render it.
+pub(crate) fn unchecked_code<D: ToQrPayload>(data: D, options: &QrOptions) -> Result<Qr, QrError> {
+    generate_part(&prepare_payload(data.to_qr_payload(), options), options)
+}
+
+/// Generate the QR code for the prepared `payload`, or a part of it.
+pub(crate) fn generate_part(payload: &Payload, options: &QrOptions) -> Result<Qr, QrError> {
+    match options.deterministic {
+        true => Qr::from_payload_with_mask(payload, options.ec_level, MaskPattern::Checkerboard),
+        false => Qr::from_payload(payload, options.ec_level),
+    }
+}
+
+/// Transform the payload as configured in `options` before encoding it,
+/// checking it as described in [`lint`] first.
+pub(crate) fn checked_payload<'a>(
+    payload: Payload<'a>,
+    options: &QrOptions,
+) -> Result<Payload<'a>, Error> {
+    let mut payload = normalize_payload(payload, options);
+    let text = String::from_utf8_lossy(payload.data());
+    for lint in lint::check(&text) {
+        log_event!(warn, "{}", lint);
+        if let Some(hooks) = &options.hooks {
+            hooks.on_lint(&lint);
+        }
+    }
+    let result = options
+        .validators
+        .0
+        .iter()
+        .try_for_each(|validator| validator(&text).map_err(Error::Rejected));
+    if options.secret {
+        if let Cow::Owned(text) = text {
+            payload::wipe(&mut text.into_bytes());
+        }
+        if result.is_err() {
+            payload.wipe();
+        }
+    }
+    result.map(|()| {
+        let payload = checksum_payload(payload, options);
+        pin_payload(compress_payload(payload, options), options)
+    })
+}
+
+/// Transform the payload as configured in `options` before encoding it.
+pub(crate) fn prepare_payload<'a>(payload: Payload<'a>, options: &QrOptions) -> Payload<'a> {
+    let payload = checksum_payload(normalize_payload(payload, options), options);
+    pin_payload(compress_payload(payload, options), options)
+}
+
+/// Normalize the payload as configured in `options`.
+fn normalize_payload<'a>(payload: Payload<'a>, options: &QrOptions) -> Payload<'a> {
+    let normalized = match options.normalize.apply(payload.data()) {
+        Cow::Borrowed(data) if data.len() == payload.data().len() => None,
+        data => Some(data.into_owned()),
+    };
+    match normalized {
+        None => payload,
+        Some(data) => {
+            let normalized = Payload::new(data).with_mode(payload.mode());
+            match payload.eci() {
+                Some(eci) => normalized.with_eci(eci),
+                None => normalized,
+            }
+        }
+    }
+}
+
+/// Append the checksum configured in `options` to the payload, see [`checksum`].
+///
+/// Numeric payloads are encoded in alphanumeric mode instead, to fit the suffix.
+fn checksum_payload<'a>(mut payload: Payload<'a>, options: &QrOptions) -> Payload<'a> {
+    if options.checksum == checksum::Checksum::None {
+        return payload;
+    }
+    let data = checksum::append_checksum(payload.data(), options.checksum);
+    let mode = match payload.mode() {
+        payload::Mode::Numeric => payload::Mode::Alphanumeric,
+        mode => mode,
+    };
+    let suffixed = Payload::new(data).with_mode(mode);
+    let suffixed = match payload.eci() {
+        Some(eci) => suffixed.with_eci(eci),
+        None => suffixed,
+    };
+    if options.secret {
+        payload.wipe();
+    }
+    suffixed
+}
+
+/// Compress the payload if configured in `options`.
+fn compress_payload<'a>(payload: Payload<'a>, options: &QrOptions) -> Payload<'a> {
+    if options.compress {
+        return Payload::new(compress::compress(payload.data())).with_mode(payload::Mode::Byte);
+    }
+    payload
+}
+
+/// Pin the mode of the payload if `options` are deterministic, see
+/// [`QrOptions::deterministic`].
+fn pin_payload<'a>(payload: Payload<'a>, options: &QrOptions) -> Payload<'a> {
+    match (options.deterministic, payload.mode()) {
+        (true, payload::Mode::Auto) => {
+            let mode = payload::Mode::single_segment(payload.data());
+            payload.with_mode(mode)
+        }
+        _ => payload,
+    }
+}
+
+/// Get the pixel matrix of the given QR `code`, including its quiet zone.
+///
+/// Fails if the quiet zone makes the matrix too large.
+pub(crate) fn code_matrix(code: &Qr, options: &QrOptions) -> Result<Matrix<Color>, MatrixError> {
+    code_matrix_in(code, options, &mut Arena::new())
+}
+
+/// Build the pixel matrix for `code` like [`code_matrix`], in buffers of the
+/// `arena`.
+pub(crate) fn code_matrix_in(
+    code: &Qr,
+    options: &QrOptions,
+    arena: &mut Arena<Color>,
+) -> Result<Matrix<Color>, MatrixError> {
+    let mut matrix = code.to_matrix_in(arena);
+    matrix.surround_in(options.quiet_zone, render::QrLight, arena)?;
+    if options.mirror {
+        matrix.mirror();
+    }
+    matrix.rotate_in(options.rotate, arena);
+    Ok(matrix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Generating QR codes for text that is too large returns an error.
+    #[test]
+    fn try_generate_too_long() {
+        let err = try_generate_qr_string("a".repeat(8000)).unwrap_err();
+        assert!(matches!(err, Error::Qr(QrError::DataTooLong)));
+    }
+
+    /// A quiet zone too large to render returns an error instead of panicking.
+    #[test]
+    fn huge_quiet_zone() {
+        let options = QrOptions::new().quiet_zone(3_000_000_000);
+        let err = try_generate_qr_string_with_options("qr2term", &options).unwrap_err();
+        assert!(matches!(err, Error::Matrix(MatrixError::TooLarge)));
+    }
+
+    /// Compressing makes repetitive data fit a single QR code.
+    #[test]
+    fn compressed_fits() {
+        let data = r#"{"key":"value"}"#.repeat(500);
+        let options = QrOptions::new();
+        assert!(qr_info(&data, &options).is_err());
+        assert!(qr_info(&data, &options.compress(true)).is_ok());
+    }
+
+    /// Checksums are appended after normalizing, keeping numeric payloads encodable.
+    #[test]
+    fn checksum_suffix() {
+        use crate::checksum::Checksum;
+        use crate::normalize::Normalize;
+
+        let options = QrOptions::new()
+            .normalize(Normalize::new().trim(true))
+            .checksum(Checksum::Crc32);
+        let payload = prepare_payload(" hello\n".to_qr_payload(), &options);
+        assert_eq!(payload.data(), b"hello*3610A686");
+
+        let numeric = Payload::new(&b"0123"[..]).with_mode(payload::Mode::Numeric);
+        let payload = prepare_payload(numeric, &options);
+        assert_eq!(payload.mode(), payload::Mode::Alphanumeric);
+        assert!(generate_part(&payload, &options).is_ok());
+    }
+
+    /// URL payloads are printed under the code, escaped as hyperlink if asked
+    /// to, and never escaped in strings when detecting support.
+    #[test]
+    fn hyperlink() {
+        use crate::options::Hyperlink;
+
+        let options = QrOptions::new().hyperlink(Hyperlink::Auto);
+        let output =
+            try_generate_qr_string_with_options("https://rust-lang.org/", &options).unwrap();
+        assert!(output.ends_with("\nhttps://rust-lang.org/\n"));
+        let escaped = options.clone().hyperlink(Hyperlink::Escaped);
+        let output =
+            try_generate_qr_string_with_options("https://rust-lang.org/", &escaped).unwrap();
+        assert!(output.ends_with("\x1B]8;;\x1B\\\n"));
+        assert_eq!(
+            options.deterministic(true).for_terminal().hyperlink,
+            Hyperlink::Auto
+        );
+
+        let options = QrOptions::new().hyperlink(Hyperlink::Plain);
+        for data in ["rust-lang.org", "https://rust-lang.org/ docs"] {
+            let output = try_generate_qr_string_with_options(data, &options).unwrap();
+            assert!(!output.contains(data));
+        }
+        let output =
+            try_generate_qr_string_with_options("https://rust-lang.org/", &options.secret(true))
+                .unwrap();
+        assert!(!output.contains("rust-lang"));
+
+        let mut buf = Vec::new();
+        ansi::write_hyperlink(&mut buf, "https://rust-lang.org/", true).unwrap();
+        assert_eq!(
+            buf,
+            b"\x1B]8;;https://rust-lang.org/\x1B\\https://rust-lang.org/\x1B]8;;\x1B\\"
+        );
+    }
+
+    /// Long payloads are previewed shortened, with a hash telling them apart.
+    #[test]
+    fn preview() {
+        let options = QrOptions::new().preview(12).deterministic(true);
+        let output = try_generate_qr_string_with_options("qr2term", &options).unwrap();
+        assert!(output.ends_with("qr2term\n"));
+        let plain = try_generate_qr_string_with_options("qr2term", &QrOptions::new()).unwrap();
+        let output =
+            try_generate_qr_string_with_options("qr2term", &options.clone().preview(0)).unwrap();
+        assert_eq!(output, plain);
+
+        let data = "https://example.com/invite/1";
+        let output = try_generate_qr_string_with_options(data, &options).unwrap();
+        let caption = output.lines().last().unwrap().trim_start();
+        assert_eq!(
+            caption,
+            format!("https://exa… [{:08x}]", png::crc32(data.as_bytes()))
+        );
+        assert!(!output.contains(data));
+        assert_ne!(
+            preview_caption(b"https://example.com/invite/2", 12),
+            caption
+        );
+
+        let options = options.accessibility(options::Accessibility::Instead);
+        let output = try_generate_qr_string_with_options(data, &options).unwrap();
+        assert!(output.ends_with(&format!("encodes \"{}\"\n", caption)));
+        let output = try_generate_qr_string_with_options(data, &options.secret(true)).unwrap();
+        assert!(!output.contains("https"));
+    }
+
+    /// Accessibility descriptions are printed alongside or instead of the code.
+    #[test]
+    fn accessibility_description() {
+        use crate::options::{Accessibility, Overflow};
+
+        let options = QrOptions::new().accessibility(Accessibility::Instead);
+        assert_eq!(
+            try_generate_qr_string_with_options("qr2term", &options).unwrap(),
+            "QR code, version 1, error correction M, encodes \"qr2term\"\n"
+        );
+
+        let options = options.accessibility(Accessibility::Alongside);
+        let output = try_generate_qr_string_with_options("qr2term", &options).unwrap();
+        assert_eq!(output.lines().count(), 14);
+        assert!(output.ends_with("encodes \"qr2term\"\n"));
+
+        let options = QrOptions::new()
+            .accessibility(Accessibility::Instead)
+            .on_overflow(Overflow::Split);
+        let output = try_generate_qr_string_with_options([0xFF; 3000], &options).unwrap();
+        assert!(output.starts_with("QR code 1 of 2, version 40, error correction M, encodes "));
+        assert!(output.contains("bytes of binary data\nQR code 2 of 2"));
+    }
+
+    /// Secret payloads are left out of descriptions, and their buffers wiped.
+    #[test]
+    fn secret_payload() {
+        use crate::normalize::Normalize;
+        use crate::options::Accessibility;
+
+        let options = QrOptions::new()
+            .accessibility(Accessibility::Alongside)
+            .secret(true);
+        let output = try_generate_qr_string_with_options("hunter2", &options).unwrap();
+        assert!(output.ends_with("encodes secret data\n"));
+        assert!(!output.contains("hunter2"));
+        let public = options.clone().secret(false);
+        assert_eq!(
+            output.replace("secret data", "\"hunter2\""),
+            try_generate_qr_string_with_options("hunter2", &public).unwrap()
+        );
+
+        let options = options.normalize(Normalize::new().trim(true));
+        let mut payload = prepare_payload(" hunter2 ".to_qr_payload(), &options);
+        payload.wipe();
+        assert_eq!(payload.data(), [0; 7]);
+        let data = b"hunter2".to_vec();
+        let mut payload = Payload::new(&data[..]);
+        payload.wipe();
+        assert_eq!(payload.data(), b"hunter2");
+    }
+
+    /// Payloads are normalized before encoding.
+    #[test]
+    fn normalized_payload() {
+        use crate::normalize::Normalize;
+
+        let options = QrOptions::new().normalize(Normalize::new().strip_ansi(true).trim(true));
+        let payload = prepare_payload("\x1B[1mqr2term\x1B[0m\n".to_qr_payload(), &options);
+        assert_eq!(payload.data(), b"qr2term");
+        assert_eq!(
+            try_generate_qr_string_with_options("\x1B[1mqr2term\x1B[0m\n", &options).unwrap(),
+            try_generate_qr_string_with_options("qr2term", &options).unwrap()
+        );
+    }
+
+    /// Deterministic codes pin the segments and mask, and still scan.
+    #[test]
+    fn deterministic() {
+        let options = QrOptions::new().deterministic(true);
+        for (data, mode) in [
+            ("https://rust-lang.org/", qr::SegmentMode::Byte),
+            (
+                "HTTPS://RUST-LANG.ORG/1234567890",
+                qr::SegmentMode::Alphanumeric,
+            ),
+            ("0123456789", qr::SegmentMode::Numeric),
+        ] {
+            let info = qr_info(data, &options).unwrap();
+            assert_eq!((info.mask, info.mode), (0, mode));
+            #[cfg(feature = "debug")]
+            assert!(debug::roundtrip(data, &options).unwrap().is_match());
+        }
+        assert_ne!(
+            qr_info("https://rust-lang.org/", &QrOptions::new())
+                .unwrap()
+                .mask,
+            0
+        );
+
+        #[cfg(feature = "debug")]
+        {
+            let options = options.on_overflow(options::Overflow::Split);
+            assert!(debug::roundtrip("a".repeat(5000), &options)
+                .unwrap()
+                .is_match());
+        }
+    }
+
+    /// Codes wider than the maximum width fail, unless a smaller style fits.
+    #[test]
+    fn max_width() {
+        use crate::render::Style;
+
+        let options = QrOptions::new().style(Style::FullBlock).max_width(40);
+        assert!(matches!(
+            try_generate_qr_string_with_options("qr2term", &options),
+            Err(Error::TooWide {
+                width: 50,
+                max_width: 40
+            })
+        ));
+        assert_eq!(
+            generate_qr_string_with_options("qr2term", &options),
+            Err(QrError::DataTooLong)
+        );
+
+        let options = options
+            .style(Style::Auto)
+            .theme(crate::theme::Theme::Monochrome);
+        let output = try_generate_qr_string_with_options("qr2term", &options).unwrap();
+        assert!(output.lines().all(|line| line.chars().count() <= 40));
+    }
+
+    /// Validators check payloads after normalizing them.
+    #[test]
+    fn validated_payload() {
+        use crate::normalize::Normalize;
+
+        let options = QrOptions::new().validator(lint::strict);
+        let err = try_generate_qr_string_with_options("qr2term\n", &options).unwrap_err();
+        assert!(matches!(err, Error::Rejected(_)));
+        assert_eq!(err.category(), ErrorCategory::InvalidPayload);
+        assert!(qr_info("qr2term\n", &options).is_ok());
+        assert_eq!(
+            generate_qr_string_with_options("qr2term\n", &options),
+            Err(QrError::InvalidCharacter)
+        );
+
+        let options = options.normalize(Normalize::new().trim(true));
+        assert!(try_generate_qr_string_with_options("qr2term\n", &options).is_ok());
+    }
+
+    /// Output hygiene options wrap or trim the rendered code.
+    #[test]
+    fn output_hygiene() {
+        let options = QrOptions::new();
+        let plain = try_generate_qr_string_with_options("qr2term", &options).unwrap();
+        assert!(plain.ends_with('\n'));
+
+        let options = options
+            .reset(true)
+            .trailing_newline(false)
+            .hide_cursor(true);
+        let output = try_generate_qr_string_with_options("qr2term", &options).unwrap();
+        assert_eq!(
+            output,
+            format!(
+                "\x1B[?25l{}\x1B[0m\x1B[?25h",
+                plain.strip_suffix('\n').unwrap()
+            )
+        );
+    }
+
+    /// Rows passed to the callback match the lines of the rendered string.
+    #[test]
+    fn rows() {
+        let options = QrOptions::new().on_overflow(options::Overflow::Split);
+        let mut rows = Vec::new();
+        render_rows("https://rust-lang.org/", &options, |row| {
+            rows.push(row.to_owned())
+        })
+        .unwrap();
+        let code = try_generate_qr_string_with_options("https://rust-lang.org/", &options).unwrap();
+        assert_eq!(rows, code.lines().collect::<Vec<_>>());
+
+        let mut rows = Vec::new();
+        let data = "a".repeat(5000);
+        let options = options.trailing_newline(false);
+        render_rows(&data, &options, |row| rows.push(row.to_owned())).unwrap();
+        let code = try_generate_qr_string_with_options(&data, &options).unwrap();
+        assert_eq!(rows, code.lines().collect::<Vec<_>>());
+        assert!(rows.last().unwrap().contains("part 3/3"));
+
+        let raw = options.raw_mode(options::RawMode::Enabled);
+        let mut raw_rows = Vec::new();
+        render_rows(&data, &raw, |row| raw_rows.push(row.to_owned())).unwrap();
+        assert_eq!(raw_rows, rows);
+    }
 }
@@ -0,0 +1,171 @@
+//! Checks of payloads before generating codes for them.
+//!
+//! Every payload is checked for common mistakes, such as a line break piped
+//! along by `echo`, before its code is generated. These [`Lint`]s don't stop
+//! generating the code, but are logged as warnings with the `log` feature,
+//! and passed to [`Hooks::on_lint`](crate::hooks::Hooks::on_lint).
+//!
+//! To reject payloads instead, add [`Validator`]s to the options, such as
+//! [`strict`], which rejects payloads with any lint, or your own checks:
+//!
+//! ```rust
+//! use qr2term::{ErrorCategory, QrOptions};
+//!
+//! fn no_emails(text: &str) -> Result<(), String> {
+//!     match text.contains('@') {
+//!         true => Err("payload looks like it contains an email address".into()),
+//!         false => Ok(()),
+//!     }
+//! }
+//!
+//! let options = QrOptions::new().validator(no_emails);
+//! let err = qr2term::try_generate_qr_string_with_options("me@example.com", &options).unwrap_err();
+//! assert_eq!(err.category(), ErrorCategory::InvalidPayload);
+//! ```
+//!
+//! Checks see the payload after [normalization](crate::normalize), before
+//! [compression](crate::compress). Binary payloads are checked with invalid
+//! UTF-8 sequences replaced by `U+FFFD`.
+
+use std::fmt::{self, Display};
+
+/// Length in bytes above which payloads are linted as
+/// [long](Lint::LongPayload), as their dense codes are hard to scan from a
+/// terminal.
+pub const LONG_PAYLOAD: usize = 300;
+
+/// Check of a payload, returning why it is rejected, if it is, such as
+/// "payload contains an email address".
+///
+/// Add validators to options with
+/// [`QrOptions::validator`](crate::QrOptions::validator).
+pub type Validator = fn(&str) -> Result<(), String>;
+
+/// Common mistake found in a payload.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Lint {
+    /// The payload is longer than [`LONG_PAYLOAD`] bytes.
+    LongPayload {
+        /// Length of the payload, in bytes.
+        len: usize,
+    },
+
+    /// The payload ends with a line break, usually by accident, such as from
+    /// `echo` or an editor.
+    TrailingNewline,
+
+    /// The payload contains ANSI escape sequences, such as colors of piped
+    /// command output.
+    AnsiEscape,
+
+    /// The payload is a URL using `http://` instead of `https://`.
+    InsecureUrl,
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lint::LongPayload { len } => write!(
+                f,
+                "payload is {} bytes long, codes for more than {} bytes are hard to scan",
+                len, LONG_PAYLOAD
+            ),
+            Lint::TrailingNewline => f.write_str("payload ends with a line break"),
+            Lint::AnsiEscape => f.write_str("payload contains ANSI escape sequences"),
+            Lint::InsecureUrl => f.write_str("payload is a URL using http:// instead of https://"),
+        }
+    }
+}
+
+/// Check `text` for common mistakes.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::lint::{self, Lint};
+///
+/// assert_eq!(lint::check("http://example.com\n"), [Lint::TrailingNewline, Lint::InsecureUrl]);
+/// assert!(lint::check("https://example.com").is_empty());
+/// ```
+pub fn check(text: &str) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    if text.len() > LONG_PAYLOAD {
+        lints.push(Lint::LongPayload { len: text.len() });
+    }
+    if text.ends_with('\n') {
+        lints.push(Lint::TrailingNewline);
+    }
+    if text.contains('\x1B') {
+        lints.push(Lint::AnsiEscape);
+    }
+    let scheme = text.trim_start().get(..7).unwrap_or_default();
+    if scheme.eq_ignore_ascii_case("http://") {
+        lints.push(Lint::InsecureUrl);
+    }
+    lints
+}
+
+/// [`Validator`] rejecting payloads with any [`Lint`].
+pub fn strict(text: &str) -> Result<(), String> {
+    match check(text).first() {
+        Some(lint) => Err(lint.to_string()),
+        None => Ok(()),
+    }
+}
+
+/// Validators stored in [`QrOptions`](crate::QrOptions), run in the order
+/// they were added.
+///
+/// Validators are equal if they are the same functions.
+#[derive(Clone, Default)]
+pub struct Validators(pub(crate) Vec<Validator>);
+
+impl PartialEq for Validators {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self
+                .0
+                .iter()
+                .zip(&other.0)
+                .all(|(a, b)| *a as usize == *b as usize)
+    }
+}
+
+impl Eq for Validators {}
+
+impl fmt::Debug for Validators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Validators({})", self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lints() {
+        assert_eq!(check(&"a".repeat(LONG_PAYLOAD)), []);
+        assert_eq!(
+            check(&"a".repeat(LONG_PAYLOAD + 1)),
+            [Lint::LongPayload {
+                len: LONG_PAYLOAD + 1
+            }]
+        );
+        assert_eq!(check("text\r\n"), [Lint::TrailingNewline]);
+        assert_eq!(check("\x1B[1mtext\x1B[0m"), [Lint::AnsiEscape]);
+        assert_eq!(check(" HTTP://example.com"), [Lint::InsecureUrl]);
+        assert_eq!(check("see http://example.com"), []);
+        assert_eq!(check("http:/"), []);
+    }
+
+    #[test]
+    fn strict_validator() {
+        assert_eq!(strict("https://example.com"), Ok(()));
+        assert_eq!(
+            strict("http://example.com"),
+            Err("payload is a URL using http:// instead of https://".into())
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! Printing QR codes into the log.
+
+use log::Level;
+
+use crate::render::{Renderer, Style};
+use crate::{default_options, Error, QrOptions, ToQrPayload};
+
+/// Target of the log records emitted by [`log_qr`].
+pub const LOG_TARGET: &str = "qr2term::code";
+
+/// Prefix of every line emitted by [`log_qr`], so the code can be found in and
+/// filtered from other log output.
+pub const LOG_PREFIX: &str = "qr| ";
+
+/// Line logged instead of [secret](QrOptions::secret) codes.
+const SECRET_NOTE: &str = "secret QR code not logged";
+
+/// Emit the given `data` as QR code through the [`log`](https://docs.rs/log)
+/// facade, one record per line.
+///
+/// Uses the process wide [`default_options`].
+///
+/// Returns an error if generating the QR code failed.
+///
+/// # Examples
+///
+/// ```rust
+/// qr2term::log_qr(log::Level::Info, "pairing code 1234").unwrap();
+/// ```
+pub fn log_qr<D: ToQrPayload>(level: Level, data: D) -> Result<(), Error> {
+    log_qr_with_options(level, data, &default_options())
+}
+
+/// Emit the given `data` as QR code through the [`log`](https://docs.rs/log)
+/// facade, one record per line, using the given `options`.
+///
+/// Every line is logged at `level` with the [`LOG_TARGET`] target, and starts
+/// with [`LOG_PREFIX`]. The code is rendered without colors, with dark modules
+/// drawn as block characters, which scans best from log viewers with a light
+/// background. The [`Style::Auto`] style renders as the default style, as log
+/// output has no terminal size.
+///
+/// [Secret](QrOptions::secret) codes are not logged, a line noting that is
+/// logged instead.
+///
+/// Returns an error if generating the QR code failed.
+pub fn log_qr_with_options<D: ToQrPayload>(
+    level: Level,
+    data: D,
+    options: &QrOptions,
+) -> Result<(), Error> {
+    let style = match options.style {
+        Style::Auto => Style::default(),
+        style => style,
+    };
+    if options.secret {
+        log::log!(target: LOG_TARGET, level, "{}{}", LOG_PREFIX, SECRET_NOTE);
+        return Ok(());
+    }
+    let mut buf = Vec::new();
+    crate::render_qr_with(data, options, &Renderer::new(style).ansi(false), &mut buf)?;
+
+    for line in String::from_utf8_lossy(&buf).lines() {
+        log::log!(target: LOG_TARGET, level, "{}{}", LOG_PREFIX, line);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use log::{Log, Metadata, Record};
+
+    use super::*;
+
+    static LINES: Mutex<Vec<(Level, String)>> = Mutex::new(Vec::new());
+
+    struct Collector;
+
+    impl Log for Collector {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            if record.target() == LOG_TARGET {
+                let line = record.args().to_string();
+                LINES.lock().unwrap().push((record.level(), line));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn lines() {
+        log::set_logger(&Collector).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let options = QrOptions::new().style(Style::Ascii);
+        log_qr_with_options(Level::Warn, "qr2term", &options).unwrap();
+
+        let mut lines = LINES.lock().unwrap();
+        assert_eq!(lines.len(), 25);
+        assert!(lines
+            .iter()
+            .all(|(level, line)| *level == Level::Warn && line.starts_with(LOG_PREFIX)));
+        assert!(lines[2].1.contains("##############"));
+        assert!(!lines[2].1.contains('\x1b'));
+        lines.clear();
+        drop(lines);
+
+        log_qr_with_options(Level::Info, "hunter2", &options.secret(true)).unwrap();
+        let lines = LINES.lock().unwrap();
+        assert_eq!(
+            *lines,
+            [(Level::Info, format!("{}{}", LOG_PREFIX, SECRET_NOTE))]
+        );
+    }
+}
@@ -0,0 +1,1684 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Command line interface, printing the given text as QR code to the terminal.
+
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crossterm::{
+    cursor::{MoveToColumn, MoveUp},
+    queue,
+    terminal::{self, Clear, ClearType},
+};
+use qr2term::{
+    batch::{self, BatchError, BatchOptions, InputFormat},
+    checksum::Checksum,
+    clipboard::{self, ClipboardError},
+    config::{Config, ConfigError},
+    hooks::Hooks,
+    lint,
+    normalize::LineEnding,
+    notify::Channel,
+    options::{self, Accessibility, Hyperlink, Layout, Overflow},
+    pager, payload, profile,
+    render::{Style, MAX_COLUMNS},
+    theme::Theme,
+    ErrorCategory, QrInfo, QrOptions,
+};
+
+/// Interval at which a watched file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Prompt shown in interactive mode.
+const PROMPT: &str = "> ";
+
+const USAGE: &str = "\
+Usage: qr2term [OPTIONS] [--] [TEXT]
+       qr2term batch [OPTIONS] [--] TEMPLATE [FILE]
+
+Print TEXT as QR code to the terminal. Reads from stdin if TEXT is omitted.
+
+The batch command prints a code for every row of FILE or stdin instead, with
+the payload given by TEMPLATE, such as 'https://example.com/t/{id}', whose
+{column} variables are replaced by the values of the row.
+
+Arguments after -- are taken as TEXT, TEMPLATE or FILE, even if they start
+with a dash.
+
+Options:
+  -w, --watch <FILE>        Re-render in place whenever FILE changes
+  -i, --interactive         Re-render in place for every line typed on stdin
+  -f, --fit                 Keep the code on screen, re-rendering it to fit whenever the
+                            terminal is resized, until a key is pressed
+      --copy                Also copy the code to the clipboard, as plain text
+  -s, --style <STYLE>       Render style: half, full, ascii, quadrant, sextant,
+                            braille, ci, smooth or auto
+                            [env: QR2TERM_STYLE]
+  -t, --theme <THEME>       Colors: classic, high-contrast, solarized-dark or monochrome
+                            [env: QR2TERM_THEME]
+  -e, --ec-level <LEVEL>    Error correction level: L, M, Q or H [env: QR2TERM_EC_LEVEL]
+  -q, --quiet-zone <SIZE>   Quiet zone size in modules [env: QR2TERM_QUIET_ZONE]
+  -c, --compress            Compress TEXT before encoding, to fit more data
+  -o, --overflow <MODE>     When TEXT doesn't fit: error, split or truncate [env: QR2TERM_OVERFLOW]
+      --layout <LAYOUT>     Arrange codes TEXT is split over: stack, one below the other,
+                            or grid, side by side as far as the terminal is wide
+  -r, --rotate <DEGREES>    Rotate clockwise by 0, 90, 180 or 270 degrees
+  -m, --mirror              Mirror horizontally, for scanning through a reflection
+      --stretch <FACTOR>    Draw every module FACTOR times wider, for fonts or styles
+                            drawing modules taller than wide [env: QR2TERM_STRETCH]
+      --nbsp                Draw light modules as non-breaking spaces, so copies keep
+                            their quiet zone
+      --max-width <COLUMNS> Fail if the code is wider than COLUMNS, or pick a style that
+                            fits with --style auto
+      --trim                Remove leading and trailing whitespace from TEXT
+      --strip-ansi          Remove ANSI escape sequences, such as colors, from TEXT
+      --line-ending <ENDING>
+                            Convert line breaks in TEXT to: keep, lf or crlf
+      --checksum <CHECKSUM> Append a checksum of TEXT after a '*' for scanners to verify:
+                            none, crc32 or sha256 (a 16 digit prefix)
+  -n, --no-newline          Don't end the output with a newline, and reset colors after it
+      --double-size         Print lines at double width and height, on terminals
+                            supporting it such as xterm
+  -a, --accessibility <MODE>
+                            Describe the code for screen readers: off, alongside or instead
+                            [env: QR2TERM_ACCESSIBILITY]
+      --porcelain           Print a line of metadata per code to stderr, such as
+                            'version=2 ec-level=M mask=5 modules=25 columns=29 rows=15'
+      --json-meta           End the output with a line of JSON holding the metadata per
+                            code, such as {\"codes\":[{\"version\":\"2\",...}]}
+      --secret              Treat TEXT as a secret, such as an API token: leave it out of
+                            descriptions and the batch index, wipe it from memory after
+                            printing, and refuse --porcelain and --json-meta
+      --deterministic       Render the same TEXT and options byte for byte alike across
+                            releases and terminals, such as for golden files
+      --strict              Refuse TEXT with common mistakes, such as a trailing line
+                            break, ANSI escape sequences or an http:// URL
+      --hyperlink           Print TEXT under the code if it is a URL, as clickable link on
+                            terminals supporting it
+      --preview <CHARS>     Print a preview of TEXT under the code, shortened to CHARS
+                            characters with a hash to tell codes apart
+      --notify <CHANNEL>    Prompt to come and scan the code through CHANNEL: title
+                            for the terminal title, osc9 for a desktop notification
+                            through the terminal, bell for the terminal bell, or
+                            desktop for a desktop notification through notify-send
+                            or osascript; may be repeated
+      --notify-message <TEXT>
+                            Message to notify [default: QR code ready, scan it]
+  -p, --profile <NAME>      Apply the options of a profile: presentation, ci, or one
+                            defined in the config file, before all other options
+      --completions <SHELL> Print completions for SHELL: bash, zsh or fish
+  -h, --help                Print this help
+
+Batch options:
+      --format <FORMAT>     Format of the rows: csv with a header row, or a json
+                            array of objects [default: csv]
+      --name <COLUMN>       Print the value of COLUMN above every code
+      --index               Number the codes, and end with an index of all codes
+      --pager               Page the codes through $PAGER (default: less -R) if
+                            they don't fit the terminal
+      --images <DIR>        Write every code as PNG image into DIR instead, named by
+                            the --name column
+      --reserve <AREA>      Leave a WxH or WxH+X+Y rectangle of modules blank in the
+                            images, for a sticker, failing if the code wouldn't scan
+
+Exit status:
+  0  Success
+  1  Any other failure
+  2  Invalid command line arguments
+  3  TEXT is too long for a QR code
+  4  The terminal can't show the code: it is too narrow for --max-width, not a
+     terminal for --fit, or has no clipboard tool for --copy
+  5  Reading input or writing output failed
+  6  Input couldn't be decoded: it isn't UTF-8 text, or has malformed batch rows,
+     or the config file is malformed
+
+Config:
+  Defaults are read from ~/.config/qr2term/config.toml, or the file named by
+  QR2TERM_CONFIG, with the keys style, theme, ec_level, quiet_zone, overflow,
+  accessibility, stretch and format (of batch rows), such as:
+
+    style = \"full\"
+    quiet_zone = 4
+
+  Profiles are defined in [profiles.NAME] tables with the same keys, such as:
+
+    [profiles.badge]
+    style = \"half\"
+    ec_level = \"H\"
+
+  Environment variables override the config file, --profile overrides both, and
+  all other options override --profile.";
+
+/// Exit code for failures without a more specific exit code.
+const EXIT_FAILURE: i32 = 1;
+
+/// Exit code for invalid command line arguments.
+const EXIT_USAGE: i32 = 2;
+
+/// Exit code for text too long for a QR code.
+const EXIT_TOO_LONG: i32 = 3;
+
+/// Exit code for a terminal that can't show the code.
+const EXIT_TERMINAL: i32 = 4;
+
+/// Exit code for failing to read input or write output.
+const EXIT_IO: i32 = 5;
+
+/// Exit code for input that couldn't be decoded.
+const EXIT_DECODE: i32 = 6;
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+/// What the command line asked us to do.
+#[derive(Debug, PartialEq, Eq)]
+enum Mode {
+    /// Render once, from the given text or stdin.
+    Once(Option<String>, Metadata),
+
+    /// Render the contents of a file, re-rendering whenever it changes.
+    Watch(PathBuf),
+
+    /// Render every line typed on stdin.
+    Interactive,
+
+    /// Render once, from the given text or stdin, following the terminal size.
+    Fit(Option<String>),
+
+    /// Render once, from the given text or stdin, and copy it to the clipboard.
+    Copy(Option<String>, Metadata),
+
+    /// Render a code for every row of a file or stdin.
+    Batch(Batch),
+
+    /// Print completions for a shell.
+    Completions(Shell),
+
+    /// Print usage information.
+    Help,
+}
+
+/// Shell to print completions for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    const ALL: [Shell; 3] = [Shell::Bash, Shell::Zsh, Shell::Fish];
+
+    fn name(self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+        }
+    }
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        Shell::ALL
+            .into_iter()
+            .find(|shell| shell.name() == name)
+            .ok_or_else(|| format!("unsupported shell '{}'", s))
+    }
+}
+
+/// Metadata to print about the printed codes, for scripts.
+///
+/// The formats are stable: fields are only ever added.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+struct Metadata {
+    /// Print `key=value` pairs to stderr, a line per code.
+    porcelain: bool,
+
+    /// End the output with a line of JSON.
+    json: bool,
+}
+
+/// What the batch command should do.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Batch {
+    /// Template of the payload of every row.
+    template: String,
+
+    /// File to read rows from, stdin if `None`.
+    input: Option<PathBuf>,
+
+    /// Format of the rows.
+    format: InputFormat,
+
+    /// Column to label codes or name images by.
+    name: Option<String>,
+
+    /// Whether to number the codes and end with an index.
+    index: bool,
+
+    /// Whether to page the codes if they don't fit the terminal.
+    pager: bool,
+
+    /// Directory to write images into, printing codes if `None`.
+    images: Option<PathBuf>,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let config = match config_for(Config::load(), &args) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(exit_code(&err));
+        }
+    };
+
+    let options = config.apply(QrOptions::new()).with_env();
+    let (mode, mut options) = match parse_args(args.into_iter(), options, &config) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            process::exit(EXIT_USAGE);
+        }
+    };
+    // Codes are printed to the terminal, also when rendered into strings first
+    if !options.deterministic {
+        options.hyperlink = options.hyperlink.for_terminal();
+    }
+
+    let result = match mode {
+        Mode::Once(text, metadata) => run_once(text, &options, metadata),
+        Mode::Watch(path) => run_watch(&path, &options),
+        Mode::Interactive => run_interactive(&options),
+        Mode::Fit(text) => run_fit(text, &options),
+        Mode::Copy(text, metadata) => run_copy(text, &options, metadata),
+        Mode::Batch(batch) => run_batch(&batch, &options),
+        Mode::Completions(shell) => {
+            print!("{}", completions(shell));
+            Ok(())
+        }
+        Mode::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    };
+
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        process::exit(exit_code(err.as_ref()));
+    }
+}
+
+/// Get the `loaded` config, or the default config if loading it failed but
+/// `args` only ask for usage information or completions, which don't depend on
+/// it, so a broken config can't lock users out of the help.
+fn config_for(loaded: Result<Config, ConfigError>, args: &[String]) -> Result<Config, ConfigError> {
+    loaded.or_else(|err| {
+        let args = args.iter().cloned();
+        match parse_args(args, QrOptions::new(), &Config::default()) {
+            Ok((Mode::Help | Mode::Completions(_), _)) => Ok(Config::default()),
+            _ => Err(err),
+        }
+    })
+}
+
+/// Get the exit code for `err`, by its category.
+fn exit_code(err: &(dyn Error + 'static)) -> i32 {
+    let category = if let Some(err) = err.downcast_ref::<qr2term::Error>() {
+        err.category()
+    } else if let Some(err) = err.downcast_ref::<BatchError>() {
+        err.category()
+    } else if let Some(err) = err.downcast_ref::<ClipboardError>() {
+        err.category()
+    } else if let Some(err) = err.downcast_ref::<ConfigError>() {
+        err.category()
+    } else if let Some(err) = err.downcast_ref::<io::Error>() {
+        err.into()
+    } else {
+        return EXIT_FAILURE;
+    };
+    match category {
+        ErrorCategory::PayloadTooLong => EXIT_TOO_LONG,
+        ErrorCategory::UnsupportedTerminal => EXIT_TERMINAL,
+        ErrorCategory::Io => EXIT_IO,
+        ErrorCategory::Decode => EXIT_DECODE,
+        _ => EXIT_FAILURE,
+    }
+}
+
+/// Largest stretch factor accepted.
+const MAX_STRETCH: usize = 16;
+
+/// Kind of value a command line flag takes.
+#[derive(Clone, Copy)]
+enum Value {
+    /// No value, the flag is a switch.
+    None,
+
+    /// Free text, such as a column name.
+    Text,
+
+    /// A number from the given range, inclusive.
+    Number(usize, usize),
+
+    /// Path to a file.
+    File,
+
+    /// Path to a directory.
+    Dir,
+
+    /// One of the names returned by the function.
+    Choice(fn() -> Vec<&'static str>),
+}
+
+/// A command line flag, for parsing and shell completions.
+struct Flag {
+    short: Option<&'static str>,
+    long: &'static str,
+    value: Value,
+
+    /// Short description for shell completions.
+    about: &'static str,
+
+    /// Whether the flag is only accepted by the batch command.
+    batch: bool,
+}
+
+impl Flag {
+    const fn new(short: Option<&'static str>, long: &'static str, about: &'static str) -> Self {
+        Self {
+            short,
+            long,
+            value: Value::None,
+            about,
+            batch: false,
+        }
+    }
+
+    const fn value(mut self, value: Value) -> Self {
+        self.value = value;
+        self
+    }
+
+    const fn batch(mut self) -> Self {
+        self.batch = true;
+        self
+    }
+}
+
+/// All command line flags, see [`USAGE`].
+const FLAGS: &[Flag] = &[
+    Flag::new(Some("-w"), "--watch", "Re-render whenever FILE changes").value(Value::File),
+    Flag::new(
+        Some("-i"),
+        "--interactive",
+        "Re-render for every line typed",
+    ),
+    Flag::new(Some("-f"), "--fit", "Re-render to fit the terminal"),
+    Flag::new(None, "--copy", "Also copy the code to the clipboard"),
+    Flag::new(Some("-s"), "--style", "Render style").value(Value::Choice(|| {
+        let styles = Style::ALL.iter().map(|style| style.name());
+        styles.chain([Style::Auto.name()]).collect()
+    })),
+    Flag::new(Some("-t"), "--theme", "Colors")
+        .value(Value::Choice(|| Theme::ALL.map(Theme::name).to_vec())),
+    Flag::new(Some("-e"), "--ec-level", "Error correction level")
+        .value(Value::Choice(|| vec!["L", "M", "Q", "H"])),
+    Flag::new(Some("-q"), "--quiet-zone", "Quiet zone size in modules")
+        .value(Value::Number(0, options::MAX_QUIET_ZONE)),
+    Flag::new(Some("-c"), "--compress", "Compress TEXT before encoding"),
+    Flag::new(Some("-o"), "--overflow", "When TEXT does not fit").value(Value::Choice(|| {
+        [Overflow::Error, Overflow::Split, Overflow::Truncate]
+            .map(Overflow::name)
+            .to_vec()
+    })),
+    Flag::new(None, "--layout", "How to arrange split codes")
+        .value(Value::Choice(|| Layout::ALL.map(Layout::name).to_vec())),
+    Flag::new(Some("-r"), "--rotate", "Rotate clockwise by DEGREES")
+        .value(Value::Choice(|| vec!["0", "90", "180", "270"])),
+    Flag::new(Some("-m"), "--mirror", "Mirror horizontally"),
+    Flag::new(None, "--stretch", "Draw every module wider").value(Value::Number(1, MAX_STRETCH)),
+    Flag::new(None, "--nbsp", "Draw light modules as non-breaking spaces"),
+    Flag::new(None, "--max-width", "Maximum width in columns").value(Value::Number(1, MAX_COLUMNS)),
+    Flag::new(None, "--trim", "Remove surrounding whitespace from TEXT"),
+    Flag::new(
+        None,
+        "--strip-ansi",
+        "Remove ANSI escape sequences from TEXT",
+    ),
+    Flag::new(None, "--line-ending", "Convert line breaks in TEXT").value(Value::Choice(|| {
+        [LineEnding::Keep, LineEnding::Lf, LineEnding::CrLf]
+            .map(LineEnding::name)
+            .to_vec()
+    })),
+    Flag::new(None, "--checksum", "Append a checksum of TEXT")
+        .value(Value::Choice(|| Checksum::ALL.map(Checksum::name).to_vec())),
+    Flag::new(
+        Some("-n"),
+        "--no-newline",
+        "Do not end the output with a newline",
+    ),
+    Flag::new(
+        None,
+        "--double-size",
+        "Print lines at double width and height",
+    ),
+    Flag::new(
+        Some("-a"),
+        "--accessibility",
+        "Describe the code for screen readers",
+    )
+    .value(Value::Choice(|| {
+        [
+            Accessibility::Off,
+            Accessibility::Alongside,
+            Accessibility::Instead,
+        ]
+        .map(Accessibility::name)
+        .to_vec()
+    })),
+    Flag::new(None, "--porcelain", "Print metadata per code to stderr"),
+    Flag::new(None, "--json-meta", "End the output with JSON metadata"),
+    Flag::new(
+        None,
+        "--secret",
+        "Keep TEXT out of all output besides the code",
+    ),
+    Flag::new(None, "--deterministic", "Render alike across releases"),
+    Flag::new(None, "--strict", "Refuse TEXT with common mistakes"),
+    Flag::new(None, "--hyperlink", "Print URLs under the code as link"),
+    Flag::new(None, "--preview", "Longest preview of TEXT under the code")
+        .value(Value::Number(1, MAX_COLUMNS)),
+    Flag::new(None, "--notify", "Prompt to come and scan the code")
+        .value(Value::Choice(|| Channel::ALL.map(Channel::name).to_vec())),
+    Flag::new(None, "--notify-message", "Message to notify").value(Value::Text),
+    Flag::new(Some("-p"), "--profile", "Apply a profile of options")
+        .value(Value::Choice(|| profile::BUILTIN.to_vec())),
+    Flag::new(None, "--completions", "Print shell completions")
+        .value(Value::Choice(|| Shell::ALL.map(Shell::name).to_vec())),
+    Flag::new(Some("-h"), "--help", "Print help"),
+    Flag::new(None, "--format", "Format of the rows")
+        .value(Value::Choice(|| {
+            [InputFormat::Csv, InputFormat::Json]
+                .map(InputFormat::name)
+                .to_vec()
+        }))
+        .batch(),
+    Flag::new(None, "--name", "Label codes by COLUMN")
+        .value(Value::Text)
+        .batch(),
+    Flag::new(None, "--index", "Number the codes and end with an index").batch(),
+    Flag::new(None, "--pager", "Page the codes through $PAGER").batch(),
+    Flag::new(None, "--images", "Write PNG images into DIR")
+        .value(Value::Dir)
+        .batch(),
+    Flag::new(None, "--reserve", "Leave a rectangle blank in the images")
+        .value(Value::Text)
+        .batch(),
+];
+
+/// Find the flag given as `arg`, suggesting the closest flag if unknown.
+fn find_flag(arg: &str, batch: bool) -> CliResult<&'static Flag> {
+    let flag = FLAGS
+        .iter()
+        .find(|flag| flag.long == arg || flag.short == Some(arg));
+    match flag {
+        Some(flag) if flag.batch && !batch => {
+            Err(format!("'{}' is only accepted by the batch command", arg).into())
+        }
+        Some(flag) => Ok(flag),
+        None => {
+            let flags = FLAGS.iter().filter(|flag| batch || !flag.batch);
+            let mut message = format!("unknown option '{}'", arg);
+            if let Some(closest) = closest(arg, flags.map(|flag| flag.long)) {
+                message.push_str(&format!(", did you mean '{}'?", closest));
+            }
+            Err(message.into())
+        }
+    }
+}
+
+/// Parse the `value` given for `flag` as `arg`.
+fn parse_value<T>(arg: &str, flag: &Flag, value: &str) -> CliResult<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|err| invalid_value(arg, flag, value, err))
+}
+
+/// Parse the `value` given for `flag` as `arg` as a number in its range.
+fn parse_number(arg: &str, flag: &Flag, value: &str) -> CliResult<usize> {
+    let (min, max) = match flag.value {
+        Value::Number(min, max) => (min, max),
+        _ => (usize::MIN, usize::MAX),
+    };
+    match value.trim().parse::<usize>() {
+        Ok(number) if (min..=max).contains(&number) => Ok(number),
+        Ok(_) => Err(invalid_value(
+            arg,
+            flag,
+            value,
+            format!("must be from {} to {}", min, max),
+        )),
+        Err(err) => Err(invalid_value(arg, flag, value, err)),
+    }
+}
+
+/// Error for the invalid `value` given for `flag` as `arg`, for the given
+/// `reason`, suggesting the closest valid value.
+fn invalid_value(arg: &str, flag: &Flag, value: &str, reason: impl Display) -> Box<dyn Error> {
+    let choices = match flag.value {
+        Value::Choice(choices) => choices(),
+        _ => Vec::new(),
+    };
+    invalid_choice(arg, value, reason, &choices)
+}
+
+/// Error for the invalid `value` given as `arg`, for the given `reason`,
+/// suggesting the closest of the valid `choices` if any.
+fn invalid_choice(
+    arg: &str,
+    value: &str,
+    reason: impl Display,
+    choices: &[&str],
+) -> Box<dyn Error> {
+    let mut message = format!("invalid value '{}' for '{}': {}", value, arg, reason);
+    if !choices.is_empty() {
+        match closest(value, choices.iter().copied()) {
+            Some(closest) => message.push_str(&format!(", did you mean '{}'?", closest)),
+            None => message.push_str(&format!(" (possible values: {})", choices.join(", "))),
+        }
+    }
+    message.into()
+}
+
+/// Get the candidate closest to `text`, if any is close enough to be a likely typo.
+fn closest<'a, I: Iterator<Item = &'a str>>(text: &str, candidates: I) -> Option<&'a str> {
+    let text = text.to_ascii_lowercase();
+    candidates
+        .map(|candidate| {
+            (
+                edit_distance(&text, &candidate.to_ascii_lowercase()),
+                candidate,
+            )
+        })
+        .filter(|&(distance, candidate)| distance <= (candidate.len() / 3).min(2))
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Number of single character insertions, deletions and substitutions to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Parse command line arguments, excluding the binary name.
+///
+/// Options given on the command line override the given `options`, and the
+/// batch format of `config`.
+fn parse_args<I: Iterator<Item = String>>(
+    args: I,
+    mut options: QrOptions,
+    config: &Config,
+) -> CliResult<(Mode, QrOptions)> {
+    let mut args = args.peekable();
+    let mut batch = args.next_if(|arg| arg == "batch").map(|_| Batch {
+        format: config.format.unwrap_or_default(),
+        ..Batch::default()
+    });
+    let mut text = None;
+    let mut watch = None;
+    let mut interactive = false;
+    let mut fit = false;
+    let mut copy = false;
+    let mut metadata = Metadata::default();
+    let mut given = Vec::new();
+    let mut positional = false;
+
+    while let Some(arg) = args.next() {
+        if arg == "--" && !positional {
+            positional = true;
+            continue;
+        }
+        if positional || !arg.starts_with('-') || arg == "-" {
+            match (&text, &mut batch) {
+                (None, _) => text = Some(arg),
+                (Some(_), Some(batch)) if batch.input.is_none() => {
+                    batch.input = Some(PathBuf::from(arg))
+                }
+                (Some(_), Some(_)) => return Err("more than one FILE given".into()),
+                (Some(_), None) => return Err("more than one TEXT given".into()),
+            }
+            continue;
+        }
+
+        let flag = find_flag(&arg, batch.is_some())?;
+        let value = match flag.value {
+            Value::None => String::new(),
+            _ => args
+                .next()
+                .ok_or_else(|| format!("missing value for '{}'", arg))?,
+        };
+        match flag.long {
+            "--help" => return Ok((Mode::Help, options)),
+            "--completions" => {
+                return Ok((Mode::Completions(parse_value(&arg, flag, &value)?), options))
+            }
+            _ => given.push((flag, arg, value)),
+        }
+    }
+
+    // Apply profiles first, so other options override them wherever given
+    for (_, arg, name) in given.iter().filter(|(flag, ..)| flag.long == "--profile") {
+        options = config.apply_profile(name, options).map_err(|err| {
+            let names = config.profiles.iter().map(|(name, _)| name.as_str());
+            let names: Vec<&str> = profile::BUILTIN.into_iter().chain(names).collect();
+            invalid_choice(arg, name, err, &names)
+        })?;
+        let format = config.profile(name).and_then(|profile| profile.format);
+        if let (Some(batch), Some(format)) = (&mut batch, format) {
+            batch.format = format;
+        }
+    }
+
+    for (flag, arg, value) in given {
+        match flag.long {
+            "--profile" => {}
+            "--watch" => watch = Some(PathBuf::from(value)),
+            "--interactive" => interactive = true,
+            "--fit" => fit = true,
+            "--copy" => copy = true,
+            "--style" => options.style = parse_value(&arg, flag, &value)?,
+            "--theme" => options.theme = parse_value(&arg, flag, &value)?,
+            "--ec-level" => {
+                options.ec_level = options::parse_ec_level(&value).ok_or_else(|| {
+                    invalid_value(&arg, flag, &value, "unknown error correction level")
+                })?
+            }
+            "--quiet-zone" => options.quiet_zone = parse_number(&arg, flag, &value)?,
+            "--compress" => options.compress = true,
+            "--overflow" => options.on_overflow = parse_value(&arg, flag, &value)?,
+            "--layout" => options.layout = parse_value(&arg, flag, &value)?,
+            "--rotate" => options.rotate = parse_value(&arg, flag, &value)?,
+            "--mirror" => options.mirror = true,
+            "--double-size" => options.double_size = true,
+            "--nbsp" => options.non_breaking = true,
+            "--trim" => options.normalize.trim = true,
+            "--strip-ansi" => options.normalize.strip_ansi = true,
+            "--line-ending" => options.normalize.line_ending = parse_value(&arg, flag, &value)?,
+            "--checksum" => options.checksum = parse_value(&arg, flag, &value)?,
+            "--max-width" => options.max_width = Some(parse_number(&arg, flag, &value)?),
+            "--no-newline" => {
+                options.trailing_newline = false;
+                options.reset = true;
+            }
+            "--stretch" => options.stretch = parse_number(&arg, flag, &value)?,
+            "--accessibility" => options.accessibility = parse_value(&arg, flag, &value)?,
+            "--porcelain" => metadata.porcelain = true,
+            "--json-meta" => metadata.json = true,
+            "--secret" => options.secret = true,
+            "--deterministic" => options.deterministic = true,
+            "--strict" => options = options.validator(lint::strict),
+            "--hyperlink" => options.hyperlink = Hyperlink::Auto,
+            "--preview" => options.preview = Some(parse_number(&arg, flag, &value)?),
+            "--notify" => options.notify = options.notify.channel(parse_value(&arg, flag, &value)?),
+            "--notify-message" => options.notify.message = Some(value),
+            "--format" => batch.as_mut().unwrap().format = parse_value(&arg, flag, &value)?,
+            "--name" => batch.as_mut().unwrap().name = Some(value),
+            "--index" => batch.as_mut().unwrap().index = true,
+            "--pager" => batch.as_mut().unwrap().pager = true,
+            "--images" => batch.as_mut().unwrap().images = Some(PathBuf::from(value)),
+            "--reserve" => options.reserve = Some(parse_value(&arg, flag, &value)?),
+            _ => unreachable!("flag '{}' is not handled", flag.long),
+        }
+    }
+
+    if let Some(mut batch) = batch {
+        if watch.is_some() || interactive || fit || copy {
+            return Err(
+                "batch can't be combined with --watch, --interactive, --fit or --copy".into(),
+            );
+        }
+        if metadata != Metadata::default() {
+            return Err("batch can't be combined with --porcelain or --json-meta".into());
+        }
+        if batch.images.is_some() && batch.name.is_none() {
+            return Err("--images requires --name".into());
+        }
+        if batch.images.is_none() && options.reserve.is_some() {
+            return Err("--reserve requires --images".into());
+        }
+        if batch.images.is_some() && batch.pager {
+            return Err("--pager can't be combined with --images".into());
+        }
+        batch.template = text.ok_or("missing TEMPLATE for batch")?;
+        return Ok((Mode::Batch(batch), options));
+    }
+    if options.secret && metadata != Metadata::default() {
+        return Err("--secret can't be combined with --porcelain or --json-meta".into());
+    }
+    if fit && (watch.is_some() || interactive) {
+        return Err("--fit can't be combined with --watch or --interactive".into());
+    }
+    if copy && (watch.is_some() || interactive || fit) {
+        return Err("--copy can't be combined with --watch, --interactive or --fit".into());
+    }
+    if metadata != Metadata::default() && (watch.is_some() || interactive || fit) {
+        return Err(
+            "--porcelain and --json-meta can't be combined with --watch, --interactive or --fit"
+                .into(),
+        );
+    }
+    let mode = match (watch, interactive, text) {
+        (Some(_), true, _) => return Err("--watch and --interactive can't be combined".into()),
+        (Some(_), _, Some(_)) | (_, true, Some(_)) => {
+            return Err("TEXT can't be combined with --watch or --interactive".into())
+        }
+        (Some(path), false, None) => Mode::Watch(path),
+        (None, true, None) => Mode::Interactive,
+        (None, false, text) if fit => Mode::Fit(text),
+        (None, false, text) if copy => Mode::Copy(text, metadata),
+        (None, false, text) => Mode::Once(text, metadata),
+    };
+    Ok((mode, options))
+}
+
+/// Print a single QR code for the given text, or all of stdin.
+fn run_once(text: Option<String>, options: &QrOptions, metadata: Metadata) -> CliResult<()> {
+    let text = text_or_stdin(text)?;
+    let result = print_qr(&text, options, metadata);
+    wipe_secret(text, options);
+    result
+}
+
+/// Print a single QR code for the given text, or all of stdin, and copy it to
+/// the clipboard as plain text.
+fn run_copy(text: Option<String>, options: &QrOptions, metadata: Metadata) -> CliResult<()> {
+    let text = text_or_stdin(text)?;
+    let result = print_qr(&text, options, metadata).and_then(|_| {
+        clipboard::copy_to_clipboard_with_options(&text, options, clipboard::Format::Plain)?;
+        Ok(())
+    });
+    wipe_secret(text, options);
+    result
+}
+
+/// Print the QR code for `text`, followed by the requested `metadata` about
+/// the printed codes.
+fn print_qr(text: &str, options: &QrOptions, metadata: Metadata) -> CliResult<()> {
+    let collector = Collector::default();
+    let options = options.clone().hooks(collector.clone());
+    qr2term::try_print_qr_with_options(text, &options)?;
+
+    let codes = collector.0.lock().unwrap();
+    if metadata.porcelain {
+        for code in codes.iter() {
+            eprintln!("{}", code.porcelain());
+        }
+    }
+    if metadata.json {
+        let codes: Vec<_> = codes.iter().map(CodeMetadata::json).collect();
+        let separator = if options.trailing_newline { "" } else { "\n" };
+        println!("{}{{\"codes\":[{}]}}", separator, codes.join(","));
+    }
+    Ok(())
+}
+
+/// Metadata about a printed code.
+#[derive(Debug)]
+struct CodeMetadata {
+    info: QrInfo,
+
+    /// Size of the rendering as `(columns, rows)`, `None` if not rendered.
+    size: Option<(usize, usize)>,
+}
+
+impl CodeMetadata {
+    /// Format as `key=value` pairs separated by spaces.
+    fn porcelain(&self) -> String {
+        let mut line = format!(
+            "version={} ec-level={:?} mask={} modules={}",
+            self.info.version_name(),
+            self.info.ec_level,
+            self.info.mask,
+            self.info.module_count
+        );
+        if let Some((columns, rows)) = self.size {
+            line.push_str(&format!(" columns={} rows={}", columns, rows));
+        }
+        line
+    }
+
+    /// Format as a JSON object.
+    fn json(&self) -> String {
+        let mut json = format!(
+            "{{\"version\":\"{}\",\"ecLevel\":\"{:?}\",\"mask\":{},\"modules\":{}",
+            self.info.version_name(),
+            self.info.ec_level,
+            self.info.mask,
+            self.info.module_count
+        );
+        if let Some((columns, rows)) = self.size {
+            json.push_str(&format!(",\"columns\":{},\"rows\":{}", columns, rows));
+        }
+        json.push('}');
+        json
+    }
+}
+
+/// Hooks collecting metadata about the printed codes.
+#[derive(Clone, Default)]
+struct Collector(Arc<Mutex<Vec<CodeMetadata>>>);
+
+impl Hooks for Collector {
+    fn on_generated(&self, info: &QrInfo) {
+        self.0.lock().unwrap().push(CodeMetadata {
+            info: *info,
+            size: None,
+        });
+    }
+
+    fn on_rendered(&self, rows: usize, columns: usize) {
+        if let Some(code) = self.0.lock().unwrap().last_mut() {
+            code.size = Some((columns, rows));
+        }
+    }
+}
+
+/// Print a QR code for the given text, or all of stdin, re-rendering it to fit
+/// whenever the terminal is resized.
+fn run_fit(text: Option<String>, options: &QrOptions) -> CliResult<()> {
+    qr2term::responsive::print_qr_responsive(text_or_stdin(text)?, options)?;
+    Ok(())
+}
+
+/// Print or write a code for every row of the batch input.
+fn run_batch(batch: &Batch, options: &QrOptions) -> CliResult<()> {
+    let template = payload::template(&batch.template)?;
+    let input = match &batch.input {
+        Some(path) => fs::read_to_string(path).map_err(|err| path_error("read", path, err))?,
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            input
+        }
+    };
+    let rows = batch.format.read(&input)?;
+
+    match (&batch.images, &batch.name) {
+        (Some(dir), Some(name)) => {
+            for path in batch::write_images(&rows, &template, name, options, dir)? {
+                println!("{}", path.display());
+            }
+        }
+        _ => {
+            let batch_options = BatchOptions {
+                label: batch.name.clone(),
+                index: batch.index,
+            };
+            if batch.pager {
+                let mut output = Vec::new();
+                batch::render_batch(&rows, &template, &batch_options, options, &mut output)?;
+                pager::page(&output)?;
+            } else {
+                batch::print_batch_with_options(&rows, &template, &batch_options, options)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Get the given text, or read all of stdin if none is given.
+fn text_or_stdin(text: Option<String>) -> CliResult<String> {
+    match text {
+        Some(text) => Ok(text),
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            input.truncate(strip_newline(&input).len());
+            Ok(input)
+        }
+    }
+}
+
+/// Wipe `text` from memory if the options mark it as secret.
+fn wipe_secret(text: String, options: &QrOptions) {
+    if options.secret {
+        payload::wipe(&mut text.into_bytes());
+    }
+}
+
+/// Render the contents of `path`, and re-render in place whenever it changes.
+fn run_watch(path: &Path, options: &QrOptions) -> CliResult<()> {
+    let mut stdout = io::stdout();
+    let mut rows = 0;
+    let mut last_modified = None;
+
+    loop {
+        let modified = modified(path)?;
+        if Some(modified) != last_modified {
+            last_modified = Some(modified);
+            let text = read_payload(path)?;
+            let result = redraw(&mut stdout, rows, &text, options);
+            wipe_secret(text, options);
+            rows = result?;
+        }
+        thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+/// Render each line read from stdin, replacing the previously rendered code.
+fn run_interactive(options: &QrOptions) -> CliResult<()> {
+    let mut stdout = io::stdout();
+    let mut rows = 0;
+
+    write!(stdout, "{}", PROMPT)?;
+    stdout.flush()?;
+    for line in io::stdin().lock().lines() {
+        // The typed line takes extra rows below the code
+        let line = line?;
+        let width = PROMPT.chars().count() + line.chars().count();
+        let typed = wrapped_rows(width, terminal_columns());
+        let result = redraw(&mut stdout, rows + typed, &line, options);
+        wipe_secret(line, options);
+        rows = result?;
+        write!(stdout, "{}", PROMPT)?;
+        stdout.flush()?;
+    }
+    writeln!(stdout)?;
+
+    Ok(())
+}
+
+/// Clear the last `rows` rows above the cursor, and render `data` in their place.
+///
+/// If `data` can't be encoded, the error is shown instead so a watch or
+/// interactive session can continue.
+///
+/// Returns the number of rows that were drawn.
+fn redraw<W: Write>(
+    target: &mut W,
+    rows: usize,
+    data: &str,
+    options: &QrOptions,
+) -> CliResult<usize> {
+    if rows > 0 {
+        queue!(
+            target,
+            MoveUp(u16::try_from(rows).unwrap_or(u16::MAX)),
+            MoveToColumn(0),
+            Clear(ClearType::FromCursorDown)
+        )?;
+    }
+
+    let output = match qr2term::try_generate_qr_string_with_options(data, options) {
+        Ok(qr) => qr,
+        Err(err) => format!("error: {}\n", err),
+    };
+    let rows = screen_rows(&output, terminal_columns());
+    let result = target
+        .write_all(output.as_bytes())
+        .and_then(|_| target.flush());
+    wipe_secret(output, options);
+    result?;
+
+    Ok(rows)
+}
+
+/// Get the number of columns of the terminal, if known.
+fn terminal_columns() -> Option<usize> {
+    terminal::size()
+        .ok()
+        .map(|(columns, _)| usize::from(columns))
+}
+
+/// Get the number of terminal rows `output` takes up, with lines wider than
+/// the terminal's `columns` wrapping onto the next rows.
+fn screen_rows(output: &str, columns: Option<usize>) -> usize {
+    output
+        .lines()
+        .map(|line| wrapped_rows(display_width(line), columns))
+        .sum()
+}
+
+/// Get the number of terminal rows a line `width` characters wide takes up.
+fn wrapped_rows(width: usize, columns: Option<usize>) -> usize {
+    match columns {
+        Some(columns) if columns > 0 => ((width + columns - 1) / columns).max(1),
+        _ => 1,
+    }
+}
+
+/// Get the number of characters `line` takes up on the terminal, skipping
+/// ANSI escape sequences.
+fn display_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut double = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1B' {
+            width += 1;
+            continue;
+        }
+        match chars.next() {
+            // Control sequences end with a final byte from @ to ~
+            Some('[') => {
+                chars.by_ref().find(|c| ('@'..='~').contains(c));
+            }
+            // Operating system commands, such as hyperlinks, end with BEL or ST
+            Some(']') => {
+                let mut escaped = false;
+                chars.by_ref().find(|&c| {
+                    let end = c == '\x07' || (escaped && c == '\\');
+                    escaped = c == '\x1B';
+                    end
+                });
+            }
+            // Double-width and double-height lines
+            Some('#') => double |= matches!(chars.next(), Some('3' | '4' | '6')),
+            _ => {}
+        }
+    }
+    if double {
+        width * 2
+    } else {
+        width
+    }
+}
+
+/// Read the payload to render from the file at `path`.
+fn read_payload(path: &Path) -> CliResult<String> {
+    let mut contents = fs::read_to_string(path).map_err(|err| path_error("read", path, err))?;
+    contents.truncate(strip_newline(&contents).len());
+    Ok(contents)
+}
+
+/// Get the last modification time of the file at `path`.
+fn modified(path: &Path) -> CliResult<SystemTime> {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .map_err(|err| path_error("watch", path, err).into())
+}
+
+/// Describe `err` as failing to `action` the file at `path`, keeping its kind.
+fn path_error(action: &str, path: &Path, err: io::Error) -> io::Error {
+    let message = format!("failed to {} '{}': {}", action, path.display(), err);
+    io::Error::new(err.kind(), message)
+}
+
+/// Completion script for `shell`, generated from [`FLAGS`].
+fn completions(shell: Shell) -> String {
+    let mut script = String::new();
+    match shell {
+        Shell::Bash => bash_completions(&mut script),
+        Shell::Zsh => zsh_completions(&mut script),
+        Shell::Fish => fish_completions(&mut script),
+    }
+    .expect("writing to a string can't fail");
+    script
+}
+
+fn bash_completions(script: &mut String) -> fmt::Result {
+    use fmt::Write;
+
+    writeln!(script, "_qr2term() {{")?;
+    writeln!(script, "    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"")?;
+    writeln!(script, "    local prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"")?;
+    writeln!(script, "    case \"$prev\" in")?;
+    for flag in FLAGS {
+        let reply = match flag.value {
+            Value::None | Value::Text | Value::Number(..) => continue,
+            Value::File => "-f -- \"$cur\"".to_string(),
+            Value::Dir => "-d -- \"$cur\"".to_string(),
+            Value::Choice(choices) => format!("-W \"{}\" -- \"$cur\"", choices().join(" ")),
+        };
+        let names = flag.short.iter().chain([&flag.long]);
+        let pattern = names.copied().collect::<Vec<_>>().join("|");
+        writeln!(script, "        {})", pattern)?;
+        writeln!(script, "            COMPREPLY=($(compgen {}))", reply)?;
+        writeln!(script, "            return ;;")?;
+    }
+    writeln!(script, "    esac")?;
+    let flags = |batch: bool| {
+        let flags = FLAGS.iter().filter(|flag| batch || !flag.batch);
+        let names = flags.flat_map(|flag| flag.short.into_iter().chain([flag.long]));
+        names.collect::<Vec<_>>().join(" ")
+    };
+    writeln!(script, "    if [[ \"${{COMP_WORDS[1]}}\" == batch ]]; then")?;
+    writeln!(
+        script,
+        "        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+        flags(true)
+    )?;
+    writeln!(script, "    else")?;
+    writeln!(
+        script,
+        "        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+        flags(false)
+    )?;
+    writeln!(script, "    fi")?;
+    writeln!(script, "}}")?;
+    writeln!(script, "complete -F _qr2term qr2term")
+}
+
+fn zsh_completions(script: &mut String) -> fmt::Result {
+    use fmt::Write;
+
+    writeln!(script, "#compdef qr2term")?;
+    writeln!(script)?;
+    writeln!(script, "_arguments \\")?;
+    for flag in FLAGS {
+        let about = flag.about.replace('\'', "'\\''");
+        let action = match flag.value {
+            Value::None => String::new(),
+            Value::Text => ":value: ".to_string(),
+            Value::Number(..) => ":number: ".to_string(),
+            Value::File => ":file:_files".to_string(),
+            Value::Dir => ":directory:_files -/".to_string(),
+            Value::Choice(choices) => format!(":value:({})", choices().join(" ")),
+        };
+        match flag.short {
+            Some(short) => writeln!(
+                script,
+                "    '({short} {long})'{{{short},{long}}}'[{about}]{action}' \\",
+                short = short,
+                long = flag.long,
+                about = about,
+                action = action,
+            )?,
+            None => writeln!(script, "    '{}[{}]{}' \\", flag.long, about, action)?,
+        }
+    }
+    writeln!(script, "    '1:text or batch: ' \\")?;
+    writeln!(script, "    '*:file:_files'")
+}
+
+fn fish_completions(script: &mut String) -> fmt::Result {
+    use fmt::Write;
+
+    writeln!(
+        script,
+        "complete -c qr2term -n __fish_use_subcommand -f -a batch -d 'Print a code per row'"
+    )?;
+    for flag in FLAGS {
+        write!(script, "complete -c qr2term")?;
+        if flag.batch {
+            write!(script, " -n '__fish_seen_subcommand_from batch'")?;
+        }
+        if let Some(short) = flag.short {
+            write!(script, " -s {}", short.trim_start_matches('-'))?;
+        }
+        write!(script, " -l {}", flag.long.trim_start_matches("--"))?;
+        match flag.value {
+            Value::None => {}
+            Value::Text | Value::Number(..) => write!(script, " -x")?,
+            Value::File => write!(script, " -r -F")?,
+            Value::Dir => write!(script, " -x -a '(__fish_complete_directories)'")?,
+            Value::Choice(choices) => write!(script, " -x -a '{}'", choices().join(" "))?,
+        }
+        writeln!(script, " -d '{}'", flag.about.replace('\'', "\\'"))?;
+    }
+    Ok(())
+}
+
+/// Strip a single trailing newline, as added by most editors and `echo`.
+fn strip_newline(text: &str) -> &str {
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    text.strip_suffix('\r').unwrap_or(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use qr2term::{
+        normalize::{LineEnding, Normalize},
+        notify::Notify,
+        options::{Accessibility, EcLevel, Rotation},
+        render::Style,
+        theme::Theme,
+    };
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> CliResult<Mode> {
+        parse_args(
+            args.iter().map(|arg| arg.to_string()),
+            QrOptions::default(),
+            &Config::default(),
+        )
+        .map(|(mode, _)| mode)
+    }
+
+    #[test]
+    fn parse_modes() {
+        let metadata = Metadata::default();
+        assert_eq!(parse(&[]).unwrap(), Mode::Once(None, metadata));
+        assert_eq!(
+            parse(&["abc"]).unwrap(),
+            Mode::Once(Some("abc".into()), metadata)
+        );
+        assert_eq!(
+            parse(&["--watch", "a.txt"]).unwrap(),
+            Mode::Watch(PathBuf::from("a.txt"))
+        );
+        assert_eq!(parse(&["-i"]).unwrap(), Mode::Interactive);
+        assert_eq!(
+            parse(&["--fit", "abc"]).unwrap(),
+            Mode::Fit(Some("abc".into()))
+        );
+        assert_eq!(parse(&["--copy"]).unwrap(), Mode::Copy(None, metadata));
+        assert_eq!(
+            parse(&["--porcelain", "abc", "--json-meta"]).unwrap(),
+            Mode::Once(
+                Some("abc".into()),
+                Metadata {
+                    porcelain: true,
+                    json: true
+                }
+            )
+        );
+        assert_eq!(
+            parse(&["batch", "--format", "json", "t/{id}", "--name", "id", "--index", "--pager"])
+                .unwrap(),
+            Mode::Batch(Batch {
+                template: "t/{id}".into(),
+                format: InputFormat::Json,
+                name: Some("id".into()),
+                index: true,
+                pager: true,
+                ..Batch::default()
+            })
+        );
+        assert_eq!(
+            parse(&["batch", "t/{id}", "rows.csv", "--images", "out", "--name", "id"]).unwrap(),
+            Mode::Batch(Batch {
+                template: "t/{id}".into(),
+                input: Some(PathBuf::from("rows.csv")),
+                name: Some("id".into()),
+                images: Some(PathBuf::from("out")),
+                ..Batch::default()
+            })
+        );
+    }
+
+    #[test]
+    fn parse_config() {
+        let config: Config = "format = \"json\"\nstyle = \"ascii\"".parse().unwrap();
+        let parse = |args: &[&str]| {
+            let options = config.apply(QrOptions::default());
+            parse_args(args.iter().map(|arg| arg.to_string()), options, &config).unwrap()
+        };
+        let (mode, options) = parse(&["batch", "t/{id}"]);
+        assert_eq!(
+            mode,
+            Mode::Batch(Batch {
+                template: "t/{id}".into(),
+                format: InputFormat::Json,
+                ..Batch::default()
+            })
+        );
+        assert_eq!(options.style, Style::Ascii);
+        let (mode, options) = parse(&["batch", "--format", "csv", "-s", "full", "t/{id}"]);
+        assert!(matches!(
+            mode,
+            Mode::Batch(Batch {
+                format: InputFormat::Csv,
+                ..
+            })
+        ));
+        assert_eq!(options.style, Style::FullBlock);
+    }
+
+    #[test]
+    fn end_of_options() {
+        let metadata = Metadata::default();
+        assert_eq!(
+            parse(&["--", "-s"]).unwrap(),
+            Mode::Once(Some("-s".into()), metadata)
+        );
+        assert_eq!(
+            parse(&["--fit", "--", "--"]).unwrap(),
+            Mode::Fit(Some("--".into()))
+        );
+        assert_eq!(
+            parse(&["batch", "--index", "--", "-{id}", "-rows.csv"]).unwrap(),
+            Mode::Batch(Batch {
+                template: "-{id}".into(),
+                input: Some(PathBuf::from("-rows.csv")),
+                index: true,
+                ..Batch::default()
+            })
+        );
+        assert!(parse(&["--", "a", "b"]).is_err());
+        assert!(parse(&["abc", "--", "--fit"]).is_err());
+    }
+
+    #[test]
+    fn broken_config() {
+        let config = || "style = \"ascii\"".parse::<Config>();
+        let broken = || "style = \"nope\"".parse::<Config>();
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            config_for(config(), &args(&["abc"])).unwrap(),
+            config().unwrap()
+        );
+        assert_eq!(
+            config_for(broken(), &args(&["--help"])).unwrap(),
+            Config::default()
+        );
+        assert_eq!(
+            config_for(broken(), &args(&["--completions", "bash"])).unwrap(),
+            Config::default()
+        );
+        assert!(config_for(broken(), &args(&["abc"])).is_err());
+        assert!(config_for(broken(), &args(&["--nope", "--help"])).is_err());
+    }
+
+    #[test]
+    fn parse_profile() {
+        let config: Config = "[profiles.badge]\nstyle = \"half\"\nformat = \"json\""
+            .parse()
+            .unwrap();
+        let parse = |args: &[&str]| {
+            let args = args.iter().map(|arg| arg.to_string());
+            parse_args(args, QrOptions::default(), &config)
+        };
+        let (_, options) = parse(&["-s", "ascii", "--profile", "presentation"]).unwrap();
+        assert_eq!(options.style, Style::Ascii);
+        assert_eq!(options.quiet_zone, 4);
+        let (mode, options) = parse(&["batch", "t/{id}", "-p", "badge"]).unwrap();
+        assert!(matches!(
+            mode,
+            Mode::Batch(Batch {
+                format: InputFormat::Json,
+                ..
+            })
+        ));
+        assert_eq!(options.style, Style::HalfBlock);
+        assert_eq!(
+            parse(&["-p", "badg"]).unwrap_err().to_string(),
+            "invalid value 'badg' for '-p': unknown profile 'badg', did you mean 'badge'?"
+        );
+    }
+
+    #[test]
+    fn parse_secret() {
+        let args = ["--secret", "token"].iter().map(|arg| arg.to_string());
+        let (mode, options) = parse_args(args, QrOptions::default(), &Config::default()).unwrap();
+        assert_eq!(mode, Mode::Once(Some("token".into()), Metadata::default()));
+        assert!(options.secret);
+        assert_eq!(
+            parse(&["--secret", "--porcelain", "token"])
+                .unwrap_err()
+                .to_string(),
+            "--secret can't be combined with --porcelain or --json-meta"
+        );
+        assert!(parse(&["--json-meta", "--secret"]).is_err());
+    }
+
+    #[test]
+    fn parse_strict() {
+        let args = ["--strict", "http://example.com"]
+            .iter()
+            .map(|arg| arg.to_string());
+        let (_, options) = parse_args(args, QrOptions::default(), &Config::default()).unwrap();
+        let err = qr2term::try_generate_qr_string_with_options("http://example.com", &options)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "payload is a URL using http:// instead of https://"
+        );
+    }
+
+    #[test]
+    fn parse_conflicts() {
+        assert!(parse(&["--watch"]).is_err());
+        assert!(parse(&["-w", "a.txt", "-i"]).is_err());
+        assert!(parse(&["-i", "abc"]).is_err());
+        assert!(parse(&["-f", "-i"]).is_err());
+        assert!(parse(&["--copy", "--fit"]).is_err());
+        assert!(parse(&["--porcelain", "-i"]).is_err());
+        assert!(parse(&["batch", "t", "--json-meta"]).is_err());
+        assert!(parse(&["--bogus"]).is_err());
+        assert!(parse(&["--name", "id", "abc"]).is_err());
+        assert!(parse(&["batch"]).is_err());
+        assert!(parse(&["batch", "t", "a.csv", "b.csv"]).is_err());
+        assert!(parse(&["batch", "t", "--images", "out"]).is_err());
+        assert!(parse(&["batch", "t", "--images", "out", "--name", "id", "--pager"]).is_err());
+        assert!(parse(&["batch", "t", "--reserve", "8x6"]).is_err());
+        let images = ["batch", "t", "--images", "out", "--name", "id", "--reserve"];
+        assert!(parse(&[&images[..], &["8x6+4+4"]].concat()).is_ok());
+        assert!(parse(&[&images[..], &["8"]].concat()).is_err());
+        assert!(parse(&["batch", "t", "--copy"]).is_err());
+        assert!(parse(&["batch", "t", "--format", "xml"]).is_err());
+    }
+
+    #[test]
+    fn parse_options() {
+        let args = [
+            "-s",
+            "ascii",
+            "-t",
+            "monochrome",
+            "--ec-level",
+            "q",
+            "-q",
+            "4",
+            "-r",
+            "90",
+            "-m",
+            "--double-size",
+            "--nbsp",
+            "-n",
+            "--trim",
+            "--line-ending",
+            "crlf",
+            "--checksum",
+            "crc32",
+            "--hyperlink",
+            "--layout",
+            "grid",
+            "--preview",
+            "24",
+            "--notify",
+            "title",
+            "--notify",
+            "osc9",
+            "--notify-message",
+            "scan me",
+            "--max-width",
+            "60",
+            "--stretch",
+            "2",
+            "-a",
+            "alongside",
+            "abc",
+        ];
+        let (_, options) = parse_args(
+            args.iter().map(|arg| arg.to_string()),
+            QrOptions::default(),
+            &Config::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            options,
+            QrOptions::new()
+                .style(Style::Ascii)
+                .theme(Theme::Monochrome)
+                .ec_level(EcLevel::Q)
+                .quiet_zone(4)
+                .rotate(Rotation::Rotate90)
+                .mirror(true)
+                .double_size(true)
+                .stretch(2)
+                .non_breaking(true)
+                .trailing_newline(false)
+                .reset(true)
+                .max_width(60)
+                .normalize(Normalize::new().trim(true).line_ending(LineEnding::CrLf))
+                .checksum(Checksum::Crc32)
+                .hyperlink(Hyperlink::Auto)
+                .layout(Layout::Grid)
+                .preview(24)
+                .notify(
+                    Notify::new()
+                        .channel(Channel::Title)
+                        .channel(Channel::Osc9)
+                        .message("scan me")
+                )
+                .accessibility(Accessibility::Alongside)
+        );
+        assert!(parse(&["--style", "sparkles"]).is_err());
+        assert!(parse(&["--theme", "neon"]).is_err());
+        assert!(parse(&["-e", "X"]).is_err());
+        assert!(parse(&["-q", "-1"]).is_err());
+        assert!(parse(&["--rotate", "45"]).is_err());
+        assert!(parse(&["--stretch", "0"]).is_err());
+        assert!(parse(&["--line-ending", "cr"]).is_err());
+        assert!(parse(&["--checksum", "md5"]).is_err());
+        assert!(parse(&["--notify", "email"]).is_err());
+        assert!(parse(&["--accessibility", "loud"]).is_err());
+    }
+
+    #[test]
+    fn suggestions() {
+        let message = |args: &[&str]| parse(args).unwrap_err().to_string();
+        assert_eq!(
+            message(&["--stlye", "half"]),
+            "unknown option '--stlye', did you mean '--style'?"
+        );
+        assert_eq!(message(&["--xyz"]), "unknown option '--xyz'");
+        assert_eq!(
+            message(&["-s", "haf"]),
+            "invalid value 'haf' for '-s': unknown render style 'haf', did you mean 'half'?"
+        );
+        assert_eq!(
+            message(&["-e", "X"]),
+            "invalid value 'X' for '-e': unknown error correction level \
+             (possible values: L, M, Q, H)"
+        );
+        assert_eq!(
+            message(&["--quiet-zone", "1000"]),
+            "invalid value '1000' for '--quiet-zone': must be from 0 to 100"
+        );
+        assert_eq!(message(&["--theme"]), "missing value for '--theme'");
+        assert_eq!(
+            message(&["--index"]),
+            "'--index' is only accepted by the batch command"
+        );
+        assert_eq!(
+            message(&["batch", "--indx", "{id}"]),
+            "unknown option '--indx', did you mean '--index'?"
+        );
+    }
+
+    #[test]
+    fn completions() {
+        assert_eq!(
+            parse(&["--completions", "zsh"]).unwrap(),
+            Mode::Completions(Shell::Zsh)
+        );
+        assert!(parse(&["--completions", "tcsh"]).is_err());
+
+        let bash = super::completions(Shell::Bash);
+        assert!(bash.contains("        -e|--ec-level)\n"));
+        assert!(bash.contains("compgen -W \"L M Q H\""));
+        assert!(bash.ends_with("complete -F _qr2term qr2term\n"));
+        let zsh = super::completions(Shell::Zsh);
+        assert!(zsh.starts_with("#compdef qr2term\n"));
+        assert!(zsh.contains("'--images[Write PNG images into DIR]:directory:_files -/'"));
+        let fish = super::completions(Shell::Fish);
+        assert!(
+            fish.contains("complete -c qr2term -n '__fish_seen_subcommand_from batch' -l index -d")
+        );
+        for flag in FLAGS {
+            assert!(fish.contains(&format!(" -l {} ", &flag.long[2..])));
+        }
+    }
+
+    #[test]
+    fn code_metadata() {
+        let mut code = CodeMetadata {
+            info: qr2term::qr_info("abc", &QrOptions::new()).unwrap(),
+            size: None,
+        };
+        assert_eq!(code.porcelain(), "version=1 ec-level=M mask=2 modules=21");
+        code.size = Some((25, 13));
+        assert_eq!(
+            code.porcelain(),
+            "version=1 ec-level=M mask=2 modules=21 columns=25 rows=13"
+        );
+        assert_eq!(
+            code.json(),
+            r#"{"version":"1","ecLevel":"M","mask":2,"modules":21,"columns":25,"rows":13}"#
+        );
+    }
+
+    #[test]
+    fn exit_codes() {
+        let err = qr2term::try_generate_qr_string_with_options("a".repeat(8000), &QrOptions::new())
+            .unwrap_err();
+        assert_eq!(exit_code(&err), 3);
+        assert_eq!(exit_code(&qr2term::Error::NotATerminal), 4);
+        assert_eq!(
+            exit_code(&ClipboardError::Unavailable(clipboard::Format::Png)),
+            4
+        );
+        let err = path_error("read", Path::new("a.txt"), io::ErrorKind::NotFound.into());
+        assert_eq!(exit_code(&err), 5);
+        assert_eq!(exit_code(&BatchError::InvalidCsv(2)), 6);
+        let err = io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stream did not contain valid UTF-8",
+        );
+        assert_eq!(exit_code(&err), 6);
+        let err: Box<dyn Error> = "unknown".into();
+        assert_eq!(exit_code(err.as_ref()), 1);
+    }
+
+    #[test]
+    fn screen_rows_wrap() {
+        assert_eq!(display_width("\x1B[1;37;40m██\x1B[0m  "), 4);
+        assert_eq!(
+            display_width("\x1B]8;;https://a.b\x1B\\https://a.b\x1B]8;;\x1B\\"),
+            11
+        );
+        assert_eq!(display_width("\x1B#3ab"), 4);
+        assert_eq!(screen_rows("abcd\n\nabcdefghi\n", Some(4)), 5);
+        assert_eq!(screen_rows("abcd\n\nabcdefghi\n", None), 3);
+        assert_eq!(wrapped_rows(10, Some(0)), 1);
+
+        let mut target = Vec::new();
+        let rows = redraw(&mut target, 100_000, "a", &QrOptions::new()).unwrap();
+        let output = String::from_utf8(target).unwrap();
+        assert!(output.starts_with("\x1B[65535A"));
+        assert!(rows >= output.lines().count());
+    }
+}
@@ -1,11 +1,20 @@
 //! Matrix types representing 2D barcode.
 
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::mem;
+
+use crate::options::Rotation;
 use crate::util;
 
+/// Width and height in modules of the largest QR code, version 40.
+pub const MAX_CODE_SIZE: usize = 177;
+
 /// A square 2D matrix representing a barcode.
 #[derive(Debug)]
 pub struct Matrix<T> {
     pixels: Vec<T>,
+    size: usize,
 }
 
 impl<T> Matrix<T> {
@@ -13,17 +22,23 @@ impl<T> Matrix<T> {
     ///
     /// # Panics
     ///
-    /// Panics if given pixel map does not have a length that is a multiple of 2.
+    /// Panics if the number of pixels is not a perfect square. Use
+    /// [`try_new`](Self::try_new) to get an error instead.
     pub fn new(pixels: Vec<T>) -> Self {
-        // Assert pixels being multiple of 2
-        util::usize_sqrt(pixels.len());
+        Self::try_new(pixels).expect("failed to construct matrix")
+    }
 
-        Self { pixels }
+    /// Construct a new QR matrix from given pixels, row by row.
+    ///
+    /// Returns an error if the number of pixels is not a perfect square.
+    pub fn try_new(pixels: Vec<T>) -> Result<Self, MatrixError> {
+        let size = util::usize_sqrt(pixels.len()).ok_or(MatrixError::NotSquare(pixels.len()))?;
+        Ok(Self { pixels, size })
     }
 
     /// Get the width and height of the QR code in pixels.
     pub fn size(&self) -> usize {
-        util::usize_sqrt(self.pixels.len())
+        self.size
     }
 
     /// Get the pixel matrix.
@@ -31,38 +46,263 @@ impl<T> Matrix<T> {
         &self.pixels
     }
 
+    /// Overwrite all pixels with `value`, such as for matrices of secret codes.
+    pub(crate) fn wipe(&mut self, value: T)
+    where
+        T: Copy,
+    {
+        util::wipe(&mut self.pixels, value);
+    }
+
     /// Surround this matrix with `quiet` pixels having the specified `thickness`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the surrounded matrix has more pixels than fit in memory.
+    /// Use [`try_surround`](Self::try_surround) to get an error instead.
     pub fn surround(&mut self, thickness: usize, quiet: T)
+    where
+        T: Copy,
+    {
+        self.try_surround(thickness, quiet)
+            .expect("failed to surround matrix")
+    }
+
+    /// Surround this matrix with `quiet` pixels having the specified `thickness`.
+    ///
+    /// Returns an error, leaving this matrix unchanged, if the surrounded
+    /// matrix has more pixels than fit in memory.
+    pub fn try_surround(&mut self, thickness: usize, quiet: T) -> Result<(), MatrixError>
+    where
+        T: Copy,
+    {
+        self.surround_into(thickness, quiet, &mut Vec::new())
+    }
+
+    /// Surround this matrix with `quiet` pixels having the specified
+    /// `thickness`, reusing buffers of the `arena`.
+    ///
+    /// Returns an error, leaving this matrix unchanged, if the surrounded
+    /// matrix has more pixels than fit in memory.
+    pub fn surround_in(
+        &mut self,
+        thickness: usize,
+        quiet: T,
+        arena: &mut Arena<T>,
+    ) -> Result<(), MatrixError>
+    where
+        T: Copy,
+    {
+        let mut out = arena.take();
+        let result = self.surround_into(thickness, quiet, &mut out);
+        arena.give(out);
+        result
+    }
+
+    /// Surround this matrix with `quiet` pixels, building it in `out`, which
+    /// then holds the previous pixels.
+    fn surround_into(
+        &mut self,
+        thickness: usize,
+        quiet: T,
+        out: &mut Vec<T>,
+    ) -> Result<(), MatrixError>
     where
         T: Copy,
     {
         // Calculate widths
-        let width = self.size();
-        let out_width = width + thickness * 2;
+        let width = self.size;
+        let out_width = thickness
+            .checked_mul(2)
+            .and_then(|border| border.checked_add(width))
+            .ok_or(MatrixError::TooLarge)?;
+        let len = out_width
+            .checked_mul(out_width)
+            .ok_or(MatrixError::TooLarge)?;
 
         // Build the new pixel matrix, move given matrix in the center
-        let mut out = vec![quiet; out_width.pow(2)];
-        for vec_row in 0..width {
-            for vec_col in 0..width {
-                let vec_pos = width * vec_row + vec_col;
-                let out_row = vec_row + thickness;
-                let out_col = vec_col + thickness;
-                let out_pos = out_row * out_width + out_col;
-                out[out_pos] = self.pixels[vec_pos];
+        out.clear();
+        out.try_reserve_exact(len)
+            .map_err(|_| MatrixError::TooLarge)?;
+        out.resize(out_width * thickness + thickness, quiet);
+        for (row, pixels) in self.pixels.chunks(width.max(1)).enumerate() {
+            if row > 0 {
+                out.resize(out.len() + thickness * 2, quiet);
             }
+            out.extend_from_slice(pixels);
+        }
+        out.resize(len, quiet);
+
+        mem::swap(&mut self.pixels, out);
+        self.size = out_width;
+        Ok(())
+    }
+
+    /// Flip this matrix horizontally, swapping its left and right side.
+    pub fn mirror(&mut self) {
+        let width = self.size();
+        for row in self.pixels.chunks_mut(width.max(1)) {
+            row.reverse();
+        }
+    }
+
+    /// Rotate this matrix clockwise.
+    pub fn rotate(&mut self, rotation: Rotation)
+    where
+        T: Copy,
+    {
+        self.rotate_into(rotation, &mut Vec::new());
+    }
+
+    /// Rotate this matrix clockwise, reusing buffers of the `arena`.
+    pub fn rotate_in(&mut self, rotation: Rotation, arena: &mut Arena<T>)
+    where
+        T: Copy,
+    {
+        if rotation != Rotation::Rotate0 {
+            let mut out = arena.take();
+            self.rotate_into(rotation, &mut out);
+            arena.give(out);
+        }
+    }
+
+    /// Rotate this matrix clockwise, building it in `out`, which then holds
+    /// the previous pixels.
+    fn rotate_into(&mut self, rotation: Rotation, out: &mut Vec<T>)
+    where
+        T: Copy,
+    {
+        let width = self.size;
+        let source = |row: usize, col: usize| match rotation {
+            Rotation::Rotate0 => (row, col),
+            Rotation::Rotate90 => (width - 1 - col, row),
+            Rotation::Rotate180 => (width - 1 - row, width - 1 - col),
+            Rotation::Rotate270 => (col, width - 1 - row),
+        };
+        out.clear();
+        out.extend((0..self.pixels.len()).map(|i| {
+            let (row, col) = source(i / width, i % width);
+            self.pixels[row * width + col]
+        }));
+        mem::swap(&mut self.pixels, out);
+    }
+}
+
+/// Reusable storage for the pixels of matrices, to generate many codes
+/// without allocating for every one.
+///
+/// Matrices built in an arena, such as by [`stage::encode_in`](crate::stage::encode_in),
+/// take buffers from it, sized for the largest code of [`MAX_CODE_SIZE`] by
+/// [`MAX_CODE_SIZE`] modules. [`recycle`](Self::recycle) matrices that are
+/// no longer needed, so later matrices reuse their buffers.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::{matrix::Arena, stage, QrOptions};
+///
+/// let options = QrOptions::new();
+/// let mut arena = Arena::new();
+/// for id in 0..100 {
+///     let matrix = stage::encode_in(format!("https://example.com/t/{}", id), &options, &mut arena)?;
+///     let cells = stage::layout(&matrix, &options);
+///     arena.recycle(matrix);
+///     # let _ = cells;
+/// }
+/// # Ok::<(), qr2term::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct Arena<T> {
+    buffers: Vec<Vec<T>>,
+}
+
+impl<T> Arena<T> {
+    /// Construct an empty arena, which allocates buffers as they are needed.
+    pub fn new() -> Self {
+        Self {
+            buffers: Vec::new(),
         }
+    }
+
+    /// Build a matrix from `pixels`, row by row, in a buffer of this arena.
+    ///
+    /// Returns an error if the number of pixels is not a perfect square.
+    pub fn matrix(&mut self, pixels: &[T]) -> Result<Matrix<T>, MatrixError>
+    where
+        T: Copy,
+    {
+        let mut buffer = self.take();
+        buffer.extend_from_slice(pixels);
+        Matrix::try_new(buffer)
+    }
+
+    /// Return the pixel buffer of `matrix` to this arena, for later matrices
+    /// to reuse.
+    pub fn recycle(&mut self, matrix: Matrix<T>) {
+        self.give(matrix.pixels);
+    }
+
+    /// Number of buffers held for reuse.
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Whether no buffers are held for reuse.
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+
+    /// Take an empty buffer, with capacity for at least the largest code.
+    fn take(&mut self) -> Vec<T> {
+        let mut buffer = self.buffers.pop().unwrap_or_default();
+        buffer.reserve(MAX_CODE_SIZE * MAX_CODE_SIZE);
+        buffer
+    }
+
+    /// Keep `buffer` for reuse.
+    fn give(&mut self, mut buffer: Vec<T>) {
+        buffer.clear();
+        self.buffers.push(buffer);
+    }
+}
 
-        self.pixels = out;
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
+/// Error returned when a matrix can't be constructed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MatrixError {
+    /// The given number of pixels is not a perfect square.
+    NotSquare(usize),
+
+    /// The matrix would have more pixels than fit in memory.
+    TooLarge,
+}
+
+impl Display for MatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatrixError::NotSquare(len) => {
+                write!(f, "{} pixels don't form a square matrix", len)
+            }
+            MatrixError::TooLarge => f.write_str("matrix is too large"),
+        }
+    }
+}
+
+impl Error for MatrixError {}
+
 #[cfg(test)]
 mod tests {
     use qrcode::types::Color::{Dark as QrDark, Light as QrLight};
 
     use super::*;
 
-    /// Printing a matrix with the number of pixels not being a multiple of 2 fails.
+    /// Constructing a matrix with the number of pixels not being a perfect square fails.
     #[test]
     #[should_panic]
     fn matrix_incorrect_size() {
@@ -93,6 +333,84 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn mirror_and_rotate() {
+        let mut matrix = Matrix::new(vec![
+            0, 1, 2, //
+            3, 4, 5, //
+            6, 7, 8,
+        ]);
+        matrix.mirror();
+        assert_eq!(matrix.pixels(), [2, 1, 0, 5, 4, 3, 8, 7, 6]);
+        matrix.mirror();
+
+        matrix.rotate(Rotation::Rotate90);
+        assert_eq!(matrix.pixels(), [6, 3, 0, 7, 4, 1, 8, 5, 2]);
+        matrix.rotate(Rotation::Rotate270);
+        assert_eq!(matrix.pixels(), [0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        matrix.rotate(Rotation::Rotate180);
+        assert_eq!(matrix.pixels(), [8, 7, 6, 5, 4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn arena_reuses_buffers() {
+        let mut arena = Arena::new();
+        let mut matrix = arena.matrix(&[0, 1, 2, 3]).unwrap();
+        assert!(arena.is_empty());
+        matrix.surround_in(1, 9, &mut arena).unwrap();
+        assert_eq!(matrix.size(), 4);
+        assert_eq!(arena.len(), 1);
+        matrix.rotate_in(Rotation::Rotate0, &mut arena);
+        matrix.rotate_in(Rotation::Rotate90, &mut arena);
+        assert_eq!(arena.len(), 1);
+        assert_eq!(
+            matrix.pixels(),
+            [9, 9, 9, 9, 9, 2, 0, 9, 9, 3, 1, 9, 9, 9, 9, 9]
+        );
+
+        let pixels = matrix.pixels().as_ptr();
+        arena.recycle(matrix);
+        let matrix = arena.matrix(&[5]).unwrap();
+        assert_eq!(matrix.pixels(), [5]);
+        assert_eq!(matrix.pixels().as_ptr(), pixels);
+        assert!(matrix.pixels.capacity() >= MAX_CODE_SIZE * MAX_CODE_SIZE);
+        assert_eq!(
+            arena.matrix(&[0, 1]).unwrap_err(),
+            MatrixError::NotSquare(2)
+        );
+    }
+
+    #[test]
+    fn try_new_and_surround() {
+        assert_eq!(
+            Matrix::try_new(vec![0; 6]).unwrap_err(),
+            MatrixError::NotSquare(6)
+        );
+
+        let mut matrix = Matrix::try_new(vec![0, 1, 2, 3]).unwrap();
+        assert_eq!(
+            matrix.try_surround(usize::MAX / 2, 9),
+            Err(MatrixError::TooLarge)
+        );
+        assert_eq!(
+            matrix.try_surround(usize::MAX, 9),
+            Err(MatrixError::TooLarge)
+        );
+        assert_eq!(
+            matrix.try_surround(1 << (usize::BITS / 2), 9),
+            Err(MatrixError::TooLarge)
+        );
+        assert_eq!(matrix.pixels(), [0, 1, 2, 3]);
+        assert_eq!(matrix.size(), 2);
+
+        matrix.try_surround(1, 9).unwrap();
+        assert_eq!(matrix.size(), 4);
+        assert_eq!(
+            matrix.pixels(),
+            [9, 9, 9, 9, 9, 0, 1, 9, 9, 2, 3, 9, 9, 9, 9, 9]
+        );
+    }
+
     #[test]
     fn surround_quiet_empty() {
         let mut matrix = Matrix::new(vec![]);
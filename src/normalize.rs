@@ -0,0 +1,285 @@
+//! Normalizing text payloads before encoding.
+//!
+//! Text piped from other commands often carries stray whitespace, mixed line
+//! endings or ANSI escape sequences for colors. Encoded as is, these bloat
+//! the code or corrupt what scanners show. [`Normalize`] cleans such text up,
+//! and is applied to every payload through [`QrOptions::normalize`](crate::QrOptions::normalize).
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// Line ending to convert line breaks to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum LineEnding {
+    /// Keep line breaks as they are.
+    #[default]
+    Keep,
+
+    /// Convert line breaks to `\n`.
+    Lf,
+
+    /// Convert line breaks to `\r\n`.
+    CrLf,
+}
+
+impl LineEnding {
+    /// Name of this line ending, as accepted by its `FromStr` implementation.
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::Keep => "keep",
+            LineEnding::Lf => "lf",
+            LineEnding::CrLf => "crlf",
+        }
+    }
+
+    /// The characters of this line ending, or `None` to keep line breaks.
+    fn ending(self) -> Option<&'static [u8]> {
+        match self {
+            LineEnding::Keep => None,
+            LineEnding::Lf => Some(b"\n"),
+            LineEnding::CrLf => Some(b"\r\n"),
+        }
+    }
+}
+
+impl Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for LineEnding {
+    type Err = ParseLineEndingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        [LineEnding::Keep, LineEnding::Lf, LineEnding::CrLf]
+            .into_iter()
+            .find(|ending| ending.name() == name)
+            .ok_or(ParseLineEndingError { name })
+    }
+}
+
+/// Error returned when parsing an unknown [`LineEnding`] name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLineEndingError {
+    name: String,
+}
+
+impl Display for ParseLineEndingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown line ending '{}'", self.name)
+    }
+}
+
+impl Error for ParseLineEndingError {}
+
+/// How to normalize text payloads before encoding, nothing by default.
+///
+/// Only data that is valid UTF-8 is normalized, binary data is encoded as is.
+/// ANSI escape sequences are stripped first, then line breaks are converted,
+/// then whitespace is trimmed.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::normalize::{LineEnding, Normalize};
+///
+/// let normalize = Normalize::new()
+///     .strip_ansi(true)
+///     .line_ending(LineEnding::Lf)
+///     .trim(true);
+/// assert_eq!(normalize.apply(b"\x1B[32mok\x1B[0m\r\ndone\r\n"), &b"ok\ndone"[..]);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Normalize {
+    /// Whether to remove leading and trailing whitespace.
+    pub trim: bool,
+
+    /// Line ending to convert `\r\n`, `\r` and `\n` line breaks to.
+    pub line_ending: LineEnding,
+
+    /// Whether to remove ANSI escape sequences, such as colors.
+    pub strip_ansi: bool,
+}
+
+impl Normalize {
+    /// Construct a normalization that leaves text as is.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether to remove leading and trailing whitespace.
+    pub fn trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Set the line ending to convert line breaks to.
+    pub fn line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Set whether to remove ANSI escape sequences.
+    pub fn strip_ansi(mut self, strip_ansi: bool) -> Self {
+        self.strip_ansi = strip_ansi;
+        self
+    }
+
+    /// Normalize `data`, borrowing it if nothing changed.
+    pub fn apply<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        if *self == Self::default() || std::str::from_utf8(data).is_err() {
+            return Cow::Borrowed(data);
+        }
+
+        let mut data = Cow::Borrowed(data);
+        if self.strip_ansi && data.contains(&ESC) {
+            data = Cow::Owned(strip_ansi(&data));
+        }
+        if let Some(ending) = self.line_ending.ending() {
+            if data.iter().any(|&byte| byte == b'\r' || byte == b'\n') {
+                data = Cow::Owned(convert_line_breaks(&data, ending));
+            }
+        }
+        if self.trim {
+            let start = data.iter().position(|byte| !byte.is_ascii_whitespace());
+            let end = data.iter().rposition(|byte| !byte.is_ascii_whitespace());
+            let range = match (start, end) {
+                (Some(start), Some(end)) => start..end + 1,
+                _ => 0..0,
+            };
+            if range.len() != data.len() {
+                data = match data {
+                    Cow::Borrowed(data) => Cow::Borrowed(&data[range]),
+                    Cow::Owned(data) => Cow::Owned(data[range].to_vec()),
+                };
+            }
+        }
+        data
+    }
+}
+
+/// The escape character starting ANSI escape sequences.
+const ESC: u8 = 0x1B;
+
+/// Remove ANSI escape sequences from `data`.
+///
+/// Handles control sequences such as colors (`ESC [ ... m`), operating system
+/// commands such as window titles and hyperlinks (`ESC ] ... BEL` or
+/// `ESC ] ... ESC \`), and other escape sequences such as character set
+/// designations (`ESC ( B`).
+pub(crate) fn strip_ansi(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        if data[i] != ESC {
+            out.push(data[i]);
+            i += 1;
+            continue;
+        }
+        i += 1;
+        match data.get(i) {
+            // Parameters and intermediates, up to the final byte
+            Some(b'[') => {
+                i += 1;
+                while i < data.len() && !(0x40..=0x7E).contains(&data[i]) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            // Text up to the bell or string terminator
+            Some(b']') => {
+                i += 1;
+                while i < data.len() {
+                    match data[i] {
+                        0x07 => break,
+                        ESC if data.get(i + 1) == Some(&b'\\') => {
+                            i += 1;
+                            break;
+                        }
+                        _ => i += 1,
+                    }
+                }
+                i += 1;
+            }
+            // Intermediates, such as for character sets, up to the final byte
+            Some(_) => {
+                while i < data.len() && (0x20..=0x2F).contains(&data[i]) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            None => {}
+        }
+    }
+    out
+}
+
+/// Replace every `\r\n`, `\r` and `\n` line break in `data` with `ending`.
+fn convert_line_breaks(data: &[u8], ending: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut bytes = data.iter().peekable();
+    while let Some(&byte) = bytes.next() {
+        match byte {
+            b'\r' => {
+                bytes.next_if_eq(&&b'\n');
+                out.extend_from_slice(ending);
+            }
+            b'\n' => out.extend_from_slice(ending),
+            byte => out.push(byte),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_ending_from_str() {
+        for ending in [LineEnding::Keep, LineEnding::Lf, LineEnding::CrLf] {
+            assert_eq!(ending.name().parse(), Ok(ending));
+        }
+        assert_eq!(" CRLF ".parse(), Ok(LineEnding::CrLf));
+        assert!("cr".parse::<LineEnding>().is_err());
+    }
+
+    #[test]
+    fn strips_ansi() {
+        assert_eq!(strip_ansi(b"\x1B[1;32mgreen\x1B[0m"), b"green");
+        assert_eq!(
+            strip_ansi(b"\x1B]8;;https://a.b\x1B\\link\x1B]8;;\x07!"),
+            b"link!"
+        );
+        assert_eq!(strip_ansi(b"a\x1B(Bb\x1B"), b"ab");
+        assert_eq!(strip_ansi("é\x1B[Kü".as_bytes()), "éü".as_bytes());
+    }
+
+    #[test]
+    fn converts_line_breaks() {
+        assert_eq!(convert_line_breaks(b"a\r\nb\rc\nd", b"\n"), b"a\nb\nc\nd");
+        assert_eq!(convert_line_breaks(b"a\n\nb", b"\r\n"), b"a\r\n\r\nb");
+    }
+
+    #[test]
+    fn apply() {
+        let normalize = Normalize::new().trim(true);
+        assert!(matches!(normalize.apply(b"  ok \n"), Cow::Borrowed(b"ok")));
+        assert_eq!(normalize.apply(b" \n "), &b""[..]);
+        assert_eq!(normalize.apply(b" \xFF "), &b" \xFF "[..]);
+        assert!(matches!(
+            Normalize::new().apply(b" a "),
+            Cow::Borrowed(b" a ")
+        ));
+
+        let normalize = normalize.strip_ansi(true).line_ending(LineEnding::CrLf);
+        assert_eq!(
+            normalize.apply(b"\x1B[31m\nerror\nfailed\n\x1B[0m"),
+            &b"error\r\nfailed"[..]
+        );
+    }
+}
@@ -0,0 +1,263 @@
+//! Notifying users that a code is ready to scan.
+//!
+//! Users who switched to another window or tab while waiting for a code, such
+//! as a login code printed by a long running command, easily miss it. With
+//! [`QrOptions::notify`](crate::QrOptions::notify) the output also carries
+//! escape sequences prompting them to come and scan it: a terminal title
+//! shown in the tab and task bar, an OSC 9 desktop notification as supported
+//! by iTerm2, Windows Terminal, kitty and others, or the terminal bell. A
+//! desktop notification can also be sent directly, through the platform's
+//! command line tools: `notify-send` on Linux and the BSDs, and `osascript`
+//! on macOS.
+//!
+//! Applications notify of other events, such as a [stream](crate::stream)
+//! completing, with [`Notify::send`]:
+//!
+//! ```rust
+//! use qr2term::notify::{Channel, Notify};
+//!
+//! let notify = Notify::new().channel(Channel::Bell).message("Transfer complete");
+//! notify.send(&mut std::io::stdout()).unwrap();
+//! ```
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io::{Result as IoResult, Write};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+/// Message notified by default.
+pub const DEFAULT_MESSAGE: &str = "QR code ready, scan it";
+
+/// A way to notify users that a code is ready.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Channel {
+    /// Set the terminal title, through OSC 2.
+    Title,
+
+    /// Show a desktop notification through the terminal, through OSC 9.
+    Osc9,
+
+    /// Ring the terminal bell, which terminals may flash or mark the tab for.
+    Bell,
+
+    /// Send a desktop notification through the platform's tools.
+    Desktop,
+}
+
+impl Channel {
+    /// All channels.
+    pub const ALL: [Channel; 4] = [
+        Channel::Title,
+        Channel::Osc9,
+        Channel::Bell,
+        Channel::Desktop,
+    ];
+
+    /// Name of this channel, as accepted by its `FromStr` implementation.
+    pub fn name(self) -> &'static str {
+        match self {
+            Channel::Title => "title",
+            Channel::Osc9 => "osc9",
+            Channel::Bell => "bell",
+            Channel::Desktop => "desktop",
+        }
+    }
+}
+
+impl Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Channel {
+    type Err = ParseChannelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        Channel::ALL
+            .into_iter()
+            .find(|channel| channel.name() == name)
+            .ok_or(ParseChannelError { name })
+    }
+}
+
+/// Error returned when parsing an unknown [`Channel`] name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseChannelError {
+    name: String,
+}
+
+impl Display for ParseChannelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown notification channel '{}'", self.name)
+    }
+}
+
+impl Error for ParseChannelError {}
+
+/// How to notify users that a code is ready, not at all by default.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::{notify::{Channel, Notify}, QrOptions};
+///
+/// let notify = Notify::new()
+///     .channel(Channel::Title)
+///     .channel(Channel::Osc9)
+///     .message("QR code ready, scan within 60s");
+/// qr2term::print_qr_with_options("https://rust-lang.org/", &QrOptions::new().notify(notify)).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Notify {
+    /// Whether to set the terminal title to the message.
+    ///
+    /// The title is left in place after the code, as it can't tell when the
+    /// code was scanned.
+    pub title: bool,
+
+    /// Whether to show the message as OSC 9 desktop notification.
+    pub osc9: bool,
+
+    /// Whether to ring the terminal bell.
+    pub bell: bool,
+
+    /// Whether to send the message as desktop notification through the
+    /// platform's tools.
+    ///
+    /// Silently does nothing if no tool is available, such as over SSH.
+    pub desktop: bool,
+
+    /// The message, or `None` for [`DEFAULT_MESSAGE`].
+    pub message: Option<String>,
+}
+
+impl Notify {
+    /// Construct a notification that doesn't notify through any channel.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Notify through `channel` too.
+    pub fn channel(mut self, channel: Channel) -> Self {
+        match channel {
+            Channel::Title => self.title = true,
+            Channel::Osc9 => self.osc9 = true,
+            Channel::Bell => self.bell = true,
+            Channel::Desktop => self.desktop = true,
+        }
+        self
+    }
+
+    /// Set the message to notify, such as `QR code ready, scan within 60s`.
+    pub fn message<S: Into<String>>(mut self, message: S) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Whether any channel is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.title || self.osc9 || self.bell || self.desktop
+    }
+
+    /// Notify through the enabled channels, writing escape sequences to the
+    /// terminal `target`.
+    ///
+    /// Returns an error if writing to `target` failed. Failing to send a
+    /// desktop notification is not an error.
+    pub fn send<W: Write>(&self, target: &mut W) -> IoResult<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        // Control characters would end the sequence early
+        let message: String = self
+            .message
+            .as_deref()
+            .unwrap_or(DEFAULT_MESSAGE)
+            .chars()
+            .filter(|c| !c.is_control())
+            .collect();
+        if self.title {
+            write!(target, "\x1B]2;{}\x07", message)?;
+        }
+        if self.osc9 {
+            write!(target, "\x1B]9;{}\x07", message)?;
+        }
+        if self.bell {
+            target.write_all(b"\x07")?;
+        }
+        if self.desktop {
+            desktop(&message);
+        }
+        Ok(())
+    }
+}
+
+/// Send `message` as desktop notification through the first available tool.
+fn desktop(message: &str) {
+    let script = format!(
+        "display notification \"{}\" with title \"qr2term\"",
+        message.replace('\\', "\\\\").replace('"', "\\\"")
+    );
+    let tools: [(&str, Vec<&str>); 2] = [
+        ("notify-send", vec!["--", "qr2term", message]),
+        ("osascript", vec!["-e", &script]),
+    ];
+    for (program, args) in tools {
+        let status = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        if let Ok(status) = status {
+            // Tools without a notification service to connect to fail
+            if status.success() {
+                log_event!(debug, "sent desktop notification through {}", program);
+                return;
+            }
+        }
+    }
+    log_event!(warn, "no tool found to send desktop notification");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequences() {
+        let write = |notify: Notify| {
+            let mut buf = Vec::new();
+            notify.send(&mut buf).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        assert_eq!(write(Notify::new().message("unused")), "");
+        assert_eq!(
+            write(Notify::new().channel(Channel::Title)),
+            "\x1B]2;QR code ready, scan it\x07"
+        );
+        assert_eq!(
+            write(
+                Notify::new()
+                    .channel(Channel::Title)
+                    .channel(Channel::Osc9)
+                    .channel(Channel::Bell)
+                    .message("scan\x07 me\n")
+            ),
+            "\x1B]2;scan me\x07\x1B]9;scan me\x07\x07"
+        );
+    }
+
+    #[test]
+    fn parse() {
+        for channel in Channel::ALL {
+            assert_eq!(channel.name().parse(), Ok(channel));
+        }
+        assert!("email".parse::<Channel>().is_err());
+    }
+}
@@ -0,0 +1,1067 @@
+//! Options to control QR code generation and rendering.
+
+use std::borrow::Cow;
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use std::sync::RwLock;
+
+pub use qrcode::EcLevel;
+
+use crate::checksum::Checksum;
+use crate::hooks::{Hooks, SharedHooks};
+use crate::lint::{Validator, Validators};
+use crate::normalize::Normalize;
+use crate::notify::Notify;
+use crate::profile::{self, ProfileError};
+use crate::render::Style;
+use crate::reserve::Reserve;
+use crate::theme::Theme;
+
+/// Environment variable to override the default render style.
+pub const ENV_STYLE: &str = "QR2TERM_STYLE";
+
+/// Environment variable to override the default error correction level.
+pub const ENV_EC_LEVEL: &str = "QR2TERM_EC_LEVEL";
+
+/// Environment variable to override the default quiet zone size.
+pub const ENV_QUIET_ZONE: &str = "QR2TERM_QUIET_ZONE";
+
+/// Environment variable to override the default overflow strategy.
+pub const ENV_OVERFLOW: &str = "QR2TERM_OVERFLOW";
+
+/// Environment variable to override the default theme.
+pub const ENV_THEME: &str = "QR2TERM_THEME";
+
+/// Environment variable to override the default accessibility mode.
+pub const ENV_ACCESSIBILITY: &str = "QR2TERM_ACCESSIBILITY";
+
+/// Environment variable to override the default module stretch.
+pub const ENV_STRETCH: &str = "QR2TERM_STRETCH";
+
+/// Quiet zone size in pixels around QR code.
+///
+/// Should be 4, but using 2 for small terminals:
+/// https://qrworld.wordpress.com/2011/08/09/the-quiet-zone/
+pub const DEFAULT_QUIET_ZONE: usize = 2;
+
+/// Largest quiet zone accepted from the environment and configuration, in
+/// modules.
+pub const MAX_QUIET_ZONE: usize = 100;
+
+/// Process wide default options, see [`set_default_options`].
+static DEFAULT_OPTIONS: Defaults = Defaults::new();
+
+/// Default options that can be replaced at any time, falling back to
+/// [`QrOptions::from_env`] until they are set.
+struct Defaults(RwLock<Option<QrOptions>>);
+
+impl Defaults {
+    const fn new() -> Self {
+        Self(RwLock::new(None))
+    }
+
+    fn set(&self, options: QrOptions) {
+        *self.0.write().unwrap_or_else(|err| err.into_inner()) = Some(options);
+    }
+
+    fn get(&self) -> QrOptions {
+        self.0
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+            .unwrap_or_else(QrOptions::from_env)
+    }
+}
+
+/// Set the process wide default options.
+///
+/// These are used by all functions that don't take explicit options, such as
+/// [`print_qr`](crate::print_qr), so an application can configure styling once
+/// at startup instead of passing options to every call site. Calling this again
+/// replaces the previously set options.
+///
+/// Environment variables are not applied on top of these options. To respect
+/// them, start from [`QrOptions::from_env`].
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::{render::Style, QrOptions};
+///
+/// qr2term::set_default_options(QrOptions::from_env().style(Style::FullBlock));
+/// qr2term::print_qr("https://rust-lang.org/").unwrap();
+/// ```
+pub fn set_default_options(options: QrOptions) {
+    DEFAULT_OPTIONS.set(options);
+}
+
+/// Get the process wide default options.
+///
+/// Returns the options set through [`set_default_options`], or
+/// [`QrOptions::from_env`] if none were set.
+pub fn default_options() -> QrOptions {
+    DEFAULT_OPTIONS.get()
+}
+
+/// Options for generating and rendering a QR code.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::{options::EcLevel, render::Style, QrOptions};
+///
+/// let options = QrOptions::new()
+///     .style(Style::FullBlock)
+///     .ec_level(EcLevel::H)
+///     .quiet_zone(4);
+/// qr2term::print_qr_with_options("https://rust-lang.org/", &options).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct QrOptions {
+    /// Error correction level of the generated code.
+    pub ec_level: EcLevel,
+
+    /// Size of the quiet zone around the code, in modules.
+    pub quiet_zone: usize,
+
+    /// Style used to render the code.
+    pub style: Style,
+
+    /// Colors used to render the code.
+    pub theme: Theme,
+
+    /// What to do when the data doesn't fit a single QR code.
+    pub on_overflow: Overflow,
+
+    /// How to arrange the codes data is split over, see [`layout`](Self::layout).
+    pub layout: Layout,
+
+    /// Whether to compress the data before encoding, see [`compress`](crate::compress).
+    pub compress: bool,
+
+    /// Clockwise rotation of the rendered code.
+    pub rotate: Rotation,
+
+    /// Whether to mirror the rendered code horizontally, before rotating it.
+    pub mirror: bool,
+
+    /// Callbacks invoked while generating and rendering, see [`hooks`](crate::hooks).
+    pub hooks: Option<SharedHooks>,
+
+    /// Whether to print a textual description of the code for screen readers.
+    pub accessibility: Accessibility,
+
+    /// Whether [`debug`](crate::debug) renderings print row and column indices around the code.
+    pub ruler: bool,
+
+    /// Whether to render every line at double width and height, see [`Renderer::double_size`](crate::render::Renderer::double_size).
+    pub double_size: bool,
+
+    /// How many times wider than normal to draw every module.
+    ///
+    /// See [`Renderer::stretch`](crate::render::Renderer::stretch).
+    pub stretch: usize,
+
+    /// Whether to draw light modules with non-breaking spaces, so copies keep their quiet zone.
+    pub non_breaking: bool,
+
+    /// Whether to reset all colors and attributes after the output.
+    pub reset: bool,
+
+    /// Whether the output ends with a newline.
+    pub trailing_newline: bool,
+
+    /// Whether to hide the cursor while printing, showing it again after.
+    pub hide_cursor: bool,
+
+    /// Maximum width of the rendered code in columns, or `None` for no limit.
+    pub max_width: Option<usize>,
+
+    /// How to normalize text before encoding it.
+    pub normalize: Normalize,
+
+    /// Checksum appended to the payload before encoding it, see [`checksum`](crate::checksum).
+    pub checksum: Checksum,
+
+    /// Whether the payload is a secret, such as an API token or recovery code.
+    ///
+    /// See [`secret`](Self::secret).
+    pub secret: bool,
+
+    /// Checks rejecting payloads before generating codes, see [`lint`](crate::lint).
+    pub validators: Validators,
+
+    /// Whether the same payload and options always render the same output.
+    ///
+    /// See [`deterministic`](Self::deterministic).
+    pub deterministic: bool,
+
+    /// Whether the terminal is in raw mode, see [`raw_mode`](Self::raw_mode).
+    pub raw_mode: RawMode,
+
+    /// How to notify users that the code is ready, see [`notify`](crate::notify).
+    pub notify: Notify,
+
+    /// How to print URL payloads under the code, see [`hyperlink`](Self::hyperlink).
+    pub hyperlink: Hyperlink,
+
+    /// Longest payload preview printed under the code, in characters, see
+    /// [`preview`](Self::preview).
+    pub preview: Option<usize>,
+
+    /// Area kept blank in image exports, see [`reserve`](Self::reserve).
+    pub reserve: Option<Reserve>,
+}
+
+impl QrOptions {
+    /// Construct options with the default values.
+    ///
+    /// This does not consider environment variables, see [`from_env`](Self::from_env).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Construct the default options, overridden by any environment variables that are set.
+    ///
+    /// See [`with_env`](Self::with_env) for the supported variables.
+    pub fn from_env() -> Self {
+        Self::default().with_env()
+    }
+
+    /// Construct the default options with the [profile](crate::profile) `name` applied.
+    ///
+    /// With the `config` feature, profiles defined in the config file are
+    /// considered too. Environment variables are not applied.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use qr2term::{profile, render::Style, QrOptions};
+    ///
+    /// let options = QrOptions::from_profile(profile::CI).unwrap();
+    /// assert_eq!(options.style, Style::CiLog);
+    /// ```
+    pub fn from_profile(name: &str) -> Result<Self, ProfileError> {
+        profile::apply(name, Self::default())
+    }
+
+    /// Override these options with any environment variables that are set.
+    ///
+    /// The following variables are supported:
+    ///
+    /// - `QR2TERM_STYLE`: render style, see [`Style`]'s `FromStr` implementation
+    /// - `QR2TERM_THEME`: color theme, see [`Theme`]'s `FromStr` implementation
+    /// - `QR2TERM_EC_LEVEL`: error correction level, one of `L`, `M`, `Q` or `H`
+    /// - `QR2TERM_QUIET_ZONE`: quiet zone size in modules, at most [`MAX_QUIET_ZONE`]
+    /// - `QR2TERM_OVERFLOW`: overflow strategy, one of `error`, `split` or `truncate`
+    /// - `QR2TERM_ACCESSIBILITY`: accessibility mode, one of `off`, `alongside` or `instead`
+    /// - `QR2TERM_STRETCH`: how many times wider to draw every module, at least 1
+    ///
+    /// Variables with a value that can't be parsed are ignored.
+    pub fn with_env(self) -> Self {
+        self.with_vars(|key| env::var(key).ok())
+    }
+
+    /// Override these options with variables obtained through `var`.
+    fn with_vars<F: Fn(&str) -> Option<String>>(mut self, var: F) -> Self {
+        if let Some(style) = var(ENV_STYLE).and_then(|s| valid(ENV_STYLE, &s, s.parse().ok())) {
+            self.style = style;
+        }
+        if let Some(theme) = var(ENV_THEME).and_then(|s| valid(ENV_THEME, &s, s.parse().ok())) {
+            self.theme = theme;
+        }
+        if let Some(ec_level) =
+            var(ENV_EC_LEVEL).and_then(|s| valid(ENV_EC_LEVEL, &s, parse_ec_level(&s)))
+        {
+            self.ec_level = ec_level;
+        }
+        if let Some(quiet_zone) = var(ENV_QUIET_ZONE).and_then(|s| {
+            let quiet_zone = s.trim().parse().ok().filter(|&zone| zone <= MAX_QUIET_ZONE);
+            valid(ENV_QUIET_ZONE, &s, quiet_zone)
+        }) {
+            self.quiet_zone = quiet_zone;
+        }
+        if let Some(on_overflow) =
+            var(ENV_OVERFLOW).and_then(|s| valid(ENV_OVERFLOW, &s, s.parse().ok()))
+        {
+            self.on_overflow = on_overflow;
+        }
+        if let Some(accessibility) =
+            var(ENV_ACCESSIBILITY).and_then(|s| valid(ENV_ACCESSIBILITY, &s, s.parse().ok()))
+        {
+            self.accessibility = accessibility;
+        }
+        if let Some(stretch) = var(ENV_STRETCH).and_then(|s| {
+            let stretch = s.trim().parse().ok().filter(|&stretch| stretch > 0);
+            valid(ENV_STRETCH, &s, stretch)
+        }) {
+            self.stretch = stretch;
+        }
+        self
+    }
+
+    /// Set the error correction level.
+    pub fn ec_level(mut self, ec_level: EcLevel) -> Self {
+        self.ec_level = ec_level;
+        self
+    }
+
+    /// Set the quiet zone size in modules.
+    pub fn quiet_zone(mut self, quiet_zone: usize) -> Self {
+        self.quiet_zone = quiet_zone;
+        self
+    }
+
+    /// Set the render style.
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Set the colors to render in.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Set what to do when the data doesn't fit a single QR code.
+    pub fn on_overflow(mut self, on_overflow: Overflow) -> Self {
+        self.on_overflow = on_overflow;
+        self
+    }
+
+    /// Set how to arrange the codes data is [split](Overflow::Split) over.
+    ///
+    /// With [`Layout::Grid`] the codes are arranged in a grid fitting the
+    /// width of the terminal, such as 2×2 or 3×1, each captioned with its
+    /// part number, see [`grid`](crate::grid).
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Set whether to compress the data before encoding.
+    ///
+    /// Compressed codes must be decoded with [`compress::decompress`](crate::compress::decompress)
+    /// after scanning.
+    pub fn compress(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// Set the clockwise rotation of the rendered code.
+    pub fn rotate(mut self, rotate: Rotation) -> Self {
+        self.rotate = rotate;
+        self
+    }
+
+    /// Set whether to mirror the rendered code horizontally.
+    ///
+    /// Useful when the code is scanned through a reflection or a mirrored
+    /// camera preview.
+    pub fn mirror(mut self, mirror: bool) -> Self {
+        self.mirror = mirror;
+        self
+    }
+
+    /// Set the callbacks invoked while generating and rendering.
+    pub fn hooks<H: Hooks + 'static>(mut self, hooks: H) -> Self {
+        self.hooks = Some(SharedHooks::new(hooks));
+        self
+    }
+
+    /// Set whether to print a textual description of the code for screen readers.
+    pub fn accessibility(mut self, accessibility: Accessibility) -> Self {
+        self.accessibility = accessibility;
+        self
+    }
+
+    /// Set whether debug renderings print row and column indices around the code.
+    ///
+    /// Helps referring to specific modules, for example when reporting scanning
+    /// issues. See [`debug::render_layout`](crate::debug::render_layout).
+    pub fn ruler(mut self, ruler: bool) -> Self {
+        self.ruler = ruler;
+        self
+    }
+
+    /// Set whether to render every line at double width and height.
+    ///
+    /// Makes codes physically larger on terminals supporting the DECDHL escape
+    /// sequences, such as xterm and VTE based terminals.
+    pub fn double_size(mut self, double_size: bool) -> Self {
+        self.double_size = double_size;
+        self
+    }
+
+    /// Set how many times wider than normal to draw every module.
+    ///
+    /// Compensates for fonts and styles that draw modules taller than wide,
+    /// such as the quadrant style. A stretch of 2 keeps quadrant modules square.
+    pub fn stretch(mut self, stretch: usize) -> Self {
+        self.stretch = stretch;
+        self
+    }
+
+    /// Set whether to draw light modules with non-breaking spaces.
+    ///
+    /// Keeps the right quiet zone when the output is copied through terminals,
+    /// pagers or chat clients that trim trailing spaces. See
+    /// [`Renderer::non_breaking`](crate::render::Renderer::non_breaking).
+    pub fn non_breaking(mut self, non_breaking: bool) -> Self {
+        self.non_breaking = non_breaking;
+        self
+    }
+
+    /// Set whether to reset all colors and attributes after the output.
+    ///
+    /// Renderers restore the colors they change, but a final reset guarantees
+    /// that prompts or interfaces printed after the code aren't affected by
+    /// terminals handling this differently.
+    pub fn reset(mut self, reset: bool) -> Self {
+        self.reset = reset;
+        self
+    }
+
+    /// Set whether the output ends with a newline, enabled by default.
+    ///
+    /// Without it, the cursor stays on the last line of the code, for example
+    /// to embed it in a prompt.
+    pub fn trailing_newline(mut self, trailing_newline: bool) -> Self {
+        self.trailing_newline = trailing_newline;
+        self
+    }
+
+    /// Set whether to hide the cursor while printing, showing it again after.
+    ///
+    /// Avoids the cursor flickering over the code on slow terminals.
+    pub fn hide_cursor(mut self, hide_cursor: bool) -> Self {
+        self.hide_cursor = hide_cursor;
+        self
+    }
+
+    /// Set the maximum width of the rendered code, in columns.
+    ///
+    /// For embedding codes in panels of known width. The [`Style::Auto`] style
+    /// picks the largest style that fits. Rendering wider than this fails with
+    /// [`Error::TooWide`](crate::Error::TooWide) from the `try_` functions.
+    pub fn max_width(mut self, columns: usize) -> Self {
+        self.max_width = Some(columns);
+        self
+    }
+
+    /// Set how to normalize text before encoding it, see [`Normalize`].
+    ///
+    /// Useful for text piped from other commands, which often carries stray
+    /// whitespace or ANSI escape sequences.
+    pub fn normalize(mut self, normalize: Normalize) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Set the checksum to append to the payload, see [`checksum`](crate::checksum).
+    ///
+    /// The checksum is computed over the normalized payload, and appended
+    /// before it is [compressed](Self::compress).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use qr2term::{checksum::Checksum, QrOptions};
+    ///
+    /// let options = QrOptions::new().checksum(Checksum::Crc32);
+    /// qr2term::print_qr_with_options("hello", &options).unwrap();
+    /// ```
+    pub fn checksum(mut self, checksum: Checksum) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Set whether the payload is a secret, such as an API token or recovery code.
+    ///
+    /// Secret payloads are left out of all text output besides the code
+    /// itself: [accessibility](Self::accessibility) descriptions, the
+    /// [batch](crate::batch) index and [`log_qr`](crate::log_qr), which logs a
+    /// note instead of the code. Buffers holding the payload or code, such as
+    /// the normalized or compressed payload and the module matrix, are wiped
+    /// after rendering. Buffers owned by the caller, or by the `qrcode` crate
+    /// while generating, are not.
+    pub fn secret(mut self, secret: bool) -> Self {
+        self.secret = secret;
+        self
+    }
+
+    /// Add a check rejecting payloads before generating codes for them.
+    ///
+    /// Generating codes for payloads the `validator` returns an error for
+    /// fails with [`Error::Rejected`](crate::Error::Rejected). See
+    /// [`lint`](crate::lint) for an example, and the built-in
+    /// [`strict`](crate::lint::strict) validator.
+    pub fn validator(mut self, validator: Validator) -> Self {
+        self.validators.0.push(validator);
+        self
+    }
+
+    /// Set whether the same payload and options always render byte-identical
+    /// output, across releases and environments.
+    ///
+    /// For golden files of rendered codes. This pins the choices that
+    /// otherwise may change between releases or depend on the environment:
+    ///
+    /// - Payloads in the automatic [mode](crate::payload::Mode) are encoded in
+    ///   a [single segment](crate::payload::Mode::single_segment) instead of
+    ///   optimized segments, in the smallest version they fit, trying versions
+    ///   in increasing order.
+    /// - Codes use mask pattern 0 instead of the one with the lowest penalty
+    ///   score.
+    /// - The [`Auto`](Style::Auto) style ignores the terminal size, only
+    ///   fitting the [maximum width](Self::max_width) if set, and the
+    ///   [`CiLog`](Style::CiLog) style doesn't group codes for the CI service
+    ///   it runs on.
+    /// - The [grid](Layout::Grid) layout fills
+    ///   [`grid::DEFAULT_COLUMNS`](crate::grid::DEFAULT_COLUMNS) columns
+    ///   instead of the width of the terminal.
+    ///
+    /// Codes may be larger than without it. Options read from the environment
+    /// by [`with_env`](Self::with_env) still apply.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Set whether the terminal is in raw mode, as in REPLs and TUIs.
+    ///
+    /// In raw mode a line feed doesn't return the cursor to the first column,
+    /// so codes would print as a staircase. Output for a terminal in raw mode
+    /// ends every line with `\r\n` instead. See [`RawMode`] for how
+    /// [`Auto`](RawMode::Auto) detects it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use qr2term::{options::RawMode, QrOptions};
+    ///
+    /// let options = QrOptions::new().raw_mode(RawMode::Enabled);
+    /// let code = qr2term::try_generate_qr_string_with_options("qr2term", &options).unwrap();
+    /// assert!(code.ends_with("\r\n"));
+    /// assert!(!code.replace("\r\n", "").contains('\n'));
+    /// ```
+    pub fn raw_mode(mut self, raw_mode: RawMode) -> Self {
+        self.raw_mode = raw_mode;
+        self
+    }
+
+    /// Set how to notify users that the code is ready, such as through the
+    /// terminal title, see [`notify`](crate::notify).
+    ///
+    /// The notification is written after the code.
+    pub fn notify(mut self, notify: Notify) -> Self {
+        self.notify = notify;
+        self
+    }
+
+    /// Set how to print URL payloads under the code, so users can click them
+    /// instead of scanning.
+    ///
+    /// With [`Hyperlink::Auto`], the URL is printed as OSC 8 hyperlink when
+    /// printing to a terminal known to support them, and as plain text
+    /// elsewhere, such as into strings. Pick [`Hyperlink::Escaped`] to escape
+    /// them in strings shown on such a terminal. Only payloads starting
+    /// with `http://` or `https://` are printed, and not for
+    /// [secret](Self::secret), [compressed](Self::compress) or split payloads.
+    /// Output is plain text for the [`CiLog`](Style::CiLog) style.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use qr2term::{options::Hyperlink, QrOptions};
+    ///
+    /// let options = QrOptions::new().hyperlink(Hyperlink::Escaped);
+    /// let code = qr2term::try_generate_qr_string_with_options("https://rust-lang.org/", &options)
+    ///     .unwrap();
+    /// assert!(code.contains("\x1B]8;;https://rust-lang.org/"));
+    /// ```
+    pub fn hyperlink(mut self, hyperlink: Hyperlink) -> Self {
+        self.hyperlink = hyperlink;
+        self
+    }
+
+    /// Resolve [`Hyperlink::Auto`] for output printed to the terminal, unless
+    /// the output is [deterministic](Self::deterministic).
+    pub(crate) fn for_terminal(&self) -> Cow<'_, Self> {
+        match self.hyperlink {
+            Hyperlink::Auto if !self.deterministic => {
+                Cow::Owned(self.clone().hyperlink(self.hyperlink.for_terminal()))
+            }
+            _ => Cow::Borrowed(self),
+        }
+    }
+
+    /// Print a preview of the payload under the code, of at most `len`
+    /// characters, to tell codes apart in long output.
+    ///
+    /// Longer payloads are shortened with an ellipsis and followed by their
+    /// CRC-32 in brackets, such as `https://example.com/invite/7Kq… [0c5f3e2a]`,
+    /// so codes with the same start are told apart too. The preview also
+    /// replaces the full text in [accessibility](Self::accessibility)
+    /// descriptions. Nothing is printed for [secret](Self::secret) payloads,
+    /// or if `len` is 0.
+    pub fn preview(mut self, len: usize) -> Self {
+        self.preview = Some(len);
+        self
+    }
+
+    /// Keep a rectangle of modules blank in image exports, such as PNG
+    /// images written by [`batch::write_images`](crate::batch::write_images),
+    /// to place a sticker or label on later.
+    ///
+    /// Exporting fails with [`Error::Reserve`](crate::Error::Reserve) if the
+    /// rectangle would keep the code from scanning, see
+    /// [`reserve`](crate::reserve). Codes rendered to the terminal are left
+    /// whole.
+    pub fn reserve(mut self, reserve: Reserve) -> Self {
+        self.reserve = Some(reserve);
+        self
+    }
+}
+
+impl Default for QrOptions {
+    fn default() -> Self {
+        Self {
+            ec_level: EcLevel::M,
+            quiet_zone: DEFAULT_QUIET_ZONE,
+            style: Style::default(),
+            theme: Theme::default(),
+            on_overflow: Overflow::default(),
+            layout: Layout::default(),
+            compress: false,
+            rotate: Rotation::default(),
+            mirror: false,
+            hooks: None,
+            accessibility: Accessibility::default(),
+            ruler: false,
+            double_size: false,
+            stretch: 1,
+            non_breaking: false,
+            reset: false,
+            trailing_newline: true,
+            hide_cursor: false,
+            max_width: None,
+            normalize: Normalize::default(),
+            checksum: Checksum::default(),
+            secret: false,
+            validators: Validators::default(),
+            deterministic: false,
+            raw_mode: RawMode::default(),
+            notify: Notify::default(),
+            hyperlink: Hyperlink::default(),
+            preview: None,
+            reserve: None,
+        }
+    }
+}
+
+/// What to do when the data is too long to fit a single QR code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Overflow {
+    /// Return a [`QrError::DataTooLong`](crate::QrError::DataTooLong) error.
+    #[default]
+    Error,
+
+    /// Split the data over multiple QR codes, each labeled with its part number.
+    Split,
+
+    /// Truncate the data to what fits a single QR code.
+    Truncate,
+}
+
+impl Overflow {
+    /// Name of this strategy, as accepted by its `FromStr` implementation.
+    pub fn name(self) -> &'static str {
+        match self {
+            Overflow::Error => "error",
+            Overflow::Split => "split",
+            Overflow::Truncate => "truncate",
+        }
+    }
+}
+
+impl Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Overflow {
+    type Err = ParseOverflowError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        [Overflow::Error, Overflow::Split, Overflow::Truncate]
+            .into_iter()
+            .find(|overflow| overflow.name() == name)
+            .ok_or(ParseOverflowError { name })
+    }
+}
+
+/// Error returned when parsing an unknown [`Overflow`] name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOverflowError {
+    name: String,
+}
+
+impl Display for ParseOverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown overflow strategy '{}'", self.name)
+    }
+}
+
+impl Error for ParseOverflowError {}
+
+/// How to arrange the codes data is split over.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Layout {
+    /// One code below the other.
+    #[default]
+    Stack,
+
+    /// Side by side in a grid fitting the width of the terminal.
+    Grid,
+}
+
+impl Layout {
+    /// All layouts.
+    pub const ALL: [Layout; 2] = [Layout::Stack, Layout::Grid];
+
+    /// Name of this layout, as accepted by its `FromStr` implementation.
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::Stack => "stack",
+            Layout::Grid => "grid",
+        }
+    }
+}
+
+impl Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Layout {
+    type Err = ParseLayoutError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        Layout::ALL
+            .into_iter()
+            .find(|layout| layout.name() == name)
+            .ok_or(ParseLayoutError { name })
+    }
+}
+
+/// Error returned when parsing an unknown [`Layout`] name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLayoutError {
+    name: String,
+}
+
+impl Display for ParseLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown layout '{}'", self.name)
+    }
+}
+
+impl Error for ParseLayoutError {}
+
+/// Clockwise rotation of a rendered code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Rotation {
+    /// Not rotated.
+    #[default]
+    Rotate0,
+
+    /// Rotated by 90 degrees.
+    Rotate90,
+
+    /// Rotated by 180 degrees.
+    Rotate180,
+
+    /// Rotated by 270 degrees.
+    Rotate270,
+}
+
+impl Rotation {
+    /// All rotations, in increasing order.
+    pub const ALL: [Rotation; 4] = [
+        Rotation::Rotate0,
+        Rotation::Rotate90,
+        Rotation::Rotate180,
+        Rotation::Rotate270,
+    ];
+
+    /// The rotation in degrees, as accepted by its `FromStr` implementation.
+    pub fn degrees(self) -> u16 {
+        match self {
+            Rotation::Rotate0 => 0,
+            Rotation::Rotate90 => 90,
+            Rotation::Rotate180 => 180,
+            Rotation::Rotate270 => 270,
+        }
+    }
+}
+
+impl Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.degrees().fmt(f)
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = ParseRotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let degrees = s.trim();
+        Rotation::ALL
+            .into_iter()
+            .find(|rotation| degrees.parse() == Ok(rotation.degrees()))
+            .ok_or_else(|| ParseRotationError {
+                degrees: degrees.into(),
+            })
+    }
+}
+
+/// Error returned when parsing an unsupported [`Rotation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRotationError {
+    degrees: String,
+}
+
+impl Display for ParseRotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unsupported rotation '{}', must be 0, 90, 180 or 270",
+            self.degrees
+        )
+    }
+}
+
+impl Error for ParseRotationError {}
+
+/// Whether the terminal the output is written to is in raw mode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum RawMode {
+    /// Detect whether the terminal is in raw mode.
+    ///
+    /// Only detects raw mode enabled through `crossterm`, with the `terminal`
+    /// feature, as other libraries such as `termion` don't expose it. Hosts
+    /// using those set [`Enabled`](Self::Enabled) while in raw mode.
+    #[default]
+    Auto,
+
+    /// The terminal is in raw mode, end lines with `\r\n`.
+    Enabled,
+
+    /// The terminal is not in raw mode, end lines with `\n`.
+    Disabled,
+}
+
+impl RawMode {
+    /// Whether the terminal is in raw mode, detecting it for [`Auto`](Self::Auto).
+    pub fn is_enabled(self) -> bool {
+        match self {
+            RawMode::Auto => crate::term::is_raw_mode(),
+            RawMode::Enabled => true,
+            RawMode::Disabled => false,
+        }
+    }
+}
+
+/// How to print URL payloads under the code, see
+/// [`QrOptions::hyperlink`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Hyperlink {
+    /// Don't print URLs.
+    #[default]
+    Off,
+
+    /// Print URLs as OSC 8 hyperlinks when printing to a terminal known to
+    /// support them, and as plain text otherwise.
+    ///
+    /// `FORCE_HYPERLINK` set to `1` or `0` overrides the detection.
+    Auto,
+
+    /// Print URLs as plain text.
+    Plain,
+
+    /// Print URLs as OSC 8 hyperlinks, which terminals without support show
+    /// as escape sequences or not at all.
+    Escaped,
+}
+
+impl Hyperlink {
+    /// Resolve [`Auto`](Self::Auto) for output printed to the terminal, to
+    /// [`Escaped`](Self::Escaped) if it is known to support hyperlinks and to
+    /// [`Plain`](Self::Plain) otherwise.
+    pub fn for_terminal(self) -> Self {
+        match self {
+            Hyperlink::Auto if crate::term::supports_hyperlinks() => Hyperlink::Escaped,
+            Hyperlink::Auto => Hyperlink::Plain,
+            hyperlink => hyperlink,
+        }
+    }
+}
+
+/// Whether to print a textual description of a code, which screen readers can
+/// read out unlike the block characters of the code itself.
+///
+/// The description includes the symbol version and the encoded data, for
+/// example `QR code, version 1, error correction M, encodes "qr2term"`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Accessibility {
+    /// Only print the code.
+    #[default]
+    Off,
+
+    /// Print the description below the code.
+    Alongside,
+
+    /// Print the description instead of the code.
+    Instead,
+}
+
+impl Accessibility {
+    /// Name of this mode, as accepted by its `FromStr` implementation.
+    pub fn name(self) -> &'static str {
+        match self {
+            Accessibility::Off => "off",
+            Accessibility::Alongside => "alongside",
+            Accessibility::Instead => "instead",
+        }
+    }
+}
+
+impl Display for Accessibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Accessibility {
+    type Err = ParseAccessibilityError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        [
+            Accessibility::Off,
+            Accessibility::Alongside,
+            Accessibility::Instead,
+        ]
+        .into_iter()
+        .find(|accessibility| accessibility.name() == name)
+        .ok_or(ParseAccessibilityError { name })
+    }
+}
+
+/// Error returned when parsing an unknown [`Accessibility`] name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseAccessibilityError {
+    name: String,
+}
+
+impl Display for ParseAccessibilityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown accessibility mode '{}'", self.name)
+    }
+}
+
+impl Error for ParseAccessibilityError {}
+
+/// Pass through the `parsed` value of environment variable `key`, logging
+/// that its `value` is ignored if it couldn't be parsed.
+fn valid<T>(key: &str, value: &str, parsed: Option<T>) -> Option<T> {
+    if parsed.is_none() {
+        log_event!(warn, "ignoring invalid value '{}' for {}", value, key);
+    }
+    parsed
+}
+
+/// Parse an error correction level from its letter, case insensitive.
+///
+/// Returns `None` if the given text is not one of `L`, `M`, `Q` or `H`.
+pub fn parse_ec_level(text: &str) -> Option<EcLevel> {
+    match text.trim().to_ascii_uppercase().as_str() {
+        "L" => Some(EcLevel::L),
+        "M" => Some(EcLevel::M),
+        "Q" => Some(EcLevel::Q),
+        "H" => Some(EcLevel::H),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_overrides() {
+        let options = QrOptions::new().with_vars(|key| match key {
+            ENV_STYLE => Some("full".into()),
+            ENV_THEME => Some("high-contrast".into()),
+            ENV_EC_LEVEL => Some("h".into()),
+            ENV_QUIET_ZONE => Some("4".into()),
+            ENV_OVERFLOW => Some("Split".into()),
+            ENV_ACCESSIBILITY => Some("instead".into()),
+            ENV_STRETCH => Some("2".into()),
+            _ => None,
+        });
+        assert_eq!(options.stretch, 2);
+        assert_eq!(options.on_overflow, Overflow::Split);
+        assert_eq!(options.accessibility, Accessibility::Instead);
+        assert_eq!(options.style, Style::FullBlock);
+        assert_eq!(options.theme, Theme::HighContrast);
+        assert_eq!(options.ec_level, EcLevel::H);
+        assert_eq!(options.quiet_zone, 4);
+    }
+
+    #[test]
+    fn env_invalid_ignored() {
+        let options = QrOptions::new().with_vars(|key| match key {
+            ENV_STYLE => Some("sparkles".into()),
+            ENV_EC_LEVEL => Some("X".into()),
+            ENV_QUIET_ZONE => Some("-1".into()),
+            ENV_STRETCH => Some("0".into()),
+            _ => None,
+        });
+        assert_eq!(options, QrOptions::default());
+
+        let options = QrOptions::new().with_vars(|key| match key {
+            ENV_QUIET_ZONE => Some("3000000000".into()),
+            _ => None,
+        });
+        assert_eq!(options.quiet_zone, DEFAULT_QUIET_ZONE);
+    }
+
+    #[test]
+    fn rotation_from_str() {
+        for rotation in Rotation::ALL {
+            assert_eq!(rotation.to_string().parse(), Ok(rotation));
+        }
+        assert_eq!(" 90 ".parse(), Ok(Rotation::Rotate90));
+        assert!("45".parse::<Rotation>().is_err());
+    }
+
+    #[test]
+    fn default_options_replaced() {
+        let defaults = Defaults::new();
+        assert_eq!(defaults.get(), QrOptions::from_env());
+
+        let options = QrOptions::new().style(Style::Ascii).quiet_zone(7);
+        defaults.set(options.clone());
+        assert_eq!(defaults.get(), options);
+    }
+}
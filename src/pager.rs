@@ -0,0 +1,112 @@
+//! Paging long output, such as large batches of codes.
+//!
+//! Output is piped through the pager in the `PAGER` environment variable, or
+//! `less -R` if unset, so the colors of rendered codes are kept.
+
+use std::env;
+use std::ffi::OsStr;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use crossterm::tty::IsTty;
+
+/// Pager used if `PAGER` is unset.
+pub const DEFAULT_PAGER: &str = "less -R";
+
+/// Write `output` to stdout, through a pager if it doesn't fit the terminal.
+///
+/// The pager is only used if stdout is a terminal and `output` has more lines
+/// than the terminal has rows. It is taken from the `PAGER` environment
+/// variable, falling back to [`DEFAULT_PAGER`], and disabled if `PAGER` is
+/// empty or `cat`. `LESS` is set to `R` if unset, so `less` shows colors
+/// rather than escape codes. If the pager can't be found, `output` is written
+/// to stdout directly.
+///
+/// Returns an error if writing the output failed. Quitting the pager before
+/// reaching the end is not an error.
+///
+/// # Examples
+///
+/// ```rust
+/// let code = qr2term::generate_qr_string("https://rust-lang.org/").unwrap();
+/// qr2term::pager::page(code.repeat(10).as_bytes()).unwrap();
+/// ```
+pub fn page(output: &[u8]) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    let rows = crate::term::size().map(|(_, rows)| rows);
+    let pager = env::var("PAGER").ok();
+    let command = match command(pager.as_deref()) {
+        Some(command) if stdout.is_tty() && exceeds(output, rows) => command,
+        _ => return stdout.write_all(output),
+    };
+
+    let (program, args) = command;
+    let mut child = match Command::new(program)
+        .args(args)
+        .envs(
+            env::var_os("LESS")
+                .is_none()
+                .then_some(("LESS", OsStr::new("R"))),
+        )
+        .stdin(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            log_event!(debug, "pager '{}' not found, writing to stdout", program);
+            return stdout.write_all(output);
+        }
+        Err(err) => return Err(err),
+    };
+    let written = child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(output);
+    child.wait()?;
+    match written {
+        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Get the program and arguments of the given `pager` command, or of the
+/// [`DEFAULT_PAGER`] if `None`.
+///
+/// Returns `None` if paging is disabled.
+fn command(pager: Option<&str>) -> Option<(&str, Vec<&str>)> {
+    let mut words = pager.unwrap_or(DEFAULT_PAGER).split_whitespace();
+    match words.next()? {
+        "cat" => None,
+        program => Some((program, words.collect())),
+    }
+}
+
+/// Whether `output` has more lines than the terminal has `rows`.
+///
+/// Output is assumed to fit a terminal of unknown size.
+fn exceeds(output: &[u8], rows: Option<usize>) -> bool {
+    let lines = output.split(|&b| b == b'\n').count() - usize::from(output.ends_with(b"\n"));
+    rows.map_or(false, |rows| lines > rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pager_command() {
+        assert_eq!(command(None), Some(("less", vec!["-R"])));
+        assert_eq!(command(Some(" most  -s ")), Some(("most", vec!["-s"])));
+        assert_eq!(command(Some("")), None);
+        assert_eq!(command(Some("cat")), None);
+    }
+
+    #[test]
+    fn exceeds_rows() {
+        assert!(!exceeds(b"a\nb\n", Some(2)));
+        assert!(!exceeds(b"a\nb", Some(2)));
+        assert!(exceeds(b"a\nb\nc", Some(2)));
+        assert!(!exceeds(b"a\nb\nc", None));
+    }
+}
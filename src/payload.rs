@@ -0,0 +1,301 @@
+//! Payload types that can be encoded as QR code.
+
+use std::borrow::Cow;
+use std::rc::Rc;
+use std::sync::Arc;
+
+#[cfg(feature = "payloads")]
+mod expiry;
+#[cfg(feature = "payloads")]
+mod provisioning;
+#[cfg(feature = "payloads")]
+mod ssh;
+#[cfg(feature = "payloads")]
+mod template;
+#[cfg(feature = "url")]
+mod url;
+
+#[cfg(all(feature = "payloads", unix))]
+pub use self::expiry::with_expiry;
+#[cfg(feature = "payloads")]
+pub use self::expiry::{validate_expiry, Expiring, ExpiryError, EXPIRY_NONCE_LEN};
+#[cfg(feature = "payloads")]
+pub use self::provisioning::{provisioning, Provisioning, ProvisioningError};
+#[cfg(feature = "payloads")]
+pub use self::ssh::{ssh_fingerprint, SshFingerprint, SshKeyError};
+#[cfg(feature = "payloads")]
+pub use self::template::{template, Template, TemplateError};
+#[cfg(feature = "url")]
+pub use self::url::{url, Url, UrlError};
+
+/// Mode used to encode a payload into a QR code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum Mode {
+    /// Automatically split the data into the most compact mode segments.
+    #[default]
+    Auto,
+
+    /// Encode all data in numeric mode, only digits `0` to `9` are allowed.
+    Numeric,
+
+    /// Encode all data in alphanumeric mode, only digits, uppercase letters
+    /// and ` $%*+-./:` are allowed.
+    Alphanumeric,
+
+    /// Encode all data as-is in byte mode.
+    Byte,
+
+    /// Encode a GS1 element string, with FNC1 in first position.
+    ///
+    /// Variable length element strings are separated by the ASCII group
+    /// separator `0x1D`. See [`gs1`](crate::gs1) to build such payloads.
+    Gs1,
+}
+
+impl Mode {
+    /// Find the position of the first byte in `data` this mode can't encode.
+    ///
+    /// Returns `None` if all of `data` can be encoded. Encoding a payload with
+    /// such a byte fails with [`QrError::InvalidCharacter`](crate::QrError::InvalidCharacter).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use qr2term::payload::Mode;
+    ///
+    /// assert_eq!(Mode::Numeric.invalid_position(b"0123"), None);
+    /// assert_eq!(Mode::Alphanumeric.invalid_position(b"ABc"), Some(2));
+    /// ```
+    pub fn invalid_position(self, data: &[u8]) -> Option<usize> {
+        let valid: fn(&u8) -> bool = match self {
+            Mode::Numeric => u8::is_ascii_digit,
+            Mode::Alphanumeric => |byte| {
+                byte.is_ascii_digit() || byte.is_ascii_uppercase() || b" $%*+-./:".contains(byte)
+            },
+            Mode::Auto | Mode::Byte | Mode::Gs1 => return None,
+        };
+        data.iter().position(|byte| !valid(byte))
+    }
+
+    /// Get the most compact mode encoding all of `data` in a single segment:
+    /// numeric, alphanumeric or byte mode.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use qr2term::payload::Mode;
+    ///
+    /// assert_eq!(Mode::single_segment(b"0123"), Mode::Numeric);
+    /// assert_eq!(Mode::single_segment(b"HTTPS://A.B/0123"), Mode::Alphanumeric);
+    /// assert_eq!(Mode::single_segment(b"https://a.b/"), Mode::Byte);
+    /// ```
+    pub fn single_segment(data: &[u8]) -> Self {
+        [Mode::Numeric, Mode::Alphanumeric]
+            .into_iter()
+            .find(|mode| mode.invalid_position(data).is_none())
+            .unwrap_or(Mode::Byte)
+    }
+}
+
+/// Data to encode as QR code, along with the mode to encode it with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Payload<'a> {
+    data: Cow<'a, [u8]>,
+    mode: Mode,
+    eci: Option<u32>,
+}
+
+impl<'a> Payload<'a> {
+    /// Construct a payload for the given data, using the automatic encoding mode.
+    pub fn new<D: Into<Cow<'a, [u8]>>>(data: D) -> Self {
+        Self {
+            data: data.into(),
+            mode: Mode::Auto,
+            eci: None,
+        }
+    }
+
+    /// Set the mode to encode this payload with.
+    pub fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Declare the ECI designator of the character set the data is encoded in.
+    ///
+    /// Scanners use it to interpret the bytes, instead of guessing. See
+    /// [`Charset`](crate::charset::Charset) to transcode text and set it.
+    pub fn with_eci(mut self, eci: u32) -> Self {
+        self.eci = Some(eci);
+        self
+    }
+
+    /// The data to encode.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The mode to encode the data with.
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// The declared ECI designator, if any.
+    pub fn eci(&self) -> Option<u32> {
+        self.eci
+    }
+
+    /// Overwrite the data with zeros if this payload owns it, see [`wipe`].
+    pub(crate) fn wipe(&mut self) {
+        if let Cow::Owned(data) = &mut self.data {
+            wipe(data);
+        }
+    }
+
+    /// Convert into a payload that owns its data.
+    pub fn into_owned(self) -> Payload<'static> {
+        Payload {
+            data: Cow::Owned(self.data.into_owned()),
+            mode: self.mode,
+            eci: self.eci,
+        }
+    }
+}
+
+/// Overwrite `data` with zeros, for buffers that held secrets such as API
+/// tokens or recovery codes.
+///
+/// The writes are not optimized out, even if `data` is freed right after.
+/// Copies made before, such as when a `Vec` grew, are not overwritten.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut token = b"hunter2".to_vec();
+/// qr2term::payload::wipe(&mut token);
+/// assert_eq!(token, [0; 7]);
+/// ```
+pub fn wipe(data: &mut [u8]) {
+    crate::util::wipe(data, 0);
+}
+
+/// Types that can be encoded as QR code.
+///
+/// Text is encoded with the automatic mode, binary data in byte mode.
+pub trait ToQrPayload {
+    /// Get the payload to encode for this value.
+    fn to_qr_payload(&self) -> Payload<'_>;
+}
+
+impl ToQrPayload for Payload<'_> {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        Payload {
+            data: Cow::Borrowed(&self.data),
+            mode: self.mode,
+            eci: self.eci,
+        }
+    }
+}
+
+impl ToQrPayload for str {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        Payload::new(self.as_bytes())
+    }
+}
+
+impl ToQrPayload for String {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        self.as_str().to_qr_payload()
+    }
+}
+
+impl ToQrPayload for Cow<'_, str> {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        self.as_ref().to_qr_payload()
+    }
+}
+
+impl ToQrPayload for [u8] {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        Payload::new(self).with_mode(Mode::Byte)
+    }
+}
+
+impl<const N: usize> ToQrPayload for [u8; N] {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        self.as_slice().to_qr_payload()
+    }
+}
+
+impl ToQrPayload for Vec<u8> {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        self.as_slice().to_qr_payload()
+    }
+}
+
+impl ToQrPayload for Cow<'_, [u8]> {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        self.as_ref().to_qr_payload()
+    }
+}
+
+impl<T: ToQrPayload + ?Sized> ToQrPayload for Box<T> {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        (**self).to_qr_payload()
+    }
+}
+
+impl<T: ToQrPayload + ?Sized> ToQrPayload for Rc<T> {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        (**self).to_qr_payload()
+    }
+}
+
+impl<T: ToQrPayload + ?Sized> ToQrPayload for Arc<T> {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        (**self).to_qr_payload()
+    }
+}
+
+impl<T: ToQrPayload + ?Sized> ToQrPayload for &T {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        (**self).to_qr_payload()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_modes() {
+        assert_eq!("abc".to_qr_payload().mode(), Mode::Auto);
+        assert_eq!(String::from("abc").to_qr_payload().mode(), Mode::Auto);
+        assert_eq!(b"abc".to_qr_payload().mode(), Mode::Byte);
+        assert_eq!(vec![1u8, 2, 3].to_qr_payload().mode(), Mode::Byte);
+        assert_eq!(b"abc".to_qr_payload().data(), b"abc");
+
+        // Smart pointers and Cow keep the mode of what they point to
+        let bytes: Box<[u8]> = Box::new([1, 2, 3]);
+        assert_eq!(bytes.to_qr_payload().mode(), Mode::Byte);
+        assert_eq!(
+            Cow::Borrowed(&b"abc"[..]).to_qr_payload().mode(),
+            Mode::Byte
+        );
+        assert_eq!(Rc::<str>::from("abc").to_qr_payload().mode(), Mode::Auto);
+        assert_eq!(Arc::new(vec![1u8]).to_qr_payload().mode(), Mode::Byte);
+    }
+
+    #[test]
+    fn invalid_position() {
+        assert_eq!(Mode::Numeric.invalid_position(b"0123456789"), None);
+        assert_eq!(Mode::Numeric.invalid_position(b"12 3"), Some(2));
+        assert_eq!(
+            Mode::Alphanumeric.invalid_position(b"HTTPS://A.B/$%*+-"),
+            None
+        );
+        assert_eq!(Mode::Alphanumeric.invalid_position(b"AB_"), Some(2));
+        assert_eq!(Mode::Byte.invalid_position(&[0xff]), None);
+    }
+}
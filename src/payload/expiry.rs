@@ -0,0 +1,262 @@
+//! Expiring payloads, stamped with an expiry time and a nonce.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+#[cfg(unix)]
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::{Payload, ToQrPayload};
+#[cfg(unix)]
+use crate::crypto::random_bytes;
+
+/// Size of the nonce of expiring payloads, in bytes.
+pub const EXPIRY_NONCE_LEN: usize = 8;
+
+/// Separator between the fields of an expiring payload.
+const SEPARATOR: char = '.';
+
+/// A payload in a canonical envelope with an expiry time and a nonce.
+///
+/// Constructed through [`with_expiry`](super::with_expiry), or parsed from
+/// scanned text with [`validate_expiry`](super::validate_expiry).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Expiring {
+    /// The canonical `<expiry>.<nonce>.<inner>` envelope.
+    envelope: String,
+
+    /// Offset of the inner payload in the envelope.
+    inner_start: usize,
+
+    /// Expiry time, in whole seconds.
+    expires_at: SystemTime,
+
+    /// Random nonce, unique for every envelope.
+    nonce: [u8; EXPIRY_NONCE_LEN],
+}
+
+impl Expiring {
+    /// Build the envelope for `inner`, expiring at `expires_at` seconds since
+    /// the Unix epoch.
+    fn new(inner: &str, expires_at: u64, nonce: [u8; EXPIRY_NONCE_LEN]) -> Option<Self> {
+        let mut envelope = expires_at.to_string();
+        envelope.push(SEPARATOR);
+        for byte in nonce {
+            envelope.push_str(&format!("{:02X}", byte));
+        }
+        envelope.push(SEPARATOR);
+        let inner_start = envelope.len();
+        envelope.push_str(inner);
+        Some(Self {
+            envelope,
+            inner_start,
+            expires_at: UNIX_EPOCH.checked_add(Duration::from_secs(expires_at))?,
+            nonce,
+        })
+    }
+
+    /// The canonical `<expiry>.<nonce>.<inner>` envelope.
+    pub fn as_str(&self) -> &str {
+        &self.envelope
+    }
+
+    /// The inner payload.
+    pub fn inner(&self) -> &str {
+        &self.envelope[self.inner_start..]
+    }
+
+    /// The time the payload expires at.
+    pub fn expires_at(&self) -> SystemTime {
+        self.expires_at
+    }
+
+    /// The random nonce, unique for every envelope.
+    ///
+    /// Backends refusing replays remember the nonces of accepted payloads
+    /// until they expire.
+    pub fn nonce(&self) -> [u8; EXPIRY_NONCE_LEN] {
+        self.nonce
+    }
+}
+
+impl Display for Expiring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.envelope)
+    }
+}
+
+impl ToQrPayload for Expiring {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        self.envelope.to_qr_payload()
+    }
+}
+
+/// Error returned when validating an expiring payload failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExpiryError {
+    /// The text isn't an `<expiry>.<nonce>.<inner>` envelope.
+    Malformed,
+
+    /// The payload expired.
+    Expired {
+        /// The time the payload expired at.
+        expired_at: SystemTime,
+    },
+}
+
+impl Display for ExpiryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpiryError::Malformed => f.write_str("malformed expiring payload"),
+            ExpiryError::Expired { expired_at } => {
+                let secs = expired_at
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |time| time.as_secs());
+                write!(
+                    f,
+                    "payload expired at {} seconds since the Unix epoch",
+                    secs
+                )
+            }
+        }
+    }
+}
+
+impl Error for ExpiryError {}
+
+/// Wrap `inner` in an envelope that expires `ttl` from now, rounded up to
+/// whole seconds.
+///
+/// The envelope is `<expiry>.<nonce>.<inner>`, with the expiry time in
+/// seconds since the Unix epoch and a random nonce of [`EXPIRY_NONCE_LEN`] bytes in
+/// uppercase hexadecimal. Backends check it with
+/// [`validate_expiry`](super::validate_expiry), so kiosks can show codes that
+/// are refused after a few minutes, and refuse replays by remembering the
+/// [nonces](Expiring::nonce) they accepted.
+///
+/// The envelope isn't authenticated, anyone can change the expiry time. Sign
+/// it with [`sign_text`](crate::sign::sign_text) if scanners aren't trusted.
+///
+/// Only available on Unix, which provides random nonces through
+/// `/dev/urandom`.
+///
+/// Returns an error if the expiry time can't be represented, such as for a
+/// `ttl` of [`Duration::MAX`], or if no random nonce could be obtained from
+/// the operating system.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::{Duration, SystemTime};
+///
+/// use qr2term::payload;
+///
+/// let expiring = payload::with_expiry("checkin:42", Duration::from_secs(5 * 60)).unwrap();
+/// assert!(expiring.as_str().ends_with(".checkin:42"));
+/// qr2term::print_qr(&expiring).unwrap();
+///
+/// let valid = payload::validate_expiry(expiring.as_str(), SystemTime::now()).unwrap();
+/// assert_eq!(valid.inner(), "checkin:42");
+/// ```
+#[cfg(unix)]
+pub fn with_expiry(inner: &str, ttl: Duration) -> io::Result<Expiring> {
+    let out_of_range = || io::Error::new(io::ErrorKind::InvalidInput, "expiry time out of range");
+    let expires_at = SystemTime::now()
+        .checked_add(ttl)
+        .ok_or_else(out_of_range)?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| {
+            time.as_secs() + u64::from(time.subsec_nanos() > 0)
+        });
+    let mut nonce = [0; EXPIRY_NONCE_LEN];
+    random_bytes(&mut nonce)?;
+    Expiring::new(inner, expires_at, nonce).ok_or_else(out_of_range)
+}
+
+/// Parse and validate an envelope built by [`with_expiry`](super::with_expiry).
+///
+/// Backends pass the current time as `now`, or a time slightly before it to
+/// allow for clocks running ahead on kiosks.
+///
+/// Returns an error if `envelope` is malformed, or if it expired at or
+/// before `now`.
+pub fn validate_expiry(envelope: &str, now: SystemTime) -> Result<Expiring, ExpiryError> {
+    let mut fields = envelope.splitn(3, SEPARATOR);
+    let (expiry, hex, inner) = match (fields.next(), fields.next(), fields.next()) {
+        (Some(expiry), Some(hex), Some(inner)) => (expiry, hex, inner),
+        _ => return Err(ExpiryError::Malformed),
+    };
+    if expiry.is_empty() || !expiry.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(ExpiryError::Malformed);
+    }
+    let expires_at: u64 = expiry.parse().map_err(|_| ExpiryError::Malformed)?;
+    if hex.len() != 2 * EXPIRY_NONCE_LEN || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ExpiryError::Malformed);
+    }
+    let mut nonce = [0; EXPIRY_NONCE_LEN];
+    for (i, byte) in nonce.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+    }
+
+    let expiring = Expiring::new(inner, expires_at, nonce).ok_or(ExpiryError::Malformed)?;
+    if expiring.expires_at() <= now {
+        return Err(ExpiryError::Expired {
+            expired_at: expiring.expires_at(),
+        });
+    }
+    Ok(expiring)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelope() {
+        let expiring = Expiring::new("a.b", 1_700_000_000, [0xAB; EXPIRY_NONCE_LEN]).unwrap();
+        assert_eq!(expiring.as_str(), "1700000000.ABABABABABABABAB.a.b");
+
+        let now = UNIX_EPOCH + Duration::from_secs(1_699_999_999);
+        let valid = validate_expiry(expiring.as_str(), now).unwrap();
+        assert_eq!(valid, expiring);
+        assert_eq!(valid.inner(), "a.b");
+
+        let later = now + Duration::from_secs(1);
+        assert_eq!(
+            validate_expiry(expiring.as_str(), later),
+            Err(ExpiryError::Expired { expired_at: later })
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        let now = UNIX_EPOCH;
+        for envelope in [
+            "",
+            "1700000000.ABABABABABABABAB",
+            "+1700000000.ABABABABABABABAB.a",
+            "1700000000.ABABABABABABABA.a",
+            "1700000000.ABABABABABABABAG.a",
+            "99999999999999999999.ABABABABABABABAB.a",
+            "18446744073709551615.ABABABABABABABAB.a",
+        ] {
+            assert_eq!(validate_expiry(envelope, now), Err(ExpiryError::Malformed));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unique_nonce() {
+        let ttl = Duration::from_secs(60);
+        let (a, b) = (
+            with_expiry("x", ttl).unwrap(),
+            with_expiry("x", ttl).unwrap(),
+        );
+        assert_ne!(a.nonce(), b.nonce());
+        assert!(validate_expiry(a.as_str(), SystemTime::now()).is_ok());
+
+        let err = with_expiry("x", Duration::MAX).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
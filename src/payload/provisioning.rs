@@ -0,0 +1,234 @@
+//! Canonical JSON payloads for device provisioning.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use qrcode::EcLevel;
+
+use super::{Mode, Payload, ToQrPayload};
+use crate::json::{self, JsonError, Value};
+use crate::{compress, qr, QrError};
+
+/// A device provisioning payload of canonical JSON.
+///
+/// Constructed through [`provisioning`](super::provisioning).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Provisioning {
+    /// The canonical JSON.
+    json: String,
+
+    /// Keys of the top level object.
+    keys: Vec<String>,
+
+    /// The compressed JSON, if compression was enabled and made it smaller.
+    compressed: Option<Vec<u8>>,
+}
+
+impl Provisioning {
+    /// The canonical JSON.
+    pub fn as_str(&self) -> &str {
+        &self.json
+    }
+
+    /// Whether the payload is compressed.
+    pub fn is_compressed(&self) -> bool {
+        self.compressed.is_some()
+    }
+
+    /// Set whether to compress the payload, see [`compress`](crate::compress).
+    ///
+    /// The payload is only compressed if that makes it smaller, so devices
+    /// should check scanned data with [`compress::is_compressed`].
+    pub fn compress(mut self, compress: bool) -> Self {
+        self.compressed = None;
+        if compress {
+            let compressed = compress::compress(self.json.as_bytes());
+            if compressed.len() < self.json.len() {
+                self.compressed = Some(compressed);
+            }
+        }
+        self
+    }
+
+    /// Check that the top level object has all of the given keys.
+    ///
+    /// Returns an error with the first missing key.
+    pub fn require(self, keys: &[&str]) -> Result<Self, ProvisioningError> {
+        match keys
+            .iter()
+            .find(|key| !self.keys.iter().any(|present| present == *key))
+        {
+            Some(key) => Err(ProvisioningError::MissingKey((*key).into())),
+            None => Ok(self),
+        }
+    }
+
+    /// Check that the payload fits a single QR code at the given error correction level.
+    ///
+    /// Returns an error with the payload length if it doesn't.
+    pub fn check_fits(&self, ec_level: EcLevel) -> Result<(), ProvisioningError> {
+        let payload = self.to_qr_payload();
+        match qr::check_fits(&payload, ec_level) {
+            Err(QrError::DataTooLong) => Err(ProvisioningError::TooLarge {
+                len: payload.data().len(),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for Provisioning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.json)
+    }
+}
+
+impl ToQrPayload for Provisioning {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        match &self.compressed {
+            Some(compressed) => Payload::new(&compressed[..]).with_mode(Mode::Byte),
+            None => self.json.to_qr_payload(),
+        }
+    }
+}
+
+/// Error returned when a provisioning payload is invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProvisioningError {
+    /// The JSON is invalid at the given byte position.
+    InvalidJson {
+        /// Byte position of the error.
+        position: usize,
+    },
+
+    /// The JSON is not an object.
+    NotAnObject,
+
+    /// An object has the given key more than once.
+    DuplicateKey(String),
+
+    /// The top level object is missing the given required key.
+    MissingKey(String),
+
+    /// The payload of the given length in bytes doesn't fit a single QR code.
+    TooLarge {
+        /// Length of the payload in bytes.
+        len: usize,
+    },
+}
+
+impl Display for ProvisioningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProvisioningError::InvalidJson { position } => {
+                write!(f, "invalid JSON at position {}", position)
+            }
+            ProvisioningError::NotAnObject => f.write_str("JSON is not an object"),
+            ProvisioningError::DuplicateKey(key) => write!(f, "duplicate key '{}'", key),
+            ProvisioningError::MissingKey(key) => write!(f, "missing key '{}'", key),
+            ProvisioningError::TooLarge { len } => {
+                write!(f, "payload of {} bytes doesn't fit a single QR code", len)
+            }
+        }
+    }
+}
+
+impl Error for ProvisioningError {}
+
+/// Build a device provisioning payload from a JSON object.
+///
+/// The JSON is canonicalized, so the same enrollment data always gives the
+/// same code: whitespace is removed, object keys are sorted, and strings are
+/// re-escaped with as few escapes as possible. Numbers are kept as written.
+/// Chain [`Provisioning::compress`], [`Provisioning::require`] and
+/// [`Provisioning::check_fits`] to compress and check it.
+///
+/// Returns an error if the JSON is invalid, is not an object, or has an
+/// object with duplicate keys.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::{options::EcLevel, payload};
+///
+/// let json = r#"{ "wifi": { "ssid": "lab", "psk": "hunter2" }, "server": "https://mdm.example" }"#;
+/// let enrollment = payload::provisioning(json)?.require(&["server"])?;
+/// assert_eq!(
+///     enrollment.as_str(),
+///     r#"{"server":"https://mdm.example","wifi":{"psk":"hunter2","ssid":"lab"}}"#
+/// );
+/// enrollment.check_fits(EcLevel::M)?;
+/// qr2term::print_qr(&enrollment).unwrap();
+/// # Ok::<(), qr2term::payload::ProvisioningError>(())
+/// ```
+pub fn provisioning(json: &str) -> Result<Provisioning, ProvisioningError> {
+    let value = json::parse(json).map_err(|err| match err {
+        JsonError::Invalid(position) => ProvisioningError::InvalidJson { position },
+        JsonError::DuplicateKey(key) => ProvisioningError::DuplicateKey(key),
+    })?;
+    let keys = match &value {
+        Value::Object(members) => members.iter().map(|(key, _)| key.clone()).collect(),
+        _ => return Err(ProvisioningError::NotAnObject),
+    };
+    Ok(Provisioning {
+        json: value.to_canonical(),
+        keys,
+        compressed: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid() {
+        assert_eq!(provisioning("[1]"), Err(ProvisioningError::NotAnObject));
+        assert_eq!(
+            provisioning(r#"{"a":1,}"#),
+            Err(ProvisioningError::InvalidJson { position: 7 })
+        );
+        assert_eq!(
+            provisioning(r#"{"c":1,"c":2}"#),
+            Err(ProvisioningError::DuplicateKey("c".into()))
+        );
+    }
+
+    #[test]
+    fn require() {
+        let json = provisioning(r#"{"b":{"c":1},"a":[","],"d":"x"}"#).unwrap();
+        assert_eq!(json.as_str(), r#"{"a":[","],"b":{"c":1},"d":"x"}"#);
+        assert!(json.clone().require(&["a", "d"]).is_ok());
+        assert_eq!(
+            json.require(&["a", "c"]),
+            Err(ProvisioningError::MissingKey("c".into()))
+        );
+    }
+
+    #[test]
+    fn compress_and_check_fits() {
+        let small = provisioning(r#"{"device":"sensor-0001"}"#)
+            .unwrap()
+            .compress(true);
+        assert!(!small.is_compressed());
+
+        let devices: Vec<String> = (0..300).map(|i| format!("\"sensor-{:04}\"", i)).collect();
+        let json = format!(r#"{{"devices":[{}]}}"#, devices.join(","));
+        let large = provisioning(&json).unwrap();
+        assert_eq!(
+            large.check_fits(EcLevel::H),
+            Err(ProvisioningError::TooLarge {
+                len: large.as_str().len()
+            })
+        );
+        let compressed = large.compress(true);
+        assert!(compressed.is_compressed());
+        assert_eq!(compressed.to_qr_payload().mode(), Mode::Byte);
+        assert!(compressed.check_fits(EcLevel::H).is_ok());
+        assert_eq!(
+            compress::decompress(compressed.to_qr_payload().data()).unwrap(),
+            compressed.as_str().as_bytes()
+        );
+    }
+}
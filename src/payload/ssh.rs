@@ -0,0 +1,194 @@
+//! SSH host key fingerprint payloads.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use super::{Payload, ToQrPayload};
+use crate::crypto::sha256::sha256;
+use crate::util::{decode_base64, encode_base64};
+
+/// The fingerprint of an SSH host key, in a canonical form for verification.
+///
+/// Constructed through [`ssh_fingerprint`](super::ssh_fingerprint).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SshFingerprint {
+    /// The canonical `<hostname> <key type> SHA256:<fingerprint>` line.
+    line: String,
+
+    /// Offset of the fingerprint in the line.
+    fingerprint_start: usize,
+}
+
+impl SshFingerprint {
+    /// The canonical `<hostname> <key type> SHA256:<fingerprint>` line.
+    pub fn as_str(&self) -> &str {
+        &self.line
+    }
+
+    /// The fingerprint, as shown by `ssh-keygen -l` and when first connecting.
+    pub fn fingerprint(&self) -> &str {
+        &self.line[self.fingerprint_start..]
+    }
+}
+
+impl Display for SshFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.line)
+    }
+}
+
+impl ToQrPayload for SshFingerprint {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        self.line.to_qr_payload()
+    }
+}
+
+/// Error returned when building an SSH fingerprint from invalid parts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SshKeyError {
+    /// The hostname is empty or contains whitespace.
+    InvalidHostname(String),
+
+    /// The key is not an OpenSSH public key.
+    InvalidKey,
+
+    /// The key type doesn't match the type encoded in the key.
+    KeyTypeMismatch {
+        /// The key type before the key data.
+        declared: String,
+
+        /// The key type encoded in the key data.
+        encoded: String,
+    },
+}
+
+impl Display for SshKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SshKeyError::InvalidHostname(hostname) => write!(f, "invalid hostname '{}'", hostname),
+            SshKeyError::InvalidKey => f.write_str("invalid OpenSSH public key"),
+            SshKeyError::KeyTypeMismatch { declared, encoded } => write!(
+                f,
+                "key type '{}' doesn't match the encoded key type '{}'",
+                declared, encoded
+            ),
+        }
+    }
+}
+
+impl Error for SshKeyError {}
+
+/// Build the fingerprint payload of the SSH host key of `hostname`.
+///
+/// The `key` is an OpenSSH public key line, such as the contents of
+/// `/etc/ssh/ssh_host_ed25519_key.pub`, with an optional trailing comment.
+/// The payload is `<hostname> <key type> SHA256:<fingerprint>`, with the
+/// hostname lowercased and the fingerprint computed like `ssh-keygen -l`, so
+/// admins can verify a server's key by scanning its console at first boot.
+///
+/// Returns an error if the hostname is empty or contains whitespace, or if
+/// the key is not a valid OpenSSH public key.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::payload;
+///
+/// let key = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIFBMOwLpCFjjRqr8cpqoFtcTJsV6FEsAGg2tZnKayM3w root@box";
+/// let fingerprint = payload::ssh_fingerprint("Box.example.com", key).unwrap();
+/// assert_eq!(
+///     fingerprint.as_str(),
+///     "box.example.com ssh-ed25519 SHA256:dfP6i1Y5SKukzTZgTEufFZ/ceSgc0FDEFxhnpA+nl0w"
+/// );
+/// qr2term::print_qr(&fingerprint).unwrap();
+/// ```
+pub fn ssh_fingerprint(hostname: &str, key: &str) -> Result<SshFingerprint, SshKeyError> {
+    let host = hostname.trim();
+    if host.is_empty() || host.contains(char::is_whitespace) {
+        return Err(SshKeyError::InvalidHostname(hostname.into()));
+    }
+
+    let mut fields = key.split_whitespace();
+    let (key_type, data) = match (fields.next(), fields.next()) {
+        (Some(key_type), Some(data)) => (key_type, data),
+        _ => return Err(SshKeyError::InvalidKey),
+    };
+    let blob = decode_base64(data).ok_or(SshKeyError::InvalidKey)?;
+
+    // The key data starts with the length prefixed key type
+    let encoded = blob
+        .get(..4)
+        .map(|len| u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize)
+        .and_then(|len| blob.get(4..4 + len))
+        .and_then(|encoded| std::str::from_utf8(encoded).ok())
+        .filter(|encoded| !encoded.is_empty())
+        .ok_or(SshKeyError::InvalidKey)?;
+    if encoded != key_type {
+        return Err(SshKeyError::KeyTypeMismatch {
+            declared: key_type.into(),
+            encoded: encoded.into(),
+        });
+    }
+
+    let mut line = format!("{} {} ", host.to_ascii_lowercase(), key_type);
+    let fingerprint_start = line.len();
+    line.push_str("SHA256:");
+    line.push_str(&encode_base64(&sha256(&blob)));
+    Ok(SshFingerprint {
+        line,
+        fingerprint_start,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "ssh-ed25519 \
+        AAAAC3NzaC1lZDI1NTE5AAAAIFBMOwLpCFjjRqr8cpqoFtcTJsV6FEsAGg2tZnKayM3w";
+
+    #[test]
+    fn fingerprint() {
+        let fingerprint = ssh_fingerprint(" host ", KEY).unwrap();
+        assert_eq!(
+            fingerprint.as_str(),
+            "host ssh-ed25519 SHA256:dfP6i1Y5SKukzTZgTEufFZ/ceSgc0FDEFxhnpA+nl0w"
+        );
+        assert_eq!(
+            fingerprint.fingerprint(),
+            "SHA256:dfP6i1Y5SKukzTZgTEufFZ/ceSgc0FDEFxhnpA+nl0w"
+        );
+        assert_eq!(
+            ssh_fingerprint("host", &format!("  {}  root@host\n", KEY)),
+            Ok(fingerprint)
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            ssh_fingerprint("my host", KEY),
+            Err(SshKeyError::InvalidHostname("my host".into()))
+        );
+        assert_eq!(
+            ssh_fingerprint("host", "ssh-ed25519"),
+            Err(SshKeyError::InvalidKey)
+        );
+        assert_eq!(
+            ssh_fingerprint("host", "ssh-ed25519 AAAA!"),
+            Err(SshKeyError::InvalidKey)
+        );
+        assert_eq!(
+            ssh_fingerprint("host", "ssh-ed25519 AAAAAQ=="),
+            Err(SshKeyError::InvalidKey)
+        );
+        assert_eq!(
+            ssh_fingerprint("host", &KEY.replace("ssh-ed25519", "ssh-rsa")),
+            Err(SshKeyError::KeyTypeMismatch {
+                declared: "ssh-rsa".into(),
+                encoded: "ssh-ed25519".into(),
+            })
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//! Payload templates with variable substitution.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+/// Part of a URL a variable is substituted into, determining how it is escaped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Component {
+    /// Not a URL, values are substituted as is.
+    Text,
+
+    /// The host, values must be valid host names.
+    Host,
+
+    /// The path, values are percent-encoded as a single segment.
+    Path,
+
+    /// The query, values are percent-encoded as a single name or value.
+    Query,
+
+    /// The fragment, values are percent-encoded.
+    Fragment,
+}
+
+/// Part of a template.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Part {
+    /// Literal text, copied as is.
+    Literal(String),
+
+    /// A variable, substituted with its escaped value.
+    Variable(String, Component),
+}
+
+/// A payload template with `{name}` variables.
+///
+/// Constructed through [`template`](super::template).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Names of the variables in this template, in order of appearance.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Variable(name, _) => Some(name.as_str()),
+            Part::Literal(_) => None,
+        })
+    }
+
+    /// Substitute the variables with the given `(name, value)` pairs.
+    ///
+    /// Values are escaped for the part of the URL they are substituted into,
+    /// see [`template`](super::template).
+    ///
+    /// Returns an error if a variable has no value, or a value can't be
+    /// substituted into the host.
+    pub fn render(&self, values: &[(&str, &str)]) -> Result<String, TemplateError> {
+        let mut out = String::new();
+        for part in &self.parts {
+            let (name, component) = match part {
+                Part::Literal(literal) => {
+                    out.push_str(literal);
+                    continue;
+                }
+                Part::Variable(name, component) => (name, *component),
+            };
+            let value = values
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|&(_, value)| value)
+                .ok_or_else(|| TemplateError::MissingValue(name.clone()))?;
+            match component {
+                Component::Text => out.push_str(value),
+                Component::Host => {
+                    let valid = !value.is_empty()
+                        && value
+                            .bytes()
+                            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.');
+                    if !valid {
+                        return Err(TemplateError::InvalidHost {
+                            name: name.clone(),
+                            value: value.into(),
+                        });
+                    }
+                    out.push_str(&value.to_ascii_lowercase());
+                }
+                Component::Path | Component::Query | Component::Fragment => {
+                    encode_into(&mut out, value)
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => {
+                    f.write_str(&literal.replace('{', "{{").replace('}', "}}"))?
+                }
+                Part::Variable(name, _) => write!(f, "{{{}}}", name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error returned when parsing or rendering a template fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TemplateError {
+    /// A `{` at the given byte position isn't closed by a `}`.
+    Unclosed(usize),
+
+    /// A `}` at the given byte position doesn't close a `{`.
+    Unopened(usize),
+
+    /// The variable name at the given byte position is empty or has characters
+    /// other than letters, digits, `_` and `-`.
+    InvalidName(usize),
+
+    /// A variable is in the scheme of a URL.
+    VariableInScheme(String),
+
+    /// No value was given for the variable.
+    MissingValue(String),
+
+    /// The value of a variable in the host of a URL is not a host name.
+    InvalidHost {
+        /// Name of the variable.
+        name: String,
+
+        /// The invalid value.
+        value: String,
+    },
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Unclosed(position) => write!(f, "unclosed '{{' at {}", position),
+            TemplateError::Unopened(position) => write!(f, "unopened '}}' at {}", position),
+            TemplateError::InvalidName(position) => {
+                write!(f, "invalid variable name at {}", position)
+            }
+            TemplateError::VariableInScheme(name) => {
+                write!(f, "variable '{}' can't be in the URL scheme", name)
+            }
+            TemplateError::MissingValue(name) => write!(f, "no value for variable '{}'", name),
+            TemplateError::InvalidHost { name, value } => {
+                write!(
+                    f,
+                    "value '{}' of variable '{}' is not a host name",
+                    value, name
+                )
+            }
+        }
+    }
+}
+
+impl Error for TemplateError {}
+
+/// Parse a payload template with `{name}` variables, for generating many
+/// payloads from tabular data.
+///
+/// Variable names consist of letters, digits, `_` and `-`. Use `{{` and `}}`
+/// for literal braces.
+///
+/// If the template is a URL, starting with a scheme and authority such as
+/// `https://` or with `mailto:`, `tel:` or `sms:`, values are strictly
+/// escaped for the part of the URL they are in, so no value can change the
+/// structure of the URL:
+///
+/// - in the host, values must be host names, and are lowercased
+/// - in the path, values are percent-encoded as a single segment, including `/`
+/// - in the query and fragment, values are percent-encoded including `&`, `=`
+///   and `#`
+///
+/// Other templates, such as Wi-Fi or vCard payloads, get values as is.
+///
+/// Returns an error if the template is malformed, or has a variable in the
+/// URL scheme.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::payload;
+///
+/// let template = payload::template("https://tickets.example/{event}/{id}?t={token}")?;
+/// let url = template.render(&[("event", "rust conf"), ("id", "a/1"), ("token", "x&y=z")])?;
+/// assert_eq!(url, "https://tickets.example/rust%20conf/a%2F1?t=x%26y%3Dz");
+/// # Ok::<(), qr2term::payload::TemplateError>(())
+/// ```
+pub fn template(template: &str) -> Result<Template, TemplateError> {
+    let url = url_prefix_len(template);
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    // The template so far, with variables replaced by a placeholder
+    let mut prefix = String::new();
+    let mut chars = template.char_indices().peekable();
+
+    while let Some((position, c)) = chars.next() {
+        match c {
+            '{' if chars.next_if(|&(_, c)| c == '{').is_some() => literal.push('{'),
+            '}' if chars.next_if(|&(_, c)| c == '}').is_some() => literal.push('}'),
+            '}' => return Err(TemplateError::Unopened(position)),
+            '{' => {
+                let end = template[position..]
+                    .find('}')
+                    .map(|end| position + end)
+                    .ok_or(TemplateError::Unclosed(position))?;
+                let name = &template[position + 1..end];
+                let valid = !name.is_empty()
+                    && name
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+                if !valid {
+                    return Err(TemplateError::InvalidName(position + 1));
+                }
+                while chars.next_if(|&(next, _)| next <= end).is_some() {}
+
+                prefix.push_str(&literal);
+                let component = match url {
+                    None => Component::Text,
+                    Some(len) if prefix.len() < len => {
+                        return Err(TemplateError::VariableInScheme(name.into()))
+                    }
+                    Some(len) => url_component(&prefix[len..], template[..len].ends_with("//")),
+                };
+                prefix.push('x');
+                if !literal.is_empty() {
+                    parts.push(Part::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(Part::Variable(name.into(), component));
+            }
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(Part::Literal(literal));
+    }
+    Ok(Template { parts })
+}
+
+/// Schemes of URLs without an authority that templates escape values for.
+const OPAQUE_SCHEMES: [&str; 3] = ["mailto", "tel", "sms"];
+
+/// Get the length of the scheme and `:` or `://` starting a URL `template`.
+///
+/// Returns `None` if the template is not a URL.
+fn url_prefix_len(template: &str) -> Option<usize> {
+    let (scheme, rest) = template.split_once(':')?;
+    if rest.starts_with("//") {
+        // Also a URL with a variable in the scheme, which is rejected
+        return Some(scheme.len() + 3);
+    }
+    OPAQUE_SCHEMES
+        .iter()
+        .any(|opaque| scheme.eq_ignore_ascii_case(opaque))
+        .then_some(scheme.len() + 1)
+}
+
+/// Get the component of a URL that the text `rest` after its scheme ends in.
+fn url_component(rest: &str, has_authority: bool) -> Component {
+    if rest.contains('#') {
+        Component::Fragment
+    } else if rest.contains('?') {
+        Component::Query
+    } else if has_authority && !rest.contains('/') {
+        Component::Host
+    } else {
+        Component::Path
+    }
+}
+
+/// Percent-encode all but the unreserved characters of `value` into `out`.
+fn encode_into(out: &mut String, value: &str) {
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(char::from(byte))
+            }
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let t = template("a{{b}}{c}{d_1}").unwrap();
+        assert_eq!(t.names().collect::<Vec<_>>(), ["c", "d_1"]);
+        assert_eq!(t.to_string(), "a{{b}}{c}{d_1}");
+        assert_eq!(t.render(&[("c", "&"), ("d_1", "/")]).unwrap(), "a{b}&/");
+
+        assert_eq!(template("a{b"), Err(TemplateError::Unclosed(1)));
+        assert_eq!(template("a}b"), Err(TemplateError::Unopened(1)));
+        assert_eq!(template("a{}"), Err(TemplateError::InvalidName(2)));
+        assert_eq!(template("{a b}"), Err(TemplateError::InvalidName(1)));
+    }
+
+    #[test]
+    fn url_components() {
+        let t = template("https://{host}.example/{a}/x?{b}={c}&d={d}#{e}").unwrap();
+        let values = [
+            ("host", "EU-1"),
+            ("a", "x/y z"),
+            ("b", "k=v"),
+            ("c", "a&b"),
+            ("d", "é"),
+            ("e", "#top"),
+        ];
+        assert_eq!(
+            t.render(&values).unwrap(),
+            "https://eu-1.example/x%2Fy%20z/x?k%3Dv=a%26b&d=%C3%A9#%23top"
+        );
+        assert_eq!(
+            t.render(&[("host", "evil.com/")]),
+            Err(TemplateError::InvalidHost {
+                name: "host".into(),
+                value: "evil.com/".into()
+            })
+        );
+        assert_eq!(
+            t.render(&values[..1]),
+            Err(TemplateError::MissingValue("a".into()))
+        );
+    }
+
+    #[test]
+    fn schemes() {
+        let t = template("mailto:{to}?subject={subject}").unwrap();
+        assert_eq!(
+            t.render(&[("to", "a b@x.y"), ("subject", "Hi?")]).unwrap(),
+            "mailto:a%20b%40x.y?subject=Hi%3F"
+        );
+        assert_eq!(
+            template("{scheme}://example.com"),
+            Err(TemplateError::VariableInScheme("scheme".into()))
+        );
+        let t = template("WIFI:S:{ssid};;").unwrap();
+        assert_eq!(t.render(&[("ssid", "my net")]).unwrap(), "WIFI:S:my net;;");
+        let t = template("Ticket {id}: {name}").unwrap();
+        assert_eq!(
+            t.render(&[("id", "7"), ("name", "A&B")]).unwrap(),
+            "Ticket 7: A&B"
+        );
+    }
+}
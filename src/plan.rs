@@ -0,0 +1,127 @@
+//! Dry-run planning of how a QR code would render.
+
+use crate::qr::{Qr, QrInfo};
+use crate::render::{Renderer, Style};
+use crate::{term, Error, QrOptions, ToQrPayload};
+
+/// Plan of how a QR code would be rendered, without rendering it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrPlan {
+    /// Metadata about the symbol that would be generated.
+    pub info: QrInfo,
+
+    /// Size of the current terminal as `(columns, rows)`, if known.
+    pub terminal_size: Option<(usize, usize)>,
+
+    /// Rendered size for each available style.
+    pub styles: Vec<StylePlan>,
+
+    /// The style [`Style::Auto`] picks for the current terminal.
+    pub auto_style: Style,
+}
+
+/// Rendered size of a QR code in a specific style.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StylePlan {
+    /// The render style.
+    pub style: Style,
+
+    /// Width in terminal columns, including the quiet zone.
+    pub width: usize,
+
+    /// Height in terminal rows, including the quiet zone.
+    pub height: usize,
+
+    /// Whether the code fits the current terminal, `None` if its size is unknown.
+    pub fits: Option<bool>,
+}
+
+impl QrPlan {
+    /// Get the plan for the given style.
+    pub fn style(&self, style: Style) -> Option<&StylePlan> {
+        self.styles.iter().find(|plan| plan.style == style)
+    }
+
+    /// Whether the code fits the current terminal in the given style, `None` if unknown.
+    pub fn fits(&self, style: Style) -> Option<bool> {
+        self.style(style)?.fits
+    }
+}
+
+/// Plan how `data` would be rendered with the given `options`, without rendering it.
+///
+/// Reports the symbol that would be generated, its rendered size in every
+/// [`Style`], and whether it fits the current terminal.
+///
+/// Returns an error if generating the QR code failed.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::render::Style;
+///
+/// let plan = qr2term::plan("https://rust-lang.org/", &qr2term::default_options()).unwrap();
+/// let style = if plan.fits(Style::FullBlock) == Some(true) {
+///     Style::FullBlock
+/// } else {
+///     Style::HalfBlock
+/// };
+/// ```
+pub fn plan<D: ToQrPayload>(data: D, options: &QrOptions) -> Result<QrPlan, Error> {
+    let code = crate::unchecked_code(data, options)?;
+    plan_code(&code, options, term::size())
+}
+
+/// Plan how the given `code` renders, for a terminal of the given size.
+pub(crate) fn plan_code(
+    code: &Qr,
+    options: &QrOptions,
+    terminal_size: Option<(usize, usize)>,
+) -> Result<QrPlan, Error> {
+    let matrix = crate::code_matrix(code, options)?;
+    let styles = Style::ALL
+        .iter()
+        .map(|&style| {
+            let renderer = Renderer::new(style);
+            let (width, height) = (renderer.width(&matrix), renderer.height(&matrix));
+            StylePlan {
+                style,
+                width,
+                height,
+                fits: terminal_size.map(|(columns, rows)| width <= columns && height <= rows),
+            }
+        })
+        .collect();
+
+    Ok(QrPlan {
+        info: code.info(),
+        terminal_size,
+        styles,
+        auto_style: Style::fit(&matrix, terminal_size),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plan_sizes() {
+        let options = QrOptions::new();
+        let code = crate::generate_code("qr2term", &options).unwrap();
+        let plan = plan_code(&code, &options, Some((40, 20))).unwrap();
+
+        // Version 1 is 21 modules, plus a quiet zone of 2 on each side
+        let half = plan.style(Style::HalfBlock).unwrap();
+        assert_eq!((half.width, half.height), (25, 13));
+        assert_eq!(half.fits, Some(true));
+        let full = plan.style(Style::FullBlock).unwrap();
+        assert_eq!((full.width, full.height), (50, 25));
+        assert_eq!(full.fits, Some(false));
+
+        assert_eq!(plan.auto_style, Style::HalfBlock);
+
+        let plan = plan_code(&code, &options, None).unwrap();
+        assert_eq!(plan.fits(Style::HalfBlock), None);
+    }
+}
@@ -0,0 +1,133 @@
+//! Minimal PNG encoder for module matrices.
+
+use crate::color::Rgb;
+use crate::deflate;
+#[cfg(any(feature = "batch", feature = "clipboard", feature = "service"))]
+use crate::matrix::Matrix;
+#[cfg(any(feature = "batch", feature = "clipboard", feature = "service"))]
+use crate::render::{Color, QrDark};
+
+/// The PNG file signature.
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// Encode `matrix` as 8-bit grayscale PNG image, drawing every module as a
+/// square of `scale` by `scale` pixels.
+#[cfg(any(feature = "batch", feature = "clipboard", feature = "service"))]
+pub fn encode(matrix: &Matrix<Color>, scale: usize) -> Vec<u8> {
+    let size = matrix.size() * scale;
+    let pixels = matrix.pixels();
+
+    // Scanlines, each prefixed with filter type 0
+    let mut raw = Vec::with_capacity((size + 1) * size);
+    for row in 0..size {
+        raw.push(0);
+        let modules = &pixels[row / scale * matrix.size()..][..matrix.size()];
+        for &module in modules {
+            let gray = if module == QrDark { 0 } else { 255 };
+            raw.extend(std::iter::repeat(gray).take(scale));
+        }
+    }
+    // Grayscale
+    finish(size, 0, &raw)
+}
+
+/// Encode a matrix of `modules` by `modules` as 8-bit truecolor PNG image,
+/// drawing the module at every row and column as a square of `scale` by
+/// `scale` pixels in the `color` it maps to.
+pub fn encode_rgb<F: Fn(usize, usize) -> Rgb>(modules: usize, scale: usize, color: F) -> Vec<u8> {
+    let size = modules * scale;
+    let mut raw = Vec::with_capacity((size * 3 + 1) * size);
+    for row in 0..size {
+        raw.push(0);
+        for col in 0..modules {
+            let rgb = color(row / scale, col);
+            for _ in 0..scale {
+                raw.extend_from_slice(&rgb);
+            }
+        }
+    }
+    // Truecolor
+    finish(size, 2, &raw)
+}
+
+/// Wrap the filtered scanlines `raw` of a `size` by `size` image of the
+/// given color type in a PNG file.
+fn finish(size: usize, color_type: u8, raw: &[u8]) -> Vec<u8> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(size as u32).to_be_bytes());
+    header.extend_from_slice(&(size as u32).to_be_bytes());
+    // Bit depth 8, deflate, adaptive filtering, no interlacing
+    header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+
+    // Zlib stream around the raw deflate data
+    let mut data = vec![0x78, 0x01];
+    data.extend(deflate::deflate(raw));
+    data.extend_from_slice(&adler32(raw).to_be_bytes());
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &data);
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Append a chunk of the given type, with its length and checksum.
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Get the CRC-32 checksum of `data`, as used by PNG chunks.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ u32::from(byte), |crc, _| {
+            (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+        })
+    })
+}
+
+/// Get the Adler-32 checksum of `data`, as used by zlib streams.
+fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + u32::from(byte)) % 65521;
+        (a, (b + a) % 65521)
+    });
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[cfg(any(feature = "batch", feature = "clipboard", feature = "service"))]
+    #[test]
+    fn encode_image() {
+        use crate::render::QrLight;
+
+        let matrix = Matrix::new(vec![QrDark, QrLight, QrLight, QrDark]);
+        let png = encode(&matrix, 3);
+        assert_eq!(png[..8], SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(png[16..24], [0, 0, 0, 6, 0, 0, 0, 6]);
+        assert_eq!(&png[png.len() - 12..], b"\0\0\0\0IEND\xAE\x42\x60\x82");
+
+        // Image data is a zlib stream of the scanlines
+        let len = u32::from_be_bytes([png[33], png[34], png[35], png[36]]) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let data = &png[41..41 + len];
+        let raw = deflate::inflate(&data[2..len - 4]).unwrap();
+        assert_eq!(raw.len(), 6 * 7);
+        assert_eq!(raw[..7], [0, 0, 0, 0, 255, 255, 255]);
+        assert_eq!(raw[21..28], [0, 255, 255, 255, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,120 @@
+//! Full screen presentation of QR codes.
+
+use std::io::{self, Write};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event},
+    execute, queue,
+    style::Print,
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+    tty::IsTty,
+};
+
+use crate::matrix::Matrix;
+use crate::render::{Color, Renderer, Style};
+use crate::term::RawMode;
+use crate::{default_options, Error, QrOptions, ToQrPayload};
+
+/// Show the given `data` as QR code on its own screen, until a key is pressed.
+///
+/// Uses the process wide [`default_options`].
+///
+/// Returns an error if generating the QR code, or using the terminal failed.
+pub fn present_qr<D: ToQrPayload>(data: D) -> Result<(), Error> {
+    present_qr_with_options(data, &default_options())
+}
+
+/// Show the given `data` as QR code on its own screen until a key is pressed,
+/// using the given `options`.
+///
+/// Switches to the terminal's alternate screen, and draws the code in its
+/// center, re-centering it when the terminal is resized. Once a key is
+/// pressed the previous screen contents are restored.
+///
+/// Returns an error if generating the QR code, or using the terminal failed,
+/// or [`Error::NotATerminal`] if stdout is not a terminal.
+pub fn present_qr_with_options<D: ToQrPayload>(data: D, options: &QrOptions) -> Result<(), Error> {
+    let mut stdout = io::stdout();
+    if !stdout.is_tty() {
+        return Err(Error::NotATerminal);
+    }
+    let matrix = crate::code_matrix(&crate::generate_code(data, options)?, options)?;
+
+    let _screen = AlternateScreen::enter()?;
+    let _raw = RawMode::enable()?;
+    let mut size = terminal::size()?;
+    loop {
+        draw_centered(&mut stdout, &matrix, options, size)?;
+        match event::read()? {
+            Event::Resize(columns, rows) => size = (columns, rows),
+            Event::Key(_) => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+/// Clear the screen, and draw `matrix` in its center.
+///
+/// Uses the style and theme configured in `options`. The [`Style::Auto`]
+/// style is resolved for the given terminal `size`, as `(columns, rows)`.
+fn draw_centered<W: Write>(
+    target: &mut W,
+    matrix: &Matrix<Color>,
+    options: &QrOptions,
+    (columns, rows): (u16, u16),
+) -> io::Result<()> {
+    let style = match options.style {
+        Style::Auto => Style::fit(matrix, Some((columns as usize, rows as usize))),
+        style => style,
+    };
+    let renderer = Renderer::new(style).theme(options.theme);
+    let mut buf = Vec::new();
+    renderer.render(matrix, &mut buf)?;
+    let output = String::from_utf8_lossy(&buf);
+
+    let left = (columns as usize).saturating_sub(renderer.width(matrix)) / 2;
+    let top = (rows as usize).saturating_sub(renderer.height(matrix)) / 2;
+    queue!(target, Clear(ClearType::All))?;
+    for (i, line) in output.lines().enumerate() {
+        queue!(target, MoveTo(left as u16, (top + i) as u16), Print(line))?;
+    }
+    target.flush()
+}
+
+/// Guard switching to the alternate screen with the cursor hidden, until dropped.
+struct AlternateScreen;
+
+impl AlternateScreen {
+    fn enter() -> io::Result<Self> {
+        execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+        Ok(AlternateScreen)
+    }
+}
+
+impl Drop for AlternateScreen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qr::Qr;
+
+    #[test]
+    fn draw_centered_offsets() {
+        let options = QrOptions::new().style(Style::HalfBlock);
+        let matrix = crate::code_matrix(&Qr::from("qr2term").unwrap(), &options).unwrap();
+        assert_eq!(matrix.size(), 25);
+
+        // Half block code of 25 columns by 13 rows
+        let mut buf = Vec::new();
+        draw_centered(&mut buf, &matrix, &options, (80, 25)).unwrap();
+        let output = String::from_utf8(buf).unwrap();
+        assert!(output.starts_with("\x1b[2J\x1b[7;28H"));
+        assert!(output.contains("\x1b[19;28H"));
+        assert!(!output.contains("\x1b[20;28H"));
+    }
+}
@@ -0,0 +1,134 @@
+//! Named sets of options, so tools can render codes alike.
+//!
+//! The built-in profiles are [`PRESENTATION`] and [`CI`]. With the `config`
+//! feature, config files can define more profiles, or override settings of
+//! the built-in ones, in `[profiles.NAME]` tables:
+//!
+//! ```toml
+//! [profiles.presentation]
+//! theme = "solarized-dark"
+//!
+//! [profiles.badge]
+//! style = "half"
+//! ec_level = "H"
+//! ```
+//!
+//! Construct options from a profile with
+//! [`QrOptions::from_profile`](crate::QrOptions::from_profile).
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+#[cfg(feature = "config")]
+use crate::config::{Config, ConfigError};
+use crate::options::EcLevel;
+use crate::render::Style;
+use crate::theme::Theme;
+use crate::QrOptions;
+
+/// Profile for showing codes on projectors and shared screens: full blocks in
+/// high contrast, with a quiet zone of 4 modules and error correction level
+/// Q, to scan from across a room.
+pub const PRESENTATION: &str = "presentation";
+
+/// Profile for CI logs: the [`CiLog`](Style::CiLog) style in high contrast,
+/// which log viewers' color schemes leave alone, resetting colors after the
+/// code so they don't leak into later log lines.
+pub const CI: &str = "ci";
+
+/// Names of all built-in profiles.
+pub const BUILTIN: [&str; 2] = [PRESENTATION, CI];
+
+/// Apply the built-in profile `name` to `options`.
+///
+/// Returns `None` if there is no built-in profile with that name.
+pub fn builtin(name: &str, options: QrOptions) -> Option<QrOptions> {
+    match name {
+        PRESENTATION => Some(
+            options
+                .style(Style::FullBlock)
+                .theme(Theme::HighContrast)
+                .quiet_zone(4)
+                .ec_level(EcLevel::Q),
+        ),
+        CI => Some(
+            options
+                .style(Style::CiLog)
+                .theme(Theme::HighContrast)
+                .reset(true),
+        ),
+        _ => None,
+    }
+}
+
+/// Apply the profile `name` to `options`.
+///
+/// With the `config` feature, the profile is looked up in the config file at
+/// its default [`path`](crate::config::path) too, whose settings override
+/// those of a built-in profile with the same name.
+pub fn apply(name: &str, options: QrOptions) -> Result<QrOptions, ProfileError> {
+    #[cfg(feature = "config")]
+    {
+        Config::load()?.apply_profile(name, options)
+    }
+    #[cfg(not(feature = "config"))]
+    {
+        builtin(name, options).ok_or_else(|| ProfileError::Unknown(name.into()))
+    }
+}
+
+/// Error returned when a profile couldn't be applied.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ProfileError {
+    /// There is no profile with the given name.
+    Unknown(String),
+
+    /// Reading the config file defining profiles failed.
+    #[cfg(feature = "config")]
+    Config(ConfigError),
+}
+
+impl Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::Unknown(name) => write!(f, "unknown profile '{}'", name),
+            #[cfg(feature = "config")]
+            ProfileError::Config(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for ProfileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProfileError::Unknown(_) => None,
+            #[cfg(feature = "config")]
+            ProfileError::Config(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(feature = "config")]
+impl From<ConfigError> for ProfileError {
+    fn from(err: ConfigError) -> Self {
+        ProfileError::Config(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_profiles() {
+        let options = builtin(PRESENTATION, QrOptions::new()).unwrap();
+        assert_eq!(options.style, Style::FullBlock);
+        assert_eq!(options.quiet_zone, 4);
+        let options = builtin(CI, QrOptions::new().quiet_zone(1)).unwrap();
+        assert_eq!(options.style, Style::CiLog);
+        assert_eq!(options.quiet_zone, 1);
+        assert!(options.reset);
+        assert_eq!(builtin("Presentation", QrOptions::new()), None);
+    }
+}
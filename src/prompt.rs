@@ -0,0 +1,99 @@
+//! Printing codes above the prompt of interactive shells.
+//!
+//! Line editors such as `reedline` and `rustyline` own the prompt line while
+//! reading input, and keep the terminal in raw mode. Printing a code straight
+//! to stdout then garbles the line being edited. Both editors provide an
+//! external printer, which prints text above the prompt and repaints it, for
+//! which [`render_for_printer`] renders the code:
+//!
+//! ```rust,ignore
+//! let printer = reedline::ExternalPrinter::default();
+//! let mut editor = reedline::Reedline::create().with_external_printer(printer.clone());
+//!
+//! let code = qr2term::prompt::render_for_printer(login_url, &options)?;
+//! printer.print(code)?;
+//! ```
+//!
+//! Shells drawing their own prompt use [`print_above_prompt`] instead, which
+//! redraws the prompt line below the code and restores the cursor in it.
+
+use std::io::Write;
+
+use crate::options::RawMode;
+use crate::{ansi, Error, QrOptions, ToQrPayload};
+
+/// Render `data` as QR code for the external printer of a line editor, such
+/// as those of `reedline` and `rustyline`.
+///
+/// The code has `\r\n` line endings, as editors keep the terminal in raw
+/// mode, no trailing line ending, as printers add one, and resets colors
+/// after it.
+///
+/// Returns an error if generating or rendering the QR code failed.
+///
+/// # Examples
+///
+/// ```rust
+/// let code = qr2term::prompt::render_for_printer("qr2term", &qr2term::QrOptions::new()).unwrap();
+/// assert!(!code.ends_with('\n'));
+/// ```
+pub fn render_for_printer<D: ToQrPayload>(data: D, options: &QrOptions) -> Result<String, Error> {
+    let options = QrOptions {
+        raw_mode: RawMode::Enabled,
+        trailing_newline: false,
+        reset: true,
+        ..options.clone()
+    };
+    crate::try_generate_qr_string_with_options(data, &options)
+}
+
+/// Print `data` as QR code above the prompt line the cursor is on, to the
+/// given writer.
+///
+/// The prompt line is cleared and the code printed in its place, after which
+/// the `prompt` line is printed again with the cursor put back at `cursor`
+/// columns from its start. `prompt` holds the full line as shown, such as the
+/// prompt followed by the input typed so far, and must fit a single terminal
+/// line. Works whether the terminal is in raw mode or not.
+///
+/// Returns an error if generating the QR code or writing it failed.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut stdout = std::io::stdout();
+/// let options = qr2term::QrOptions::new();
+/// qr2term::prompt::print_above_prompt("qr2term", &options, "> ls", 4, &mut stdout).unwrap();
+/// ```
+pub fn print_above_prompt<D: ToQrPayload, W: Write>(
+    data: D,
+    options: &QrOptions,
+    prompt: &str,
+    cursor: usize,
+    target: &mut W,
+) -> Result<(), Error> {
+    // Render first, so failing leaves the prompt line untouched
+    let code = render_for_printer(data, options)?;
+    write!(target, "\r{}{}\r\n{}\r", ansi::CLEAR_LINE, code, prompt)?;
+    if cursor > 0 {
+        write!(target, "\x1B[{}C", cursor)?;
+    }
+    Ok(target.flush()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn above_prompt() {
+        let options = QrOptions::new();
+        let mut buf = Vec::new();
+        print_above_prompt("qr2term", &options, "> ls -l", 4, &mut buf).unwrap();
+        let output = String::from_utf8(buf).unwrap();
+
+        let code = render_for_printer("qr2term", &options).unwrap();
+        assert_eq!(output, format!("\r\x1B[2K{}\r\n> ls -l\r\x1B[4C", code));
+        assert!(!code.replace("\r\n", "").contains('\n'));
+    }
+}
@@ -1,29 +1,288 @@
 //! QR code type.
 
-use qrcode::{types::Color, QrCode};
+use std::fmt::{self, Display};
+
+use qrcode::canvas::{Canvas, MaskPattern};
+pub use qrcode::types::Mode as SegmentMode;
+pub use qrcode::Version;
+use qrcode::{bits::Bits, ec, optimize::Parser, types::Color, EcLevel, QrCode};
 
 use super::QrError;
+use crate::matrix::Arena;
+use crate::payload::{Mode, Payload};
 use crate::Matrix;
 
+/// Coordinates of the main format information modules, most significant bit first.
+const FORMAT_INFO_COORDS: [(usize, usize); 15] = [
+    (0, 8),
+    (1, 8),
+    (2, 8),
+    (3, 8),
+    (4, 8),
+    (5, 8),
+    (7, 8),
+    (8, 8),
+    (8, 7),
+    (8, 5),
+    (8, 4),
+    (8, 3),
+    (8, 2),
+    (8, 1),
+    (8, 0),
+];
+
+/// Mask XOR-ed with the format information.
+const FORMAT_INFO_MASK: u16 = 0b101_0100_0001_0010;
+
 /// Raw QR code.
 #[allow(missing_debug_implementations)]
 pub struct Qr {
-    code: QrCode,
+    colors: Vec<Color>,
+    version: Version,
+    ec_level: EcLevel,
+    mode: SegmentMode,
 }
 
 impl Qr {
     /// Construct a new QR code.
     pub fn from<D: AsRef<[u8]>>(data: D) -> Result<Self, QrError> {
+        Self::from_payload(&Payload::new(data.as_ref()), EcLevel::M)
+    }
+
+    /// Construct a new QR code for the given payload and error correction level.
+    pub fn from_payload(payload: &Payload, ec_level: EcLevel) -> Result<Self, QrError> {
+        let (code, mode) = match (payload.mode(), payload.eci()) {
+            (Mode::Auto, None) => {
+                let code = QrCode::with_error_correction_level(payload.data(), ec_level)?;
+                let mode = Parser::new(payload.data())
+                    .optimize(code.version())
+                    .map(|segment| segment.mode)
+                    .fold(SegmentMode::Numeric, SegmentMode::max);
+                (code, mode)
+            }
+            _ => return Self::from_payload_min_version(payload, ec_level, 1),
+        };
+        Ok(Self::from_code(code, mode))
+    }
+
+    /// Construct a new QR code for the given payload, in at least the given version.
+    pub(crate) fn from_payload_min_version(
+        payload: &Payload,
+        ec_level: EcLevel,
+        min_version: i16,
+    ) -> Result<Self, QrError> {
+        let (bits, mode) = encode_bits(payload, ec_level, min_version)?;
+        let code = QrCode::with_bits(bits, ec_level)?;
+        Ok(Self::from_code(code, mode))
+    }
+
+    /// Construct a QR code from a symbol generated by the `qrcode` crate.
+    fn from_code(code: QrCode, mode: SegmentMode) -> Self {
+        Self {
+            version: code.version(),
+            ec_level: code.error_correction_level(),
+            colors: code.into_colors(),
+            mode,
+        }
+    }
+
+    /// Construct a new QR code for the given payload, in the smallest version
+    /// it fits, with the given `mask` instead of the one with the lowest
+    /// penalty score.
+    pub(crate) fn from_payload_with_mask(
+        payload: &Payload,
+        ec_level: EcLevel,
+        mask: MaskPattern,
+    ) -> Result<Self, QrError> {
+        let (bits, mode) = encode_bits(payload, ec_level, 1)?;
+        let version = bits.version();
+        let (data, ec) = ec::construct_codewords(&bits.into_bytes(), version, ec_level)?;
+        let mut canvas = Canvas::new(version, ec_level);
+        canvas.draw_all_functional_patterns();
+        canvas.draw_data(&data, &ec);
+        canvas.apply_mask(mask);
         Ok(Self {
-            // TODO: error handle here!
-            code: QrCode::new(data.as_ref())?,
+            colors: canvas.into_colors(),
+            version,
+            ec_level,
+            mode,
         })
     }
 
     /// Create pixel matrix from this QR code.
     pub fn to_matrix(&self) -> Matrix<Color> {
-        Matrix::new(self.code.to_colors())
+        Matrix::new(self.colors.clone())
+    }
+
+    /// Create pixel matrix from this QR code, in a buffer of the `arena`.
+    pub fn to_matrix_in(&self, arena: &mut Arena<Color>) -> Matrix<Color> {
+        arena.matrix(&self.colors).expect("QR codes are square")
+    }
+
+    /// Set the modules in the rectangle of `width` by `height` modules, with
+    /// its top left corner at column `x` of row `y`, to light.
+    pub(crate) fn blank(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        let size = self.width();
+        for row in self.colors.chunks_mut(size).skip(y).take(height) {
+            row.iter_mut()
+                .skip(x)
+                .take(width)
+                .for_each(|module| *module = Color::Light);
+        }
+    }
+
+    /// Get metadata about the generated symbol.
+    pub fn info(&self) -> QrInfo {
+        QrInfo {
+            version: self.version,
+            ec_level: self.ec_level,
+            mask: self.mask(),
+            module_count: self.width(),
+            mode: self.mode,
+        }
+    }
+
+    /// Width of the symbol in modules.
+    fn width(&self) -> usize {
+        self.version.width() as usize
+    }
+
+    /// Read the mask pattern reference that was chosen from the format information.
+    fn mask(&self) -> u8 {
+        let width = self.width();
+        let format_info = FORMAT_INFO_COORDS.iter().fold(0u16, |bits, &(x, y)| {
+            (bits << 1) | (self.colors[y * width + x] == Color::Dark) as u16
+        });
+        ((format_info ^ FORMAT_INFO_MASK) >> 10) as u8 & 0b111
+    }
+}
+
+/// Metadata about a generated QR code symbol.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct QrInfo {
+    /// Symbol version, determining its size.
+    pub version: Version,
+
+    /// Error correction level.
+    pub ec_level: EcLevel,
+
+    /// Mask pattern reference, from 0 to 7.
+    pub mask: u8,
+
+    /// Width and height of the symbol in modules, excluding the quiet zone.
+    pub module_count: usize,
+
+    /// Most general mode used to encode the data segments.
+    pub mode: SegmentMode,
+}
+
+impl QrInfo {
+    /// Name of the symbol version: its number, prefixed with `M` for Micro QR codes.
+    pub fn version_name(&self) -> String {
+        match self.version {
+            Version::Normal(version) => version.to_string(),
+            Version::Micro(version) => format!("M{}", version),
+        }
+    }
+}
+
+impl Display for QrInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "version {}, EC level {:?}, mask {}, {}x{} modules, {:?} mode",
+            self.version_name(),
+            self.ec_level,
+            self.mask,
+            self.module_count,
+            self.module_count,
+            self.mode,
+        )
+    }
+}
+
+/// Check whether `payload` fits a single QR code, without building the symbol.
+pub(crate) fn check_fits(payload: &Payload, ec_level: EcLevel) -> Result<(), QrError> {
+    match (payload.mode(), payload.eci()) {
+        (Mode::Auto, None) => qrcode::bits::encode_auto(payload.data(), ec_level).map(|_| ()),
+        _ => encode_bits(payload, ec_level, 1).map(|_| ()),
+    }
+}
+
+/// Encode `payload` in the smallest version it fits, starting at `min_version`.
+///
+/// Returns the bits along with the most general segment mode used.
+fn encode_bits(
+    payload: &Payload,
+    ec_level: EcLevel,
+    min_version: i16,
+) -> Result<(Bits, SegmentMode), QrError> {
+    let (data, mode) = (payload.data(), payload.mode());
+    if mode.invalid_position(data).is_some() {
+        return Err(QrError::InvalidCharacter);
+    }
+
+    for version in min_version..=40 {
+        let mut bits = Bits::new(Version::Normal(version));
+        let result = match payload.eci() {
+            Some(eci) => bits.push_eci_designator(eci),
+            None => Ok(()),
+        };
+        let result = result.and_then(|_| match mode {
+            Mode::Auto => push_optimal_data(&mut bits, data),
+            Mode::Numeric => bits.push_numeric_data(data).map(|_| SegmentMode::Numeric),
+            Mode::Alphanumeric => bits
+                .push_alphanumeric_data(data)
+                .map(|_| SegmentMode::Alphanumeric),
+            Mode::Byte => bits.push_byte_data(data).map(|_| SegmentMode::Byte),
+            Mode::Gs1 => push_gs1_data(&mut bits, data),
+        });
+        match result.and_then(|mode| bits.push_terminator(ec_level).map(|_| mode)) {
+            Ok(mode) => return Ok((bits, mode)),
+            Err(QrError::DataTooLong) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Err(QrError::DataTooLong)
+}
+
+/// Push `data` in optimal segments, returning the most general segment mode used.
+fn push_optimal_data(bits: &mut Bits, data: &[u8]) -> Result<SegmentMode, QrError> {
+    let segments: Vec<_> = Parser::new(data).optimize(bits.version()).collect();
+    bits.push_segments(data, segments.iter().copied())?;
+    Ok(segments
+        .iter()
+        .map(|segment| segment.mode)
+        .fold(SegmentMode::Numeric, SegmentMode::max))
+}
+
+/// Push a GS1 element string with FNC1 in first position, in optimal segments.
+///
+/// Group separators are encoded as-is in byte segments, but as `%` in
+/// alphanumeric segments, where a literal `%` is escaped as `%%`.
+fn push_gs1_data(bits: &mut Bits, data: &[u8]) -> Result<SegmentMode, QrError> {
+    bits.push_fnc1_first_position()?;
+
+    let mut mode = SegmentMode::Numeric;
+    for segment in Parser::new(data).optimize(bits.version()) {
+        let slice = &data[segment.begin..segment.end];
+        match segment.mode {
+            SegmentMode::Alphanumeric => {
+                let escaped: Vec<u8> = slice
+                    .iter()
+                    .flat_map(|byte| match byte {
+                        b'%' => &b"%%"[..],
+                        _ => std::slice::from_ref(byte),
+                    })
+                    .copied()
+                    .collect();
+                bits.push_alphanumeric_data(&escaped)
+            }
+            _ => bits.push_segments(data, std::iter::once(segment)),
+        }?;
+        mode = mode.max(segment.mode);
     }
+    Ok(mode)
 }
 
 #[cfg(test)]
@@ -34,6 +293,109 @@ mod tests {
     #[test]
     #[should_panic]
     fn print_qr_too_long() {
-        Qr::from(&String::from_utf8(vec![b'a'; 8000]).unwrap()).unwrap();
+        Qr::from(String::from_utf8(vec![b'a'; 8000]).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn info() {
+        let qr = Qr::from_payload(&Payload::new(&b"HELLO WORLD"[..]), EcLevel::Q).unwrap();
+        let info = qr.info();
+        assert_eq!(info.version, Version::Normal(1));
+        assert_eq!(info.ec_level, EcLevel::Q);
+        assert_eq!(info.module_count, 21);
+        assert_eq!(info.mode, SegmentMode::Alphanumeric);
+        assert_eq!(info.version_name(), "1");
+
+        // Check the mask read back against the one chosen by penalty score
+        for (data, ec_level) in [
+            ("01234567", EcLevel::H),
+            ("https://rust-lang.org/", EcLevel::L),
+        ] {
+            let code = Qr::from_payload(&Payload::new(data.as_bytes()), ec_level).unwrap();
+            let bits = qrcode::bits::encode_auto(data.as_bytes(), ec_level).unwrap();
+            let version = bits.version();
+            let data = bits.into_bytes();
+            let (data, ec) = qrcode::ec::construct_codewords(&data, version, ec_level).unwrap();
+            let mut canvas = qrcode::canvas::Canvas::new(version, ec_level);
+            canvas.draw_all_functional_patterns();
+            canvas.draw_data(&data, &ec);
+            let mut masked = canvas.clone();
+            masked.apply_mask(mask_pattern(code.info().mask));
+            assert_eq!(masked.into_colors(), code.colors);
+        }
+    }
+
+    fn mask_pattern(mask: u8) -> qrcode::canvas::MaskPattern {
+        use qrcode::canvas::MaskPattern::*;
+        [
+            Checkerboard,
+            HorizontalLines,
+            VerticalLines,
+            DiagonalLines,
+            LargeCheckerboard,
+            Fields,
+            Diamonds,
+            Meadow,
+        ][mask as usize]
+    }
+
+    #[test]
+    fn byte_mode_payload() {
+        let payload = Payload::new(&b"0123456789"[..]).with_mode(Mode::Byte);
+        let byte = Qr::from_payload(&payload, EcLevel::M).unwrap();
+        assert_eq!(byte.info().version, Version::Normal(1));
+        assert_eq!(byte.info().mode, SegmentMode::Byte);
+
+        let payload = Payload::new(vec![b'a'; 3000]).with_mode(Mode::Byte);
+        assert_eq!(
+            Qr::from_payload(&payload, EcLevel::L).err(),
+            Some(QrError::DataTooLong)
+        );
+    }
+
+    #[test]
+    fn gs1_payload() {
+        let payload = Payload::new(&b"AB%CD"[..]).with_mode(Mode::Gs1);
+        let (bits, mode) = encode_bits(&payload, EcLevel::M, 1).unwrap();
+        assert_eq!(mode, SegmentMode::Alphanumeric);
+
+        // FNC1 in first position, with the literal percent sign escaped
+        let mut expected = Bits::new(Version::Normal(1));
+        expected.push_fnc1_first_position().unwrap();
+        expected.push_alphanumeric_data(b"AB%%CD").unwrap();
+        expected.push_terminator(EcLevel::M).unwrap();
+        assert_eq!(bits.into_bytes(), expected.into_bytes());
+    }
+
+    #[test]
+    fn forced_mode_payload() {
+        let payload = Payload::new(&b"0123456789"[..]).with_mode(Mode::Numeric);
+        let numeric = Qr::from_payload(&payload, EcLevel::M).unwrap();
+        assert_eq!(numeric.info().mode, SegmentMode::Numeric);
+
+        let payload = Payload::new(&b"HELLO WORLD"[..]).with_mode(Mode::Alphanumeric);
+        let alphanumeric = Qr::from_payload(&payload, EcLevel::M).unwrap();
+        assert_eq!(alphanumeric.info().mode, SegmentMode::Alphanumeric);
+
+        for (data, mode) in [("12a", Mode::Numeric), ("hello", Mode::Alphanumeric)] {
+            let payload = Payload::new(data.as_bytes()).with_mode(mode);
+            assert_eq!(
+                Qr::from_payload(&payload, EcLevel::M).err(),
+                Some(QrError::InvalidCharacter)
+            );
+        }
+    }
+
+    #[test]
+    fn eci_payload() {
+        let payload = Payload::new(&b"Gr\xFC\xDFe"[..]).with_eci(3);
+        let (bits, mode) = encode_bits(&payload, EcLevel::M, 1).unwrap();
+        assert_eq!(mode, SegmentMode::Byte);
+
+        let mut expected = Bits::new(Version::Normal(1));
+        expected.push_eci_designator(3).unwrap();
+        expected.push_byte_data(payload.data()).unwrap();
+        expected.push_terminator(EcLevel::M).unwrap();
+        assert_eq!(bits.into_bytes(), expected.into_bytes());
     }
 }
@@ -0,0 +1,164 @@
+//! Process wide print queue, so codes printed from many threads don't mix.
+//!
+//! Threads printing through [`print_qr_locked`](crate::print_qr_locked) render
+//! their code first, and then take turns writing it to stdout in the order
+//! they asked. Output surrounding a code, such as a caption, is kept next to
+//! it by holding the [`TerminalGuard`] from [`lock_terminal`] while printing
+//! both:
+//!
+//! ```rust
+//! use std::io::Write;
+//!
+//! let mut terminal = qr2term::queue::lock_terminal();
+//! writeln!(terminal, "Scan to pair device 1:").unwrap();
+//! qr2term::print_qr_locked("pair:1").unwrap();
+//! ```
+
+use std::fmt;
+use std::io::{self, StdoutLock, Write};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+
+/// The print queue, handing out turns in the order they were asked for.
+static QUEUE: Mutex<Queue> = Mutex::new(Queue {
+    next: 0,
+    serving: 0,
+    owner: None,
+    depth: 0,
+});
+
+/// Signaled whenever a turn ends.
+static TURN_ENDED: Condvar = Condvar::new();
+
+/// State of the print queue.
+struct Queue {
+    /// Ticket handed to the next thread asking for a turn.
+    next: u64,
+
+    /// Ticket of the thread whose turn it is.
+    serving: u64,
+
+    /// The thread whose turn it is, if any.
+    owner: Option<ThreadId>,
+
+    /// Number of guards the owning thread holds.
+    depth: usize,
+}
+
+/// Lock the queue state, ignoring poisoning as the state is always consistent.
+fn queue() -> MutexGuard<'static, Queue> {
+    QUEUE.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Guard holding the turn to print to the terminal, released when dropped.
+///
+/// Writes go to stdout. While held, no other thread prints through the queue,
+/// or writes to stdout at all, as the guard holds the stdout lock too. The
+/// guard is reentrant: the holding thread may lock the terminal and print
+/// through the queue again.
+pub struct TerminalGuard {
+    stdout: StdoutLock<'static>,
+}
+
+impl Write for TerminalGuard {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stdout.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = self.stdout.flush();
+        let mut queue = queue();
+        queue.depth -= 1;
+        if queue.depth == 0 {
+            queue.owner = None;
+            queue.serving += 1;
+            TURN_ENDED.notify_all();
+        }
+    }
+}
+
+impl fmt::Debug for TerminalGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TerminalGuard").finish_non_exhaustive()
+    }
+}
+
+/// Wait for the turn to print to the terminal, and hold it until the
+/// returned guard is dropped.
+///
+/// Turns are handed out in the order threads ask for them. Returns right
+/// away if the current thread already holds the turn. Don't call this while
+/// holding the lock of [`io::stdout`], as the turn's owner may be waiting for
+/// it.
+pub fn lock_terminal() -> TerminalGuard {
+    let current = thread::current().id();
+    let mut queue = queue();
+    if queue.owner == Some(current) {
+        queue.depth += 1;
+    } else {
+        let ticket = queue.next;
+        queue.next += 1;
+        while queue.serving != ticket {
+            queue = TURN_ENDED
+                .wait(queue)
+                .unwrap_or_else(|err| err.into_inner());
+        }
+        queue.owner = Some(current);
+        queue.depth = 1;
+    }
+    drop(queue);
+    TerminalGuard {
+        stdout: io::stdout().lock(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// Turns of different threads never overlap.
+    #[test]
+    fn exclusive() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let events = Arc::clone(&events);
+                thread::spawn(move || {
+                    for _ in 0..20 {
+                        let _terminal = lock_terminal();
+                        events.lock().unwrap().push(i);
+                        thread::yield_now();
+                        events.lock().unwrap().push(i);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 8 * 20 * 2);
+        assert!(events.chunks(2).all(|turn| turn[0] == turn[1]));
+    }
+
+    /// The thread holding the turn may lock again, without waiting on itself.
+    #[test]
+    fn reentrant() {
+        let outer = lock_terminal();
+        let inner = lock_terminal();
+        drop(inner);
+        assert_eq!(queue().owner, Some(thread::current().id()));
+        drop(outer);
+
+        thread::spawn(|| drop(lock_terminal())).join().unwrap();
+    }
+}
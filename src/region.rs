@@ -0,0 +1,230 @@
+//! Erasing and replacing codes printed earlier.
+//!
+//! Interactive applications show a code while waiting for something, such as
+//! a login, and remove it afterwards, or swap it for a fresh one when it
+//! expires. [`print_tracked`] prints a code and returns a [`RenderHandle`]
+//! recording the screen region it took, which erases or replaces it later:
+//!
+//! ```rust
+//! use qr2term::region;
+//!
+//! let mut handle = region::print_tracked("https://example.com/login?nonce=1").unwrap();
+//! handle.replace("https://example.com/login?nonce=2", &qr2term::default_options()).unwrap();
+//! handle.erase().unwrap();
+//! ```
+//!
+//! The region is found back relative to the cursor, which has to be right
+//! after the code: output printed after the code is erased along with it.
+//! Lines the terminal wrapped because they didn't fit, and lines scrolled off
+//! the top of the screen, aren't erased.
+
+use std::io::{self, Write};
+
+use crate::normalize::strip_ansi;
+use crate::{default_options, payload, Error, QrOptions, ToQrPayload};
+
+/// The screen region taken by a code printed earlier, to erase or replace it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[must_use = "the handle is needed to erase or replace the code"]
+pub struct RenderHandle {
+    /// Number of rows taken.
+    rows: usize,
+
+    /// Number of columns taken by the widest row.
+    columns: usize,
+
+    /// Number of line breaks in the output, the rows the cursor moved down.
+    line_breaks: usize,
+}
+
+impl RenderHandle {
+    /// Measure the region taken by the rendered `output`.
+    pub(crate) fn measure(output: &[u8]) -> Self {
+        let text = strip_ansi(output);
+        let text = String::from_utf8_lossy(&text);
+        let mut lines: Vec<&str> = text.split('\n').collect();
+        if lines.last() == Some(&"") {
+            lines.pop();
+        }
+        Self {
+            rows: lines.len(),
+            columns: lines
+                .iter()
+                .map(|line| line.trim_end_matches('\r').chars().count())
+                .max()
+                .unwrap_or(0),
+            line_breaks: text.matches('\n').count(),
+        }
+    }
+
+    /// Number of rows the code takes, including its captions.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Number of columns the widest row of the code takes.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Erase the code from the terminal, leaving the cursor where it started.
+    ///
+    /// Returns an error if writing to the terminal failed.
+    pub fn erase(&mut self) -> Result<(), Error> {
+        self.erase_to(&mut io::stdout().lock())
+    }
+
+    /// Erase the code from the terminal the given writer writes to, leaving
+    /// the cursor where it started.
+    ///
+    /// Erasing again does nothing, as the handle then tracks an empty region.
+    ///
+    /// Returns an error if writing failed.
+    pub fn erase_to<W: Write>(&mut self, target: &mut W) -> Result<(), Error> {
+        target.write_all(self.erase_sequence().as_bytes())?;
+        target.flush()?;
+        *self = Self::measure(b"");
+        Ok(())
+    }
+
+    /// Replace the code in the terminal with the QR code for `data`, using
+    /// the given `options`.
+    ///
+    /// Returns an error if generating the QR code or writing it to the
+    /// terminal failed.
+    pub fn replace<D: ToQrPayload>(&mut self, data: D, options: &QrOptions) -> Result<(), Error> {
+        self.replace_to(data, &options.for_terminal(), &mut io::stdout().lock())
+    }
+
+    /// Replace the code in the terminal the given writer writes to with the
+    /// QR code for `data`, using the given `options`.
+    ///
+    /// The new code is rendered first, and written together with the
+    /// sequence erasing the old one, so the terminal doesn't show an empty
+    /// region in between. If rendering fails the old code is left in place.
+    ///
+    /// Returns an error if generating the QR code or writing it failed.
+    pub fn replace_to<D: ToQrPayload, W: Write>(
+        &mut self,
+        data: D,
+        options: &QrOptions,
+        target: &mut W,
+    ) -> Result<(), Error> {
+        let mut buf = self.erase_sequence().into_bytes();
+        let start = buf.len();
+        let result = crate::render_qr(data, options, &mut buf).and_then(|()| {
+            target.write_all(&buf)?;
+            target.flush()?;
+            Ok(())
+        });
+        if result.is_ok() {
+            *self = Self::measure(&buf[start..]);
+        }
+        if options.secret {
+            payload::wipe(&mut buf);
+        }
+        result
+    }
+
+    /// The escape sequence moving the cursor to the start of the region, and
+    /// erasing everything from there on.
+    pub(crate) fn erase_sequence(&self) -> String {
+        match (self.rows, self.line_breaks) {
+            (0, _) => String::new(),
+            (_, 0) => "\r\x1B[J".to_owned(),
+            (_, line_breaks) => format!("\r\x1B[{}A\x1B[J", line_breaks),
+        }
+    }
+}
+
+/// Print the given `data` as QR code in the terminal, returning the handle to
+/// erase or replace it later.
+///
+/// Uses the process wide [`default_options`].
+///
+/// Returns an error if generating the QR code or writing it to the terminal failed.
+pub fn print_tracked<D: ToQrPayload>(data: D) -> Result<RenderHandle, Error> {
+    print_tracked_with_options(data, &default_options())
+}
+
+/// Print the given `data` as QR code in the terminal using the given
+/// `options`, returning the handle to erase or replace it later.
+///
+/// Returns an error if generating the QR code or writing it to the terminal failed.
+pub fn print_tracked_with_options<D: ToQrPayload>(
+    data: D,
+    options: &QrOptions,
+) -> Result<RenderHandle, Error> {
+    render_tracked(data, &options.for_terminal(), &mut io::stdout().lock())
+}
+
+/// Render the given `data` as QR code to the given writer using the given
+/// `options`, returning the handle to erase or replace it later.
+///
+/// The code is rendered first, and written at once.
+///
+/// Returns an error if generating the QR code or writing it failed.
+pub fn render_tracked<D: ToQrPayload, W: Write>(
+    data: D,
+    options: &QrOptions,
+    target: &mut W,
+) -> Result<RenderHandle, Error> {
+    let mut buf = Vec::new();
+    let result = crate::render_qr(data, options, &mut buf).and_then(|()| {
+        target.write_all(&buf)?;
+        target.flush()?;
+        Ok(RenderHandle::measure(&buf))
+    });
+    if options.secret {
+        payload::wipe(&mut buf);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Style;
+
+    #[test]
+    fn measure() {
+        let handle = RenderHandle::measure(b"\x1B[47m  \x1B[0m\nabc\r\n");
+        assert_eq!((handle.rows(), handle.columns()), (2, 3));
+        assert_eq!(handle.erase_sequence(), "\r\x1B[2A\x1B[J");
+
+        let handle = RenderHandle::measure(b"ab\ncd");
+        assert_eq!((handle.rows(), handle.columns()), (2, 2));
+        assert_eq!(handle.erase_sequence(), "\r\x1B[1A\x1B[J");
+        assert_eq!(RenderHandle::measure(b"ab").erase_sequence(), "\r\x1B[J");
+        assert_eq!(RenderHandle::measure(b"").erase_sequence(), "");
+    }
+
+    #[test]
+    fn erase_and_replace() {
+        let options = QrOptions::new().style(Style::Ascii);
+        let mut buf = Vec::new();
+        let mut handle = render_tracked("qr2term", &options, &mut buf).unwrap();
+        assert_eq!((handle.rows(), handle.columns()), (25, 50));
+
+        buf.clear();
+        handle
+            .replace_to("https://rust-lang.org/", &options, &mut buf)
+            .unwrap();
+        let code =
+            crate::try_generate_qr_string_with_options("https://rust-lang.org/", &options).unwrap();
+        assert_eq!(buf, format!("\r\x1B[25A\x1B[J{}", code).into_bytes());
+        assert_eq!(handle.rows(), code.lines().count());
+
+        buf.clear();
+        assert!(handle
+            .replace_to(&[0; 4000][..], &options, &mut buf)
+            .is_err());
+        assert!(buf.is_empty());
+        handle.erase_to(&mut buf).unwrap();
+        handle.erase_to(&mut buf).unwrap();
+        assert_eq!(
+            buf,
+            format!("\r\x1B[{}A\x1B[J", code.lines().count()).into_bytes()
+        );
+    }
+}
@@ -1,66 +1,791 @@
 //! Rendering utilities.
 
+use std::error::Error;
+use std::fmt::{self, Display};
 use std::io::{self, Result as IoResult, Write};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crossterm::style::Stylize;
 pub use qrcode::types::Color::{self, Dark as QrDark, Light as QrLight};
 
+use crate::ansi;
 use crate::matrix::Matrix;
+use crate::rle;
+use crate::term;
+use crate::theme::Theme;
+
+/// Widest rendering in columns, the most a terminal can report having.
+pub const MAX_COLUMNS: usize = u16::MAX as usize;
+
+/// Style in which a QR code is rendered to the terminal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum Style {
+    /// Two pixels per character using half block characters, the most compact style.
+    #[default]
+    HalfBlock,
+
+    /// One pixel per two colored characters, for terminals where half blocks render poorly.
+    FullBlock,
+
+    /// One pixel per two plain ASCII characters, for terminals without color or Unicode.
+    Ascii,
+
+    /// Four pixels per character using quadrant block characters.
+    Quadrant,
+
+    /// Six pixels per character using sextant block characters.
+    ///
+    /// Sextants are squarer than Braille dots, but are part of the Symbols for
+    /// Legacy Computing block, which not all fonts support.
+    Sextant,
+
+    /// Eight pixels per character using Braille patterns, the smallest style.
+    ///
+    /// Dots are drawn for dark pixels. Without colors the code is inverted on
+    /// terminals with a dark background, which not all scanners can read.
+    Braille,
+
+    /// One pixel per two characters with colored backgrounds, for CI log viewers.
+    ///
+    /// Log viewers such as those of GitHub Actions and GitLab CI add spacing
+    /// between lines, which breaks up block characters but not backgrounds.
+    /// Colors only change where needed, keeping logs small. When running in
+    /// GitHub Actions or GitLab CI, the code is wrapped in a collapsible group.
+    CiLog,
+
+    /// Two pixels per character like [`Style::HalfBlock`], scaled to fill the
+    /// terminal at a fractional number of pixels per module, with module
+    /// edges smoothed in truecolor grays.
+    ///
+    /// Block styles only scale by whole characters, leaving much of awkwardly
+    /// sized terminals unused. Pixels are shaded by how much of them dark
+    /// modules cover, so modules come out evenly sized rather than some a
+    /// pixel larger than others. Without colors, cells are drawn with the
+    /// shaded blocks `░`, `▒` and `▓`. Needs a terminal with truecolor
+    /// support, and ignores the [theme](Renderer::theme). Codes larger than
+    /// the terminal are drawn at one pixel per module.
+    Smooth,
+
+    /// Pick the largest of the full block, half block, quadrant and Braille
+    /// styles that fits the terminal, see [`Style::fit`].
+    Auto,
+}
+
+impl Style {
+    /// All available styles, excluding [`Style::Auto`] which picks one of them.
+    pub const ALL: [Style; 8] = [
+        Style::HalfBlock,
+        Style::FullBlock,
+        Style::Ascii,
+        Style::Quadrant,
+        Style::Sextant,
+        Style::Braille,
+        Style::CiLog,
+        Style::Smooth,
+    ];
+
+    /// Styles [`Style::Auto`] picks from, largest first.
+    const AUTO: [Style; 4] = [
+        Style::FullBlock,
+        Style::HalfBlock,
+        Style::Quadrant,
+        Style::Braille,
+    ];
+
+    /// Name of this style, as accepted by its `FromStr` implementation.
+    pub fn name(self) -> &'static str {
+        match self {
+            Style::HalfBlock => "half",
+            Style::FullBlock => "full",
+            Style::Ascii => "ascii",
+            Style::Quadrant => "quadrant",
+            Style::Sextant => "sextant",
+            Style::Braille => "braille",
+            Style::CiLog => "ci",
+            Style::Smooth => "smooth",
+            Style::Auto => "auto",
+        }
+    }
+
+    /// Pick the largest style that renders `matrix` within a terminal of the
+    /// given size, as `(columns, rows)`.
+    ///
+    /// Falls back to the smallest style if none fits, or to the default
+    /// style if the terminal size is unknown.
+    pub fn fit(matrix: &Matrix<Color>, terminal_size: Option<(usize, usize)>) -> Style {
+        let (columns, rows) = match terminal_size {
+            Some(size) => size,
+            None => {
+                log_event!(debug, "terminal size unknown, using default style");
+                return Style::default();
+            }
+        };
+        let style = Style::AUTO.into_iter().find(|&style| {
+            let renderer = Renderer::new(style);
+            renderer.width(matrix) <= columns && renderer.height(matrix) <= rows
+        });
+        match style {
+            Some(style) => {
+                log_event!(
+                    debug,
+                    "picked {} style for {}x{} terminal",
+                    style,
+                    columns,
+                    rows
+                );
+                style
+            }
+            None => {
+                log_event!(
+                    warn,
+                    "code doesn't fit {}x{} terminal, falling back to braille style",
+                    columns,
+                    rows
+                );
+                Style::Braille
+            }
+        }
+    }
+}
+
+impl Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Style {
+    type Err = ParseStyleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        match name.as_str() {
+            "half-block" | "halfblock" => Ok(Style::HalfBlock),
+            "full-block" | "fullblock" => Ok(Style::FullBlock),
+            "ci-log" | "cilog" => Ok(Style::CiLog),
+            "auto" => Ok(Style::Auto),
+            _ => Style::ALL
+                .iter()
+                .copied()
+                .find(|style| style.name() == name)
+                .ok_or(ParseStyleError { name }),
+        }
+    }
+}
+
+/// Error returned when parsing an unknown [`Style`] name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseStyleError {
+    name: String,
+}
+
+impl Display for ParseStyleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown render style '{}'", self.name)
+    }
+}
+
+impl Error for ParseStyleError {}
 
 /// QR barcode terminal renderer intended for terminals.
-#[derive(Debug, Default)]
-pub struct Renderer {}
+#[derive(Debug)]
+pub struct Renderer {
+    style: Style,
+    ansi: bool,
+    theme: Theme,
+    double_size: bool,
+    stretch: usize,
+    non_breaking: bool,
+    max_width: Option<usize>,
+    terminal_size: Option<(usize, usize)>,
+    deterministic: bool,
+}
 
 impl Renderer {
+    /// Construct a renderer using the given style.
+    pub fn new(style: Style) -> Self {
+        Self {
+            style,
+            ansi: true,
+            theme: Theme::default(),
+            double_size: false,
+            stretch: 1,
+            non_breaking: false,
+            max_width: None,
+            terminal_size: None,
+            deterministic: false,
+        }
+    }
+
+    /// Set whether to use ANSI colors, enabled by default.
+    ///
+    /// Without colors, dark pixels are drawn as block characters on the
+    /// terminal's own background. The code then only scans well on terminals
+    /// with a light background, or when copied into a light document.
+    pub fn ansi(mut self, ansi: bool) -> Self {
+        self.ansi = ansi;
+        self
+    }
+
+    /// Set the colors to render in, see [`Theme`].
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Set whether to render every line at double width and height, disabled by default.
+    ///
+    /// Uses the DECDHL escape sequences, which xterm and VTE based terminals
+    /// support, to make codes physically larger without using a larger style.
+    /// Every line is printed twice, once for each half. Other terminals show
+    /// the code stretched vertically. Ignored for [`Style::CiLog`].
+    pub fn double_size(mut self, double_size: bool) -> Self {
+        self.double_size = double_size;
+        self
+    }
+
+    /// Set how many times wider than normal to draw every module, 1 by default.
+    ///
+    /// Styles assume terminal cells about twice as tall as wide. The quadrant
+    /// style, and fonts with narrower cells, draw modules taller than wide,
+    /// which can hamper scanning. A stretch of 2 emits twice as many columns
+    /// per module to compensate. A stretch of 0 is treated as 1.
+    pub fn stretch(mut self, stretch: usize) -> Self {
+        self.stretch = stretch.max(1);
+        self
+    }
+
+    /// Set whether to draw light pixels with non-breaking spaces, disabled by default.
+    ///
+    /// Some terminals, pagers and chat clients trim trailing spaces when
+    /// copying, which removes the right quiet zone. Non-breaking spaces look
+    /// the same, but survive. Ignored for [`Style::Ascii`], which only uses
+    /// ASCII characters.
+    pub fn non_breaking(mut self, non_breaking: bool) -> Self {
+        self.non_breaking = non_breaking;
+        self
+    }
+
+    /// Set the maximum width in columns [`Style::Auto`] picks a style for, unlimited by default.
+    ///
+    /// The terminal width is used if it is narrower.
+    pub fn max_width(mut self, max_width: Option<usize>) -> Self {
+        self.max_width = max_width;
+        self
+    }
+
+    /// Set the size of the terminal [`Style::Auto`] and [`Style::Smooth`]
+    /// fit codes to, as `(columns, rows)`, the local terminal by default.
+    ///
+    /// For rendering to a remote terminal, such as the client of an SSH
+    /// session. The given size is used even if rendering is
+    /// [deterministic](Self::deterministic).
+    pub fn terminal_size(mut self, terminal_size: Option<(usize, usize)>) -> Self {
+        self.terminal_size = terminal_size;
+        self
+    }
+
+    /// Set whether to render independently of the environment, disabled by default.
+    ///
+    /// [`Style::Auto`] then ignores the terminal size, fitting the
+    /// [maximum width](Self::max_width) only, and [`Style::CiLog`] doesn't
+    /// group codes for the CI service it runs on. See
+    /// [`QrOptions::deterministic`](crate::QrOptions::deterministic).
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Get the blank text `columns` wide, of at most 2 columns.
+    fn blank(&self, columns: usize) -> &'static str {
+        match self.non_breaking {
+            true => &"\u{A0}\u{A0}"[..columns * '\u{A0}'.len_utf8()],
+            false => &"  "[..columns],
+        }
+    }
+
+    /// Get how many times larger `style` is rendered in each direction.
+    fn scale(&self, style: Style) -> usize {
+        match self.double_size && style != Style::CiLog {
+            true => 2,
+            false => 1,
+        }
+    }
+
+    /// The palette indices of dark and light pixels, or `None` to render without colors.
+    fn colors(&self) -> Option<(u8, u8)> {
+        match self.ansi {
+            true => self.theme.colors(),
+            false => None,
+        }
+    }
+
+    /// Get the style `matrix` is rendered in.
+    ///
+    /// This is the configured style, or the style picked for the current
+    /// terminal if it is [`Style::Auto`].
+    pub fn resolve_style(&self, matrix: &Matrix<Color>) -> Style {
+        match self.style {
+            Style::Auto => Style::fit(matrix, self.fit_size(Style::Auto)),
+            style => style,
+        }
+    }
+
+    /// Get the size `style` may fill, as `(columns, rows)` of unstretched
+    /// pixel columns and normal size rows, or `None` if unknown.
+    ///
+    /// This is the size of the [terminal](Self::terminal_size), narrowed to
+    /// the [maximum width](Self::max_width).
+    fn fit_size(&self, style: Style) -> Option<(usize, usize)> {
+        let scale = self.scale(style);
+        let terminal_size = match (self.terminal_size, self.deterministic) {
+            (Some(size), _) => Some(size),
+            (None, true) => None,
+            (None, false) => term::size(),
+        };
+        let size = match (terminal_size, self.max_width) {
+            (Some((columns, rows)), Some(max_width)) => Some((columns.min(max_width), rows)),
+            (None, Some(max_width)) => Some((max_width, usize::MAX)),
+            (size, None) => size,
+        };
+        size.map(|(columns, rows)| (columns / scale / self.stretch, rows / scale))
+    }
+
+    /// Get the number of pixels across the [`Style::Smooth`] rendering of
+    /// `matrix`, filling the [available size](Self::fit_size) with square
+    /// pixels of half a row, and at least one pixel per module.
+    ///
+    /// The last row is left free, so the prompt below the code doesn't
+    /// scroll its first line off screen.
+    fn smooth_size(&self, matrix: &Matrix<Color>) -> usize {
+        let size = self.fit_size(Style::Smooth).map_or(0, |(columns, rows)| {
+            columns.min(rows.saturating_sub(1).saturating_mul(2))
+        });
+        size.max(matrix.size())
+    }
+
     /// Print a matrix describing a 2D barcode to the given writer.
+    ///
+    /// Returns an error if writing failed, or if the rendered matrix would be
+    /// wider than [`MAX_COLUMNS`], so matrices from untrusted input can't
+    /// exhaust memory.
     pub fn render<W: Write>(&self, matrix: &Matrix<Color>, target: &mut W) -> IoResult<()> {
+        let width = self.width(matrix);
+        if width > MAX_COLUMNS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "rendered matrix is {} columns wide, exceeding the maximum of {}",
+                    width, MAX_COLUMNS
+                ),
+            ));
+        }
+        if self.resolve_style(matrix) == Style::Smooth {
+            return match self.scale(Style::Smooth) {
+                1 => self.render_smooth(matrix, target),
+                _ => {
+                    let mut buf = Vec::new();
+                    self.render_smooth(matrix, &mut buf)?;
+                    self.write_double_size(&buf, target)
+                }
+            };
+        }
+        self.emit(&self.layout(matrix), target)
+    }
+
+    /// Group the pixels of `matrix` into the character cells of the style it
+    /// is rendered in, the first stage of [`render`](Self::render).
+    ///
+    /// Pixels beyond the edge of the matrix are light. Cells are
+    /// [stretched](Self::stretch), but not doubled in size. [`Style::Smooth`]
+    /// is laid out in half block cells of one pixel per module, as shades
+    /// don't fit bit masks.
+    pub fn layout(&self, matrix: &Matrix<Color>) -> Cells {
+        let style = self.resolve_style(matrix);
+        let (cell_width, cell_height) = cell_size(style);
         let width = matrix.size();
+        let columns = width * self.stretch;
         let pixels = matrix.pixels();
+        let cell_columns = (columns + cell_width - 1) / cell_width;
+        let mut dark = Vec::with_capacity(cell_columns * ((width + cell_height - 1) / cell_height));
+
+        for row in (0..width).step_by(cell_height) {
+            for col in (0..columns).step_by(cell_width) {
+                let mut cell = 0;
+                for y in 0..cell_height {
+                    for x in 0..cell_width {
+                        let (row, col) = (row + y, col + x);
+                        if row < width
+                            && col < columns
+                            && pixels[row * width + col / self.stretch] == QrDark
+                        {
+                            cell |= 1 << (y * cell_width + x);
+                        }
+                    }
+                }
+                dark.push(cell);
+            }
+        }
+        Cells {
+            style,
+            columns: cell_columns,
+            dark,
+        }
+    }
+
+    /// Write the text drawing `cells` to the given writer, the last stage of
+    /// [`render`](Self::render).
+    ///
+    /// Cells are drawn in their own style, with the colors, non-breaking
+    /// spaces and double size of this renderer.
+    pub fn emit<W: Write>(&self, cells: &Cells, target: &mut W) -> IoResult<()> {
+        if self.scale(cells.style) == 1 {
+            return self.emit_style(cells, target);
+        }
 
-        for row in 0..width / 2 {
-            for col in 0..width {
-                let vec_pos = (row * 2) * width + col;
-                let vec_pos_below = (row * 2 + 1) * width + col;
-                match (pixels[vec_pos], pixels[vec_pos_below]) {
-                    (QrDark, QrDark) => self.black_above_black(target)?,
-                    (QrDark, QrLight) => self.black_above_white(target)?,
-                    (QrLight, QrDark) => self.white_above_black(target)?,
-                    (QrLight, QrLight) => self.white_above_white(target)?,
+        let mut buf = Vec::new();
+        self.emit_style(cells, &mut buf)?;
+        self.write_double_size(&buf, target)
+    }
+
+    /// Write every line of the rendered `buf` twice, as top and bottom half
+    /// of a double size line.
+    fn write_double_size<W: Write>(&self, buf: &[u8], target: &mut W) -> IoResult<()> {
+        for line in buf.split_inclusive(|&byte| byte == b'\n') {
+            target.write_all(ansi::DOUBLE_HEIGHT_TOP.as_bytes())?;
+            target.write_all(line)?;
+            target.write_all(ansi::DOUBLE_HEIGHT_BOTTOM.as_bytes())?;
+            target.write_all(line)?;
+        }
+        Ok(())
+    }
+
+    /// Write the text drawing `cells` in their style, at normal size.
+    fn emit_style<W: Write>(&self, cells: &Cells, target: &mut W) -> IoResult<()> {
+        match cells.style {
+            Style::CiLog => {
+                let service = match self.deterministic {
+                    true => None,
+                    false => CiService::detect(),
                 };
+                self.render_ci_log(cells, target, service)
             }
-            self.newline(target)?;
+            style => {
+                let table = self.cell_table(style)?;
+                self.render_cells(cells, target, &table)
+            }
+        }
+    }
+
+    /// Render cells of one pixel per two characters for CI log viewers,
+    /// grouped for the given `service`.
+    fn render_ci_log<W: Write>(
+        &self,
+        cells: &Cells,
+        target: &mut W,
+        service: Option<CiService>,
+    ) -> IoResult<()> {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        match service {
+            Some(CiService::GitHub) => writeln!(target, "::group::QR code")?,
+            Some(CiService::GitLab) => writeln!(
+                target,
+                "\x1B[0Ksection_start:{}:qr_code\r\x1B[0KQR code",
+                since_epoch
+            )?,
+            None => {}
         }
 
-        // Because one character is two "pixels" above each other, the last pixel-line
-        // has only white ("empty") "pixels" in case of an odd number of pixelrows.
-        if width % 2 == 1 {
-            for col in 0..width {
-                let vec_pos = width * (width - 1) + col;
-                match pixels[vec_pos] {
-                    QrDark => self.black_above_white(target)?,
-                    QrLight => self.white_above_white(target)?,
+        for row in cells.rows() {
+            let mut current = None;
+            for (cell, len) in rle::runs(row) {
+                let text = match self.colors() {
+                    Some((dark, light)) => {
+                        let color = if cell != 0 { dark } else { light };
+                        if current != Some(color) {
+                            ansi::write_background(target, color)?;
+                            current = Some(color);
+                        }
+                        self.blank(2)
+                    }
+                    None if cell != 0 => "██",
+                    None => self.blank(2),
                 };
+                for _ in 0..len {
+                    target.write_all(text.as_bytes())?;
+                }
+            }
+            if current.is_some() {
+                ansi::write_reset(target)?;
             }
             self.newline(target)?;
         }
 
+        match service {
+            Some(CiService::GitHub) => writeln!(target, "::endgroup::"),
+            Some(CiService::GitLab) => writeln!(
+                target,
+                "\x1B[0Ksection_end:{}:qr_code\r\x1B[0K",
+                since_epoch
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the styled text of every cell of the given resolved `style`,
+    /// indexed by the bit mask of its dark pixels.
+    ///
+    /// Styling cells up front once per rendering, rather than for every cell,
+    /// keeps formatting and allocations off the hot path.
+    fn cell_table(&self, style: Style) -> IoResult<Vec<Vec<u8>>> {
+        let (cell_width, cell_height) = cell_size(style);
+        (0..1u16 << (cell_width * cell_height))
+            .map(|dark| {
+                let mut text = Vec::new();
+                self.write_cell(style, dark as u8, &mut text)?;
+                Ok(text)
+            })
+            .collect()
+    }
+
+    /// Write the character drawing a cell of the given resolved `style`,
+    /// with a bit mask of its dark pixels row by row, see [`cell_size`].
+    fn write_cell<W: Write>(&self, style: Style, dark: u8, target: &mut W) -> IoResult<()> {
+        match style {
+            // Two pixels above each other per character
+            Style::HalfBlock | Style::Smooth => match (dark & 1 != 0, dark & 2 != 0) {
+                (true, true) => self.black_above_black(target),
+                (true, false) => self.black_above_white(target),
+                (false, true) => self.white_above_black(target),
+                (false, false) => self.white_above_white(target),
+            },
+            // One pixel per two characters, to keep pixels roughly square
+            Style::FullBlock | Style::Ascii | Style::CiLog => {
+                match (style, self.colors(), dark != 0) {
+                    (Style::Ascii, _, true) => write!(target, "##"),
+                    (_, None, true) => write!(target, "██"),
+                    (_, Some((dark, light)), true) => {
+                        ansi::write_colored(target, self.blank(2), light, dark)
+                    }
+                    (Style::Ascii, _, false) => write!(target, "  "),
+                    (_, None, false) => target.write_all(self.blank(2).as_bytes()),
+                    (_, Some((dark, light)), false) => {
+                        ansi::write_colored(target, self.blank(2), dark, light)
+                    }
+                }
+            }
+            Style::Quadrant => self.write_glyph(quadrant_glyph(dark), target),
+            Style::Sextant => self.write_glyph(sextant_glyph(dark), target),
+            Style::Braille => self.write_glyph(braille_glyph(dark), target),
+            Style::Auto => unreachable!("auto style is resolved"),
+        }
+    }
+
+    /// Write a block or braille `glyph`, drawing its pixels dark.
+    fn write_glyph<W: Write>(&self, glyph: char, target: &mut W) -> IoResult<()> {
+        let mut buf = [0; 4];
+        let glyph = match glyph {
+            ' ' => self.blank(1),
+            glyph => glyph.encode_utf8(&mut buf),
+        };
+        match self.colors() {
+            Some((dark, light)) => ansi::write_colored(target, glyph, dark, light),
+            None => target.write_all(glyph.as_bytes()),
+        }
+    }
+
+    /// Render `matrix` in the [`Style::Smooth`] style, at normal size.
+    ///
+    /// Every pixel is shaded by the share of its area dark modules cover, so
+    /// pixels straddling module edges come out gray.
+    fn render_smooth<W: Write>(&self, matrix: &Matrix<Color>, target: &mut W) -> IoResult<()> {
+        let size = self.smooth_size(matrix);
+        let weights = pixel_weights(matrix.size(), size);
+        let pixels = matrix.pixels();
+        // Pixels below the last row are light
+        let coverage = |x: usize, y: usize| -> f64 {
+            let mut dark = 0.0;
+            for &(row, row_weight) in weights.get(y).into_iter().flatten() {
+                for &(col, col_weight) in &weights[x] {
+                    if pixels[row * matrix.size() + col] == QrDark {
+                        dark += row_weight * col_weight;
+                    }
+                }
+            }
+            dark
+        };
+        let gray = |coverage: f64| {
+            let gray = (255.0 * (1.0 - coverage)).round() as u8;
+            [gray; 3]
+        };
+
+        let mut line = Vec::new();
+        for row in (0..size).step_by(2) {
+            for col in 0..size * self.stretch {
+                let (top, bottom) = (
+                    coverage(col / self.stretch, row),
+                    coverage(col / self.stretch, row + 1),
+                );
+                match self.colors() {
+                    // Inverted lower half block, see `black_above_white`
+                    Some(_) => ansi::write_rgb(&mut line, "▄", gray(bottom), gray(top))?,
+                    None => {
+                        let shade = ((top + bottom) * 2.0).round() as usize;
+                        let glyph = match shade {
+                            0 => self.blank(1),
+                            shade => ["░", "▒", "▓", "█"][shade - 1],
+                        };
+                        line.extend_from_slice(glyph.as_bytes());
+                    }
+                }
+            }
+            line.push(b'\n');
+            target.write_all(&line)?;
+            line.clear();
+        }
+        Ok(())
+    }
+
+    /// Render `cells`, drawn with the styled text in `table`, see
+    /// [`cell_table`](Self::cell_table).
+    ///
+    /// Every line is collected before writing it, so targets get a single
+    /// write per line.
+    fn render_cells<W: Write>(
+        &self,
+        cells: &Cells,
+        target: &mut W,
+        table: &[Vec<u8>],
+    ) -> IoResult<()> {
+        let longest = table.iter().map(Vec::len).max().unwrap_or(0);
+        let mut line = Vec::with_capacity(cells.columns * longest + 1);
+        for row in cells.rows() {
+            for (cell, len) in rle::runs(row) {
+                let text = &table[usize::from(cell)];
+                for _ in 0..len {
+                    line.extend_from_slice(text);
+                }
+            }
+            line.push(b'\n');
+            target.write_all(&line)?;
+            line.clear();
+        }
+        Ok(())
+    }
+
+    /// Render a square of `width` pixels that `is_dark` tells the color of,
+    /// by row and column, without allocating.
+    ///
+    /// [`Style::Auto`] renders as the default style, [`Style::CiLog`] as
+    /// [`Style::FullBlock`], [`Style::Smooth`] as [`Style::HalfBlock`], and
+    /// double size is ignored, as they need the whole matrix or output up
+    /// front.
+    ///
+    /// Returns an error if writing failed, or if the rendering would be wider
+    /// than [`MAX_COLUMNS`].
+    pub(crate) fn render_pixels<W, F>(
+        &self,
+        width: usize,
+        is_dark: F,
+        target: &mut W,
+    ) -> IoResult<()>
+    where
+        W: Write,
+        F: Fn(usize, usize) -> bool,
+    {
+        let style = match self.style {
+            Style::Auto => Style::default(),
+            Style::CiLog => Style::FullBlock,
+            Style::Smooth => Style::HalfBlock,
+            style => style,
+        };
+        let (cell_width, cell_height) = cell_size(style);
+        let columns = width.saturating_mul(self.stretch);
+        let rendered_width = match cell_width {
+            1 if style == Style::HalfBlock => columns,
+            1 => columns.saturating_mul(2),
+            _ => columns / 2 + columns % 2,
+        };
+        if rendered_width > MAX_COLUMNS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "rendered matrix is wider than the maximum",
+            ));
+        }
+
+        for row in (0..width).step_by(cell_height) {
+            for col in (0..columns).step_by(cell_width) {
+                let mut dark = 0;
+                for y in 0..cell_height {
+                    for x in 0..cell_width {
+                        let (row, col) = (row + y, col + x);
+                        if row < width && col < columns && is_dark(row, col / self.stretch) {
+                            dark |= 1 << (y * cell_width + x);
+                        }
+                    }
+                }
+                self.write_cell(style, dark, target)?;
+            }
+            self.newline(target)?;
+        }
         Ok(())
     }
 
     /// Print a matrix describing a 2D barcode to the terminal.
+    ///
+    /// # Panics
+    ///
+    /// Panics if printing to the terminal failed.
+    #[deprecated(since = "0.4.0", note = "use `try_print_stdout` instead")]
     pub fn print_stdout(&self, matrix: &Matrix<Color>) {
-        self.render(matrix, &mut io::stdout())
+        self.try_print_stdout(matrix)
             .expect("failed to print QR code to stdout");
     }
 
+    /// Print a matrix describing a 2D barcode to the terminal.
+    ///
+    /// Returns an error if writing to the terminal failed.
+    pub fn try_print_stdout(&self, matrix: &Matrix<Color>) -> IoResult<()> {
+        let mut stdout = io::stdout().lock();
+        self.render(matrix, &mut stdout)?;
+        stdout.flush()
+    }
+
     /// How many horizontal characters or columns in the terminal it takes to render `matrix`.
+    ///
+    /// Saturates at `usize::MAX`.
     pub fn width(&self, matrix: &Matrix<Color>) -> usize {
-        matrix.size()
+        let style = self.resolve_style(matrix);
+        let columns = matrix.size().saturating_mul(self.stretch);
+        let width = match style {
+            Style::HalfBlock => columns,
+            Style::Smooth => self.smooth_size(matrix).saturating_mul(self.stretch),
+            Style::FullBlock | Style::Ascii | Style::CiLog => columns.saturating_mul(2),
+            Style::Quadrant | Style::Sextant | Style::Braille => columns / 2 + columns % 2,
+            Style::Auto => unreachable!("auto style is resolved"),
+        };
+        width.saturating_mul(self.scale(style))
     }
 
     /// How many vertical characters or rows or lines in the terminal it takes to render `matrix`.
     pub fn height(&self, matrix: &Matrix<Color>) -> usize {
-        matrix.size() / 2 + matrix.size() % 2
+        let style = self.resolve_style(matrix);
+        let height = match style {
+            Style::HalfBlock | Style::Quadrant => matrix.size() / 2 + matrix.size() % 2,
+            Style::Smooth => {
+                let size = self.smooth_size(matrix);
+                size / 2 + size % 2
+            }
+            Style::FullBlock | Style::Ascii | Style::CiLog => matrix.size(),
+            Style::Sextant => (matrix.size() + 2) / 3,
+            Style::Braille => (matrix.size() + 3) / 4,
+            Style::Auto => unreachable!("auto style is resolved"),
+        };
+        height.saturating_mul(self.scale(style))
     }
 
     /// Terminal-format and print one character that show a black pixel above a white pixel.
@@ -73,22 +798,34 @@ impl Renderer {
     /// using color inversion (so "█" = " " inverted, and "▀" = "▄" inverted).
     /// "▄" seems to render better than "▅".
     fn black_above_white<W: Write>(&self, target: &mut W) -> IoResult<()> {
-        write!(target, "{}", "▄".white().on_black())
+        match self.colors() {
+            Some((dark, light)) => ansi::write_colored(target, "▄", light, dark),
+            None => write!(target, "▀"),
+        }
     }
 
     /// Similar to `black_above_white`
     fn white_above_black<W: Write>(&self, target: &mut W) -> IoResult<()> {
-        write!(target, "{}", "▄".black().on_white())
+        match self.colors() {
+            Some((dark, light)) => ansi::write_colored(target, "▄", dark, light),
+            None => write!(target, "▄"),
+        }
     }
 
     /// Similar to `black_above_white`
     fn black_above_black<W: Write>(&self, target: &mut W) -> IoResult<()> {
-        write!(target, "{}", " ".white().on_black())
+        match self.colors() {
+            Some((dark, light)) => ansi::write_colored(target, self.blank(1), light, dark),
+            None => write!(target, "█"),
+        }
     }
 
     /// Similar to `black_above_white`
     fn white_above_white<W: Write>(&self, target: &mut W) -> IoResult<()> {
-        write!(target, "{}", " ".black().on_white())
+        match self.colors() {
+            Some((dark, light)) => ansi::write_colored(target, self.blank(1), dark, light),
+            None => target.write_all(self.blank(1).as_bytes()),
+        }
     }
 
     /// Print newline that does not mess up colors.
@@ -97,6 +834,152 @@ impl Renderer {
     }
 }
 
+/// Continuous integration service with a log viewer that supports grouping.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum CiService {
+    GitHub,
+    GitLab,
+}
+
+impl CiService {
+    /// Detect the service running this process from its environment variables.
+    fn detect() -> Option<Self> {
+        let is_set = |key| std::env::var(key).map_or(false, |value| value == "true");
+        if is_set("GITHUB_ACTIONS") {
+            Some(CiService::GitHub)
+        } else if is_set("GITLAB_CI") {
+            Some(CiService::GitLab)
+        } else {
+            None
+        }
+    }
+}
+
+/// Get the width and height in pixels of the cells a resolved `style` draws per character.
+fn cell_size(style: Style) -> (usize, usize) {
+    match style {
+        Style::HalfBlock | Style::Smooth => (1, 2),
+        Style::FullBlock | Style::Ascii | Style::CiLog => (1, 1),
+        Style::Quadrant => (2, 2),
+        Style::Sextant => (2, 3),
+        Style::Braille => (2, 4),
+        Style::Auto => unreachable!("auto style is resolved"),
+    }
+}
+
+/// Get the modules covering every one of `pixels` pixels across a row or
+/// column of `modules` modules, with the share of the pixel each covers.
+fn pixel_weights(modules: usize, pixels: usize) -> Vec<Vec<(usize, f64)>> {
+    let scale = modules as f64 / pixels as f64;
+    (0..pixels)
+        .map(|pixel| {
+            let (start, end) = (pixel as f64 * scale, (pixel + 1) as f64 * scale);
+            (start.floor() as usize..(end.ceil() as usize).min(modules))
+                .map(|module| {
+                    let overlap = end.min(module as f64 + 1.0) - start.max(module as f64);
+                    (module, overlap / scale)
+                })
+                .filter(|&(_, weight)| weight > 0.0)
+                .collect()
+        })
+        .collect()
+}
+
+/// Get the quadrant block character for a 2 by 2 cell with the given dark pixels.
+fn quadrant_glyph(dark: u8) -> char {
+    const GLYPHS: [char; 16] = [
+        ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+    ];
+    GLYPHS[usize::from(dark)]
+}
+
+/// Get the sextant block character for a 2 by 3 cell with the given dark pixels.
+fn sextant_glyph(dark: u8) -> char {
+    // Sextants are numbered like the mask, but skip the existing half blocks
+    match dark {
+        0 => ' ',
+        0b01_0101 => '▌',
+        0b10_1010 => '▐',
+        0b11_1111 => '█',
+        _ => {
+            let skipped = u32::from(dark > 0b01_0101) + u32::from(dark > 0b10_1010);
+            char::from_u32(0x1FB00 + u32::from(dark) - 1 - skipped).unwrap()
+        }
+    }
+}
+
+/// Get the Braille pattern for a 2 by 4 cell with the given dark pixels.
+fn braille_glyph(dark: u8) -> char {
+    // Braille dots are numbered down the left column first, with the bottom row last
+    const DOTS: [u8; 8] = [0x01, 0x08, 0x02, 0x10, 0x04, 0x20, 0x40, 0x80];
+    let dots = (0..8)
+        .filter(|bit| dark & (1 << bit) != 0)
+        .fold(0, |dots, bit| dots | DOTS[bit]);
+    char::from_u32(0x2800 + u32::from(dots)).unwrap()
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new(Style::default())
+    }
+}
+
+/// Pixels of a matrix grouped into the character cells of a style, see
+/// [`Renderer::layout`].
+///
+/// Every cell is a bit mask of its dark pixels, row by row: the pixel at `x`,
+/// `y` within the cell is bit `y * cell_width + x`, with the cell size given
+/// by [`cell_size`](Self::cell_size).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cells {
+    style: Style,
+    columns: usize,
+    dark: Vec<u8>,
+}
+
+impl Cells {
+    /// Construct cells of the given resolved `style` from their bit masks,
+    /// row by row, `columns` cells per row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `style` is [`Style::Auto`], or if the cells don't fill whole
+    /// rows.
+    pub fn new(style: Style, columns: usize, dark: Vec<u8>) -> Self {
+        assert!(style != Style::Auto, "auto style must be resolved");
+        let whole_rows = match columns {
+            0 => dark.is_empty(),
+            columns => dark.len() % columns == 0,
+        };
+        assert!(whole_rows, "cells don't fill whole rows");
+        Self {
+            style,
+            columns,
+            dark,
+        }
+    }
+
+    /// The style the cells are drawn in.
+    pub fn style(&self) -> Style {
+        self.style
+    }
+
+    /// Width and height in pixels of every cell.
+    pub fn cell_size(&self) -> (usize, usize) {
+        cell_size(self.style)
+    }
+
+    /// Number of cells per row.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Iterate over the rows of cells.
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.dark.chunks(self.columns.max(1))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -136,7 +1019,7 @@ mod tests {
             ///
             /// Panics if data seen by tracker is not valid UTF-8.
             pub fn width(&self) -> usize {
-                if self.data.len() == 0 {
+                if self.data.is_empty() {
                     return 0;
                 }
                 let data_str = std::str::from_utf8(&self.data).unwrap();
@@ -158,9 +1041,19 @@ mod tests {
     /// Checks that the expected, promised, and actual width and height match
     /// when rendering `pixels` to a terminal QR code.
     fn helper_width_and_height(pixels: Vec<Color>, expected_width: usize, expected_height: usize) {
+        helper_style_width_and_height(Style::HalfBlock, pixels, expected_width, expected_height);
+    }
+
+    /// Like `helper_width_and_height`, for the given render `style`.
+    fn helper_style_width_and_height(
+        style: Style,
+        pixels: Vec<Color>,
+        expected_width: usize,
+        expected_height: usize,
+    ) {
         // Given: a matrix, and a renderer for that matrix.
         let matrix = Matrix::new(pixels);
-        let renderer = Renderer::default();
+        let renderer = Renderer::new(style);
         let mut writer = size_tracker::SizeTracker::new();
 
         // When: rendering the matrix
@@ -188,4 +1081,291 @@ mod tests {
         helper_width_and_height(vec![QrLight; 5 * 5], 5, 3);
         helper_width_and_height(vec![QrDark; 21 * 21], 21, 11);
     }
+
+    #[test]
+    fn width_and_height_full_block() {
+        for style in [Style::FullBlock, Style::Ascii] {
+            helper_style_width_and_height(style, vec![], 0, 0);
+            helper_style_width_and_height(style, vec![QrDark], 2, 1);
+            helper_style_width_and_height(style, vec![QrLight; 5 * 5], 10, 5);
+            helper_style_width_and_height(style, vec![QrDark; 21 * 21], 42, 21);
+        }
+    }
+
+    #[test]
+    fn render_plain() {
+        let matrix = Matrix::new(vec![
+            QrDark, QrLight, QrDark, //
+            QrDark, QrDark, QrLight, //
+            QrLight, QrDark, QrLight,
+        ]);
+        let mut buf = vec![];
+        Renderer::default()
+            .ansi(false)
+            .render(&matrix, &mut buf)
+            .unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "█▄▀\n ▀ \n");
+    }
+
+    #[test]
+    fn render_theme() {
+        let matrix = Matrix::new(vec![QrDark, QrLight, QrLight, QrDark]);
+        let render = |theme| {
+            let mut buf = vec![];
+            Renderer::new(Style::FullBlock)
+                .theme(theme)
+                .render(&matrix, &mut buf)
+                .unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        assert!(render(Theme::SolarizedDark)
+            .starts_with("\x1B[48;5;234m\x1B[38;5;230m  \x1B[49m\x1B[39m\x1B[48;5;230m"));
+        assert_eq!(render(Theme::Monochrome), "██  \n  ██\n");
+    }
+
+    #[test]
+    fn render_ci_log() {
+        let matrix = Matrix::new(vec![QrDark, QrDark, QrLight, QrDark]);
+        let render = |renderer: Renderer, service| {
+            let mut buf = vec![];
+            let cells = renderer.layout(&matrix);
+            renderer.render_ci_log(&cells, &mut buf, service).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        let renderer = || Renderer::new(Style::CiLog);
+        assert_eq!(
+            render(renderer(), None),
+            "\x1B[40m    \x1B[0m\n\x1B[107m  \x1B[40m  \x1B[0m\n"
+        );
+        assert_eq!(
+            render(renderer().ansi(false), Some(CiService::GitHub)),
+            "::group::QR code\n████\n  ██\n::endgroup::\n"
+        );
+        let gitlab = render(
+            renderer().theme(Theme::HighContrast),
+            Some(CiService::GitLab),
+        );
+        assert!(gitlab.starts_with("\x1B[0Ksection_start:"));
+        assert!(gitlab.contains("\x1B[48;5;16m    \x1B[0m\n"));
+        assert!(gitlab.ends_with(":qr_code\r\x1B[0K\n"));
+    }
+
+    #[test]
+    fn render_double_size() {
+        let matrix = Matrix::new(vec![QrDark, QrLight, QrLight, QrDark]);
+        let renderer = Renderer::new(Style::FullBlock)
+            .ansi(false)
+            .double_size(true);
+        let mut buf = vec![];
+        renderer.render(&matrix, &mut buf).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "\x1B#3██  \n\x1B#4██  \n\x1B#3  ██\n\x1B#4  ██\n"
+        );
+        assert_eq!((renderer.width(&matrix), renderer.height(&matrix)), (8, 4));
+
+        let renderer = Renderer::new(Style::CiLog).double_size(true);
+        assert_eq!((renderer.width(&matrix), renderer.height(&matrix)), (4, 2));
+    }
+
+    #[test]
+    fn render_stretch() {
+        let matrix = Matrix::new(vec![QrDark, QrLight, QrLight, QrDark]);
+        let render = |style| {
+            let renderer = Renderer::new(style).ansi(false).stretch(2);
+            let mut buf = vec![];
+            renderer.render(&matrix, &mut buf).unwrap();
+            let size = (renderer.width(&matrix), renderer.height(&matrix));
+            (String::from_utf8(buf).unwrap(), size)
+        };
+        assert_eq!(render(Style::HalfBlock), ("▀▀▄▄\n".into(), (4, 1)));
+        assert_eq!(
+            render(Style::Ascii),
+            ("####    \n    ####\n".into(), (8, 2))
+        );
+        assert_eq!(render(Style::Quadrant), ("▀▄\n".into(), (2, 1)));
+        assert_eq!(Renderer::new(Style::Braille).stretch(0).width(&matrix), 1);
+    }
+
+    #[test]
+    fn render_non_breaking() {
+        let matrix = Matrix::new(vec![QrDark, QrLight, QrLight, QrLight]);
+        let render = |renderer: Renderer| {
+            let mut buf = vec![];
+            renderer
+                .non_breaking(true)
+                .render(&matrix, &mut buf)
+                .unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        let plain = |style| render(Renderer::new(style).ansi(false));
+        assert_eq!(plain(Style::HalfBlock), "▀\u{A0}\n");
+        assert_eq!(
+            plain(Style::FullBlock),
+            "██\u{A0}\u{A0}\n\u{A0}\u{A0}\u{A0}\u{A0}\n"
+        );
+        assert_eq!(plain(Style::Quadrant), "▘\n");
+        assert!(render(Renderer::new(Style::HalfBlock)).contains("m\u{A0}\x1B"));
+        assert_eq!(plain(Style::Ascii), "##  \n    \n");
+
+        let mut buf = vec![];
+        let renderer = Renderer::new(Style::CiLog).non_breaking(true);
+        let cells = renderer.layout(&matrix);
+        renderer.render_ci_log(&cells, &mut buf, None).unwrap();
+        assert!(!String::from_utf8(buf).unwrap().contains(' '));
+
+        let matrix = Matrix::new(vec![QrLight; 9]);
+        let mut buf = vec![];
+        Renderer::new(Style::Sextant)
+            .ansi(false)
+            .non_breaking(true)
+            .render(&matrix, &mut buf)
+            .unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "\u{A0}\u{A0}\n");
+    }
+
+    #[test]
+    fn width_and_height_cells() {
+        helper_style_width_and_height(Style::Quadrant, vec![QrDark], 1, 1);
+        helper_style_width_and_height(Style::Quadrant, vec![QrDark; 5 * 5], 3, 3);
+        helper_style_width_and_height(Style::Sextant, vec![QrDark; 5 * 5], 3, 2);
+        helper_style_width_and_height(Style::Sextant, vec![QrDark; 21 * 21], 11, 7);
+        helper_style_width_and_height(Style::Braille, vec![QrDark; 5 * 5], 3, 2);
+        helper_style_width_and_height(Style::Braille, vec![QrDark; 21 * 21], 11, 6);
+    }
+
+    #[test]
+    fn render_cells_plain() {
+        let matrix = Matrix::new(vec![
+            QrDark, QrLight, QrDark, //
+            QrDark, QrDark, QrLight, //
+            QrLight, QrDark, QrLight,
+        ]);
+        let render = |style| {
+            let mut buf = vec![];
+            Renderer::new(style)
+                .ansi(false)
+                .render(&matrix, &mut buf)
+                .unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        assert_eq!(render(Style::Quadrant), "▙▘\n▝ \n");
+        assert_eq!(render(Style::Sextant), "\u{1FB2A}\u{1FB00}\n");
+        assert_eq!(render(Style::Braille), "⠳⠁\n");
+    }
+
+    #[test]
+    fn render_too_wide() {
+        let matrix = Matrix::new(vec![QrDark; 21 * 21]);
+        let renderer = Renderer::new(Style::FullBlock).stretch(usize::MAX);
+        assert_eq!(renderer.width(&matrix), usize::MAX);
+        let err = renderer.render(&matrix, &mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let renderer = Renderer::new(Style::Braille).stretch(MAX_COLUMNS / 21 * 2);
+        assert!(renderer.width(&matrix) <= MAX_COLUMNS);
+        let mut buf = Vec::new();
+        renderer.render(&matrix, &mut buf).unwrap();
+        assert_eq!(buf.iter().filter(|&&byte| byte == b'\n').count(), 6);
+    }
+
+    #[test]
+    fn render_writes_lines() {
+        /// Counts the writes made to it.
+        #[derive(Default)]
+        struct Writes(usize);
+
+        impl Write for Writes {
+            fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+                self.0 += 1;
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> IoResult<()> {
+                Ok(())
+            }
+        }
+
+        let matrix = Matrix::new(vec![QrDark; 5 * 5]);
+        for (style, lines) in [
+            (Style::HalfBlock, 3),
+            (Style::FullBlock, 5),
+            (Style::Braille, 2),
+        ] {
+            let mut writes = Writes::default();
+            Renderer::new(style).render(&matrix, &mut writes).unwrap();
+            assert_eq!(writes.0, lines, "{}", style);
+        }
+    }
+
+    #[test]
+    fn sextant_glyphs() {
+        assert_eq!(sextant_glyph(0b00_0001), '\u{1FB00}');
+        assert_eq!(sextant_glyph(0b01_0100), '\u{1FB13}');
+        assert_eq!(sextant_glyph(0b01_0110), '\u{1FB14}');
+        assert_eq!(sextant_glyph(0b10_1001), '\u{1FB27}');
+        assert_eq!(sextant_glyph(0b10_1011), '\u{1FB28}');
+        assert_eq!(sextant_glyph(0b11_1110), '\u{1FB3B}');
+        assert_eq!(sextant_glyph(0b01_0101), '▌');
+    }
+
+    #[test]
+    fn smooth() {
+        // 2 modules over 3 pixels, the middle one straddling both
+        let matrix = Matrix::new(vec![QrDark, QrLight, QrLight, QrLight]);
+        let renderer = || {
+            Renderer::new(Style::Smooth)
+                .deterministic(true)
+                .max_width(Some(3))
+        };
+        assert_eq!(
+            (renderer().width(&matrix), renderer().height(&matrix)),
+            (3, 2)
+        );
+        let mut buf = Vec::new();
+        renderer().ansi(false).render(&matrix, &mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "▓▒ \n   \n");
+
+        let mut buf = Vec::new();
+        renderer().render(&matrix, &mut buf).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.starts_with("\x1B[48;2;0;0;0m\x1B[38;2;127;127;127m▄"));
+        assert_eq!(text.lines().count(), 2);
+
+        // Never fewer pixels than modules
+        let renderer = Renderer::new(Style::Smooth)
+            .deterministic(true)
+            .max_width(Some(1));
+        assert_eq!(renderer.width(&matrix), 2);
+
+        // Filling the given terminal but its last row
+        let renderer = Renderer::new(Style::Smooth).terminal_size(Some((80, 6)));
+        assert_eq!((renderer.width(&matrix), renderer.height(&matrix)), (10, 5));
+    }
+
+    #[test]
+    fn fit() {
+        let matrix = Matrix::new(vec![QrDark; 25 * 25]);
+        assert_eq!(Style::fit(&matrix, Some((80, 30))), Style::FullBlock);
+        assert_eq!(Style::fit(&matrix, Some((80, 20))), Style::HalfBlock);
+        assert_eq!(Style::fit(&matrix, Some((20, 13))), Style::Quadrant);
+        assert_eq!(Style::fit(&matrix, Some((13, 7))), Style::Braille);
+        assert_eq!(Style::fit(&matrix, Some((5, 5))), Style::Braille);
+        assert_eq!(Style::fit(&matrix, None), Style::HalfBlock);
+
+        let renderer = Renderer::new(Style::Auto).deterministic(true);
+        assert_eq!(renderer.resolve_style(&matrix), Style::HalfBlock);
+        let renderer = renderer.max_width(Some(20));
+        assert_eq!(renderer.resolve_style(&matrix), Style::Quadrant);
+    }
+
+    #[test]
+    fn style_from_str() {
+        for style in Style::ALL {
+            assert_eq!(style.name().parse(), Ok(style));
+        }
+        assert_eq!("Half-Block".parse(), Ok(Style::HalfBlock));
+        assert_eq!("auto".parse(), Ok(Style::Auto));
+        assert!("sparkles".parse::<Style>().is_err());
+    }
 }
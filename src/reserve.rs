@@ -0,0 +1,407 @@
+//! Blank areas in image exports, for stickers and labels placed later.
+//!
+//! Printed codes often get a sticker on top after printing, such as a serial
+//! number or a logo. A [`Reserve`] keeps a rectangle of modules light in PNG
+//! images, so the sticker covers nothing but blank space, and checks that
+//! the code still scans with the rectangle covered:
+//!
+//! - The rectangle may not cover finder, timing, alignment, format or version
+//!   patterns, which scanners need to find and read the code at all.
+//! - The codewords it covers may take at most [`BUDGET_PERCENT`] of the
+//!   codewords error correction can restore, leaving the rest for smudges,
+//!   glare and wear.
+//!
+//! ```rust
+//! use qr2term::reserve::Reserve;
+//! use qr2term::QrOptions;
+//!
+//! let reserve = Reserve::centered(7, 5);
+//! let options = QrOptions::new().ec_level(qr2term::options::EcLevel::H).reserve(reserve);
+//! let data = "https://example.com/asset/0042";
+//! let occlusion = qr2term::reserve::occlusion(data, reserve, &options).unwrap();
+//! assert!(occlusion.codewords <= occlusion.budget());
+//! ```
+//!
+//! Higher [error correction levels](crate::QrOptions::ec_level) leave room for
+//! larger rectangles.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use qrcode::canvas::is_functional;
+use qrcode::{ec, Version};
+
+use crate::matrix::Matrix;
+use crate::qr::Qr;
+use crate::render::Color;
+use crate::{QrOptions, ToQrPayload};
+
+/// Share of the codewords error correction can restore that a reserved
+/// rectangle may cover, in percent.
+pub const BUDGET_PERCENT: usize = 50;
+
+/// A rectangle of modules kept light in image exports.
+///
+/// Coordinates are in modules of the symbol, excluding the quiet zone, and
+/// apply before [mirroring](crate::QrOptions::mirror) and
+/// [rotating](crate::QrOptions::rotate) it. Parsed from and displayed as
+/// `WxH`, or `WxH+X+Y` with a position.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Reserve {
+    /// Width of the rectangle, in modules.
+    pub width: usize,
+
+    /// Height of the rectangle, in modules.
+    pub height: usize,
+
+    /// Column and row of the top left corner, or `None` to center the
+    /// rectangle in the symbol.
+    pub position: Option<(usize, usize)>,
+}
+
+impl Reserve {
+    /// Construct a rectangle of `width` by `height` modules, centered in the
+    /// symbol.
+    pub fn centered(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            position: None,
+        }
+    }
+
+    /// Construct a rectangle of `width` by `height` modules, with its top
+    /// left corner at column `x` of row `y`.
+    pub fn at(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            position: Some((x, y)),
+        }
+    }
+
+    /// Get the column and row of the top left corner in a symbol of `size`
+    /// modules, or `None` if the rectangle doesn't fit it.
+    fn origin(&self, size: usize) -> Option<(usize, usize)> {
+        let (x, y) = self.position.unwrap_or((
+            size.saturating_sub(self.width) / 2,
+            size.saturating_sub(self.height) / 2,
+        ));
+        match x + self.width <= size && y + self.height <= size {
+            true => Some((x, y)),
+            false => None,
+        }
+    }
+
+    /// Check that `code` still scans with this rectangle blank.
+    ///
+    /// Returns how much of the code the rectangle covers, or an error if it
+    /// doesn't fit the symbol, covers one of its function patterns, or
+    /// covers more codewords than the [budget](Occlusion::budget).
+    pub fn check(&self, code: &Qr) -> Result<Occlusion, ReserveError> {
+        let info = code.info();
+        let size = info.module_count;
+        let (x0, y0) = self
+            .origin(size)
+            .ok_or(ReserveError::OutOfBounds { size })?;
+        let inside = |x: usize, y: usize| {
+            (x0..x0 + self.width).contains(&x) && (y0..y0 + self.height).contains(&y)
+        };
+        for y in y0..y0 + self.height {
+            for x in x0..x0 + self.width {
+                if is_functional(info.version, size as i16, x as i16, y as i16) {
+                    return Err(ReserveError::FunctionPattern { x, y });
+                }
+            }
+        }
+
+        // Remainder bits after the last codeword don't belong to any
+        let modules = data_modules(info.version, size);
+        let total = modules.len() / 8;
+        let mut covered = vec![false; total];
+        for (bit, _) in modules
+            .iter()
+            .enumerate()
+            .filter(|(_, &(x, y))| inside(x, y))
+        {
+            if let Some(codeword) = covered.get_mut(bit / 8) {
+                *codeword = true;
+            }
+        }
+        let occlusion = Occlusion {
+            modules: self.width * self.height,
+            codewords: covered.iter().filter(|&&covered| covered).count(),
+            correctable: ec::max_allowed_errors(info.version, info.ec_level).unwrap_or(0),
+        };
+        match occlusion.codewords <= occlusion.budget() {
+            true => Ok(occlusion),
+            false => Err(ReserveError::OverBudget {
+                codewords: occlusion.codewords,
+                budget: occlusion.budget(),
+            }),
+        }
+    }
+}
+
+impl Display for Reserve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)?;
+        match self.position {
+            Some((x, y)) => write!(f, "+{}+{}", x, y),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Reserve {
+    type Err = ParseReserveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseReserveError { text: s.to_owned() };
+        let text = s.trim().to_ascii_lowercase();
+        let mut parts = text.split('+');
+        let (width, height) = parts
+            .next()
+            .and_then(|size| size.split_once('x'))
+            .ok_or_else(err)?;
+        let number = |text: &str| text.parse::<usize>().map_err(|_| err());
+        let (width, height) = (number(width)?, number(height)?);
+        if width == 0 || height == 0 {
+            return Err(err());
+        }
+        match (parts.next(), parts.next(), parts.next()) {
+            (None, ..) => Ok(Self::centered(width, height)),
+            (Some(x), Some(y), None) => Ok(Self::at(number(x)?, number(y)?, width, height)),
+            _ => Err(err()),
+        }
+    }
+}
+
+/// Error returned when parsing a malformed [`Reserve`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseReserveError {
+    text: String,
+}
+
+impl Display for ParseReserveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid reserved area '{}', expected WxH or WxH+X+Y",
+            self.text
+        )
+    }
+}
+
+impl Error for ParseReserveError {}
+
+/// How much of a code a reserved rectangle covers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Occlusion {
+    /// Number of modules covered.
+    pub modules: usize,
+
+    /// Number of codewords with at least one module covered.
+    pub codewords: usize,
+
+    /// Number of codewords error correction can restore.
+    pub correctable: usize,
+}
+
+impl Occlusion {
+    /// Largest number of codewords the rectangle may cover, the
+    /// [`BUDGET_PERCENT`] share of the [`correctable`](Self::correctable)
+    /// ones.
+    pub fn budget(&self) -> usize {
+        self.correctable * BUDGET_PERCENT / 100
+    }
+}
+
+/// Error returned when a reserved rectangle would keep a code from scanning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReserveError {
+    /// The rectangle doesn't fit the symbol of the given size, in modules.
+    OutOfBounds {
+        /// Width and height of the symbol, in modules.
+        size: usize,
+    },
+
+    /// The rectangle covers the function pattern module at column `x` of row
+    /// `y`.
+    FunctionPattern {
+        /// Column of the module.
+        x: usize,
+
+        /// Row of the module.
+        y: usize,
+    },
+
+    /// The rectangle covers more codewords than the budget.
+    OverBudget {
+        /// Number of codewords covered.
+        codewords: usize,
+
+        /// Largest number of codewords allowed.
+        budget: usize,
+    },
+}
+
+impl Display for ReserveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReserveError::OutOfBounds { size } => write!(
+                f,
+                "reserved area doesn't fit the code of {}x{} modules",
+                size, size
+            ),
+            ReserveError::FunctionPattern { x, y } => write!(
+                f,
+                "reserved area covers a function pattern at module {},{}",
+                x, y
+            ),
+            ReserveError::OverBudget { codewords, budget } => write!(
+                f,
+                "reserved area covers {} codewords, exceeding the budget of {}; \
+                 use a higher error correction level or a smaller area",
+                codewords, budget
+            ),
+        }
+    }
+}
+
+impl Error for ReserveError {}
+
+/// Get how much of the code for `data`, generated with the given `options`,
+/// the `reserve` rectangle covers.
+///
+/// Returns an error if generating the QR code failed, or the rectangle would
+/// keep it from scanning, see [`Reserve::check`].
+pub fn occlusion<D: ToQrPayload>(
+    data: D,
+    reserve: Reserve,
+    options: &QrOptions,
+) -> Result<Occlusion, crate::Error> {
+    let code = crate::generate_code(data, options)?;
+    Ok(reserve.check(&code)?)
+}
+
+/// Build the pixel matrix of the image export of `data`, with the rectangle
+/// [reserved](QrOptions::reserve) in `options` blank, if any.
+///
+/// Returns an error if generating the QR code failed, or the rectangle would
+/// keep it from scanning.
+pub(crate) fn image_matrix<D: ToQrPayload>(
+    data: D,
+    options: &QrOptions,
+) -> Result<Matrix<Color>, crate::Error> {
+    let mut code = crate::generate_code(data, options)?;
+    if let Some(reserve) = options.reserve {
+        let occlusion = reserve.check(&code)?;
+        log_event!(
+            debug,
+            "reserved {} covers {} of {} codewords",
+            reserve,
+            occlusion.codewords,
+            occlusion.budget()
+        );
+        let (x, y) = reserve
+            .origin(code.info().module_count)
+            .expect("checked to fit");
+        code.blank(x, y, reserve.width, reserve.height);
+    }
+    Ok(crate::code_matrix(&code, options)?)
+}
+
+/// Get the data modules of a normal symbol of the given `version` and
+/// `size`, in the order bits are placed in them.
+///
+/// Bits go up and down two columns at a time, starting at the bottom right,
+/// skipping the vertical timing pattern.
+fn data_modules(version: Version, size: usize) -> Vec<(usize, usize)> {
+    let mut modules = Vec::new();
+    let mut right = size as i16 - 1;
+    let mut upward = true;
+    while right > 0 {
+        if right == 6 {
+            right = 5;
+        }
+        for i in 0..size as i16 {
+            let y = if upward { size as i16 - 1 - i } else { i };
+            for x in [right, right - 1] {
+                if !is_functional(version, size as i16, x, y) {
+                    modules.push((x as usize, y as usize));
+                }
+            }
+        }
+        upward = !upward;
+        right -= 2;
+    }
+    modules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::EcLevel;
+    use crate::render::QrLight;
+
+    #[test]
+    fn parse() {
+        assert_eq!("8x6".parse(), Ok(Reserve::centered(8, 6)));
+        assert_eq!(" 8X6+10+12".parse(), Ok(Reserve::at(10, 12, 8, 6)));
+        assert_eq!(Reserve::at(1, 2, 3, 4).to_string(), "3x4+1+2");
+        for text in ["8", "0x6", "8x6+1", "8x6+1+2+3", "axb"] {
+            assert!(text.parse::<Reserve>().is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn budget() {
+        // Version 1 has 26 codewords in 208 data modules
+        let code = Qr::from("qr2term").unwrap();
+        assert_eq!(data_modules(Version::Normal(1), 21).len(), 208);
+
+        let occlusion = Reserve::centered(3, 3).check(&code).unwrap();
+        assert_eq!((occlusion.modules, occlusion.correctable), (9, 4));
+        assert!(occlusion.codewords <= occlusion.budget());
+        assert_eq!(
+            Reserve::at(0, 0, 3, 3).check(&code),
+            Err(ReserveError::FunctionPattern { x: 0, y: 0 })
+        );
+        assert_eq!(
+            Reserve::at(15, 15, 7, 1).check(&code),
+            Err(ReserveError::OutOfBounds { size: 21 })
+        );
+        assert!(matches!(
+            Reserve::at(9, 9, 6, 4).check(&code),
+            Err(ReserveError::OverBudget { .. })
+        ));
+    }
+
+    #[test]
+    fn blank_in_images() {
+        let data = "https://example.com/asset/0042";
+        let reserve = Reserve::centered(7, 5);
+        let options = QrOptions::new()
+            .ec_level(EcLevel::H)
+            .quiet_zone(0)
+            .reserve(reserve);
+        let occlusion = occlusion(data, reserve, &options).unwrap();
+        assert!(occlusion.codewords > 0);
+
+        let matrix = image_matrix(data, &options).unwrap();
+        let size = matrix.size();
+        let (x, y) = ((size - 7) / 2, (size - 5) / 2);
+        for row in y..y + 5 {
+            let modules = &matrix.pixels()[row * size + x..][..7];
+            assert!(modules.iter().all(|&module| module == QrLight));
+        }
+
+        let options = options.ec_level(EcLevel::L);
+        assert!(matches!(
+            image_matrix(data, &options),
+            Err(crate::Error::Reserve(ReserveError::OverBudget { .. }))
+        ));
+    }
+}
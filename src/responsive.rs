@@ -0,0 +1,105 @@
+//! Responsive rendering, following the size of the terminal.
+//!
+//! For long-lived "scan me" screens, [`print_qr_responsive`] keeps the code on
+//! screen and re-renders it in place whenever the terminal is resized, in the
+//! largest style that fits the new size.
+
+use std::io::{self, Write};
+
+use crossterm::{
+    cursor::MoveTo,
+    event::{self, Event},
+    queue,
+    terminal::{Clear, ClearType},
+    tty::IsTty,
+};
+
+use crate::matrix::Matrix;
+use crate::render::{Color, Renderer, Style};
+use crate::term::RawMode;
+use crate::{Error, QrOptions, ToQrPayload};
+
+/// Print the given `data` as QR code, re-rendering it whenever the terminal is
+/// resized, until a key is pressed.
+///
+/// The screen is cleared and the code drawn in its top left corner, in the
+/// style picked by [`Style::fit`] for the current terminal size. The style
+/// configured in `options` is ignored.
+///
+/// Returns an error if generating the QR code, or reading from or writing to
+/// the terminal failed, or [`Error::NotATerminal`] if stdout is not a terminal.
+pub fn print_qr_responsive<D: ToQrPayload>(data: D, options: &QrOptions) -> Result<(), Error> {
+    let mut stdout = io::stdout();
+    if !stdout.is_tty() {
+        return Err(Error::NotATerminal);
+    }
+    let matrix = crate::code_matrix(&crate::generate_code(data, options)?, options)?;
+    draw(&mut stdout, &matrix, options, crate::term::size())?;
+
+    loop {
+        // Only hold raw mode while waiting, as rendering relies on newlines
+        // returning the cursor to the first column
+        let event = {
+            let _raw = RawMode::enable()?;
+            event::read()?
+        };
+        match event {
+            Event::Resize(columns, rows) => {
+                draw(
+                    &mut stdout,
+                    &matrix,
+                    options,
+                    Some((columns as usize, rows as usize)),
+                )?;
+            }
+            Event::Key(_) => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+/// Clear the screen, and draw `matrix` in the style that best fits a terminal
+/// of the given size, in the theme configured in `options`.
+///
+/// Returns the style that was drawn in.
+fn draw<W: Write>(
+    target: &mut W,
+    matrix: &Matrix<Color>,
+    options: &QrOptions,
+    terminal_size: Option<(usize, usize)>,
+) -> io::Result<Style> {
+    let style = Style::fit(matrix, terminal_size);
+    queue!(target, MoveTo(0, 0), Clear(ClearType::All))?;
+    Renderer::new(style)
+        .theme(options.theme)
+        .render(matrix, target)?;
+    target.flush()?;
+    Ok(style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qr::Qr;
+
+    #[test]
+    fn draw_fits() {
+        let options = QrOptions::new();
+        let matrix = crate::code_matrix(&Qr::from("qr2term").unwrap(), &options).unwrap();
+
+        let mut buf = Vec::new();
+        assert_eq!(
+            draw(&mut buf, &matrix, &options, Some((200, 100))).unwrap(),
+            Style::FullBlock
+        );
+        assert!(String::from_utf8(buf)
+            .unwrap()
+            .starts_with("\x1b[1;1H\x1b[2J"));
+
+        let mut buf = Vec::new();
+        assert_eq!(
+            draw(&mut buf, &matrix, &options, Some((20, 10))).unwrap(),
+            Style::Braille
+        );
+    }
+}
@@ -0,0 +1,422 @@
+//! Request and response types for QR code rendering services.
+//!
+//! [`handle`] renders a [`RenderRequest`] into a [`RenderResponse`] without
+//! side effects, so an internal rendering service can be stood up behind any
+//! transport, such as HTTP, gRPC or JSON-RPC, reusing the types of this crate.
+//! Requests and responses convert from and to JSON without extra
+//! dependencies.
+//!
+//! ```rust
+//! use qr2term::service::{self, RenderRequest};
+//!
+//! let request = RenderRequest::from_json(
+//!     r#"{"payload": "https://rust-lang.org/", "style": "ascii", "format": "plain"}"#,
+//! )
+//! .unwrap();
+//! let response = service::handle(&request).unwrap();
+//! assert_eq!(response.content_type(), "text/plain;charset=utf-8");
+//! assert!(response.to_json().contains(r#""format":"plain""#));
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::json::{self, Value};
+use crate::options::parse_ec_level;
+use crate::qr::QrInfo;
+use crate::render::Style;
+use crate::{png, util, Error, QrError, QrOptions};
+
+/// Size of a module in rendered PNG images, in pixels.
+pub const PNG_MODULE_SIZE: usize = 8;
+
+/// Format to render a code in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Format {
+    /// Text without colors.
+    #[default]
+    Plain,
+
+    /// Text with ANSI colors, for printing to a terminal.
+    Ansi,
+
+    /// A PNG image, see [`PNG_MODULE_SIZE`].
+    Png,
+}
+
+impl Format {
+    /// Name of this format, as accepted by its `FromStr` implementation.
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Plain => "plain",
+            Format::Ansi => "ansi",
+            Format::Png => "png",
+        }
+    }
+
+    /// The MIME type of this format.
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Format::Plain | Format::Ansi => "text/plain;charset=utf-8",
+            Format::Png => "image/png",
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Format {
+    type Err = ParseFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        [Format::Plain, Format::Ansi, Format::Png]
+            .into_iter()
+            .find(|format| format.name() == name)
+            .ok_or(ParseFormatError { name })
+    }
+}
+
+/// Error returned when parsing an unknown [`Format`] name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFormatError {
+    name: String,
+}
+
+impl Display for ParseFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown render format '{}'", self.name)
+    }
+}
+
+impl StdError for ParseFormatError {}
+
+/// Request to render a code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderRequest {
+    /// Text to encode.
+    pub payload: String,
+
+    /// Style to render text formats in.
+    pub style: Style,
+
+    /// Format to render in.
+    pub format: Format,
+
+    /// Other options to render with, their style is replaced by [`style`](Self::style).
+    pub options: QrOptions,
+}
+
+impl RenderRequest {
+    /// Construct a request to render `payload` in plain text, with the
+    /// [`QrOptions::new`] options.
+    pub fn new(payload: &str) -> Self {
+        let options = QrOptions::new();
+        Self {
+            payload: payload.into(),
+            style: options.style,
+            format: Format::default(),
+            options,
+        }
+    }
+
+    /// Set the style to render text formats in.
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Set the format to render in.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the other options to render with.
+    pub fn options(mut self, options: QrOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Parse a request from a JSON object.
+    ///
+    /// The `payload` member is required. The optional `style`, `format`,
+    /// `theme` and `ecLevel` members take the same names as their `FromStr`
+    /// implementations, and `quietZone` a number of modules. Unknown members
+    /// are ignored, so clients can send fields of newer versions.
+    ///
+    /// Returns an error if the JSON is invalid, or a member is missing or invalid.
+    pub fn from_json(json: &str) -> Result<Self, ServiceError> {
+        let members = match json::parse(json).map_err(|err| invalid(err.to_string()))? {
+            Value::Object(members) => members,
+            _ => return Err(invalid("request is not an object")),
+        };
+        let member = |name: &str| {
+            members
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value)
+        };
+        let string = |name: &str| match member(name) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value.as_str())),
+            Some(_) => Err(invalid(format!("'{}' is not a string", name))),
+        };
+
+        let payload = string("payload")?.ok_or_else(|| invalid("'payload' is missing"))?;
+        let mut request = Self::new(payload);
+        if let Some(style) = string("style")? {
+            request.style = parse("style", style)?;
+        }
+        if let Some(format) = string("format")? {
+            request.format = parse("format", format)?;
+        }
+        if let Some(theme) = string("theme")? {
+            request.options.theme = parse("theme", theme)?;
+        }
+        if let Some(ec_level) = string("ecLevel")? {
+            request.options.ec_level = parse_ec_level(ec_level)
+                .ok_or_else(|| invalid(format!("invalid 'ecLevel': '{}'", ec_level)))?;
+        }
+        match member("quietZone") {
+            None | Some(Value::Null) => {}
+            Some(Value::Number(number)) => {
+                request.options.quiet_zone = number
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid 'quietZone': {}", number)))?;
+            }
+            Some(_) => return Err(invalid("'quietZone' is not a number")),
+        }
+        Ok(request)
+    }
+}
+
+/// Parse the member `name` from `value`.
+fn parse<T>(name: &str, value: &str) -> Result<T, ServiceError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|err| invalid(format!("invalid '{}': {}", name, err)))
+}
+
+/// Error for an invalid request, for the given `reason`.
+fn invalid<S: Into<String>>(reason: S) -> ServiceError {
+    ServiceError::InvalidRequest(reason.into())
+}
+
+/// Rendered code, in response to a [`RenderRequest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderResponse {
+    /// Format the code is rendered in.
+    pub format: Format,
+
+    /// The rendered code.
+    pub body: Vec<u8>,
+
+    /// Metadata about the generated symbol.
+    pub info: QrInfo,
+}
+
+impl RenderResponse {
+    /// The MIME type of the rendered code, for a `Content-Type` header.
+    pub fn content_type(&self) -> &'static str {
+        self.format.mime_type()
+    }
+
+    /// Format this response as a JSON object.
+    ///
+    /// Has the `format` and `contentType`, the `body` as text or Base64 for
+    /// images, and the `version`, `ecLevel`, `mask` and `modules` of the
+    /// symbol. Micro QR versions are prefixed with `M`.
+    pub fn to_json(&self) -> String {
+        let body = match self.format {
+            Format::Png => util::encode_base64(&self.body),
+            Format::Plain | Format::Ansi => String::from_utf8_lossy(&self.body).into_owned(),
+        };
+        Value::Object(vec![
+            ("format".into(), Value::String(self.format.name().into())),
+            (
+                "contentType".into(),
+                Value::String(self.content_type().into()),
+            ),
+            ("body".into(), Value::String(body)),
+            ("version".into(), Value::String(self.info.version_name())),
+            (
+                "ecLevel".into(),
+                Value::String(format!("{:?}", self.info.ec_level)),
+            ),
+            ("mask".into(), Value::Number(self.info.mask.to_string())),
+            (
+                "modules".into(),
+                Value::Number(self.info.module_count.to_string()),
+            ),
+        ])
+        .to_canonical()
+    }
+}
+
+/// Error returned when handling a [`RenderRequest`] failed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ServiceError {
+    /// The request is invalid, for the given reason.
+    InvalidRequest(String),
+
+    /// Generating or rendering the QR code failed.
+    Render(Error),
+}
+
+impl Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceError::InvalidRequest(reason) => write!(f, "invalid request: {}", reason),
+            ServiceError::Render(err) => err.fmt(f),
+        }
+    }
+}
+
+impl StdError for ServiceError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            ServiceError::InvalidRequest(_) => None,
+            ServiceError::Render(err) => Some(err),
+        }
+    }
+}
+
+impl From<Error> for ServiceError {
+    fn from(err: Error) -> Self {
+        ServiceError::Render(err)
+    }
+}
+
+impl From<QrError> for ServiceError {
+    fn from(err: QrError) -> Self {
+        ServiceError::Render(err.into())
+    }
+}
+
+/// Render the code for `request`.
+///
+/// Renders a single code, text too long for one code is an error whatever the
+/// [`on_overflow`](QrOptions::on_overflow) option. Hooks of the options are
+/// not invoked.
+///
+/// Returns an error if generating or rendering the QR code failed.
+pub fn handle(request: &RenderRequest) -> Result<RenderResponse, ServiceError> {
+    let options = QrOptions {
+        style: request.style,
+        hooks: None,
+        ..request.options.clone()
+    };
+    let code = crate::generate_code(request.payload.as_str(), &options)?;
+    let matrix = crate::code_matrix(&code, &options).map_err(Error::from)?;
+    let body = match request.format {
+        Format::Png => png::encode(&matrix, PNG_MODULE_SIZE),
+        format => {
+            let renderer = crate::renderer(&options).ansi(format == Format::Ansi);
+            if let Some(max_width) = options.max_width {
+                let width = renderer.width(&matrix);
+                if width > max_width {
+                    return Err(Error::TooWide { width, max_width }.into());
+                }
+            }
+            let mut buf = Vec::new();
+            renderer.render(&matrix, &mut buf).map_err(Error::from)?;
+            buf
+        }
+    };
+    Ok(RenderResponse {
+        format: request.format,
+        body,
+        info: code.info(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handle_formats() {
+        let request = RenderRequest::new("qr2term").style(Style::Ascii);
+        let response = handle(&request).unwrap();
+        let text = String::from_utf8(response.body.clone()).unwrap();
+        assert!(text.contains("##"));
+        assert!(!text.contains('\x1B'));
+        assert_eq!(response.info, crate::Qr::from("qr2term").unwrap().info());
+
+        let response =
+            handle(&request.clone().style(Style::FullBlock).format(Format::Ansi)).unwrap();
+        assert!(response.body.contains(&0x1B));
+
+        let response = handle(&request.format(Format::Png)).unwrap();
+        assert!(response.body.starts_with(b"\x89PNG"));
+        assert_eq!(response.content_type(), "image/png");
+    }
+
+    #[test]
+    fn request_from_json() {
+        let request = RenderRequest::from_json(
+            r#"{"payload": "a", "style": "full", "format": "PNG", "ecLevel": "H",
+                "quietZone": 1, "theme": "monochrome", "unknown": []}"#,
+        )
+        .unwrap();
+        assert_eq!(request.payload, "a");
+        assert_eq!(request.style, Style::FullBlock);
+        assert_eq!(request.format, Format::Png);
+        assert_eq!(request.options.ec_level, qrcode::EcLevel::H);
+        assert_eq!(request.options.quiet_zone, 1);
+        assert_eq!(
+            RenderRequest::from_json(r#"{"payload": "a"}"#).unwrap(),
+            RenderRequest::new("a")
+        );
+
+        for json in [
+            "[]",
+            "{",
+            "{}",
+            r#"{"payload": 1}"#,
+            r#"{"payload": "a", "style": "wide"}"#,
+            r#"{"payload": "a", "ecLevel": "X"}"#,
+            r#"{"payload": "a", "quietZone": -1}"#,
+        ] {
+            let err = RenderRequest::from_json(json).unwrap_err();
+            assert!(matches!(err, ServiceError::InvalidRequest(_)), "{}", json);
+        }
+    }
+
+    #[test]
+    fn response_to_json() {
+        let request = RenderRequest::new("a").style(Style::Ascii);
+        let json = handle(&request).unwrap().to_json();
+        assert!(json.starts_with(r#"{"body":"    "#));
+        assert!(json.ends_with(
+            r#""contentType":"text/plain;charset=utf-8","ecLevel":"M","format":"plain","mask":5,"modules":21,"version":"1"}"#
+        ));
+
+        let json = handle(&request.format(Format::Png)).unwrap().to_json();
+        assert!(json.starts_with(r#"{"body":"iVBORw0KGgo"#));
+    }
+
+    #[test]
+    fn handle_errors() {
+        let err = handle(&RenderRequest::new(&"a".repeat(8000))).unwrap_err();
+        assert!(matches!(
+            err,
+            ServiceError::Render(Error::Qr(QrError::DataTooLong))
+        ));
+
+        let request = RenderRequest::new("a").options(QrOptions::new().max_width(10));
+        let err = handle(&request).unwrap_err();
+        assert!(matches!(err, ServiceError::Render(Error::TooWide { .. })));
+    }
+}
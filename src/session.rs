@@ -0,0 +1,82 @@
+//! Rendering for remote terminal sessions, such as SSH channels.
+//!
+//! Servers showing a code to a connecting client, for example to pair a
+//! device, can't rely on the size of their own terminal, or on the client's
+//! terminal translating line feeds. [`render_for_session`] renders for an
+//! explicit terminal size, as reported by the client, with CRLF line endings
+//! written through the same raw mode conversion as
+//! [`RawMode::Enabled`](crate::options::RawMode::Enabled). The result is a
+//! byte buffer for the server to write to the channel in one go; no
+//! `AsyncWrite` target is provided.
+
+use crate::options::RawMode;
+use crate::render::{Renderer, Style};
+use crate::{Error, QrOptions, ToQrPayload};
+
+/// Render `data` as QR code for a remote terminal of the given size, as
+/// `(columns, rows)`, with CRLF line endings.
+///
+/// The [`Style::Auto`] style is resolved, and the [`Style::Smooth`] style
+/// scaled, for the given size instead of the local terminal. Data split over
+/// multiple codes is fitted by its first code.
+///
+/// Returns an error if generating the QR code failed.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::{render::Style, session, QrOptions};
+///
+/// let options = QrOptions::new().style(Style::Auto);
+/// let code = session::render_for_session("https://rust-lang.org/", &options, (80, 24)).unwrap();
+/// assert!(code.ends_with(b"\r\n"));
+/// ```
+pub fn render_for_session<D: ToQrPayload>(
+    data: D,
+    options: &QrOptions,
+    terminal_size: (usize, usize),
+) -> Result<Vec<u8>, Error> {
+    let style = match options.style {
+        Style::Auto => {
+            let matrix = crate::code_matrix(&crate::generate_code(&data, options)?, options)?;
+            Style::fit(&matrix, Some(terminal_size))
+        }
+        style => style,
+    };
+    let renderer = Renderer::new(style)
+        .theme(options.theme)
+        .terminal_size(Some(terminal_size));
+    let options = QrOptions {
+        raw_mode: RawMode::Enabled,
+        ..options.clone()
+    };
+    let mut buf = Vec::new();
+    crate::render_qr_with(&data, &options, &renderer, &mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crlf_and_size() {
+        let options = QrOptions::new().style(Style::Auto);
+        let fit = |size| {
+            let code = render_for_session("qr2term", &options, size).unwrap();
+            let text = String::from_utf8(code).unwrap();
+            assert!(!text.replace("\r\n", "").contains('\n'));
+            text.matches("\r\n").count()
+        };
+
+        // Full blocks, half blocks, then Braille for the 25 module code
+        assert_eq!(fit((80, 30)), 25);
+        assert_eq!(fit((40, 20)), 13);
+        assert_eq!(fit((10, 5)), 7);
+
+        // Smooth codes fill the client's terminal but its last row
+        let options = options.style(Style::Smooth);
+        let code = render_for_session("qr2term", &options, (200, 41)).unwrap();
+        assert_eq!(String::from_utf8(code).unwrap().matches("\r\n").count(), 40);
+    }
+}
@@ -0,0 +1,155 @@
+//! Signed payloads, for tamper-evident codes such as event tickets.
+//!
+//! A payload is signed by appending an HMAC-SHA256 tag computed with a secret
+//! key, see [`sign`]. Anyone holding the same key can check that the payload
+//! wasn't altered or forged with [`verify`]. Since the key is shared, this
+//! suits closed-loop systems where the issuer and the checker are the same
+//! party.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::crypto::constant_time_eq;
+use crate::crypto::sha256::{Hmac, DIGEST_LEN};
+
+/// Size of the signature appended by [`sign`], in bytes.
+pub const SIGNATURE_LEN: usize = DIGEST_LEN;
+
+/// Separator between the text and its signature, used by [`sign_text`].
+pub const TEXT_SEPARATOR: char = '.';
+
+/// Sign `data` with `key`, returning the data followed by its signature.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::sign;
+///
+/// let signed = sign::sign(b"ticket 42", b"event secret");
+/// assert_eq!(sign::verify(&signed, b"event secret").unwrap(), b"ticket 42");
+/// assert!(sign::verify(&signed, b"wrong secret").is_err());
+/// ```
+pub fn sign(data: &[u8], key: &[u8]) -> Vec<u8> {
+    let mut signed = data.to_vec();
+    signed.extend_from_slice(&Hmac::new(key).mac(data));
+    signed
+}
+
+/// Verify data produced by [`sign`] with `key`, returning the original data.
+///
+/// Returns an error if the data is too short to hold a signature, or if the
+/// signature doesn't match.
+pub fn verify<'a>(signed: &'a [u8], key: &[u8]) -> Result<&'a [u8], VerifyError> {
+    let split = signed
+        .len()
+        .checked_sub(SIGNATURE_LEN)
+        .ok_or(VerifyError::Missing)?;
+    let (data, signature) = signed.split_at(split);
+    check(data, signature, key)?;
+    Ok(data)
+}
+
+/// Sign `text` with `key`, keeping the result printable.
+///
+/// The signature is appended in hexadecimal, after a [`TEXT_SEPARATOR`]. This
+/// is longer than [`sign`], but keeps the code readable by any scanner.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::sign;
+///
+/// let signed = sign::sign_text("ticket 42", b"event secret");
+/// assert!(signed.starts_with("ticket 42."));
+/// qr2term::print_qr(&signed).unwrap();
+///
+/// assert_eq!(sign::verify_text(&signed, b"event secret").unwrap(), "ticket 42");
+/// ```
+pub fn sign_text(text: &str, key: &[u8]) -> String {
+    let mut signed = String::with_capacity(text.len() + 1 + 2 * SIGNATURE_LEN);
+    signed.push_str(text);
+    signed.push(TEXT_SEPARATOR);
+    for byte in Hmac::new(key).mac(text.as_bytes()) {
+        signed.push_str(&format!("{:02x}", byte));
+    }
+    signed
+}
+
+/// Verify text produced by [`sign_text`] with `key`, returning the original text.
+///
+/// Returns an error if the text has no valid signature, or if the signature
+/// doesn't match.
+pub fn verify_text<'a>(signed: &'a str, key: &[u8]) -> Result<&'a str, VerifyError> {
+    let (text, hex) = signed
+        .rsplit_once(TEXT_SEPARATOR)
+        .ok_or(VerifyError::Missing)?;
+    if hex.len() != 2 * SIGNATURE_LEN || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(VerifyError::Missing);
+    }
+    let signature: Vec<u8> = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect();
+    check(text.as_bytes(), &signature, key)?;
+    Ok(text)
+}
+
+/// Check that `signature` is the signature of `data` for `key`.
+fn check(data: &[u8], signature: &[u8], key: &[u8]) -> Result<(), VerifyError> {
+    if constant_time_eq(&Hmac::new(key).mac(data), signature) {
+        Ok(())
+    } else {
+        Err(VerifyError::Mismatch)
+    }
+}
+
+/// Error returned when verifying a signed payload failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The payload doesn't hold a signature.
+    Missing,
+
+    /// The signature doesn't match, the payload was altered or signed with another key.
+    Mismatch,
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Missing => f.write_str("missing payload signature"),
+            VerifyError::Mismatch => f.write_str("payload signature mismatch"),
+        }
+    }
+}
+
+impl Error for VerifyError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tampered() {
+        let mut signed = sign(b"ticket 42", b"secret");
+        assert_eq!(signed.len(), 9 + SIGNATURE_LEN);
+        signed[7] = b'3';
+        assert_eq!(verify(&signed, b"secret"), Err(VerifyError::Mismatch));
+        assert_eq!(verify(b"short", b"secret"), Err(VerifyError::Missing));
+    }
+
+    #[test]
+    fn text() {
+        let signed = sign_text("seat 1.2", b"secret");
+        assert_eq!(verify_text(&signed, b"secret"), Ok("seat 1.2"));
+
+        let tampered = signed.replacen("seat 1", "seat 9", 1);
+        assert_eq!(
+            verify_text(&tampered, b"secret"),
+            Err(VerifyError::Mismatch)
+        );
+        assert_eq!(
+            verify_text("seat 1.2", b"secret"),
+            Err(VerifyError::Missing)
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Splitting payloads that are too long for a single QR code.
+
+use std::borrow::Cow;
+
+use crate::options::Overflow;
+use crate::payload::{Payload, ToQrPayload};
+use crate::qr;
+use crate::{QrError, QrOptions};
+
+/// Maximum number of bytes any QR code can hold, used to bound searching.
+const MAX_CAPACITY: usize = 7089;
+
+/// Apply the [`Overflow`] strategy from `options` to `payload`.
+///
+/// Returns the payload as-is if it fits a single QR code. Otherwise it is
+/// truncated or split into multiple parts, depending on
+/// [`QrOptions::on_overflow`]. Text is never cut within a character.
+///
+/// Returns an error if the payload doesn't fit and overflowing is not allowed,
+/// or if generating the QR code failed for another reason.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::{options::Overflow, payload::Payload, QrOptions};
+///
+/// let data = "a".repeat(5000);
+/// let options = QrOptions::new().on_overflow(Overflow::Split);
+/// let payload = Payload::new(data.as_bytes());
+/// let parts = qr2term::split::split(&payload, &options).unwrap();
+/// assert_eq!(parts.len(), 3);
+/// ```
+pub fn split<'a>(payload: &'a Payload, options: &QrOptions) -> Result<Vec<Payload<'a>>, QrError> {
+    match qr::check_fits(payload, options.ec_level) {
+        Err(QrError::DataTooLong) if options.on_overflow != Overflow::Error => {}
+        result => return result.map(|_| vec![payload.to_qr_payload()]),
+    }
+
+    let is_text = std::str::from_utf8(payload.data()).is_ok();
+    let mut parts = vec![];
+    let mut rest = payload.data();
+    while !rest.is_empty() {
+        let mut len = max_fit(rest, payload, options)?;
+        if is_text {
+            while len > 0 && !is_char_boundary(rest, len) {
+                len -= 1;
+            }
+        }
+        if len == 0 {
+            return Err(QrError::DataTooLong);
+        }
+
+        let (part, remaining) = rest.split_at(len);
+        parts.push(part_payload(payload, part));
+        if options.on_overflow == Overflow::Truncate {
+            log_event!(
+                warn,
+                "data too long, truncated from {} to {} bytes",
+                payload.data().len(),
+                part.len()
+            );
+            break;
+        }
+        rest = remaining;
+    }
+
+    Ok(parts)
+}
+
+/// Find the length of the longest prefix of `data` that fits a single QR code.
+fn max_fit(data: &[u8], payload: &Payload, options: &QrOptions) -> Result<usize, QrError> {
+    // Binary search, the largest length that fits is within [low, high)
+    let (mut low, mut high) = (0, data.len().min(MAX_CAPACITY) + 1);
+    while high - low > 1 {
+        let mid = (low + high) / 2;
+        match qr::check_fits(&part_payload(payload, &data[..mid]), options.ec_level) {
+            Ok(_) => low = mid,
+            Err(QrError::DataTooLong) => high = mid,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(low)
+}
+
+/// Build a payload for `part` of the data, encoded in the same mode as `payload`.
+pub(crate) fn part_payload<'a>(payload: &Payload, part: &'a [u8]) -> Payload<'a> {
+    let part = Payload::new(Cow::Borrowed(part)).with_mode(payload.mode());
+    match payload.eci() {
+        Some(eci) => part.with_eci(eci),
+        None => part,
+    }
+}
+
+/// Whether `index` is at a UTF-8 character boundary in `data`.
+fn is_char_boundary(data: &[u8], index: usize) -> bool {
+    // Continuation bytes are formatted as 0b10xx_xxxx
+    data.get(index)
+        .map_or(true, |&byte| byte & 0b1100_0000 != 0b1000_0000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qr::Qr;
+
+    #[test]
+    fn overflow_error() {
+        let data = "a".repeat(5000);
+        let payload = Payload::new(data.as_bytes());
+        let result = split(&payload, &QrOptions::new());
+        assert_eq!(result, Err(QrError::DataTooLong));
+    }
+
+    #[test]
+    fn overflow_split() {
+        let data = "é".repeat(3000);
+        let options = QrOptions::new().on_overflow(Overflow::Split);
+        let payload = Payload::new(data.as_bytes());
+        let parts = split(&payload, &options).unwrap();
+        assert!(parts.len() > 1);
+
+        // Parts are split at character boundaries and join up to the input
+        let joined: Vec<u8> = parts.iter().flat_map(|part| part.data().to_vec()).collect();
+        assert_eq!(joined, data.as_bytes());
+        for part in &parts {
+            assert!(std::str::from_utf8(part.data()).is_ok());
+        }
+    }
+
+    #[test]
+    fn overflow_truncate() {
+        let data = "a".repeat(5000);
+        let options = QrOptions::new().on_overflow(Overflow::Truncate);
+        let payload = Payload::new(data.as_bytes());
+        let parts = split(&payload, &options).unwrap();
+        assert_eq!(parts.len(), 1);
+        assert!(parts[0].data().len() < data.len());
+        assert!(Qr::from_payload(&parts[0], options.ec_level).is_ok());
+    }
+
+    #[test]
+    fn fitting_untouched() {
+        let options = QrOptions::new().on_overflow(Overflow::Split);
+        let payload = Payload::new(&b"qr2term"[..]);
+        let parts = split(&payload, &options).unwrap();
+        assert_eq!(parts, vec![Payload::new(&b"qr2term"[..])]);
+    }
+}
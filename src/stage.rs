@@ -0,0 +1,113 @@
+//! The stages of rendering a code, to profile, cache or replace them one by one.
+//!
+//! Rendering a code runs three stages:
+//!
+//! 1. [`encode`] generates the code for a payload, as a matrix of modules
+//!    with its quiet zone, mirrored and rotated as configured.
+//! 2. [`layout`] groups the modules into the character [`Cells`] of the
+//!    render style.
+//! 3. [`emit`] draws the cells as text, in the configured colors.
+//!
+//! ```rust
+//! use qr2term::{stage, QrOptions};
+//!
+//! let options = QrOptions::new();
+//! let matrix = stage::encode("https://rust-lang.org/", &options)?;
+//! let cells = stage::layout(&matrix, &options);
+//! print!("{}", stage::emit(&cells, &options));
+//! # Ok::<(), qr2term::Error>(())
+//! ```
+//!
+//! The stages cover a single code, and leave out what
+//! [`try_generate_qr_string_with_options`](crate::try_generate_qr_string_with_options)
+//! does around them: splitting payloads, part captions, descriptions for
+//! screen readers, the maximum width and cursor and color resets. The
+//! `stages` benchmark times every stage, run it with
+//! `cargo bench --bench stages`.
+//!
+//! To generate many codes, encode them with [`encode_in`] instead, which
+//! reuses the buffers of an [`Arena`] rather than allocating them for every
+//! code.
+
+use crate::matrix::{Arena, Matrix};
+use crate::render::{Cells, Color};
+use crate::{Error, QrOptions, ToQrPayload};
+
+/// Generate the code for `data`, as a matrix of modules.
+///
+/// The payload is normalized, [checked](crate::lint) and compressed as
+/// configured in `options`, and the matrix includes the quiet zone.
+///
+/// Returns an error if the payload was rejected or doesn't fit a single code.
+pub fn encode<D: ToQrPayload>(data: D, options: &QrOptions) -> Result<Matrix<Color>, Error> {
+    let code = crate::generate_code(data, options)?;
+    Ok(crate::code_matrix(&code, options)?)
+}
+
+/// Generate the code for `data` like [`encode`], in buffers of the `arena`.
+///
+/// [Recycle](Arena::recycle) the matrix once done with it, so the next code
+/// reuses its buffer.
+pub fn encode_in<D: ToQrPayload>(
+    data: D,
+    options: &QrOptions,
+    arena: &mut Arena<Color>,
+) -> Result<Matrix<Color>, Error> {
+    let code = crate::generate_code(data, options)?;
+    Ok(crate::code_matrix_in(&code, options, arena)?)
+}
+
+/// Group the modules of `matrix` into the character cells of the style in
+/// `options`.
+///
+/// See [`Renderer::layout`](crate::render::Renderer::layout).
+pub fn layout(matrix: &Matrix<Color>, options: &QrOptions) -> Cells {
+    crate::renderer(options).layout(matrix)
+}
+
+/// Draw `cells` as text, in the colors of `options`.
+///
+/// See [`Renderer::emit`](crate::render::Renderer::emit).
+pub fn emit(cells: &Cells, options: &QrOptions) -> String {
+    let mut buf = Vec::new();
+    crate::renderer(options)
+        .emit(cells, &mut buf)
+        .expect("writing to a Vec doesn't fail");
+    String::from_utf8(buf).expect("rendered text is UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::Rotation;
+    use crate::render::Style;
+
+    #[test]
+    fn stages() {
+        for style in [Style::HalfBlock, Style::Braille, Style::CiLog] {
+            let options = QrOptions::new().style(style).stretch(2);
+            let matrix = encode("qr2term", &options).unwrap();
+            let cells = layout(&matrix, &options);
+            assert_eq!(cells.style(), style);
+            assert_eq!(
+                emit(&cells, &options),
+                crate::try_generate_qr_string_with_options("qr2term", &options).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn encode_in_arena() {
+        let mut arena = Arena::new();
+        for rotate in [Rotation::Rotate0, Rotation::Rotate90] {
+            let options = QrOptions::new().rotate(rotate).mirror(true);
+            let expected = encode("qr2term", &options).unwrap();
+            for _ in 0..3 {
+                let matrix = encode_in("qr2term", &options, &mut arena).unwrap();
+                assert_eq!(matrix.pixels(), expected.pixels());
+                arena.recycle(matrix);
+            }
+        }
+        assert_eq!(arena.len(), 2);
+    }
+}
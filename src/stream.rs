@@ -0,0 +1,258 @@
+//! Streaming payloads as a sequence of codes, shown one after another.
+//!
+//! Payloads too long for a single code are [split](crate::split) into chunks,
+//! which are shown as frames in a loop until the receiver has scanned them
+//! all. A [`Scheduler`] picks the chunk for every frame and how long to show
+//! it, adapting the frame rate to [`Feedback`] from the receiver, such as
+//! keypresses acknowledging a chunk or a decoder reading a webcam:
+//!
+//! ```rust
+//! use std::sync::mpsc;
+//! use qr2term::stream::{Feedback, Scheduler};
+//!
+//! let (feedback, channel) = mpsc::channel();
+//! let mut scheduler = Scheduler::new(3);
+//! feedback.send(Feedback::Received(1)).unwrap();
+//!
+//! let mut shown = Vec::new();
+//! while let Some(chunk) = scheduler.next_frame() {
+//!     shown.push(chunk);
+//!     // Render the chunk and wait `scheduler.interval()`, while the receiver
+//!     // scans it
+//!     feedback.send(Feedback::Received(chunk)).unwrap();
+//!     scheduler.apply_all(&channel);
+//! }
+//! assert_eq!(shown, [0, 2]);
+//! assert!(scheduler.is_complete());
+//! ```
+//!
+//! Over lossy channels, such as a webcam pointed at the terminal, encode the
+//! payload with a [`FountainEncoder`] instead: it produces an endless
+//! sequence of frames, each combining a few chunks, and a [`FountainDecoder`]
+//! reconstructs the payload from any sufficient subset of them, so missed
+//! frames needn't come around again.
+//!
+//! On the receiving end, a [`Receiver`] reassembles the payload from the
+//! scanned frames of either kind, with numbered frames cut by [`sequence`].
+//!
+//! Once [complete](Scheduler::is_complete), senders can let users know the
+//! transfer is done through [`Notify::send`](crate::notify::Notify::send),
+//! such as by ringing the terminal bell.
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+mod fountain;
+mod receiver;
+
+pub use self::fountain::{FountainDecoder, FountainEncoder, FountainError, DEFAULT_BLOCK_SIZE};
+pub use self::receiver::{acknowledgement, sequence, ReceiveError, Receiver};
+
+/// Frame rate streams start at, in frames per second.
+pub const DEFAULT_RATE: u32 = 10;
+
+/// Lowest frame rate the scheduler slows down to, in frames per second.
+pub const MIN_RATE: u32 = 1;
+
+/// Highest frame rate the scheduler speeds up to, in frames per second, as
+/// terminals don't draw faster.
+pub const MAX_RATE: u32 = 30;
+
+/// Feedback from the receiver of a stream, or from whoever controls it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Feedback {
+    /// The receiver read the chunk with this index, so it isn't shown again.
+    /// Speeds up the stream.
+    Received(usize),
+
+    /// The receiver failed to read a frame, such as a blurry webcam image.
+    /// Slows down the stream.
+    Missed,
+
+    /// Stop showing frames until resumed.
+    Pause,
+
+    /// Continue showing frames after pausing.
+    Resume,
+
+    /// Continue the stream at the chunk with this index.
+    Seek(usize),
+
+    /// The receiver has the whole payload, such as from fountain frames, so
+    /// the stream is done.
+    Complete,
+}
+
+/// Picks the chunk shown in every frame of a stream, and the frame rate.
+///
+/// Chunks are shown in order, in a loop, skipping those the receiver
+/// acknowledged as [received](Feedback::Received), until all are received.
+/// Every received chunk speeds up the stream by an eighth, and every
+/// [missed](Feedback::Missed) frame halves its rate, between [`MIN_RATE`] and
+/// [`MAX_RATE`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scheduler {
+    received: Vec<bool>,
+    remaining: usize,
+    position: usize,
+    interval: Duration,
+    paused: bool,
+}
+
+impl Scheduler {
+    /// Construct a scheduler for a stream of `count` chunks, at
+    /// [`DEFAULT_RATE`].
+    pub fn new(count: usize) -> Self {
+        Self {
+            received: vec![false; count],
+            remaining: count,
+            position: 0,
+            interval: rate_interval(DEFAULT_RATE),
+            paused: false,
+        }
+    }
+
+    /// Set the frame rate the stream starts at, in frames per second, limited
+    /// to between [`MIN_RATE`] and [`MAX_RATE`].
+    pub fn rate(mut self, rate: u32) -> Self {
+        self.interval = rate_interval(rate.clamp(MIN_RATE, MAX_RATE));
+        self
+    }
+
+    /// Get the index of the chunk to show in the next frame, and advance past
+    /// it.
+    ///
+    /// Returns `None` while paused, and once all chunks are received.
+    pub fn next_frame(&mut self) -> Option<usize> {
+        if self.paused || self.is_complete() {
+            return None;
+        }
+        let count = self.received.len();
+        let chunk = (self.position..count)
+            .chain(0..self.position)
+            .find(|&chunk| !self.received[chunk])?;
+        self.position = (chunk + 1) % count;
+        Some(chunk)
+    }
+
+    /// How long to show every frame, at the current frame rate.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Adapt the stream to `feedback`.
+    ///
+    /// Received chunks and seeks beyond the number of chunks are ignored.
+    pub fn apply(&mut self, feedback: Feedback) {
+        match feedback {
+            Feedback::Received(chunk) => {
+                if let Some(received @ false) = self.received.get_mut(chunk) {
+                    *received = true;
+                    self.remaining -= 1;
+                    self.interval =
+                        (self.interval - self.interval / 8).max(rate_interval(MAX_RATE));
+                }
+            }
+            Feedback::Missed => {
+                self.interval = (self.interval * 2).min(rate_interval(MIN_RATE));
+            }
+            Feedback::Pause => self.paused = true,
+            Feedback::Resume => self.paused = false,
+            Feedback::Seek(chunk) if chunk < self.received.len() => self.position = chunk,
+            Feedback::Seek(_) => {}
+            Feedback::Complete => {
+                self.received
+                    .iter_mut()
+                    .for_each(|received| *received = true);
+                self.remaining = 0;
+            }
+        }
+    }
+
+    /// Adapt the stream to all feedback waiting in `channel`, without
+    /// blocking.
+    pub fn apply_all(&mut self, channel: &mpsc::Receiver<Feedback>) {
+        for feedback in channel.try_iter() {
+            self.apply(feedback);
+        }
+    }
+
+    /// Whether the stream is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Whether all chunks are received.
+    pub fn is_complete(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+/// Get the time between frames at `rate` frames per second.
+fn rate_interval(rate: u32) -> Duration {
+    Duration::from_secs(1) / rate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loops_until_received() {
+        let mut scheduler = Scheduler::new(3);
+        let frames: Vec<_> = (0..4).map(|_| scheduler.next_frame()).collect();
+        assert_eq!(frames, [Some(0), Some(1), Some(2), Some(0)]);
+
+        scheduler.apply(Feedback::Received(1));
+        scheduler.apply(Feedback::Received(1));
+        scheduler.apply(Feedback::Received(7));
+        assert_eq!(scheduler.next_frame(), Some(2));
+        assert_eq!(scheduler.next_frame(), Some(0));
+        scheduler.apply(Feedback::Received(0));
+        scheduler.apply(Feedback::Received(2));
+        assert!(scheduler.is_complete());
+        assert_eq!(scheduler.next_frame(), None);
+
+        assert!(Scheduler::new(0).is_complete());
+
+        let mut scheduler = Scheduler::new(3);
+        scheduler.apply(Feedback::Complete);
+        assert_eq!(scheduler.next_frame(), None);
+    }
+
+    #[test]
+    fn pause_resume_seek() {
+        let mut scheduler = Scheduler::new(4);
+        scheduler.apply(Feedback::Pause);
+        assert!(scheduler.is_paused());
+        assert_eq!(scheduler.next_frame(), None);
+        scheduler.apply(Feedback::Resume);
+        scheduler.apply(Feedback::Seek(2));
+        assert_eq!(scheduler.next_frame(), Some(2));
+        scheduler.apply(Feedback::Seek(4));
+        assert_eq!(scheduler.next_frame(), Some(3));
+    }
+
+    #[test]
+    fn adapts_rate() {
+        let mut scheduler = Scheduler::new(100);
+        assert_eq!(scheduler.interval(), Duration::from_millis(100));
+        scheduler.apply(Feedback::Received(0));
+        assert_eq!(scheduler.interval(), Duration::from_micros(87_500));
+        for chunk in 1..50 {
+            scheduler.apply(Feedback::Received(chunk));
+        }
+        assert_eq!(scheduler.interval(), rate_interval(MAX_RATE));
+        for _ in 0..10 {
+            scheduler.apply(Feedback::Missed);
+        }
+        assert_eq!(scheduler.interval(), Duration::from_secs(1));
+
+        assert_eq!(
+            Scheduler::new(1).rate(100).interval(),
+            rate_interval(MAX_RATE)
+        );
+        assert_eq!(Scheduler::new(1).rate(0).interval(), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,451 @@
+//! Luby Transform fountain coding of streamed payloads.
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::png::crc32;
+
+/// Version of the frame format, the first byte of every frame.
+pub(super) const FORMAT: u8 = 3;
+
+/// Length of the frame header: format, payload length, block size, seed,
+/// degree and frame checksum.
+const HEADER_LEN: usize = 1 + 4 + 2 + 4 + 4 + 4;
+
+/// Offset of the frame checksum in the header, a CRC-32 of all other bytes
+/// of the frame.
+const CHECKSUM_AT: usize = HEADER_LEN - 4;
+
+/// Largest number of blocks a payload is cut into, bounding what decoders
+/// allocate for frames from untrusted sources.
+pub const MAX_BLOCKS: usize = 1 << 16;
+
+/// Robust soliton parameter scaling the expected number of degree one frames.
+const SOLITON_C: f64 = 0.1;
+
+/// Robust soliton bound on the probability of failing to decode after
+/// receiving slightly more frames than blocks.
+const SOLITON_DELTA: f64 = 0.5;
+
+/// Block size of fountain frames by default, in bytes, leaving room for the
+/// header in codes of modest size.
+pub const DEFAULT_BLOCK_SIZE: usize = 256;
+
+/// Encoder of a payload into an endless sequence of fountain frames.
+///
+/// The payload is cut into blocks, and every frame carries the XOR of a few
+/// blocks picked at random, seeded by the frame number. A
+/// [`FountainDecoder`] reconstructs the payload from any sufficient subset of
+/// frames, typically about 10% more frames than there are blocks, so lost
+/// frames don't have to be shown again, as with [`Scheduler`](super::Scheduler).
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::stream::{FountainDecoder, FountainEncoder};
+///
+/// let data = "a long payload, ".repeat(100);
+/// let encoder = FountainEncoder::new(data.as_bytes(), 64);
+/// let mut decoder = FountainDecoder::new();
+/// // Every third frame gets lost
+/// for seed in (0..).filter(|seed| seed % 3 != 0) {
+///     if decoder.push(&encoder.frame(seed))? {
+///         break;
+///     }
+/// }
+/// assert_eq!(decoder.data(), Some(data.into_bytes()));
+/// # Ok::<(), qr2term::stream::FountainError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct FountainEncoder {
+    /// Payload, padded with zeros to whole blocks.
+    data: Vec<u8>,
+    len: usize,
+    block_size: usize,
+    blocks: usize,
+    /// Cumulative probabilities of the degrees 1 to `blocks`.
+    degrees: Vec<f64>,
+}
+
+impl FountainEncoder {
+    /// Construct an encoder for `data` cut into blocks of `block_size` bytes,
+    /// limited to between 1 and 65535.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is longer than 4 GiB, or needs more than 65536
+    /// blocks.
+    pub fn new(data: &[u8], block_size: usize) -> Self {
+        assert!(u32::try_from(data.len()).is_ok(), "data too long");
+        let block_size = block_size.clamp(1, u16::MAX.into());
+        let blocks = (data.len() + block_size - 1) / block_size;
+        assert!(blocks <= MAX_BLOCKS, "too many blocks");
+        let len = data.len();
+        let mut data = data.to_vec();
+        data.resize(blocks * block_size, 0);
+        Self {
+            data,
+            len,
+            block_size,
+            blocks,
+            degrees: robust_soliton(blocks),
+        }
+    }
+
+    /// Number of blocks the payload is cut into, the least number of frames
+    /// a decoder needs.
+    pub fn blocks(&self) -> usize {
+        self.blocks
+    }
+
+    /// Build the frame with the given `seed`, usually the frame number.
+    pub fn frame(&self, seed: u32) -> Vec<u8> {
+        let mut rng = SplitMix64(seed.into());
+        let sample = rng.next_f64();
+        let degree = self
+            .degrees
+            .iter()
+            .position(|&cumulative| sample < cumulative)
+            .map_or(self.blocks, |index| index + 1);
+
+        let mut frame = Vec::with_capacity(HEADER_LEN + self.block_size);
+        frame.push(FORMAT);
+        frame.extend_from_slice(&(self.len as u32).to_be_bytes());
+        frame.extend_from_slice(&(self.block_size as u16).to_be_bytes());
+        frame.extend_from_slice(&seed.to_be_bytes());
+        frame.extend_from_slice(&(degree as u32).to_be_bytes());
+        frame.resize(HEADER_LEN + self.block_size, 0);
+        for block in pick_blocks(&mut rng, self.blocks, degree) {
+            let block = &self.data[block * self.block_size..][..self.block_size];
+            xor(&mut frame[HEADER_LEN..], block);
+        }
+        let checksum = checksum(&frame);
+        frame[CHECKSUM_AT..HEADER_LEN].copy_from_slice(&checksum.to_be_bytes());
+        frame
+    }
+}
+
+/// Decoder reconstructing a payload from fountain frames of a
+/// [`FountainEncoder`], received in any order.
+#[derive(Debug, Clone, Default)]
+pub struct FountainDecoder {
+    layout: Option<Layout>,
+    /// Decoded blocks, by index.
+    blocks: Vec<Option<Vec<u8>>>,
+    decoded: usize,
+    /// Frames combining more than one block that isn't decoded yet.
+    pending: Vec<(Vec<usize>, Vec<u8>)>,
+}
+
+/// Payload length and block size of a stream, as given by its first frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Layout {
+    len: usize,
+    block_size: usize,
+}
+
+impl Layout {
+    /// Number of blocks the payload is cut into.
+    fn blocks(&self) -> usize {
+        (self.len + self.block_size - 1) / self.block_size
+    }
+}
+
+impl FountainDecoder {
+    /// Construct a decoder that hasn't received any frames.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a received `frame`, decoding all blocks it makes known.
+    ///
+    /// Returns whether the payload is complete, or an error if the frame is
+    /// malformed, corrupt or belongs to another stream.
+    pub fn push(&mut self, frame: &[u8]) -> Result<bool, FountainError> {
+        let (layout, seed, degree, body) = parse(frame)?;
+        match self.layout {
+            Some(known) if known != layout => return Err(FountainError::OtherStream),
+            Some(_) => {}
+            None => {
+                self.layout = Some(layout);
+                self.blocks = vec![None; layout.blocks()];
+            }
+        }
+        if degree > self.blocks.len() {
+            return Err(FountainError::Malformed);
+        }
+
+        let mut rng = SplitMix64(seed.into());
+        rng.next_f64();
+        let indices = pick_blocks(&mut rng, self.blocks.len(), degree);
+        self.add(indices, body.to_vec());
+        Ok(self.is_complete())
+    }
+
+    /// Whether all blocks of the payload are decoded.
+    pub fn is_complete(&self) -> bool {
+        self.layout.is_some() && self.decoded == self.blocks.len()
+    }
+
+    /// Number of decoded blocks and the number of blocks of the payload, or
+    /// `None` before the first frame.
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        self.layout.map(|_| (self.decoded, self.blocks.len()))
+    }
+
+    /// Get the reconstructed payload, once complete.
+    pub fn data(&self) -> Option<Vec<u8>> {
+        let layout = self.layout.filter(|_| self.is_complete())?;
+        let mut data: Vec<u8> = self.blocks.iter().flatten().flatten().copied().collect();
+        data.truncate(layout.len);
+        Some(data)
+    }
+
+    /// Reduce a frame combining the blocks at `indices` by the decoded blocks,
+    /// and decode blocks by peeling frames down to single unknown blocks.
+    fn add(&mut self, indices: Vec<usize>, body: Vec<u8>) {
+        let mut queue = vec![(indices, body)];
+        while let Some((mut indices, mut body)) = queue.pop() {
+            indices.retain(|&index| match &self.blocks[index] {
+                Some(block) => {
+                    xor(&mut body, block);
+                    false
+                }
+                None => true,
+            });
+            match indices[..] {
+                [] => {}
+                [index] => {
+                    self.blocks[index] = Some(body);
+                    self.decoded += 1;
+                    // Frames including the new block may be down to one unknown
+                    let (ready, pending) = self
+                        .pending
+                        .drain(..)
+                        .partition(|(indices, _)| indices.contains(&index));
+                    self.pending = pending;
+                    queue.extend::<Vec<_>>(ready);
+                }
+                _ => self.pending.push((indices, body)),
+            }
+        }
+    }
+}
+
+/// Error returned when a fountain frame can't be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FountainError {
+    /// The frame is truncated, of an unknown format, or inconsistent.
+    Malformed,
+
+    /// The frame doesn't match its checksum.
+    Checksum,
+
+    /// The frame belongs to a stream of another payload length or block size
+    /// than the frames before it.
+    OtherStream,
+}
+
+impl Display for FountainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FountainError::Malformed => f.write_str("malformed fountain frame"),
+            FountainError::Checksum => f.write_str("fountain frame doesn't match its checksum"),
+            FountainError::OtherStream => f.write_str("fountain frame belongs to another stream"),
+        }
+    }
+}
+
+impl Error for FountainError {}
+
+/// Parse a frame into its layout, seed, degree and body.
+fn parse(frame: &[u8]) -> Result<(Layout, u32, usize, &[u8]), FountainError> {
+    if frame.len() < HEADER_LEN || frame[0] != FORMAT {
+        return Err(FountainError::Malformed);
+    }
+    let u32_at =
+        |at: usize| u32::from_be_bytes([frame[at], frame[at + 1], frame[at + 2], frame[at + 3]]);
+    let layout = Layout {
+        len: u32_at(1) as usize,
+        block_size: u16::from_be_bytes([frame[5], frame[6]]).into(),
+    };
+    let body = &frame[HEADER_LEN..];
+    if layout.block_size == 0 || body.len() != layout.block_size {
+        return Err(FountainError::Malformed);
+    }
+    if checksum(frame) != u32_at(CHECKSUM_AT) {
+        return Err(FountainError::Checksum);
+    }
+    if layout.blocks() > MAX_BLOCKS {
+        return Err(FountainError::Malformed);
+    }
+    Ok((layout, u32_at(7), u32_at(11) as usize, body))
+}
+
+/// Get the CRC-32 of all bytes of `frame` but its checksum.
+fn checksum(frame: &[u8]) -> u32 {
+    let mut data = frame[..CHECKSUM_AT].to_vec();
+    data.extend_from_slice(&frame[HEADER_LEN..]);
+    crc32(&data)
+}
+
+/// Pick `degree` distinct blocks out of `blocks`, the same for encoder and
+/// decoder given the same random state.
+fn pick_blocks(rng: &mut SplitMix64, blocks: usize, degree: usize) -> Vec<usize> {
+    // Floyd's sampling, in time and memory of the degree rather than the
+    // number of blocks
+    let mut picked = HashSet::with_capacity(degree);
+    let mut indices = Vec::with_capacity(degree);
+    for j in blocks - degree..blocks {
+        let t = (rng.next() % (j + 1) as u64) as usize;
+        let index = if picked.contains(&t) { j } else { t };
+        picked.insert(index);
+        indices.push(index);
+    }
+    indices
+}
+
+/// Cumulative robust soliton distribution of degrees 1 to `blocks`.
+///
+/// Only the encoder samples it, frames carry their degree, so decoders don't
+/// depend on floating point functions agreeing across platforms.
+fn robust_soliton(blocks: usize) -> Vec<f64> {
+    let k = blocks as f64;
+    let r = SOLITON_C * (k / SOLITON_DELTA).ln() * k.sqrt();
+    let spike = (k / r).floor().max(1.0) as usize;
+    let weights: Vec<f64> = (1..=blocks)
+        .map(|d| {
+            let ideal = match d {
+                1 => 1.0 / k,
+                _ => 1.0 / (d * (d - 1)) as f64,
+            };
+            let robust = match d {
+                d if d < spike => r / (d as f64 * k),
+                d if d == spike => r * (r / SOLITON_DELTA).ln() / k,
+                _ => 0.0,
+            };
+            ideal + robust.max(0.0)
+        })
+        .collect();
+    let total: f64 = weights.iter().sum();
+    weights
+        .iter()
+        .scan(0.0, |cumulative, weight| {
+            *cumulative += weight / total;
+            Some(*cumulative)
+        })
+        .collect()
+}
+
+/// XOR `other` into `target`.
+fn xor(target: &mut [u8], other: &[u8]) {
+    for (byte, other) in target.iter_mut().zip(other) {
+        *byte ^= other;
+    }
+}
+
+/// SplitMix64 pseudorandom number generator, small and fully specified, so
+/// encoders and decoders agree on it.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform number in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(encoder: &FountainEncoder, seeds: impl Iterator<Item = u32>) -> (usize, Vec<u8>) {
+        let mut decoder = FountainDecoder::new();
+        let mut frames = 0;
+        for seed in seeds {
+            frames += 1;
+            if decoder.push(&encoder.frame(seed)).unwrap() {
+                return (frames, decoder.data().unwrap());
+            }
+        }
+        panic!("not decoded after {} frames", frames);
+    }
+
+    #[test]
+    fn roundtrip_with_losses() {
+        let data: Vec<u8> = (0..5000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let encoder = FountainEncoder::new(&data, 100);
+        assert_eq!(encoder.blocks(), 50);
+        for skip in [2, 3, 5] {
+            let (frames, decoded) = decode(&encoder, (0..).filter(|seed| seed % skip != 0));
+            assert_eq!(decoded, data);
+            assert!(frames < 100, "{} frames for 50 blocks", frames);
+        }
+        let (_, decoded) = decode(&encoder, (0..1000).rev());
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn small_payloads() {
+        for len in [0, 1, 63, 64, 65] {
+            let data = vec![0xA5; len];
+            let encoder = FountainEncoder::new(&data, 64);
+            assert_eq!(decode(&encoder, 0..).1, data);
+        }
+    }
+
+    #[test]
+    fn malformed_frames() {
+        let encoder = FountainEncoder::new(b"payload", 4);
+        let mut decoder = FountainDecoder::new();
+        let frame = encoder.frame(0);
+        assert_eq!(decoder.progress(), None);
+        assert_eq!(
+            decoder.push(&frame[..HEADER_LEN]),
+            Err(FountainError::Malformed)
+        );
+        let mut other = frame.clone();
+        other[0] = 2;
+        assert_eq!(decoder.push(&other), Err(FountainError::Malformed));
+
+        let mut corrupt = frame.clone();
+        corrupt[HEADER_LEN] ^= 1;
+        assert_eq!(decoder.push(&corrupt), Err(FountainError::Checksum));
+        corrupt = frame.clone();
+        corrupt[2] ^= 1;
+        assert_eq!(decoder.push(&corrupt), Err(FountainError::Checksum));
+        assert_eq!(decoder.progress(), None);
+
+        decoder.push(&frame).unwrap();
+        assert_eq!(decoder.progress().map(|(_, blocks)| blocks), Some(2));
+        let other = FountainEncoder::new(b"other payload", 4).frame(0);
+        assert_eq!(decoder.push(&other), Err(FountainError::OtherStream));
+        assert_eq!(decoder.data(), None);
+    }
+
+    /// A frame claiming the longest payload in blocks of a byte doesn't
+    /// allocate for all blocks.
+    #[test]
+    fn too_many_blocks() {
+        let mut frame = vec![FORMAT, 0xFF, 0xFF, 0xFF, 0xFF, 0, 1];
+        frame.extend_from_slice(&[0; 12]);
+        frame.push(0);
+        let checksum = checksum(&frame);
+        frame[CHECKSUM_AT..HEADER_LEN].copy_from_slice(&checksum.to_be_bytes());
+        assert_eq!(
+            FountainDecoder::new().push(&frame),
+            Err(FountainError::Malformed)
+        );
+        assert_eq!(pick_blocks(&mut SplitMix64(1), MAX_BLOCKS, 0), []);
+    }
+}
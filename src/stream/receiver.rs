@@ -0,0 +1,335 @@
+//! Reassembly of streamed payloads from received frames.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use super::fountain::{self, FountainDecoder, FountainError};
+use super::Feedback;
+use crate::png::crc32;
+
+/// Version of the sequence frame format, the first byte of every frame.
+const FORMAT: u8 = 2;
+
+/// Length of the sequence frame header: format, chunk index, chunk count,
+/// payload checksum and chunk checksum.
+const HEADER_LEN: usize = 1 + 4 + 4 + 4 + 4;
+
+/// Cut `data` into numbered frames of at most `chunk_size` bytes each, to
+/// show one after another and reassemble with a [`Receiver`].
+///
+/// Every frame starts with a header holding its index, the number of frames
+/// and CRC-32 checksums of the frame and the whole payload, so receivers
+/// detect corrupt frames and frames of other streams.
+///
+/// # Panics
+///
+/// Panics if `data` needs more than 4294967295 frames.
+pub fn sequence(data: &[u8], chunk_size: usize) -> Vec<Vec<u8>> {
+    let chunks: Vec<&[u8]> = match data.is_empty() {
+        true => vec![data],
+        false => data.chunks(chunk_size.max(1)).collect(),
+    };
+    let count = u32::try_from(chunks.len()).expect("too many frames");
+    let checksum = crc32(data);
+    chunks
+        .iter()
+        .zip(0u32..)
+        .map(|(chunk, index)| {
+            let mut frame = Vec::with_capacity(HEADER_LEN + chunk.len());
+            frame.push(FORMAT);
+            frame.extend_from_slice(&index.to_be_bytes());
+            frame.extend_from_slice(&count.to_be_bytes());
+            frame.extend_from_slice(&checksum.to_be_bytes());
+            frame.extend_from_slice(&crc32(chunk).to_be_bytes());
+            frame.extend_from_slice(chunk);
+            frame
+        })
+        .collect()
+}
+
+/// Get the feedback acknowledging a `frame` the [`Receiver`] accepted to the
+/// sender's [`Scheduler`](super::Scheduler): the chunk of a [`sequence`]
+/// frame as [received](Feedback::Received).
+///
+/// Returns `None` for other frames, such as fountain frames, which aren't
+/// shown again anyway.
+pub fn acknowledgement(frame: &[u8]) -> Option<Feedback> {
+    match frame.get(..5) {
+        Some(&[FORMAT, a, b, c, d]) if frame.len() >= HEADER_LEN => {
+            Some(Feedback::Received(u32::from_be_bytes([a, b, c, d]) as usize))
+        }
+        _ => None,
+    }
+}
+
+/// Receiver reassembling a streamed payload from the frames scanned off the
+/// sender's terminal, by any scanner app or decoder.
+///
+/// Accepts numbered frames of [`sequence`] and fountain frames of a
+/// [`FountainEncoder`](super::FountainEncoder), in any order and with
+/// repeats, and validates their headers and checksums.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::stream::{self, Receiver};
+///
+/// let frames = stream::sequence(b"air-gapped payload", 8);
+/// let mut receiver = Receiver::new();
+/// for frame in frames.iter().rev() {
+///     receiver.push(frame)?;
+/// }
+/// assert_eq!(receiver.data(), Some(b"air-gapped payload".to_vec()));
+/// # Ok::<(), qr2term::stream::ReceiveError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Receiver {
+    state: State,
+}
+
+/// Frames received so far, by the format of the stream.
+#[derive(Debug, Clone, Default)]
+enum State {
+    #[default]
+    Empty,
+    /// Received chunks by index, kept sparse as the chunk count comes from
+    /// untrusted frames.
+    Sequence {
+        checksum: u32,
+        count: usize,
+        chunks: BTreeMap<usize, Vec<u8>>,
+    },
+    Fountain(FountainDecoder),
+}
+
+impl Receiver {
+    /// Construct a receiver that hasn't received any frames.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a received `frame`.
+    ///
+    /// Returns whether the payload is complete, or an error if the frame is
+    /// malformed, corrupt or belongs to another stream. Erroneous frames are
+    /// ignored, so receiving can continue with the next frame.
+    pub fn push(&mut self, frame: &[u8]) -> Result<bool, ReceiveError> {
+        match frame.first() {
+            Some(&FORMAT) => self.push_sequence(frame)?,
+            Some(&fountain::FORMAT) => {
+                if let State::Empty = self.state {
+                    self.state = State::Fountain(FountainDecoder::new());
+                }
+                match &mut self.state {
+                    State::Fountain(decoder) => decoder.push(frame)?,
+                    _ => return Err(ReceiveError::OtherStream),
+                };
+            }
+            _ => return Err(ReceiveError::Malformed),
+        }
+        Ok(self.is_complete())
+    }
+
+    /// Add a received sequence `frame`.
+    fn push_sequence(&mut self, frame: &[u8]) -> Result<(), ReceiveError> {
+        if frame.len() < HEADER_LEN {
+            return Err(ReceiveError::Malformed);
+        }
+        let u32_at = |at: usize| {
+            u32::from_be_bytes([frame[at], frame[at + 1], frame[at + 2], frame[at + 3]])
+        };
+        let (index, count) = (u32_at(1) as usize, u32_at(5) as usize);
+        let (checksum, chunk) = (u32_at(9), &frame[HEADER_LEN..]);
+        if index >= count {
+            return Err(ReceiveError::Malformed);
+        }
+        if crc32(chunk) != u32_at(13) {
+            return Err(ReceiveError::Checksum);
+        }
+
+        if let State::Empty = self.state {
+            self.state = State::Sequence {
+                checksum,
+                count,
+                chunks: BTreeMap::new(),
+            };
+        }
+        let chunks = match &mut self.state {
+            State::Sequence {
+                checksum: expected,
+                count: expected_count,
+                chunks,
+            } if *expected == checksum && *expected_count == count => chunks,
+            _ => return Err(ReceiveError::OtherStream),
+        };
+        chunks.entry(index).or_insert_with(|| chunk.to_vec());
+
+        // Chunks passed their own checksums, but may be mixed up between
+        // streams of the same length
+        if chunks.len() == count {
+            let data: Vec<u8> = chunks.values().flatten().copied().collect();
+            if crc32(&data) != checksum {
+                self.state = State::Empty;
+                return Err(ReceiveError::Checksum);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the whole payload is received.
+    pub fn is_complete(&self) -> bool {
+        match &self.state {
+            State::Empty => false,
+            State::Sequence { count, chunks, .. } => chunks.len() == *count,
+            State::Fountain(decoder) => decoder.is_complete(),
+        }
+    }
+
+    /// Number of received chunks, or decoded blocks of fountain streams, and
+    /// the number needed, or `None` before the first frame.
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        match &self.state {
+            State::Empty => None,
+            State::Sequence { count, chunks, .. } => Some((chunks.len(), *count)),
+            State::Fountain(decoder) => decoder.progress(),
+        }
+    }
+
+    /// Get the reassembled payload, once complete.
+    pub fn data(&self) -> Option<Vec<u8>> {
+        match &self.state {
+            State::Sequence { chunks, .. } if self.is_complete() => {
+                Some(chunks.values().flatten().copied().collect())
+            }
+            State::Fountain(decoder) => decoder.data(),
+            _ => None,
+        }
+    }
+}
+
+/// Error returned when a received frame can't be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReceiveError {
+    /// The frame is truncated, of an unknown format, or its header is
+    /// inconsistent.
+    Malformed,
+
+    /// The frame or the reassembled payload doesn't match its checksum.
+    Checksum,
+
+    /// The frame belongs to another stream than the frames before it.
+    OtherStream,
+}
+
+impl Display for ReceiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReceiveError::Malformed => f.write_str("malformed stream frame"),
+            ReceiveError::Checksum => f.write_str("stream frame doesn't match its checksum"),
+            ReceiveError::OtherStream => f.write_str("stream frame belongs to another stream"),
+        }
+    }
+}
+
+impl Error for ReceiveError {}
+
+impl From<FountainError> for ReceiveError {
+    fn from(err: FountainError) -> Self {
+        match err {
+            FountainError::Malformed => ReceiveError::Malformed,
+            FountainError::Checksum => ReceiveError::Checksum,
+            FountainError::OtherStream => ReceiveError::OtherStream,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::FountainEncoder;
+
+    #[test]
+    fn reassembles_sequence() {
+        let data: Vec<u8> = (0..=255).collect();
+        let frames = sequence(&data, 100);
+        assert_eq!(frames.len(), 3);
+
+        let mut receiver = Receiver::new();
+        assert_eq!(receiver.progress(), None);
+        assert_eq!(receiver.push(&frames[2]), Ok(false));
+        assert_eq!(receiver.push(&frames[2]), Ok(false));
+        assert_eq!(receiver.push(&frames[0]), Ok(false));
+        assert_eq!(receiver.progress(), Some((2, 3)));
+        assert_eq!(receiver.data(), None);
+        assert_eq!(receiver.push(&frames[1]), Ok(true));
+        assert_eq!(receiver.data(), Some(data));
+        assert_eq!(acknowledgement(&frames[1]), Some(Feedback::Received(1)));
+        assert_eq!(acknowledgement(&frames[1][..8]), None);
+
+        let mut receiver = Receiver::new();
+        assert_eq!(receiver.push(&sequence(b"", 10)[0]), Ok(true));
+        assert_eq!(receiver.data(), Some(vec![]));
+    }
+
+    #[test]
+    fn rejects_bad_frames() {
+        let frames = sequence(b"some payload", 4);
+        let mut receiver = Receiver::new();
+        assert_eq!(receiver.push(b""), Err(ReceiveError::Malformed));
+        assert_eq!(receiver.push(&frames[0][..8]), Err(ReceiveError::Malformed));
+        let mut corrupt = frames[0].clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert_eq!(receiver.push(&corrupt), Err(ReceiveError::Checksum));
+
+        receiver.push(&frames[0]).unwrap();
+        let other = sequence(b"other payload", 4);
+        assert_eq!(receiver.push(&other[1]), Err(ReceiveError::OtherStream));
+        let fountain = FountainEncoder::new(b"some payload", 4).frame(0);
+        assert_eq!(receiver.push(&fountain), Err(ReceiveError::OtherStream));
+        assert_eq!(receiver.progress(), Some((1, 3)));
+    }
+
+    /// A frame claiming the largest chunk count doesn't allocate for all
+    /// chunks up front.
+    #[test]
+    fn huge_chunk_count() {
+        let mut frame = vec![FORMAT, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF];
+        frame.extend_from_slice(&crc32(b"").to_be_bytes());
+        frame.extend_from_slice(&crc32(b"").to_be_bytes());
+        assert_eq!(frame.len(), HEADER_LEN);
+
+        let mut receiver = Receiver::new();
+        assert_eq!(receiver.push(&frame), Ok(false));
+        assert_eq!(receiver.progress(), Some((1, u32::MAX as usize)));
+        assert_eq!(receiver.data(), None);
+    }
+
+    /// Chunks of another stream with the same number of chunks and valid
+    /// checksums fail the checksum of the whole payload.
+    #[test]
+    fn mixed_up_chunks() {
+        let frames = sequence(b"aaaabbbb", 4);
+        let mut other = sequence(b"ccccdddd", 4).remove(1);
+        other[9..13].copy_from_slice(&frames[1][9..13]);
+
+        let mut receiver = Receiver::new();
+        receiver.push(&frames[0]).unwrap();
+        assert_eq!(receiver.push(&other), Err(ReceiveError::Checksum));
+        assert_eq!(receiver.progress(), None);
+    }
+
+    #[test]
+    fn reassembles_fountain() {
+        let encoder = FountainEncoder::new(b"fountain payload", 4);
+        let mut receiver = Receiver::new();
+        for seed in 0.. {
+            if receiver.push(&encoder.frame(seed)).unwrap() {
+                break;
+            }
+        }
+        assert_eq!(receiver.data(), Some(b"fountain payload".to_vec()));
+        assert_eq!(acknowledgement(&encoder.frame(0)), None);
+    }
+}
@@ -0,0 +1,86 @@
+//! Terminal probing.
+
+/// Get the size of the terminal as `(columns, rows)`, if known.
+///
+/// With the `terminal` feature the terminal is queried directly, otherwise
+/// the `COLUMNS` and `LINES` environment variables are used.
+pub fn size() -> Option<(usize, usize)> {
+    #[cfg(feature = "terminal")]
+    {
+        if let Ok((columns, rows)) = crossterm::terminal::size() {
+            return Some((columns as usize, rows as usize));
+        }
+    }
+
+    let var = |key| std::env::var(key).ok()?.trim().parse::<usize>().ok();
+    Some((var("COLUMNS")?, var("LINES")?))
+}
+
+/// Whether the terminal is in raw mode, as far as known.
+///
+/// Only raw mode enabled through `crossterm` is detected, with the `terminal`
+/// feature.
+pub fn is_raw_mode() -> bool {
+    #[cfg(feature = "terminal")]
+    {
+        crossterm::terminal::is_raw_mode_enabled().unwrap_or(false)
+    }
+    #[cfg(not(feature = "terminal"))]
+    {
+        false
+    }
+}
+
+/// Whether the terminal likely shows OSC 8 hyperlinks, rather than their
+/// escape sequences or nothing at all.
+///
+/// `FORCE_HYPERLINK` set to `0` or anything else overrides the detection,
+/// which otherwise looks for terminals known to support them. With the
+/// `terminal` feature, stdout must be a terminal too.
+pub fn supports_hyperlinks() -> bool {
+    let var = |key| std::env::var(key).ok().filter(|value| !value.is_empty());
+    if let Some(force) = var("FORCE_HYPERLINK") {
+        return force.trim() != "0";
+    }
+    #[cfg(feature = "terminal")]
+    {
+        use crossterm::tty::IsTty;
+        if !std::io::stdout().is_tty() {
+            return false;
+        }
+    }
+
+    let term_program = var("TERM_PROGRAM").unwrap_or_default();
+    let term = var("TERM").unwrap_or_default();
+    ["iTerm.app", "WezTerm", "vscode", "Hyper", "ghostty"].contains(&term_program.as_str())
+        || ["xterm-kitty", "alacritty", "foot", "xterm-ghostty"].contains(&term.as_str())
+        || var("WT_SESSION").is_some()
+        || var("KONSOLE_VERSION").is_some()
+        || var("VTE_VERSION").map_or(false, |version| {
+            version
+                .trim()
+                .parse::<u32>()
+                .map_or(false, |version| version >= 5000)
+        })
+}
+
+/// Guard keeping the terminal in raw mode, so single key presses are read,
+/// until dropped.
+#[cfg(feature = "terminal")]
+pub struct RawMode;
+
+#[cfg(feature = "terminal")]
+impl RawMode {
+    /// Switch the terminal to raw mode.
+    pub fn enable() -> std::io::Result<Self> {
+        crossterm::terminal::enable_raw_mode()?;
+        Ok(RawMode)
+    }
+}
+
+#[cfg(feature = "terminal")]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = crossterm::terminal::disable_raw_mode();
+    }
+}
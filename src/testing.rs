@@ -0,0 +1,405 @@
+//! Test support, rendering QR codes into an in-memory terminal.
+//!
+//! [`Screen`] emulates the subset of a VT100 compatible terminal that this
+//! crate's output relies on: printable characters, line breaks, cursor
+//! movement, erasing and 256-color SGR attributes. Applications can render
+//! into it and assert where and how a QR code appears, without a real terminal.
+//!
+//! ```rust
+//! use qr2term::{testing, QrOptions};
+//!
+//! let screen = testing::render("qr2term", &QrOptions::new(), 80, 24).unwrap();
+//! let area = screen.painted_area().unwrap();
+//! assert_eq!((area.columns, area.rows), (25, 13));
+//! ```
+
+use std::io::{self, Write};
+
+use crate::{Error, QrOptions, ToQrPayload};
+
+/// Render `data` as QR code into a new screen of `columns` by `rows` cells.
+///
+/// Returns an error if generating the QR code failed.
+pub fn render<D: ToQrPayload>(
+    data: D,
+    options: &QrOptions,
+    columns: usize,
+    rows: usize,
+) -> Result<Screen, Error> {
+    let mut screen = Screen::new(columns, rows);
+    crate::render_qr(data, options, &mut screen)?;
+    Ok(screen)
+}
+
+/// A single character cell of a [`Screen`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Cell {
+    /// The character in this cell, a space if nothing was written.
+    pub character: char,
+
+    /// Foreground palette color, or `None` for the terminal default.
+    pub fg: Option<u8>,
+
+    /// Background palette color, or `None` for the terminal default.
+    pub bg: Option<u8>,
+}
+
+impl Cell {
+    /// Whether anything visible was drawn in this cell.
+    pub fn is_painted(&self) -> bool {
+        self.character != ' ' || self.bg.is_some()
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            character: ' ',
+            fg: None,
+            bg: None,
+        }
+    }
+}
+
+/// A rectangular area of a [`Screen`], in cells.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Area {
+    /// Zero based index of the top row.
+    pub row: usize,
+
+    /// Zero based index of the left column.
+    pub column: usize,
+
+    /// Number of rows.
+    pub rows: usize,
+
+    /// Number of columns.
+    pub columns: usize,
+}
+
+/// Parser state for escape sequences.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    Csi(String),
+}
+
+/// An in-memory terminal screen, written to through its [`Write`] implementation.
+///
+/// Line feeds also return the cursor to the first column, like a terminal
+/// with output post-processing enabled. Writing past the last row scrolls the
+/// screen up. Unsupported escape sequences are ignored.
+#[derive(Debug, Clone)]
+pub struct Screen {
+    columns: usize,
+    rows: usize,
+    cells: Vec<Cell>,
+    cursor: (usize, usize),
+    pending_wrap: bool,
+    fg: Option<u8>,
+    bg: Option<u8>,
+    state: State,
+    utf8: Vec<u8>,
+}
+
+impl Screen {
+    /// Construct an empty screen of `columns` by `rows` cells.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either dimension is zero.
+    pub fn new(columns: usize, rows: usize) -> Self {
+        assert!(columns > 0 && rows > 0, "screen must not be empty");
+        Self {
+            columns,
+            rows,
+            cells: vec![Cell::default(); columns * rows],
+            cursor: (0, 0),
+            pending_wrap: false,
+            fg: None,
+            bg: None,
+            state: State::Ground,
+            utf8: Vec::new(),
+        }
+    }
+
+    /// The screen size as `(columns, rows)`.
+    pub fn size(&self) -> (usize, usize) {
+        (self.columns, self.rows)
+    }
+
+    /// The cursor position as zero based `(row, column)`.
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    /// Get the cell at the given zero based `row` and `column`.
+    pub fn cell(&self, row: usize, column: usize) -> Option<&Cell> {
+        if row < self.rows && column < self.columns {
+            self.cells.get(row * self.columns + column)
+        } else {
+            None
+        }
+    }
+
+    /// Get the characters of the given zero based `row`, without trailing spaces.
+    pub fn row_text(&self, row: usize) -> String {
+        let start = row.min(self.rows) * self.columns;
+        let end = (start + self.columns).min(self.cells.len());
+        let text: String = self.cells[start..end].iter().map(|c| c.character).collect();
+        text.trim_end().to_owned()
+    }
+
+    /// Get the characters of all rows, without trailing spaces and empty rows.
+    pub fn contents(&self) -> String {
+        let rows: Vec<_> = (0..self.rows).map(|row| self.row_text(row)).collect();
+        rows.join("\n").trim_end().to_owned()
+    }
+
+    /// Get the smallest area containing all painted cells, see [`Cell::is_painted`].
+    ///
+    /// Returns `None` if nothing was painted.
+    pub fn painted_area(&self) -> Option<Area> {
+        let mut painted = (0..self.rows)
+            .flat_map(|row| (0..self.columns).map(move |column| (row, column)))
+            .filter(|&(row, column)| self.cells[row * self.columns + column].is_painted());
+        let (row, column) = painted.next()?;
+        let (top, left, bottom, right) = painted.fold(
+            (row, column, row, column),
+            |(top, left, bottom, right), (row, column)| {
+                (
+                    top.min(row),
+                    left.min(column),
+                    bottom.max(row),
+                    right.max(column),
+                )
+            },
+        );
+        Some(Area {
+            row: top,
+            column: left,
+            rows: bottom - top + 1,
+            columns: right - left + 1,
+        })
+    }
+
+    /// Process the given output bytes, as a terminal would.
+    pub fn process(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.utf8.push(byte);
+            match std::str::from_utf8(&self.utf8) {
+                Ok(text) => {
+                    let c = text.chars().next().expect("non-empty");
+                    self.utf8.clear();
+                    self.process_char(c);
+                }
+                // Wait for the rest of an incomplete character
+                Err(err) if err.error_len().is_none() => {}
+                Err(_) => {
+                    self.utf8.clear();
+                    self.process_char(char::REPLACEMENT_CHARACTER);
+                }
+            }
+        }
+    }
+
+    fn process_char(&mut self, c: char) {
+        match std::mem::replace(&mut self.state, State::Ground) {
+            State::Ground => match c {
+                '\x1B' => self.state = State::Escape,
+                '\n' => {
+                    self.cursor.1 = 0;
+                    self.line_feed();
+                }
+                '\r' => self.move_to(self.cursor.0, 0),
+                '\x08' => self.move_to(self.cursor.0, self.cursor.1.saturating_sub(1)),
+                c if c.is_control() => {}
+                c => self.print(c),
+            },
+            State::Escape if c == '[' => self.state = State::Csi(String::new()),
+            State::Escape => {}
+            State::Csi(mut params) => match c {
+                '0'..='9' | ';' | '?' => {
+                    params.push(c);
+                    self.state = State::Csi(params);
+                }
+                c => self.csi(&params, c),
+            },
+        }
+    }
+
+    /// Print a character at the cursor, wrapping to the next line if needed.
+    fn print(&mut self, c: char) {
+        if self.pending_wrap {
+            self.cursor.1 = 0;
+            self.line_feed();
+        }
+        let (row, column) = self.cursor;
+        self.cells[row * self.columns + column] = Cell {
+            character: c,
+            fg: self.fg,
+            bg: self.bg,
+        };
+        if column + 1 < self.columns {
+            self.cursor.1 += 1;
+        } else {
+            self.pending_wrap = true;
+        }
+    }
+
+    /// Move the cursor down a row, scrolling the screen up at the bottom.
+    fn line_feed(&mut self) {
+        self.pending_wrap = false;
+        if self.cursor.0 + 1 < self.rows {
+            self.cursor.0 += 1;
+        } else {
+            self.cells.drain(..self.columns);
+            self.cells.resize(self.columns * self.rows, Cell::default());
+        }
+    }
+
+    fn move_to(&mut self, row: usize, column: usize) {
+        self.cursor = (row.min(self.rows - 1), column.min(self.columns - 1));
+        self.pending_wrap = false;
+    }
+
+    /// Execute a control sequence with the given parameters and final character.
+    fn csi(&mut self, params: &str, action: char) {
+        // Private sequences such as hiding the cursor don't affect the cells
+        if params.starts_with('?') {
+            return;
+        }
+        let args: Vec<usize> = params
+            .split(';')
+            .map(|arg| arg.parse().unwrap_or(0))
+            .collect();
+        let arg = |i: usize, default: usize| match args.get(i) {
+            Some(&0) | None => default,
+            Some(&n) => n,
+        };
+
+        let (row, column) = self.cursor;
+        match action {
+            'A' => self.move_to(row.saturating_sub(arg(0, 1)), column),
+            'B' => self.move_to(row + arg(0, 1), column),
+            'C' => self.move_to(row, column + arg(0, 1)),
+            'D' => self.move_to(row, column.saturating_sub(arg(0, 1))),
+            'G' => self.move_to(row, arg(0, 1) - 1),
+            'H' | 'f' => self.move_to(arg(0, 1) - 1, arg(1, 1) - 1),
+            'J' => {
+                let cursor = row * self.columns + column;
+                match args[0] {
+                    0 => self.erase(cursor, self.cells.len()),
+                    1 => self.erase(0, cursor + 1),
+                    _ => self.erase(0, self.cells.len()),
+                }
+            }
+            'K' => {
+                let start = row * self.columns;
+                match args[0] {
+                    0 => self.erase(start + column, start + self.columns),
+                    1 => self.erase(start, start + column + 1),
+                    _ => self.erase(start, start + self.columns),
+                }
+            }
+            'm' => self.sgr(&args),
+            _ => {}
+        }
+    }
+
+    /// Reset the cells in the given range.
+    fn erase(&mut self, start: usize, end: usize) {
+        for cell in &mut self.cells[start..end] {
+            *cell = Cell::default();
+        }
+    }
+
+    /// Apply select graphic rendition parameters, colors only.
+    fn sgr(&mut self, args: &[usize]) {
+        let mut args = args.iter().copied();
+        while let Some(arg) = args.next() {
+            match arg {
+                0 => (self.fg, self.bg) = (None, None),
+                30..=37 => self.fg = Some(arg as u8 - 30),
+                90..=97 => self.fg = Some(arg as u8 - 90 + 8),
+                40..=47 => self.bg = Some(arg as u8 - 40),
+                100..=107 => self.bg = Some(arg as u8 - 100 + 8),
+                38 | 48 => {
+                    let color = match (args.next(), args.next()) {
+                        (Some(5), Some(index)) => u8::try_from(index).ok(),
+                        _ => None,
+                    };
+                    if arg == 38 {
+                        self.fg = color;
+                    } else {
+                        self.bg = color;
+                    }
+                }
+                39 => self.fg = None,
+                49 => self.bg = None,
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Write for Screen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.process(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ansi::{BLACK, WHITE};
+    use crate::render::Style;
+
+    #[test]
+    fn render_half_block() {
+        let screen = render("qr2term", &QrOptions::new(), 80, 24).unwrap();
+        assert_eq!(
+            screen.painted_area(),
+            Some(Area {
+                row: 0,
+                column: 0,
+                rows: 13,
+                columns: 25,
+            })
+        );
+        assert_eq!(screen.cursor(), (13, 0));
+
+        // Top left corner of the finder pattern, dark in both halves
+        let cell = screen.cell(1, 2).unwrap();
+        assert_eq!((cell.fg, cell.bg), (Some(WHITE), Some(BLACK)));
+    }
+
+    #[test]
+    fn render_ascii() {
+        let options = QrOptions::new().style(Style::Ascii);
+        let screen = render("qr2term", &options, 80, 30).unwrap();
+        assert!(screen.row_text(2).starts_with("    ##############  "));
+        assert!(screen.cell(2, 4).unwrap().bg.is_none());
+    }
+
+    #[test]
+    fn cursor_and_erase() {
+        let mut screen = Screen::new(10, 3);
+        write!(screen, "abc\x1B[2;5Hxy\x1B[1;2H\x1B[K").unwrap();
+        assert_eq!(screen.contents(), "a\n    xy");
+
+        writeln!(screen, "\x1B[3;9Hwrap").unwrap();
+        assert_eq!(screen.contents(), "        wr\nap");
+        assert_eq!(screen.row_text(2), "");
+        assert_eq!(screen.cursor(), (2, 0));
+
+        write!(screen, "\x1B[2J").unwrap();
+        assert_eq!(screen.painted_area(), None);
+    }
+}
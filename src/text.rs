@@ -0,0 +1,119 @@
+//! QR code wrapper implementing `Display`.
+
+use std::fmt::{self, Display};
+
+use crate::matrix::Matrix;
+use crate::qr::QrInfo;
+use crate::render::{Color, Renderer, Style};
+use crate::theme::Theme;
+use crate::{Error, QrOptions, ToQrPayload};
+
+/// A generated QR code that can be formatted with `Display`.
+///
+/// By default the code is formatted as plain text without ANSI colors, use
+/// [`ansi`](Self::ansi) to format it in color for a terminal.
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::QrText;
+///
+/// let qr = QrText::new("https://rust-lang.org/")?;
+/// println!("{}", qr.ansi());
+/// # Ok::<(), qr2term::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct QrText {
+    matrix: Matrix<Color>,
+    info: QrInfo,
+    style: Style,
+    theme: Theme,
+    ansi: bool,
+}
+
+impl QrText {
+    /// Generate a QR code for `data`, using the process wide
+    /// [`default_options`](crate::default_options).
+    ///
+    /// Returns an error if generating the QR code failed.
+    pub fn new<D: ToQrPayload>(data: D) -> Result<Self, Error> {
+        Self::with_options(data, &crate::default_options())
+    }
+
+    /// Generate a QR code for `data`, using the given `options`.
+    ///
+    /// Returns an error if generating the QR code failed.
+    pub fn with_options<D: ToQrPayload>(data: D, options: &QrOptions) -> Result<Self, Error> {
+        let code = crate::generate_code(data, options)?;
+        Ok(Self {
+            matrix: crate::code_matrix(&code, options)?,
+            info: code.info(),
+            style: options.style,
+            theme: options.theme,
+            ansi: false,
+        })
+    }
+
+    /// Get metadata about the generated symbol.
+    pub fn info(&self) -> QrInfo {
+        self.info
+    }
+
+    /// Format with ANSI colors, for printing to a terminal.
+    pub fn ansi(mut self) -> Self {
+        self.ansi = true;
+        self
+    }
+
+    /// Format with the given render style.
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Format with the given theme, when formatting with ANSI colors.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+}
+
+impl Display for QrText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buf = Vec::new();
+        Renderer::new(self.style)
+            .ansi(self.ansi)
+            .theme(self.theme)
+            .render(&self.matrix, &mut buf)
+            .map_err(|_| fmt::Error)?;
+        f.write_str(std::str::from_utf8(&buf).map_err(|_| fmt::Error)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_plain_and_ansi() {
+        let options = QrOptions::new();
+        let plain = QrText::with_options("qr2term", &options)
+            .unwrap()
+            .to_string();
+        assert!(!plain.contains('\x1B'));
+        assert!(plain.contains('▀'));
+
+        let ansi = QrText::with_options("qr2term", &options).unwrap().ansi();
+        let expected = crate::generate_qr_string_with_options("qr2term", &options).unwrap();
+        assert_eq!(ansi.to_string(), expected);
+    }
+
+    #[test]
+    fn display_style() {
+        let text = QrText::with_options("qr2term", &QrOptions::new())
+            .unwrap()
+            .style(Style::Ascii)
+            .to_string();
+        assert!(text.contains("##"));
+    }
+}
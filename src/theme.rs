@@ -0,0 +1,198 @@
+//! Color theme presets for rendered codes.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::ansi;
+use crate::color::Rgb;
+
+/// Minimum contrast ratio between dark and light modules of a [`Theme`].
+///
+/// This is the WCAG level AAA ratio for text, which keeps codes readable for
+/// scanners in poor lighting and on dimmed screens.
+pub const MIN_CONTRAST_RATIO: f64 = 7.0;
+
+/// Colors to render codes in.
+///
+/// All themes with colors draw dark modules on a light background, as
+/// scanners expect, with at least [`MIN_CONTRAST_RATIO`] between them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Theme {
+    /// Black and white from the basic palette, which some terminal color
+    /// schemes adjust.
+    #[default]
+    Classic,
+
+    /// Pure black and white from the 256-color cube, which terminal color
+    /// schemes leave alone.
+    HighContrast,
+
+    /// The darkest and lightest Solarized tones, blending in with Solarized
+    /// terminal color schemes.
+    SolarizedDark,
+
+    /// No colors, drawing dark modules in the terminal's foreground color.
+    ///
+    /// Only scans well on terminals with a light background.
+    Monochrome,
+}
+
+impl Theme {
+    /// All available themes.
+    pub const ALL: [Theme; 4] = [
+        Theme::Classic,
+        Theme::HighContrast,
+        Theme::SolarizedDark,
+        Theme::Monochrome,
+    ];
+
+    /// Name of this theme, as accepted by its `FromStr` implementation.
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Classic => "classic",
+            Theme::HighContrast => "high-contrast",
+            Theme::SolarizedDark => "solarized-dark",
+            Theme::Monochrome => "monochrome",
+        }
+    }
+
+    /// The 256-color palette indices of dark and light modules, or `None` if
+    /// this theme doesn't use colors.
+    pub fn colors(self) -> Option<(u8, u8)> {
+        match self {
+            Theme::Classic => Some((ansi::BLACK, ansi::WHITE)),
+            Theme::HighContrast => Some((16, 231)),
+            Theme::SolarizedDark => Some((234, 230)),
+            Theme::Monochrome => None,
+        }
+    }
+
+    /// The contrast ratio between dark and light modules, as defined by WCAG,
+    /// or `None` if this theme doesn't use colors.
+    ///
+    /// Palette colors are assumed to have their standard xterm values.
+    pub fn contrast_ratio(self) -> Option<f64> {
+        let (dark, light) = self.colors()?;
+        Some(contrast_ratio(palette_rgb(dark), palette_rgb(light)))
+    }
+}
+
+impl Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Theme {
+    type Err = ParseThemeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase().replace('_', "-");
+        Theme::ALL
+            .into_iter()
+            .find(|theme| theme.name() == name || theme.name().replace('-', "") == name)
+            .ok_or(ParseThemeError { name })
+    }
+}
+
+/// Error returned when parsing an unknown [`Theme`] name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseThemeError {
+    name: String,
+}
+
+impl Display for ParseThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown theme '{}'", self.name)
+    }
+}
+
+impl Error for ParseThemeError {}
+
+/// Get the standard xterm color of a 256-color palette index.
+fn palette_rgb(index: u8) -> Rgb {
+    const BASIC: [Rgb; 16] = [
+        [0, 0, 0],
+        [205, 0, 0],
+        [0, 205, 0],
+        [205, 205, 0],
+        [0, 0, 238],
+        [205, 0, 205],
+        [0, 205, 205],
+        [229, 229, 229],
+        [127, 127, 127],
+        [255, 0, 0],
+        [0, 255, 0],
+        [255, 255, 0],
+        [92, 92, 255],
+        [255, 0, 255],
+        [0, 255, 255],
+        [255, 255, 255],
+    ];
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    match index {
+        0..=15 => BASIC[usize::from(index)],
+        16..=231 => {
+            let i = usize::from(index - 16);
+            [LEVELS[i / 36], LEVELS[i / 6 % 6], LEVELS[i % 6]]
+        }
+        _ => [8 + (index - 232) * 10; 3],
+    }
+}
+
+/// Get the contrast ratio between two sRGB colors, as defined by WCAG.
+pub(crate) fn contrast_ratio(a: Rgb, b: Rgb) -> f64 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Get the relative luminance of an sRGB color, as defined by WCAG.
+pub(crate) fn luminance(rgb: Rgb) -> f64 {
+    let [r, g, b] = rgb.map(|channel| {
+        let c = f64::from(channel) / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scannable() {
+        for theme in Theme::ALL {
+            let (dark, light) = match theme.colors() {
+                Some(colors) => colors,
+                None => continue,
+            };
+            assert!(luminance(palette_rgb(dark)) < luminance(palette_rgb(light)));
+            assert!(theme.contrast_ratio().unwrap() >= MIN_CONTRAST_RATIO);
+        }
+        assert_eq!(Theme::Classic.contrast_ratio(), Some(21.0));
+        assert_eq!(Theme::Monochrome.contrast_ratio(), None);
+    }
+
+    #[test]
+    fn palette() {
+        assert_eq!(palette_rgb(16), [0, 0, 0]);
+        assert_eq!(palette_rgb(196), [255, 0, 0]);
+        assert_eq!(palette_rgb(230), [255, 255, 215]);
+        assert_eq!(palette_rgb(234), [28, 28, 28]);
+    }
+
+    #[test]
+    fn theme_from_str() {
+        for theme in Theme::ALL {
+            assert_eq!(theme.to_string().parse(), Ok(theme));
+        }
+        assert_eq!("HighContrast".parse(), Ok(Theme::HighContrast));
+        assert_eq!("solarized_dark".parse(), Ok(Theme::SolarizedDark));
+        assert!("neon".parse::<Theme>().is_err());
+    }
+}
@@ -0,0 +1,233 @@
+//! Output for UEFI consoles, for pre-boot tools showing enrollment codes.
+//!
+//! The UEFI simple text output protocol takes null-terminated UCS-2 strings,
+//! doesn't translate line feeds, and only guarantees a few block element
+//! glyphs. [`render_lines`] renders a [`FixedQrMatrix`] within those limits,
+//! line by line, ready to pass to the console. This doesn't depend on
+//! `uefi-rs`, but its lines can be passed to it:
+//!
+//! ```rust,ignore
+//! let mut line = [0; 128];
+//! qr2term::uefi::render_lines(&matrix, 2, Glyphs::Block, &mut line, |line| {
+//!     let line = CStr16::from_u16_with_nul(line).unwrap();
+//!     system_table.stdout().output_string(line).unwrap();
+//! })?;
+//! ```
+
+use std::fmt::{self, Display};
+use std::io::{self, Result as IoResult, Write};
+use std::str::FromStr;
+
+use crate::fixed::{FixedQrMatrix, FixedSize, FixedVersion};
+use crate::render::{Renderer, Style};
+
+/// Characters to draw codes on a UEFI console with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Glyphs {
+    /// Full blocks (`U+2588`), which all UEFI consoles must support.
+    #[default]
+    Block,
+
+    /// `#` characters, for consoles without a font for block elements, such
+    /// as serial consoles.
+    Ascii,
+}
+
+impl Glyphs {
+    /// Name of these glyphs, as accepted by their `FromStr` implementation.
+    pub fn name(self) -> &'static str {
+        match self {
+            Glyphs::Block => "block",
+            Glyphs::Ascii => "ascii",
+        }
+    }
+
+    /// The render style drawing with these glyphs.
+    fn style(self) -> Style {
+        match self {
+            Glyphs::Block => Style::FullBlock,
+            Glyphs::Ascii => Style::Ascii,
+        }
+    }
+}
+
+impl Display for Glyphs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Glyphs {
+    type Err = ParseGlyphsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        [Glyphs::Block, Glyphs::Ascii]
+            .into_iter()
+            .find(|glyphs| glyphs.name() == name)
+            .ok_or(ParseGlyphsError { name })
+    }
+}
+
+/// Error returned when parsing unknown [`Glyphs`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseGlyphsError {
+    name: String,
+}
+
+impl Display for ParseGlyphsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown UEFI glyphs '{}'", self.name)
+    }
+}
+
+impl std::error::Error for ParseGlyphsError {}
+
+/// Render a fixed-size `matrix` with a quiet zone of `quiet_zone` modules
+/// for a UEFI console, invoking `f` with every line.
+///
+/// Every line is drawn in the given `glyphs` without colors, and passed as
+/// UCS-2 ending with a carriage return, line feed and null terminator. Lines
+/// are collected in `line`, so this doesn't allocate. A line takes 2 units
+/// per module, and 3 more for its ending.
+///
+/// Returns an error of kind [`WriteZero`](io::ErrorKind::WriteZero) if a
+/// line doesn't fit `line`, or if the rendering would be wider than
+/// [`MAX_COLUMNS`](crate::render::MAX_COLUMNS).
+///
+/// # Examples
+///
+/// ```rust
+/// use qr2term::fixed::FixedQrMatrix;
+/// use qr2term::uefi::{self, Glyphs};
+///
+/// let matrix = FixedQrMatrix::<1>::from_fn(|row, col| row == col);
+/// let mut line = [0; 2 * 25 + 3];
+/// let mut lines = 0;
+/// uefi::render_lines(&matrix, 2, Glyphs::Block, &mut line, |line| {
+///     assert!(line.ends_with(&[0x0D, 0x0A, 0]));
+///     lines += 1;
+/// })
+/// .unwrap();
+/// assert_eq!(lines, 25);
+/// ```
+pub fn render_lines<const V: usize, F: FnMut(&[u16])>(
+    matrix: &FixedQrMatrix<V>,
+    quiet_zone: usize,
+    glyphs: Glyphs,
+    line: &mut [u16],
+    f: F,
+) -> IoResult<()>
+where
+    FixedSize<V>: FixedVersion,
+{
+    let mut lines = Ucs2Lines {
+        line,
+        len: 0,
+        pending: [0; 4],
+        pending_len: 0,
+        f,
+    };
+    Renderer::new(glyphs.style())
+        .ansi(false)
+        .render_fixed(matrix, quiet_zone, &mut lines)
+}
+
+/// Writer transcoding UTF-8 into UCS-2 lines, invoking a callback with every line.
+struct Ucs2Lines<'a, F: FnMut(&[u16])> {
+    line: &'a mut [u16],
+
+    /// Length of the incomplete line.
+    len: usize,
+
+    /// Bytes of an incomplete UTF-8 character.
+    pending: [u8; 4],
+    pending_len: usize,
+    f: F,
+}
+
+impl<F: FnMut(&[u16])> Ucs2Lines<'_, F> {
+    /// Append a character to the line, ending it on a line feed.
+    fn push(&mut self, c: char) -> IoResult<()> {
+        if c == '\n' {
+            for unit in [0x0D, 0x0A, 0] {
+                self.push_unit(unit)?;
+            }
+            (self.f)(&self.line[..self.len]);
+            self.len = 0;
+            return Ok(());
+        }
+        let unit = u16::try_from(u32::from(c))
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "character is not in UCS-2"))?;
+        self.push_unit(unit)
+    }
+
+    fn push_unit(&mut self, unit: u16) -> IoResult<()> {
+        *self.line.get_mut(self.len).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::WriteZero, "line doesn't fit the buffer")
+        })? = unit;
+        self.len += 1;
+        Ok(())
+    }
+}
+
+impl<F: FnMut(&[u16])> Write for Ucs2Lines<'_, F> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        for &byte in buf {
+            self.pending[self.pending_len] = byte;
+            self.pending_len += 1;
+            match std::str::from_utf8(&self.pending[..self.pending_len]) {
+                Ok(s) => {
+                    let c = s.chars().next().expect("pending character is complete");
+                    self.pending_len = 0;
+                    self.push(c)?;
+                }
+                Err(err) if err.error_len().is_none() => {}
+                Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Render `matrix` into UCS-2 lines, decoded back into strings.
+    fn render(matrix: &FixedQrMatrix<1>, glyphs: Glyphs) -> Vec<String> {
+        let mut line = [0; 64];
+        let mut lines = Vec::new();
+        render_lines(matrix, 1, glyphs, &mut line, |line| {
+            lines.push(String::from_utf16(line).unwrap())
+        })
+        .unwrap();
+        lines
+    }
+
+    #[test]
+    fn lines() {
+        let matrix = FixedQrMatrix::<1>::from_fn(|row, col| row == 0 && col < 2);
+        let lines = render(&matrix, Glyphs::Block);
+        assert_eq!(lines.len(), 23);
+        assert_eq!(lines[0], format!("{}\r\n\0", " ".repeat(46)));
+        assert_eq!(lines[1], format!("  ████{}\r\n\0", " ".repeat(40)));
+        assert!(render(&matrix, Glyphs::Ascii)[1].starts_with("  ####  "));
+
+        let mut line = [0; 48];
+        let err = render_lines(&matrix, 1, Glyphs::Block, &mut line, |_| {}).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+        render_lines(&matrix, 1, Glyphs::Block, &mut [0; 49], |_| {}).unwrap();
+    }
+
+    #[test]
+    fn glyphs_from_str() {
+        assert_eq!(" Block ".parse(), Ok(Glyphs::Block));
+        assert_eq!("ASCII".parse(), Ok(Glyphs::Ascii));
+        assert!("braille".parse::<Glyphs>().is_err());
+    }
+}
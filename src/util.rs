@@ -1,31 +1,193 @@
-/// Take the square root of the given usize.
+use std::io::{self, Write};
+use std::ptr;
+use std::sync::atomic;
+use std::time::Duration;
+#[cfg(feature = "log")]
+use std::time::Instant;
+
+/// Take the exact integer square root of the given usize.
+///
+/// Returns `None` if the given number isn't a perfect square.
+pub fn usize_sqrt(num: usize) -> Option<usize> {
+    // Start from the floating point estimate, and correct its rounding
+    let mut sqrt = (num as f64).sqrt() as usize;
+    while sqrt.checked_mul(sqrt).map_or(true, |square| square > num) {
+        sqrt -= 1;
+    }
+    while (sqrt + 1)
+        .checked_mul(sqrt + 1)
+        .map_or(false, |square| square <= num)
+    {
+        sqrt += 1;
+    }
+    (sqrt * sqrt == num).then_some(sqrt)
+}
+
+/// Overwrite every element of `data` with `value`, such as buffers that held
+/// secrets.
 ///
-/// # Panics
+/// Writes are volatile, so the compiler doesn't remove them as dead stores
+/// before the buffer is freed.
+pub fn wipe<T: Copy>(data: &mut [T], value: T) {
+    for element in data.iter_mut() {
+        // SAFETY: `element` is a valid, aligned and exclusive reference
+        unsafe { ptr::write_volatile(element, value) };
+    }
+    atomic::compiler_fence(atomic::Ordering::SeqCst);
+}
+
+/// Timer for the durations in log messages.
+///
+/// Only reads the clock with the `log` feature, as hosts such as WASI runtimes
+/// may not grant access to it, which makes reading it panic.
+pub struct Timer {
+    #[cfg(feature = "log")]
+    start: Instant,
+}
+
+impl Timer {
+    /// Start timing.
+    pub fn start() -> Self {
+        Self {
+            #[cfg(feature = "log")]
+            start: Instant::now(),
+        }
+    }
+
+    /// Time elapsed since starting, zero without the `log` feature.
+    pub fn elapsed(&self) -> Duration {
+        #[cfg(feature = "log")]
+        return self.start.elapsed();
+        #[cfg(not(feature = "log"))]
+        Duration::ZERO
+    }
+}
+
+/// Writer ending every line written through it with `\r\n` instead of `\n`,
+/// for terminals in raw mode and remote sessions.
+pub struct CrLf<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> CrLf<W> {
+    /// Wrap `inner`, writing to it with `\r\n` line endings.
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+}
+
+impl<W: Write> Write for CrLf<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut lines = buf.split(|&byte| byte == b'\n');
+        self.inner.write_all(lines.next().unwrap_or_default())?;
+        for line in lines {
+            self.inner.write_all(b"\r\n")?;
+            self.inner.write_all(line)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The standard Base64 alphabet.
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode `data` as Base64 without padding.
+#[cfg(any(feature = "payloads", feature = "service"))]
+pub fn encode_base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity((data.len() * 4 + 2) / 3);
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+            bits | u32::from(byte) << (16 - 8 * i)
+        });
+        for i in 0..=chunk.len() {
+            encoded.push(char::from(BASE64[(bits >> (18 - 6 * i)) as usize & 0x3F]));
+        }
+    }
+    encoded
+}
+
+/// Decode padded Base64 `text`.
 ///
-/// Panics if the given number isn't a perfect square.
-#[inline(always)]
-pub fn usize_sqrt(num: usize) -> usize {
-    let sqrt = (num as f64).sqrt() as usize;
-    assert_eq!(num, sqrt * sqrt, "given number isn't a perfect square");
-    sqrt as usize
+/// Returns `None` if `text` is not valid Base64.
+pub fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 4 != 0 {
+        return None;
+    }
+    let data = text.trim_end_matches('=');
+    if text.len() - data.len() > 2 {
+        return None;
+    }
+
+    let mut decoded = Vec::with_capacity(data.len() * 3 / 4);
+    let mut bits = 0u32;
+    let mut count = 0;
+    for byte in data.bytes() {
+        let value = BASE64.iter().position(|&c| c == byte)? as u32;
+        bits = bits << 6 | value;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            decoded.push((bits >> count) as u8);
+        }
+    }
+    Some(decoded)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn timer() {
+        let timer = Timer::start();
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(timer.elapsed() > Duration::ZERO, cfg!(feature = "log"));
+    }
+
+    #[test]
+    fn crlf() {
+        let mut buf = Vec::new();
+        let mut writer = CrLf::new(&mut buf);
+        writer.write_all(b"a\nb").unwrap();
+        writer.write_all(b"\n\nc\n").unwrap();
+        assert_eq!(buf, b"a\r\nb\r\n\r\nc\r\n");
+    }
+
     #[test]
     fn usize_sqrt_squared() {
-        assert_eq!(usize_sqrt(0), 0);
-        assert_eq!(usize_sqrt(1), 1);
-        assert_eq!(usize_sqrt(4), 2);
-        assert_eq!(usize_sqrt(25), 5);
+        assert_eq!(usize_sqrt(0), Some(0));
+        assert_eq!(usize_sqrt(1), Some(1));
+        assert_eq!(usize_sqrt(4), Some(2));
+        assert_eq!(usize_sqrt(25), Some(5));
+        let max = (1 << (usize::BITS / 2)) - 1;
+        assert_eq!(usize_sqrt(max * max), Some(max));
+        assert_eq!(usize_sqrt((max - 1) * (max - 1)), Some(max - 1));
+    }
+
+    #[cfg(any(feature = "payloads", feature = "service"))]
+    #[test]
+    fn base64() {
+        for data in [&b""[..], b"f", b"fo", b"foo", b"foob", b"fooba", b"foobar"] {
+            let encoded = encode_base64(data);
+            let padding = "=".repeat((4 - encoded.len() % 4) % 4);
+            assert_eq!(decode_base64(&(encoded + &padding)).unwrap(), data);
+        }
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(encode_base64(b"fooba"), "Zm9vYmE");
+        assert_eq!(decode_base64("Zg"), None);
+        assert_eq!(decode_base64("Z==="), None);
+        assert_eq!(decode_base64("Zm9v-mFy"), None);
     }
 
-    /// Taking the integer square root of a non-square number fails.
     #[test]
-    #[should_panic]
     fn usize_sqrt_not_squared() {
-        usize_sqrt(3);
+        assert_eq!(usize_sqrt(3), None);
+        assert_eq!(usize_sqrt(usize::MAX), None);
+        let max = (1 << (usize::BITS / 2)) - 1;
+        assert_eq!(usize_sqrt(max * max - 1), None);
     }
 }
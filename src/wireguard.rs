@@ -0,0 +1,505 @@
+//! WireGuard tunnel configurations, for importing into the mobile apps.
+//!
+//! The official WireGuard apps for Android and iOS import a tunnel by
+//! scanning a QR code of its `wg-quick` configuration. [`WireGuardConfig`]
+//! builds or parses such a configuration, validates it, and strips comments
+//! and blank lines to keep the code small. [`WireGuardConfig::qr_options`]
+//! then picks the strongest error correction that still gives a code phones
+//! scan reliably from a terminal.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use qr2term::wireguard::{Interface, Peer, WireGuardConfig};
+//!
+//! let interface = Interface::new("epExKQfKdTZou8OhC/yBpfgas3EBV5+f5dxnqlGpV+8=")
+//!     .address("10.0.0.2/32")
+//!     .dns("10.0.0.1");
+//! let peer = Peer::new("OaK+V01rYvCPnYPMwxW0E3W0BRR7XgNtj08+7+acvoU=")
+//!     .endpoint("vpn.example.com:51820")
+//!     .allowed_ips("0.0.0.0/0, ::/0");
+//! let config = WireGuardConfig::new(&interface, &[peer])?;
+//!
+//! let options = config.qr_options(&qr2term::default_options())?;
+//! qr2term::print_qr_with_options(&config, &options).unwrap();
+//! # Ok::<(), qr2term::wireguard::WireGuardError>(())
+//! ```
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::options::{EcLevel, Overflow};
+use crate::payload::{Payload, ToQrPayload};
+use crate::qr::{Qr, Version};
+use crate::util::decode_base64;
+use crate::QrOptions;
+
+/// Largest symbol version [`WireGuardConfig::qr_options`] picks.
+///
+/// Version 14 codes are 73 modules wide, fitting an 80 column terminal with
+/// the default quiet zone in the half block style.
+pub const MAX_VERSION: i16 = 14;
+
+/// Error correction levels to try, strongest first.
+const EC_LEVELS: [EcLevel; 4] = [EcLevel::H, EcLevel::Q, EcLevel::M, EcLevel::L];
+
+/// The `[Interface]` section of a configuration, for the device importing it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Interface {
+    /// The Base64 private key of the device.
+    pub private_key: String,
+
+    /// Addresses of the device in the tunnel, such as `10.0.0.2/32`.
+    pub addresses: Vec<String>,
+
+    /// DNS servers to use while the tunnel is up.
+    pub dns: Vec<String>,
+
+    /// Port to listen on, random if `None`.
+    pub listen_port: Option<u16>,
+
+    /// MTU of the tunnel, automatic if `None`.
+    pub mtu: Option<u16>,
+}
+
+impl Interface {
+    /// Construct an interface with the given Base64 private key.
+    pub fn new(private_key: &str) -> Self {
+        Self {
+            private_key: private_key.into(),
+            addresses: Vec::new(),
+            dns: Vec::new(),
+            listen_port: None,
+            mtu: None,
+        }
+    }
+
+    /// Add an address, or comma-separated addresses, of the device in the tunnel.
+    pub fn address(mut self, address: &str) -> Self {
+        self.addresses.push(address.into());
+        self
+    }
+
+    /// Add a DNS server, or comma-separated DNS servers.
+    pub fn dns(mut self, dns: &str) -> Self {
+        self.dns.push(dns.into());
+        self
+    }
+
+    /// Set the port to listen on.
+    pub fn listen_port(mut self, listen_port: u16) -> Self {
+        self.listen_port = Some(listen_port);
+        self
+    }
+
+    /// Set the MTU of the tunnel.
+    pub fn mtu(mut self, mtu: u16) -> Self {
+        self.mtu = Some(mtu);
+        self
+    }
+}
+
+/// A `[Peer]` section of a configuration, for a server or other device.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Peer {
+    /// The Base64 public key of the peer.
+    pub public_key: String,
+
+    /// The Base64 preshared key, if any.
+    pub preshared_key: Option<String>,
+
+    /// The `host:port` endpoint of the peer, if known.
+    pub endpoint: Option<String>,
+
+    /// Networks routed to the peer, such as `0.0.0.0/0`.
+    pub allowed_ips: Vec<String>,
+
+    /// Interval in seconds of keepalive packets, if any.
+    pub persistent_keepalive: Option<u16>,
+}
+
+impl Peer {
+    /// Construct a peer with the given Base64 public key.
+    pub fn new(public_key: &str) -> Self {
+        Self {
+            public_key: public_key.into(),
+            preshared_key: None,
+            endpoint: None,
+            allowed_ips: Vec::new(),
+            persistent_keepalive: None,
+        }
+    }
+
+    /// Set the Base64 preshared key.
+    pub fn preshared_key(mut self, preshared_key: &str) -> Self {
+        self.preshared_key = Some(preshared_key.into());
+        self
+    }
+
+    /// Set the `host:port` endpoint of the peer.
+    pub fn endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Add a network, or comma-separated networks, routed to the peer.
+    pub fn allowed_ips(mut self, allowed_ips: &str) -> Self {
+        self.allowed_ips.push(allowed_ips.into());
+        self
+    }
+
+    /// Set the interval in seconds of keepalive packets.
+    pub fn persistent_keepalive(mut self, seconds: u16) -> Self {
+        self.persistent_keepalive = Some(seconds);
+        self
+    }
+}
+
+/// A validated WireGuard configuration, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WireGuardConfig {
+    text: String,
+}
+
+impl WireGuardConfig {
+    /// Build the configuration of an interface and its peers.
+    ///
+    /// Returns an error if there are no peers, or a key is not a Base64
+    /// encoded 32 byte key.
+    pub fn new(interface: &Interface, peers: &[Peer]) -> Result<Self, WireGuardError> {
+        let mut ini = format!("[Interface]\nPrivateKey={}\n", interface.private_key);
+        push_list(&mut ini, "Address", &interface.addresses);
+        push_list(&mut ini, "DNS", &interface.dns);
+        push_option(&mut ini, "ListenPort", interface.listen_port);
+        push_option(&mut ini, "MTU", interface.mtu);
+        for peer in peers {
+            ini.push_str("[Peer]\n");
+            push_option(&mut ini, "PublicKey", Some(&peer.public_key));
+            push_option(&mut ini, "PresharedKey", peer.preshared_key.as_ref());
+            push_list(&mut ini, "AllowedIPs", &peer.allowed_ips);
+            push_option(&mut ini, "Endpoint", peer.endpoint.as_ref());
+            push_option(&mut ini, "PersistentKeepalive", peer.persistent_keepalive);
+        }
+        ini.parse()
+    }
+
+    /// The configuration, as imported by the apps.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Get `options` adjusted for importing this configuration.
+    ///
+    /// Picks the strongest error correction level at which the code is at
+    /// most [`MAX_VERSION`], as terminals show codes at low resolution and
+    /// the extra redundancy helps phones scan them. Compression and overflow
+    /// handling are turned off, as the apps expect the configuration as is.
+    ///
+    /// Returns an error if the configuration doesn't fit a code of
+    /// [`MAX_VERSION`] even at the lowest level.
+    pub fn qr_options(&self, options: &QrOptions) -> Result<QrOptions, WireGuardError> {
+        let payload = self.to_qr_payload();
+        let ec_level = EC_LEVELS
+            .into_iter()
+            .find(|&ec_level| match Qr::from_payload(&payload, ec_level) {
+                Ok(code) => fits_max_version(&code),
+                Err(_) => false,
+            })
+            .ok_or(WireGuardError::TooLarge {
+                len: self.text.len(),
+            })?;
+        Ok(options
+            .clone()
+            .ec_level(ec_level)
+            .compress(false)
+            .on_overflow(Overflow::Error))
+    }
+}
+
+impl Display for WireGuardConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl FromStr for WireGuardConfig {
+    type Err = WireGuardError;
+
+    /// Parse and validate a `wg-quick` configuration.
+    ///
+    /// Comments, blank lines and whitespace around keys and values are
+    /// removed, section and key names are kept as given.
+    fn from_str(ini: &str) -> Result<Self, Self::Err> {
+        let mut text = String::with_capacity(ini.len());
+        let mut section = None;
+        let (mut interfaces, mut peers) = (0, 0);
+        let (mut private_key, mut public_key) = (false, false);
+
+        for (i, line) in ini.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                if section == Some(Section::Peer) && !public_key {
+                    return Err(WireGuardError::MissingKey("PublicKey"));
+                }
+                let name = line[1..line.len() - 1].trim();
+                section = match name.to_ascii_lowercase().as_str() {
+                    "interface" => Some(Section::Interface),
+                    "peer" => Some(Section::Peer),
+                    _ => return Err(WireGuardError::InvalidLine(i + 1)),
+                };
+                match section {
+                    Some(Section::Interface) => interfaces += 1,
+                    _ => peers += 1,
+                }
+                public_key = false;
+                text.push_str(&format!("[{}]\n", name));
+                continue;
+            }
+
+            let (key, value) = match (section, line.split_once('=')) {
+                (Some(_), Some((key, value))) => (key.trim(), value.trim()),
+                _ => return Err(WireGuardError::InvalidLine(i + 1)),
+            };
+            if key.is_empty() || value.is_empty() {
+                return Err(WireGuardError::InvalidLine(i + 1));
+            }
+            match key.to_ascii_lowercase().as_str() {
+                "privatekey" if section == Some(Section::Interface) => private_key = true,
+                "publickey" if section == Some(Section::Peer) => public_key = true,
+                "presharedkey" => {}
+                _ => {
+                    text.push_str(&format!("{}={}\n", key, value));
+                    continue;
+                }
+            }
+            if !matches!(decode_base64(value), Some(key) if key.len() == 32) {
+                return Err(WireGuardError::InvalidKey(value.into()));
+            }
+            text.push_str(&format!("{}={}\n", key, value));
+        }
+
+        if interfaces != 1 {
+            return Err(WireGuardError::InvalidInterfaceCount(interfaces));
+        }
+        if peers == 0 {
+            return Err(WireGuardError::MissingPeer);
+        }
+        if !private_key {
+            return Err(WireGuardError::MissingKey("PrivateKey"));
+        }
+        if section == Some(Section::Peer) && !public_key {
+            return Err(WireGuardError::MissingKey("PublicKey"));
+        }
+        text.pop();
+        Ok(Self { text })
+    }
+}
+
+impl ToQrPayload for WireGuardConfig {
+    fn to_qr_payload(&self) -> Payload<'_> {
+        self.text.to_qr_payload()
+    }
+}
+
+/// Error returned when a WireGuard configuration is invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WireGuardError {
+    /// The line with the given number is neither a section header nor a
+    /// `key = value` pair in a section.
+    InvalidLine(usize),
+
+    /// The configuration doesn't have exactly one `[Interface]` section.
+    InvalidInterfaceCount(usize),
+
+    /// The configuration has no `[Peer]` section.
+    MissingPeer,
+
+    /// A section is missing the given required key.
+    MissingKey(&'static str),
+
+    /// A key is not a Base64 encoded 32 byte key.
+    InvalidKey(String),
+
+    /// The configuration of the given length in bytes doesn't fit a code of
+    /// at most [`MAX_VERSION`].
+    TooLarge {
+        /// Length of the configuration in bytes.
+        len: usize,
+    },
+}
+
+impl Display for WireGuardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WireGuardError::InvalidLine(line) => write!(f, "invalid line {}", line),
+            WireGuardError::InvalidInterfaceCount(count) => {
+                write!(f, "expected one [Interface] section, found {}", count)
+            }
+            WireGuardError::MissingPeer => f.write_str("missing [Peer] section"),
+            WireGuardError::MissingKey(key) => write!(f, "missing {}", key),
+            WireGuardError::InvalidKey(key) => write!(f, "invalid key '{}'", key),
+            WireGuardError::TooLarge { len } => write!(
+                f,
+                "configuration of {} bytes is too large for a scannable QR code",
+                len
+            ),
+        }
+    }
+}
+
+impl Error for WireGuardError {}
+
+/// Section of a configuration.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Section {
+    Interface,
+    Peer,
+}
+
+/// Whether `code` is at most [`MAX_VERSION`].
+fn fits_max_version(code: &Qr) -> bool {
+    matches!(code.info().version, Version::Normal(version) if version <= MAX_VERSION)
+}
+
+/// Append a `key=value` line to `ini` if `value` is set.
+fn push_option<T: Display>(ini: &mut String, key: &str, value: Option<T>) {
+    if let Some(value) = value {
+        ini.push_str(&format!("{}={}\n", key, value));
+    }
+}
+
+/// Append a `key=value` line with the comma-separated `values` to `ini`, if any.
+fn push_list(ini: &mut String, key: &str, values: &[String]) {
+    if !values.is_empty() {
+        push_option(ini, key, Some(values.join(",")));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIVATE_KEY: &str = "epExKQfKdTZou8OhC/yBpfgas3EBV5+f5dxnqlGpV+8=";
+    const PUBLIC_KEY: &str = "OaK+V01rYvCPnYPMwxW0E3W0BRR7XgNtj08+7+acvoU=";
+    const PRESHARED_KEY: &str = "UJiHyQnIJrq1e8cL6PtPVN4NcuIKzSp4hOGpWFcuRMc=";
+
+    fn config() -> WireGuardConfig {
+        let interface = Interface::new(PRIVATE_KEY)
+            .address("10.0.0.2/32")
+            .address("fd00::2/128")
+            .dns("1.1.1.1")
+            .mtu(1420);
+        let peer = Peer::new(PUBLIC_KEY)
+            .preshared_key(PRESHARED_KEY)
+            .endpoint("vpn.example.com:51820")
+            .allowed_ips("0.0.0.0/0, ::/0")
+            .persistent_keepalive(25);
+        WireGuardConfig::new(&interface, &[peer]).unwrap()
+    }
+
+    #[test]
+    fn build() {
+        assert_eq!(
+            config().as_str(),
+            format!(
+                "[Interface]\nPrivateKey={}\nAddress=10.0.0.2/32,fd00::2/128\nDNS=1.1.1.1\n\
+                 MTU=1420\n[Peer]\nPublicKey={}\nPresharedKey={}\n\
+                 AllowedIPs=0.0.0.0/0, ::/0\nEndpoint=vpn.example.com:51820\n\
+                 PersistentKeepalive=25",
+                PRIVATE_KEY, PUBLIC_KEY, PRESHARED_KEY
+            )
+        );
+        assert_eq!(
+            WireGuardConfig::new(&Interface::new(PRIVATE_KEY), &[]),
+            Err(WireGuardError::MissingPeer)
+        );
+        assert_eq!(
+            WireGuardConfig::new(&Interface::new("abc="), &[Peer::new(PUBLIC_KEY)]),
+            Err(WireGuardError::InvalidKey("abc=".into()))
+        );
+    }
+
+    #[test]
+    fn parse() {
+        let ini = format!(
+            "# Laptop\n[Interface]\n  PrivateKey = {} \nAddress = 10.0.0.2/32 # tunnel\n\n\
+             [ Peer ]\r\nPublicKey={}\r\nAllowedIPs = 10.0.0.0/24\r\n",
+            PRIVATE_KEY, PUBLIC_KEY
+        );
+        assert_eq!(
+            ini.parse::<WireGuardConfig>().unwrap().as_str(),
+            format!(
+                "[Interface]\nPrivateKey={}\nAddress=10.0.0.2/32\n\
+                 [Peer]\nPublicKey={}\nAllowedIPs=10.0.0.0/24",
+                PRIVATE_KEY, PUBLIC_KEY
+            )
+        );
+        assert_eq!(config().as_str().parse(), Ok(config()));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let interface = format!("[Interface]\nPrivateKey={}\n", PRIVATE_KEY);
+        let peer = format!("[Peer]\nPublicKey={}\n", PUBLIC_KEY);
+        let parse = |ini: String| ini.parse::<WireGuardConfig>();
+
+        assert_eq!(
+            parse(format!("Address=10.0.0.2\n{}{}", interface, peer)),
+            Err(WireGuardError::InvalidLine(1))
+        );
+        assert_eq!(
+            parse(format!("{}Address\n{}", interface, peer)),
+            Err(WireGuardError::InvalidLine(3))
+        );
+        assert_eq!(
+            parse(format!("{}[Wireguard]\n", interface)),
+            Err(WireGuardError::InvalidLine(3))
+        );
+        assert_eq!(
+            parse(peer.clone()),
+            Err(WireGuardError::InvalidInterfaceCount(0))
+        );
+        assert_eq!(
+            parse(format!("{}{}{}", interface, interface, peer)),
+            Err(WireGuardError::InvalidInterfaceCount(2))
+        );
+        assert_eq!(parse(interface.clone()), Err(WireGuardError::MissingPeer));
+        assert_eq!(
+            parse(format!("[Interface]\n{}", peer)),
+            Err(WireGuardError::MissingKey("PrivateKey"))
+        );
+        assert_eq!(
+            parse(format!("{}[Peer]\n{}", interface, peer)),
+            Err(WireGuardError::MissingKey("PublicKey"))
+        );
+        assert_eq!(
+            parse(format!("{}{}PresharedKey=AAAA\n", interface, peer)),
+            Err(WireGuardError::InvalidKey("AAAA".into()))
+        );
+    }
+
+    #[test]
+    fn qr_options() {
+        let options = QrOptions::default().compress(true);
+        let options = config().qr_options(&options).unwrap();
+        assert_eq!(options.ec_level, EcLevel::M);
+        assert!(!options.compress);
+        let code = Qr::from_payload(&config().to_qr_payload(), options.ec_level).unwrap();
+        assert!(fits_max_version(&code));
+
+        let peer = Peer::new(PUBLIC_KEY).allowed_ips(&"10.0.0.0/24,".repeat(40));
+        let config = WireGuardConfig::new(&Interface::new(PRIVATE_KEY), &[peer]).unwrap();
+        assert_eq!(
+            config.qr_options(&options),
+            Err(WireGuardError::TooLarge {
+                len: config.as_str().len()
+            })
+        );
+    }
+}